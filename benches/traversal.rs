@@ -0,0 +1,118 @@
+//! Times rays traversing bounding volume hierarchies, to compare changes to their layout.
+//!
+//! Run with `cargo bench --bench traversal`.
+extern crate rrt;
+
+use rrt::math::{AABB, Bvh, Point, Ray, Solid, TriangleMesh, Vector};
+use std::time::{Duration, Instant};
+
+/// Rays cast in each measurement.
+const RAYS: usize = 200_000;
+
+/// Cells along each side of the bumpy grid of triangles.
+const GRID_CELLS: u32 = 256;
+
+/// Small boxes scattered through a cube.
+const BOXES: usize = 100_000;
+
+/// Deterministic pseudorandom numbers in [0, 1), so every run measures the same rays.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> f32 {
+        self.0 = self.0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// A square grid of triangles in the XZ plane, with heights bumping up and down.
+fn bumpy_grid() -> TriangleMesh {
+    let mut vertices = Vec::new();
+    for z in 0..(GRID_CELLS + 1) {
+        for x in 0..(GRID_CELLS + 1) {
+            let height = 0.5 * ((x as f32 * 0.3).sin() + (z as f32 * 0.2).cos());
+            vertices.push(Point::new(x as f32, height, z as f32));
+        }
+    }
+    let mut indices = Vec::new();
+    let row = GRID_CELLS + 1;
+    for z in 0..GRID_CELLS {
+        for x in 0..GRID_CELLS {
+            let corner = z * row + x;
+            indices.extend_from_slice(&[corner, corner + row, corner + 1]);
+            indices.extend_from_slice(&[corner + 1, corner + row, corner + row + 1]);
+        }
+    }
+    TriangleMesh::new(vertices, indices)
+}
+
+/// Rays from above the middle of a square of the given size, aimed at random points on it.
+fn rays_toward_square(size: f32, rng: &mut Lcg) -> Vec<Ray> {
+    let origin = Point::new(0.5 * size, size, -0.5 * size);
+    (0..RAYS)
+        .map(|_| {
+            let target = Point::new(size * rng.next(), 0.0, size * rng.next());
+            let mut direction = target - origin;
+            direction.normalize().unwrap();
+            Ray {
+                origin: origin,
+                direction: direction,
+                time: 0.0,
+            }
+        })
+        .collect()
+}
+
+/// Prints how long casting every ray took, and the rate they were cast at.
+fn report(name: &str, elapsed: Duration, hits: usize) {
+    let seconds = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9;
+    println!(
+        "{:<8} {:>8.3}s {:>12.0} rays/s {:>8} hits",
+        name,
+        seconds,
+        RAYS as f64 / seconds,
+        hits
+    );
+}
+
+fn bench_mesh(rng: &mut Lcg) {
+    let mesh = bumpy_grid();
+    let rays = rays_toward_square(GRID_CELLS as f32, rng);
+    let start = Instant::now();
+    let hits = rays.iter().filter(|r| mesh.intersect(r).is_some()).count();
+    report("mesh", start.elapsed(), hits);
+}
+
+fn bench_boxes(rng: &mut Lcg) {
+    const SIZE: f32 = 100.0;
+    let boxes: Vec<AABB> = (0..BOXES)
+        .map(|_| {
+            let lower = Point::new(SIZE * rng.next(), SIZE * rng.next(), SIZE * rng.next());
+            AABB::new(lower, lower + Vector::new(0.5, 0.5, 0.5))
+        })
+        .collect();
+    let bvh = Bvh::new(&boxes);
+    let rays = rays_toward_square(SIZE, rng);
+    let start = Instant::now();
+    let mut hits = 0;
+    for ray in rays.iter() {
+        let mut hit = false;
+        bvh.traverse(ray, |i| {
+            let time = boxes[i].intersection_time(*ray);
+            hit |= time.is_some();
+            time
+        });
+        if hit {
+            hits += 1;
+        }
+    }
+    report("boxes", start.elapsed(), hits);
+}
+
+fn main() {
+    let mut rng = Lcg(1);
+    bench_mesh(&mut rng);
+    bench_boxes(&mut rng);
+}
@@ -1,5 +1,7 @@
 //! Utility functions for dealing with color.
 #![allow(dead_code)]
+mod spectrum;
+pub use self::spectrum::RgbSpectrum;
 
 use math::Vector;
 
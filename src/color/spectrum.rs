@@ -0,0 +1,216 @@
+use approx::ApproxEq;
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, Mul, Sub};
+
+/// Light, or the fraction of it a surface passes on, in each of the linear red, green and blue
+/// channels.
+///
+/// Kept apart from `Vector` so colors cannot be mistaken for positions or directions.  Products
+/// of spectra are taken channel by channel, such as light filtered by a surface's reflectance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RgbSpectrum {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+impl RgbSpectrum {
+    pub fn new(r: f32, g: f32, b: f32) -> RgbSpectrum {
+        RgbSpectrum { r: r, g: g, b: b }
+    }
+
+    /// The same value in every channel, such as a shade of gray.
+    pub fn uniform(value: f32) -> RgbSpectrum {
+        RgbSpectrum::new(value, value, value)
+    }
+
+    pub fn black() -> RgbSpectrum {
+        RgbSpectrum::uniform(0.0)
+    }
+
+    /// Perceived brightness, with the Rec. 709 weights of each channel.
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    /// Largest of the channels.
+    pub fn max_channel(&self) -> f32 {
+        self.r.max(self.g).max(self.b)
+    }
+
+    /// Whether every channel is zero.
+    pub fn is_black(&self) -> bool {
+        self.r == 0.0 && self.g == 0.0 && self.b == 0.0
+    }
+
+    /// Whether every channel is neither NaN nor infinite.
+    pub fn is_finite(&self) -> bool {
+        self.r.is_finite() && self.g.is_finite() && self.b.is_finite()
+    }
+
+    /// Limits each channel to the range `[min, max]`.
+    pub fn clamp(&self, min: f32, max: f32) -> RgbSpectrum {
+        self.map(|c| c.max(min).min(max))
+    }
+
+    /// Blends linearly between this spectrum, at `t` of 0, and another, at `t` of 1.
+    pub fn lerp(&self, other: &RgbSpectrum, t: f32) -> RgbSpectrum {
+        (1.0 - t) * *self + t * *other
+    }
+
+    /// Applies a function to each channel.
+    pub fn map<F: Fn(f32) -> f32>(&self, f: F) -> RgbSpectrum {
+        RgbSpectrum::new(f(self.r), f(self.g), f(self.b))
+    }
+
+    /// Combines each channel with the same channel of another spectrum.
+    pub fn zip_with<F: Fn(f32, f32) -> f32>(&self, other: &RgbSpectrum, f: F) -> RgbSpectrum {
+        RgbSpectrum::new(f(self.r, other.r), f(self.g, other.g), f(self.b, other.b))
+    }
+
+    /// The channels in order, red first.
+    pub fn to_array(&self) -> [f32; 3] {
+        [self.r, self.g, self.b]
+    }
+}
+
+impl Default for RgbSpectrum {
+    fn default() -> RgbSpectrum {
+        RgbSpectrum::black()
+    }
+}
+
+impl Add for RgbSpectrum {
+    type Output = RgbSpectrum;
+    fn add(self, rhs: RgbSpectrum) -> RgbSpectrum {
+        RgbSpectrum::new(self.r + rhs.r, self.g + rhs.g, self.b + rhs.b)
+    }
+}
+
+impl AddAssign for RgbSpectrum {
+    fn add_assign(&mut self, rhs: RgbSpectrum) {
+        self.r += rhs.r;
+        self.g += rhs.g;
+        self.b += rhs.b;
+    }
+}
+
+impl Sub for RgbSpectrum {
+    type Output = RgbSpectrum;
+    fn sub(self, rhs: RgbSpectrum) -> RgbSpectrum {
+        RgbSpectrum::new(self.r - rhs.r, self.g - rhs.g, self.b - rhs.b)
+    }
+}
+
+impl Mul for RgbSpectrum {
+    type Output = RgbSpectrum;
+    fn mul(self, rhs: RgbSpectrum) -> RgbSpectrum {
+        RgbSpectrum::new(self.r * rhs.r, self.g * rhs.g, self.b * rhs.b)
+    }
+}
+
+impl Mul<f32> for RgbSpectrum {
+    type Output = RgbSpectrum;
+    fn mul(self, rhs: f32) -> RgbSpectrum {
+        RgbSpectrum::new(self.r * rhs, self.g * rhs, self.b * rhs)
+    }
+}
+
+impl Mul<RgbSpectrum> for f32 {
+    type Output = RgbSpectrum;
+    fn mul(self, rhs: RgbSpectrum) -> RgbSpectrum {
+        rhs * self
+    }
+}
+
+impl Div<f32> for RgbSpectrum {
+    type Output = RgbSpectrum;
+    fn div(self, rhs: f32) -> RgbSpectrum {
+        RgbSpectrum::new(self.r / rhs, self.g / rhs, self.b / rhs)
+    }
+}
+
+impl ApproxEq for RgbSpectrum {
+    type Epsilon = <f32 as ApproxEq>::Epsilon;
+
+    fn default_epsilon() -> Self::Epsilon {
+        f32::default_epsilon()
+    }
+
+    fn default_max_relative() -> Self::Epsilon {
+        f32::default_max_relative()
+    }
+
+    fn default_max_ulps() -> u32 {
+        f32::default_max_ulps()
+    }
+
+    /// Determines relative equality based on the difference in each channel independently.
+    fn relative_eq(
+        &self,
+        other: &Self,
+        epsilon: Self::Epsilon,
+        max_relative: Self::Epsilon,
+    ) -> bool {
+        self.to_array()
+            .iter()
+            .zip(other.to_array().iter())
+            .all(|(a, b)| f32::relative_eq(a, b, epsilon, max_relative))
+    }
+
+    fn ulps_eq(&self, other: &Self, epsilon: Self::Epsilon, max_ulps: u32) -> bool {
+        self.to_array()
+            .iter()
+            .zip(other.to_array().iter())
+            .all(|(a, b)| f32::ulps_eq(a, b, epsilon, max_ulps))
+    }
+}
+
+impl fmt::Display for RgbSpectrum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "rgb({:.4}, {:.4}, {:.4})", self.r, self.g, self.b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RgbSpectrum;
+
+    #[test]
+    fn test_arithmetic() {
+        let a = RgbSpectrum::new(1.0, 2.0, 4.0);
+        let b = RgbSpectrum::new(0.5, 0.25, 0.0);
+        assert_eq!(a + b, RgbSpectrum::new(1.5, 2.25, 4.0));
+        assert_eq!(a - b, RgbSpectrum::new(0.5, 1.75, 4.0));
+        assert_eq!(a * b, RgbSpectrum::new(0.5, 0.5, 0.0));
+        assert_eq!(2.0 * a, a * 2.0);
+        assert_eq!(a / 2.0, RgbSpectrum::new(0.5, 1.0, 2.0));
+
+        let mut sum = RgbSpectrum::black();
+        sum += a;
+        sum += a;
+        assert_eq!(sum, 2.0 * a);
+    }
+
+    #[test]
+    fn test_luminance() {
+        assert_relative_eq!(RgbSpectrum::uniform(1.0).luminance(), 1.0, epsilon = 1e-6);
+        assert_relative_eq!(RgbSpectrum::new(0.0, 1.0, 0.0).luminance(), 0.7152);
+        assert_eq!(RgbSpectrum::black().luminance(), 0.0);
+    }
+
+    #[test]
+    fn test_clamp_and_lerp() {
+        let hot = RgbSpectrum::new(-1.0, 0.5, 3.0);
+        assert_eq!(hot.clamp(0.0, 1.0), RgbSpectrum::new(0.0, 0.5, 1.0));
+        assert_eq!(hot.max_channel(), 3.0);
+
+        let white = RgbSpectrum::uniform(1.0);
+        let black = RgbSpectrum::black();
+        assert_eq!(black.lerp(&white, 0.0), black);
+        assert_eq!(black.lerp(&white, 1.0), white);
+        assert_relative_eq!(black.lerp(&white, 0.25), RgbSpectrum::uniform(0.25));
+        assert!(black.is_black() && !white.is_black());
+    }
+}
@@ -1,5 +1,19 @@
 #[macro_use] extern crate approx;
+extern crate image;
+#[cfg(feature = "window")]
+extern crate minifb;
+#[cfg(test)]
+#[macro_use]
+extern crate proptest;
+extern crate rand;
+extern crate rayon;
+extern crate ron;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
 
 pub mod color;
 pub mod math;
+pub mod network;
+pub mod output;
 pub mod scene;
@@ -2,42 +2,713 @@
 extern crate approx;
 
 extern crate image;
+#[cfg(feature = "window")]
+extern crate minifb;
+#[cfg(test)]
+#[macro_use]
+extern crate proptest;
+extern crate rand;
+extern crate rayon;
+extern crate ron;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+use rand::Rng;
+use rayon::prelude::*;
 use std::fs::File;
-use std::path::Path;
+use std::io::{BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{mpsc, Mutex};
+#[cfg(feature = "window")]
+use std::thread;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
+mod color;
 mod math;
-use math::{Axis, Matrix4x4, PlanarAngle, Plane, Point, Sphere, Vector};
+mod network;
+mod output;
+use math::{BvhLayout, PlanarAngle, Plane, Point, Ray, Sphere, Transform, Vector};
 
 mod scene;
 use scene::*;
 
+use scene::aov::{self, Aov};
+use scene::backplate::Backplate;
+use scene::loader::{description, obj};
 use scene::dimensions::Dimensions2;
 use scene::material::*;
 use scene::nonarea_light::*;
+use scene::overlay::Overlay;
+use scene::sampler::*;
+use scene::stats::RenderStats;
+use scene::tile::Tile;
+use scene::toon::{self, ToonSettings};
+use scene::visibility::{self, VisibilityMatrix};
+use scene::wireframe::{self, WireframeSettings};
+
+use output::{DeepWriter, ImageStatistics, PpmStreamWriter, Precision, Preview, ProgressBar,
+             RadianceBuffer, RenderManifest, SplitRadiance, ViewTransform};
+#[cfg(feature = "window")]
+use output::PreviewWindow;
 
 extern crate clap;
-use clap::{App, SubCommand};
+use clap::{App, Arg, ArgMatches, SubCommand};
 
 type ColorImage = image::ImageBuffer<image::Rgb<u8>, std::vec::Vec<u8>>;
 
-fn render_multiple_spheres() {
-    let film = Film::new(800, 600);
-    //let film = Film::new(3840, 2160); (4K)
-    let mut image = ColorImage::new(film.width() as u32, film.height() as u32);
+/// Width and height of the tiles rendered in parallel.
+const TILE_SIZE: u32 = 32;
+
+/// Diffuse reflectance of every surface in clay renders.
+const CLAY: f32 = 0.5;
+
+/// Least time between rewrites of the preview of a render in progress.
+const PREVIEW_INTERVAL_MILLISECONDS: u64 = 500;
+
+/// How each pixel is rendered.
+struct RenderSettings<'a> {
+    sampler: &'a Sampler,
+    /// Check for invalid radiance, reporting it and rendering it as black instead of panicking.
+    sanitize: bool,
+    /// Name of a debug material to shade every entity with instead of its own.
+    override_material: Option<&'a str>,
+    /// Shade every entity with a neutral grey to judge lighting and geometry alone.
+    clay: bool,
+    bounce_limits: BounceLimits,
+    /// Seed for every random choice made while rendering each pixel, so renders can be repeated
+    /// exactly.
+    seed: u32,
+    /// Warn about materials reflecting more light than they receive before rendering.
+    audit_energy: bool,
+    /// Show a progress bar while rendering, then print the rays traced and how long each stage
+    /// took.
+    progress: bool,
+    /// File to keep rewriting with the tiles rendered so far, as a PPM in the standard view.
+    preview: Option<&'a str>,
+    /// Show the tiles rendered so far in a window, which needs the `window` feature.
+    window: bool,
+    bvh_layout: BvhLayout,
+    /// Radiance below which light from distant lights is ignored.
+    light_cutoff: Option<f32>,
+    /// Precision rendered radiance and environment maps are stored at.
+    precision: Precision,
+}
+
+impl<'a> RenderSettings<'a> {
+    /// Prepares a scene to be rendered with these settings.
+    fn configure(&self, mut scene: Scene) -> Scene {
+        if self.sanitize || cfg!(debug_assertions) {
+            scene.set_radiance_checks(true);
+        }
+        scene.set_bounce_limits(&self.bounce_limits);
+        if self.bvh_layout != BvhLayout::default() {
+            scene.set_bvh_layout(self.bvh_layout);
+        }
+        if self.light_cutoff.is_some() {
+            scene.set_light_cutoff(self.light_cutoff);
+        }
+        if self.precision != Precision::default() {
+            scene.set_texture_precision(self.precision);
+        }
+        match self.override_material {
+            Some("uv-checker") => {
+                scene.set_material_override(Some(Box::new(UvCheckerMaterial::new())))
+            }
+            Some(name) => panic!("Unknown override material: {}", name),
+            None => {}
+        }
+        if self.clay {
+            scene.set_material_override(Some(
+                Box::new(LambertianMaterial::new(&Spectrum::uniform(CLAY))),
+            ));
+        }
+        if self.audit_energy {
+            for (entity, excess) in scene.audit_energy() {
+                eprintln!(
+                    "Entity {} reflects more light than it receives, albedo {:?} when viewed \
+                     {:.0} degrees from its normal.",
+                    entity.index(),
+                    (excess.albedo.r, excess.albedo.g, excess.albedo.b),
+                    excess.view_cosine.acos().to_degrees()
+                );
+            }
+        }
+        scene
+    }
+
+    /// Prints the rays a scene traced and how long each stage of rendering it took, if progress
+    /// was requested.
+    fn report(&self, scene: &Scene, stages: Vec<(&'static str, Duration)>) {
+        if self.progress {
+            let stats = RenderStats {
+                stages: stages,
+                ..scene.render_stats()
+            };
+            print!("{}", stats);
+        }
+    }
+}
+
+/// Runs a stage of rendering, recording how long it took.
+fn timed<T, F: FnOnce() -> T>(
+    stages: &mut Vec<(&'static str, Duration)>,
+    name: &'static str,
+    stage: F,
+) -> T {
+    let start = Instant::now();
+    let result = stage();
+    stages.push((name, start.elapsed()));
+    result
+}
+
+/// Size and field of view of renders of built in scenes and models, whose cameras are not
+/// described in a scene file.
+struct FilmOptions {
+    width: u32,
+    height: u32,
+    /// Angle the camera sees across, or the default of each scene if `None`.
+    fov: Option<PlanarAngle>,
+}
+
+impl FilmOptions {
+    /// # Panics
+    /// If the width or height are invalid for a film.
+    fn film(&self) -> Film {
+        Film::try_new(self.width, self.height).unwrap_or_else(|e| {
+            panic!("Cannot render at {}x{}: {}", self.width, self.height, e)
+        })
+    }
+
+    /// The field of view, or `default` if none was chosen.
+    fn fov_or(&self, default: PlanarAngle) -> PlanarAngle {
+        self.fov.unwrap_or(default)
+    }
+}
+
+/// Field of view of the multiple spheres scene, unless another is chosen.
+const MULTIPLE_SPHERES_FOV_DEGREES: f32 = 90.0;
+
+/// Field of view models are framed within, unless another is chosen.
+const OBJ_FOV_DEGREES: f32 = 60.0;
+
+/// Field of view of the basic sphere scene, unless another is chosen.
+const BASIC_SPHERE_FOV_DEGREES: f32 = 45.0;
+
+/// How rendered radiance becomes an image, and what is reported about it.
+struct OutputOptions<'a> {
+    view: ViewTransform,
+    /// Scale radiance so the log-average luminance of each render is middle grey, in images
+    /// which are developed once complete.
+    auto_exposure: bool,
+    /// Show pixels with NaN or infinite radiance in magenta.
+    highlight_invalid: bool,
+    /// Print statistics of the rendered radiance once complete.
+    print_statistics: bool,
+    /// File to write a luminance histogram to, as comma separated values.
+    histogram_file_name: Option<&'a str>,
+    /// File to save the render to, a Radiance HDR image if it ends in `.hdr` or a PNG otherwise.
+    file_name: &'a str,
+    /// Settings shared by every render, completed and written next to each saved render.
+    manifest: RenderManifest,
+}
+
+impl<'a> OutputOptions<'a> {
+    /// Determines the displayed color of a pixel.
+    fn display(&self, radiance: &Spectrum, x: u32, y: u32) -> [u8; 3] {
+        if self.highlight_invalid && !output::is_valid(radiance) {
+            output::INVALID_COLOR
+        } else {
+            self.view.apply(radiance, x, y)
+        }
+    }
+
+    /// Prints or writes the statistics of a completed render, as requested.
+    fn report(&self, statistics: &ImageStatistics) {
+        if self.print_statistics {
+            print!("{}", statistics);
+        }
+        if let Some(file_name) = self.histogram_file_name {
+            let fout = File::create(&Path::new(file_name)).unwrap();
+            statistics
+                .write_histogram_csv(BufWriter::new(fout))
+                .expect("Unable to write histogram.");
+        }
+    }
+
+    /// Saves a render, keeping the full range of its radiance in HDR images.
+    fn save(&self, image: ColorImage, radiance: &RadianceBuffer) {
+        save_image(image, radiance, self.file_name);
+    }
+
+    /// Records how to reproduce a saved render, in a JSON file named after it.
+    ///
+    /// # Arguments
+    /// * `scene` - file the scene was loaded from, with every file read loading it, `None` for
+    ///   built in scenes
+    /// * `integrator` - how radiance was determined
+    fn write_manifest(
+        &self,
+        scene: Option<(&str, &[PathBuf])>,
+        width: u32,
+        height: u32,
+        integrator: &str,
+    ) {
+        let mut manifest = self.manifest.clone();
+        manifest.scene_file = scene.map(|(f, _)| f.to_string());
+        manifest.scene_hash = scene.map(|(f, files)| {
+            output::hash_files(files).unwrap_or_else(|e| panic!("Unable to hash {}: {}", f, e))
+        });
+        manifest.width = width;
+        manifest.height = height;
+        manifest.integrator = integrator.to_string();
+
+        let fout = File::create(Path::new(self.file_name).with_extension("json")).unwrap();
+        manifest
+            .write_json(BufWriter::new(fout))
+            .expect("Unable to write render manifest.");
+    }
+
+    /// Saves one pass of a render, next to where the render itself is saved.
+    fn save_pass(&self, name: &str, image: ColorImage, radiance: &RadianceBuffer) {
+        let path = Path::new(self.file_name);
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("scene");
+        let file_name = match path.extension().and_then(|e| e.to_str()) {
+            Some(extension) => format!("{}_{}.{}", stem, name, extension),
+            None => format!("{}_{}", stem, name),
+        };
+        save_image(image, radiance, &path.with_file_name(file_name).to_string_lossy());
+    }
+}
+
+/// Whether a file should be written as a Radiance HDR image.
+fn is_hdr_file(file_name: &str) -> bool {
+    Path::new(file_name).extension().map_or(false, |e| e == "hdr")
+}
+
+fn render_multiple_spheres(
+    background: Option<&str>,
+    stream_file_name: Option<&str>,
+    deep: Option<(&str, usize)>,
+    overlay: bool,
+    film_options: &FilmOptions,
+    settings: &RenderSettings,
+    output: &OutputOptions,
+) {
+    let film = film_options.film();
+    let fov = film_options.fov_or(PlanarAngle::Degrees(MULTIPLE_SPHERES_FOV_DEGREES));
+
+    if let Some((file_name, max_samples)) = deep {
+        write_deep(
+            &create_default_camera(&film, fov),
+            &build_scene(),
+            file_name,
+            max_samples,
+        );
+    }
+
+    let backplate = background.map(|file_name| {
+        Backplate::open(Path::new(file_name), &film).expect("Unable to load background image.")
+    });
+
+    if let Some(file_name) = stream_file_name {
+        let fout = File::create(&Path::new(file_name)).unwrap();
+        let writer = PpmStreamWriter::new(BufWriter::new(fout), film.width(), film.height())
+            .unwrap();
+        ray_cast_streamed(
+            create_default_camera(&film, fov),
+            settings.configure(build_scene()),
+            backplate.as_ref(),
+            settings,
+            output,
+            writer,
+        );
+        return;
+    }
+
+    let mut stages = Vec::new();
+    let camera = create_default_camera(&film, fov);
+    let scene = timed(&mut stages, "build", || settings.configure(build_scene()));
+    let radiance = timed(&mut stages, "render", || {
+        ray_cast(&camera, &scene, backplate.as_ref(), settings)
+    });
+    timed(&mut stages, "save", || {
+        let mut image = ColorImage::new(film.width(), film.height());
+        develop(&radiance, output, &mut image);
+        if overlay {
+            draw_overlay(&camera, &scene, &mut image);
+        }
+
+        output.save(image, &radiance);
+        output.write_manifest(None, film.width(), film.height(), "path");
+    });
+    settings.report(&scene, stages);
+}
+
+/// Renders a single sphere lit by a single light, the least a working renderer must get right.
+///
+/// Renders quickly, so it serves as a check that rendering works end to end, for example:
+///
+/// ```text
+/// rust_ray_tracer --output sphere.png --width 200 --height 200 basic_sphere
+/// ```
+fn render_basic_sphere(
+    film_options: &FilmOptions,
+    settings: &RenderSettings,
+    output: &OutputOptions,
+) {
+    let film = film_options.film();
+    let fov = film_options.fov_or(PlanarAngle::Degrees(BASIC_SPHERE_FOV_DEGREES));
+
+    let mut stages = Vec::new();
+    let camera = create_default_camera(&film, fov);
+    let scene = timed(&mut stages, "build", || settings.configure(build_basic_sphere_scene()));
+    let radiance = timed(&mut stages, "render", || ray_cast(&camera, &scene, None, settings));
+    timed(&mut stages, "save", || {
+        let mut image = ColorImage::new(film.width(), film.height());
+        develop(&radiance, output, &mut image);
+        output.save(image, &radiance);
+        output.write_manifest(None, film.width(), film.height(), "path");
+    });
+    settings.report(&scene, stages);
+}
+
+/// Renders a model from an OBJ file, framed to fill the view.
+fn render_obj(
+    file_name: &str,
+    overlay: bool,
+    toon: bool,
+    wireframe: Option<&WireframeSettings>,
+    aov: Option<Aov>,
+    passes: bool,
+    split: bool,
+    film_options: &FilmOptions,
+    settings: &RenderSettings,
+    output: &OutputOptions,
+) {
+    let film = film_options.film();
+    let files = [PathBuf::from(file_name)];
+    let mut stages = Vec::new();
+    let scene = timed(&mut stages, "load", || {
+        let mut scene = Scene::new();
+        scene.add_light(Box::new(DirectionalLight::new(
+            &Vector::new(-0.5, -1.0, 1.0),
+            &Spectrum::uniform(1.0),
+        )));
+        obj::add_to_scene(
+            &mut scene,
+            file_name,
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(0.8))),
+            Transform::identity(),
+        ).unwrap_or_else(|e| panic!("Unable to load {}: {}", file_name, e));
+        scene.finalize();
+        settings.configure(scene)
+    });
+
+    let bounds = scene.bounds().expect("Model has no vertices.");
+    let camera = Camera::frame_scene(
+        &film,
+        &bounds,
+        film_options.fov_or(PlanarAngle::Degrees(OBJ_FOV_DEGREES)),
+        &Vector::new(0.0, -0.25, 1.0),
+    );
+    if let Some(aov) = aov {
+        write_aov(&camera, &scene, aov, output);
+        output.write_manifest(
+            Some((file_name, &files)),
+            camera.raster_width(),
+            camera.raster_height(),
+            aov_name(aov),
+        );
+        return;
+    }
+
+    let radiance = timed(&mut stages, "render", || if toon {
+        RadianceBuffer::from(toon::render(&camera, &scene, &ToonSettings::default()))
+    } else if let Some(wireframe) = wireframe {
+        RadianceBuffer::from(wireframe::render(&camera, &scene, wireframe))
+    } else {
+        ray_cast(&camera, &scene, None, settings)
+    });
+    timed(&mut stages, "save", || {
+        let mut image = ColorImage::new(film.width(), film.height());
+        develop(&radiance, output, &mut image);
+        if overlay {
+            draw_overlay(&camera, &scene, &mut image);
+        }
+        output.save(image, &radiance);
+        output.write_manifest(
+            Some((file_name, &files)),
+            camera.raster_width(),
+            camera.raster_height(),
+            integrator_name(toon, wireframe),
+        );
+    });
+    if passes {
+        timed(&mut stages, "passes", || write_passes(&camera, &scene, settings, output));
+    }
+    if split {
+        timed(&mut stages, "split", || write_split(&camera, &scene, settings, output));
+    }
+    settings.report(&scene, stages);
+}
+
+/// Name of how radiance is determined, for render manifests.
+fn integrator_name(toon: bool, wireframe: Option<&WireframeSettings>) -> &'static str {
+    if toon {
+        "toon"
+    } else if wireframe.is_some() {
+        "wireframe"
+    } else {
+        "path"
+    }
+}
+
+/// Name of a diagnostic image, for render manifests and the command line.
+fn aov_name(aov: Aov) -> &'static str {
+    match aov {
+        Aov::Normal => "normal",
+        Aov::Depth => "depth",
+        Aov::Albedo => "albedo",
+        Aov::Motion => "motion",
+    }
+}
+
+/// Reads which diagnostic image to write instead of a render from a subcommand, if any.
+fn aov_setting(matches: &ArgMatches) -> Option<Aov> {
+    matches.value_of("aov").map(|name| match name {
+        "normal" => Aov::Normal,
+        "depth" => Aov::Depth,
+        "albedo" => Aov::Albedo,
+        "motion" => Aov::Motion,
+        _ => panic!("Unknown AOV: {}", name),
+    })
+}
+
+/// Writes a diagnostic image of the surfaces seen through each pixel instead of a render.
+///
+/// # Panics
+/// If saving to an HDR image, since diagnostic images are only colors.
+fn write_aov(camera: &Camera, scene: &Scene, aov: Aov, output: &OutputOptions) {
+    if is_hdr_file(output.file_name) {
+        panic!("AOV images are saved as PNGs, not HDR images.");
+    }
+    let colors = aov::render(camera, scene, aov);
+    let mut image = ColorImage::new(camera.raster_width(), camera.raster_height());
+    for (i, color) in colors.iter().enumerate() {
+        let (x, y) = (i as u32 % image.width(), i as u32 / image.width());
+        image.put_pixel(x, y, image::Rgb(*color));
+    }
+    write_image(image, output.file_name);
+}
+
+/// Reads wireframe settings from a subcommand, if a wireframe was requested.
+fn wireframe_settings(matches: &ArgMatches) -> Option<WireframeSettings> {
+    if !matches.is_present("wireframe") {
+        return None;
+    }
+    let line_width = match matches.value_of("line-width").unwrap_or("1").parse::<f32>() {
+        Ok(width) if width > 0.0 => width,
+        _ => panic!("Line width must be a positive number of pixels."),
+    };
+    Some(WireframeSettings {
+        line_width: line_width,
+        ..WireframeSettings::default()
+    })
+}
+
+/// Renders a scene described in a file.
+fn render_scene_file(
+    file_name: &str,
+    overlay: bool,
+    toon: bool,
+    wireframe: Option<&WireframeSettings>,
+    aov: Option<Aov>,
+    passes: bool,
+    split: bool,
+    settings: &RenderSettings,
+    output: &OutputOptions,
+) {
+    let mut stages = Vec::new();
+    let loaded = timed(&mut stages, "load", || {
+        description::load(file_name).unwrap_or_else(|e| {
+            panic!("Unable to load {}: {}", file_name, e)
+        })
+    });
+    for warning in loaded.warnings.iter() {
+        eprintln!("{}: {}", file_name, warning);
+    }
+    let camera = loaded.camera;
+    let files = loaded.files;
+    let scene = settings.configure(loaded.scene);
+    if let Some(aov) = aov {
+        write_aov(&camera, &scene, aov, output);
+        output.write_manifest(
+            Some((file_name, &files)),
+            camera.raster_width(),
+            camera.raster_height(),
+            aov_name(aov),
+        );
+        return;
+    }
+
+    let radiance = timed(&mut stages, "render", || if toon {
+        RadianceBuffer::from(toon::render(&camera, &scene, &ToonSettings::default()))
+    } else if let Some(wireframe) = wireframe {
+        RadianceBuffer::from(wireframe::render(&camera, &scene, wireframe))
+    } else {
+        ray_cast(&camera, &scene, None, settings)
+    });
+    timed(&mut stages, "save", || {
+        let mut image = ColorImage::new(camera.raster_width(), camera.raster_height());
+        develop(&radiance, output, &mut image);
+        if overlay {
+            draw_overlay(&camera, &scene, &mut image);
+        }
+        output.save(image, &radiance);
+        output.write_manifest(
+            Some((file_name, &files)),
+            camera.raster_width(),
+            camera.raster_height(),
+            integrator_name(toon, wireframe),
+        );
+    });
+    if passes {
+        timed(&mut stages, "passes", || write_passes(&camera, &scene, settings, output));
+    }
+    if split {
+        timed(&mut stages, "split", || write_split(&camera, &scene, settings, output));
+    }
+    settings.report(&scene, stages);
+}
+
+/// Prints the camera and everything in a scene described in a file.
+fn inspect_scene_file(file_name: &str) {
+    let loaded = description::load(file_name).unwrap_or_else(|e| {
+        panic!("Unable to load {}: {}", file_name, e)
+    });
+    for warning in loaded.warnings.iter() {
+        eprintln!("{}: {}", file_name, warning);
+    }
+    println!("camera: {}", loaded.camera);
+    print!("{}", loaded.scene.dump());
+}
+
+/// Reads sample points from a file, one point per line.
+fn load_points(file_name: &str) -> Vec<Point> {
+    let file = File::open(file_name).unwrap_or_else(|e| {
+        panic!("Unable to open {}: {}", file_name, e)
+    });
+    visibility::read_points(BufReader::new(file)).unwrap_or_else(|e| {
+        panic!("Unable to read points from {}: {}", file_name, e)
+    })
+}
+
+/// Writes which points can see each other in a scene described in a file.
+///
+/// # Arguments
+/// * `from_file`, `to_file` - points to look from and to, or among `from_file` alone if there
+///   is no `to_file`
+/// * `matrix_file` - where to write the matrix, or standard output if `None`
+fn write_visibility(
+    scene_file: &str,
+    from_file: &str,
+    to_file: Option<&str>,
+    matrix_file: Option<&str>,
+) {
+    let loaded = description::load(scene_file).unwrap_or_else(|e| {
+        panic!("Unable to load {}: {}", scene_file, e)
+    });
+    for warning in loaded.warnings.iter() {
+        eprintln!("{}: {}", scene_file, warning);
+    }
+    let from = load_points(from_file);
+    let matrix = match to_file {
+        Some(to_file) => VisibilityMatrix::between(&loaded.scene, &from, &load_points(to_file)),
+        None => VisibilityMatrix::among(&loaded.scene, &from),
+    };
+    let written = match matrix_file {
+        Some(matrix_file) => {
+            let fout = File::create(&Path::new(matrix_file)).unwrap();
+            matrix.write(&mut BufWriter::new(fout))
+        }
+        None => matrix.write(&mut std::io::stdout()),
+    };
+    written.expect("Unable to write visibility matrix.");
+}
+
+/// Writes the nearest surfaces seen through each pixel into a deep image.
+fn write_deep(camera: &Camera, scene: &Scene, file_name: &str, max_samples: usize) {
+    let fout = File::create(&Path::new(file_name)).unwrap();
+    let mut writer = DeepWriter::new(
+        BufWriter::new(fout),
+        camera.raster_width(),
+        camera.raster_height(),
+    ).unwrap();
+    for y in 0..camera.raster_height() {
+        for x in 0..camera.raster_width() {
+            let samples = scene.deep_samples(&camera.generate_ray(x, y), max_samples);
+            writer.write_pixel(&samples).expect("Unable to write deep pixel.");
+        }
+    }
+    writer.finish().expect("Unable to finish writing deep image.");
+}
+
+/// Hands out tiles of the multiple spheres scene to workers, and saves the result.
+///
+/// Workers must render at the same size.
+fn coordinate_multiple_spheres(
+    address: &str,
+    worker_timeout: Duration,
+    film_options: &FilmOptions,
+    output: &OutputOptions,
+) {
+    let film = film_options.film();
+    let listener = TcpListener::bind(address).expect("Unable to listen for workers.");
+    println!("Waiting for workers on {}", address);
+
+    let pixels =
+        network::coordinate(listener, film.width(), film.height(), TILE_SIZE, worker_timeout)
+            .expect("Distributed render failed.");
+
+    let mut image = ColorImage::new(film.width(), film.height());
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        *pixel = image::Rgb(pixels[(y * film.width() + x) as usize]);
+    }
+    write_image(image, output.file_name);
+}
 
-    ray_cast(create_default_camera(&film), build_scene(), &mut image);
+/// Renders tiles of the multiple spheres scene for a coordinator.
+fn work_on_multiple_spheres(
+    address: &str,
+    film_options: &FilmOptions,
+    settings: &RenderSettings,
+    output: &OutputOptions,
+) {
+    let film = film_options.film();
+    let fov = film_options.fov_or(PlanarAngle::Degrees(MULTIPLE_SPHERES_FOV_DEGREES));
+    let camera = create_default_camera(&film, fov);
+    let scene = settings.configure(build_scene());
 
-    write_image(image, "scene.png");
+    let stream = TcpStream::connect(address).expect("Unable to connect to coordinator.");
+    let tiles_rendered = network::work(stream, |tile: &Tile| {
+        let radiance = render_tile(&camera, &scene, None, settings, tile);
+        tile_pixels(tile)
+            .zip(radiance.iter())
+            .map(|((x, y), r)| output.display(r, x, y))
+            .collect()
+    }).expect("Lost connection to coordinator.");
+    println!("Rendered {} tiles.", tiles_rendered);
 }
 
 fn print_view_frustum_corners(film: &Film, camera: &Camera, near: f32, far: f32) {
     // Print the corners of the view frustum
     println!("Rendered world outer points.");
     let corners = [
-        (0, film.width() as u32),
         (0, 0),
-        (film.height() as u32, 0),
-        (film.height() as u32, film.width() as u32),
+        (film.width(), 0),
+        (film.width(), film.height()),
+        (0, film.height()),
     ];
 
     for &pair in corners.iter() {
@@ -47,46 +718,62 @@ fn print_view_frustum_corners(film: &Film, camera: &Camera, near: f32, far: f32)
     }
 }
 
-fn create_default_camera(film: &Film) -> Camera {
+fn create_default_camera(film: &Film, fov: PlanarAngle) -> Camera {
     let near = 1.0;
     let far = 1000.0;
-    let projection = Perspective::new(near, far, PlanarAngle::Degrees(90.0));
+    let projection = Perspective::new(near, far, fov);
     let c = Camera::new(film, &projection);
 
     print_view_frustum_corners(film, &c, near, far);
     c
 }
 
+/// A white sphere in front of the camera, lit from above and behind the camera.
+fn build_basic_sphere_scene() -> Scene {
+    let mut scene = Scene::new();
+    scene.add_light(Box::new(PointLight::new(
+        Point::new(-10.0, 10.0, -10.0),
+        800.0 * Spectrum::uniform(1.0),
+    )));
+    scene.add_entity(
+        Box::new(Sphere::new_with_radius(5.0)),
+        Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+        Transform::translate(0.0, 0.0, 20.0),
+    );
+    scene.finalize();
+    scene
+}
+
 fn build_scene() -> Scene {
     let mut scene = Scene::new();
 
     // LIGHTS!
     scene.add_light(Box::new(DirectionalLight::new(
         &Vector::new(0.0, -1.0, 0.0),
-        &Vector::new(1.0, 1.0, 1.0),
+        &Spectrum::uniform(1.0),
     )));
 
     scene.add_light(Box::new(PointLight::new(
         Point::new(0.0, 20.0, 30.0),
-        1.0 * Vector::new(1.0, 1.0, 1.0),
+        400.0 * Spectrum::uniform(1.0),
     )));
 
     // OBJECTS
     scene.add_entity(
         Box::new(Sphere::new_with_radius(5.0)),
-        Box::new(LambertianMaterial::new(&Vector::new(1.0, 1.0, 1.0))),
-        Matrix4x4::translate(0.0, 0.0, 30.0),
+        Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+        Transform::translate(0.0, 0.0, 30.0),
     );
 
     scene.add_entity(
         Box::new(Sphere::new_with_radius(5.0)),
-        Box::new(LambertianMaterial::new(&Vector::new(1.0, 1.0, 1.0))),
-        Matrix4x4::translate(0.0, 10.0, 30.0),
+        Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+        Transform::translate(0.0, 10.0, 30.0),
     );
     scene.add_entity(
         Box::new(Sphere::new_with_radius(5.0)),
-        Box::new(LambertianMaterial::new(&Vector::new(1.0, 1.0, 1.0))),
-        Matrix4x4::translate(10.0, 0.0, 30.0),
+        Box::new(SpecularMaterial::new(&Spectrum::uniform(0.9))),
+        Transform::translate(10.0, 0.0, 30.0),
     );
 
     scene.add_entity(
@@ -94,28 +781,378 @@ fn build_scene() -> Scene {
             &Vector::new(0.0, 1.0, 0.0),
             &Point::new(0.0, -5.0, 30.0),
         )),
-        Box::new(LambertianMaterial::new(&Vector::new(0.2, 0.2, 0.2))),
-        Matrix4x4::identity(),
+        Box::new(LambertianMaterial::new(&Spectrum::uniform(0.2))),
+        Transform::identity(),
     );
 
+    scene.finalize();
     scene
 }
 
-fn ray_cast(camera: Camera, scene: Scene, image: &mut ColorImage) {
-    // Generates samples for all film points.
+/// A ray through a position on the raster, and through a random point on the lens and at a
+/// random time the shutter is open, for cameras which have them.
+fn sample_ray<R: Rng>(camera: &Camera, rng: &mut R, x: f32, y: f32) -> Ray {
+    let mut ray = if camera.has_lens() {
+        camera.generate_ray_through_lens(x, y, (rng.gen(), rng.gen()))
+    } else {
+        camera.generate_ray_sample(x, y)
+    };
+    if camera.has_motion_blur() {
+        ray.time = camera.shutter_time(rng.gen());
+    }
+    ray
+}
+
+/// Determines the radiance of a single pixel, averaging the samples taken within it.
+///
+/// # Panics
+/// If invalid radiance is found while not sanitizing, which is only checked for in debug builds.
+fn shade_pixel(
+    camera: &Camera,
+    scene: &Scene,
+    backplate: Option<&Backplate>,
+    settings: &RenderSettings,
+    x: u32,
+    y: u32,
+) -> Spectrum {
+    let mut rng = pixel_rng(settings.seed, x, y);
+    let offsets = settings.sampler.pixel_offsets(&mut rng);
+
+    let mut shade = Spectrum::black();
+    for &(dx, dy) in offsets.iter() {
+        let ray = sample_ray(camera, &mut rng, x as f32 + dx, y as f32 + dy);
+        let traced = match backplate {
+            Some(backplate) => scene
+                .try_trace_primary(&ray, &mut rng)
+                .map(|radiance| radiance.unwrap_or_else(|| backplate.at(x, y))),
+            None => scene.try_trace(&ray, &mut rng),
+        };
+        shade = shade + checked(scene, settings, x, y, traced, Spectrum::black());
+    }
+    shade / offsets.len() as f32
+}
+
+/// The radiance traced for a pixel, reporting invalid radiance found by radiance checks.
+///
+/// # Arguments
+/// * `invalid` - what to render invalid radiance as when sanitizing.
+///
+/// # Panics
+/// If invalid radiance is found without sanitizing, with the pixel and a dump of the scene.
+fn checked<T>(
+    scene: &Scene,
+    settings: &RenderSettings,
+    x: u32,
+    y: u32,
+    traced: Result<T, InvalidRadiance>,
+    invalid: T,
+) -> T {
+    traced.unwrap_or_else(|e| {
+        if !settings.sanitize {
+            panic!("Pixel ({}, {}): {}\n{}", x, y, e, scene.dump());
+        }
+        eprintln!("Pixel ({}, {}): {}", x, y, e);
+        invalid
+    })
+}
+
+/// Determines the radiance of a pixel split into passes, like `shade_pixel`.
+fn shade_pixel_passes(
+    camera: &Camera,
+    scene: &Scene,
+    settings: &RenderSettings,
+    x: u32,
+    y: u32,
+) -> RadiancePasses {
+    let mut rng = pixel_rng(settings.seed, x, y);
+    let offsets = settings.sampler.pixel_offsets(&mut rng);
+
+    let mut passes = RadiancePasses::new();
+    for &(dx, dy) in offsets.iter() {
+        let ray = sample_ray(camera, &mut rng, x as f32 + dx, y as f32 + dy);
+        let traced = scene.try_trace_passes(&ray, &mut rng);
+        passes = passes + checked(scene, settings, x, y, traced, RadiancePasses::new());
+    }
+    passes / offsets.len() as f32
+}
+
+/// Determines the radiance of a pixel from two independent halves of its samples, like
+/// `shade_pixel`.
+///
+/// Samples alternate between the halves, so with an odd count the first half has one more.
+fn shade_pixel_split(
+    camera: &Camera,
+    scene: &Scene,
+    settings: &RenderSettings,
+    x: u32,
+    y: u32,
+) -> SplitRadiance {
+    let mut rng = pixel_rng(settings.seed, x, y);
+    let offsets = settings.sampler.pixel_offsets(&mut rng);
+
+    let mut halves = [Spectrum::black(), Spectrum::black()];
+    for (i, &(dx, dy)) in offsets.iter().enumerate() {
+        let ray = sample_ray(camera, &mut rng, x as f32 + dx, y as f32 + dy);
+        let traced = scene.try_trace(&ray, &mut rng);
+        halves[i % 2] = halves[i % 2] + checked(scene, settings, x, y, traced, Spectrum::black());
+    }
+    let first = (offsets.len() + 1) / 2;
+    SplitRadiance {
+        a: halves[0] / first as f32,
+        b: halves[1] / (offsets.len() - first) as f32,
+    }
+}
+
+/// Renders every pixel as two independent halves of its samples, and writes each half and the
+/// estimated error of their average to their own images.
+///
+/// # Panics
+/// If there are fewer than two samples per pixel to split.
+fn write_split(camera: &Camera, scene: &Scene, settings: &RenderSettings, output: &OutputOptions) {
+    if settings.sampler.samples_per_pixel() < 2 {
+        panic!("Split rendering needs at least two samples per pixel.");
+    }
+    let (width, height) = (camera.raster_width(), camera.raster_height());
+    let rows: Vec<u32> = (0..height).collect();
+    let pixels: Vec<Vec<SplitRadiance>> = rows.par_iter()
+        .map(|&y| {
+            (0..width)
+                .map(|x| shade_pixel_split(camera, scene, settings, x, y))
+                .collect()
+        })
+        .collect();
+    let pixels: Vec<SplitRadiance> = pixels.into_iter().flat_map(|row| row).collect();
+
+    match output::relative_error(&pixels) {
+        Some(error) => println!("Estimated relative error: {:.4}", error),
+        None => println!("Estimated relative error: undefined for a black image"),
+    }
+
+    let selectors: [(&str, fn(&SplitRadiance) -> Spectrum); 3] = [
+        ("a", |p| p.a),
+        ("b", |p| p.b),
+        ("error", |p| {
+            let error = p.estimated_error();
+            Spectrum::uniform(error)
+        }),
+    ];
+    for &(name, select) in selectors.iter() {
+        let radiance = RadianceBuffer::from(pixels.iter().map(select).collect::<Vec<Spectrum>>());
+        let mut image = ColorImage::new(width, height);
+        develop(&radiance, output, &mut image);
+        output.save_pass(name, image, &radiance);
+    }
+}
+
+/// Renders the radiance of every pixel split into passes, and writes each pass to its own image.
+fn write_passes(camera: &Camera, scene: &Scene, settings: &RenderSettings, output: &OutputOptions) {
+    let (width, height) = (camera.raster_width(), camera.raster_height());
+    let rows: Vec<u32> = (0..height).collect();
+    let passes: Vec<Vec<RadiancePasses>> = rows.par_iter()
+        .map(|&y| {
+            (0..width)
+                .map(|x| shade_pixel_passes(camera, scene, settings, x, y))
+                .collect()
+        })
+        .collect();
+    let passes: Vec<RadiancePasses> = passes.into_iter().flat_map(|row| row).collect();
+
+    let selectors: [(&str, fn(&RadiancePasses) -> Spectrum); 5] = [
+        ("direct", |p| p.direct),
+        ("indirect", |p| p.indirect),
+        ("deeper", |p| p.deeper),
+        ("diffuse", |p| p.diffuse),
+        ("specular", |p| p.specular),
+    ];
+    for &(name, select) in selectors.iter() {
+        let radiance = RadianceBuffer::from(passes.iter().map(select).collect::<Vec<Spectrum>>());
+        let mut image = ColorImage::new(width, height);
+        develop(&radiance, output, &mut image);
+        output.save_pass(name, image, &radiance);
+    }
+}
+
+/// Positions of the pixels in a tile, in row-major order.
+fn tile_pixels<'a>(tile: &'a Tile) -> Box<Iterator<Item = (u32, u32)> + 'a> {
+    Box::new((tile.y..(tile.y + tile.height)).flat_map(move |y| {
+        (tile.x..(tile.x + tile.width)).map(move |x| (x, y))
+    }))
+}
+
+/// Determines the radiance of the pixels in a tile, in row-major order.
+fn render_tile(
+    camera: &Camera,
+    scene: &Scene,
+    backplate: Option<&Backplate>,
+    settings: &RenderSettings,
+    tile: &Tile,
+) -> Vec<Spectrum> {
+    tile_pixels(tile)
+        .map(|(x, y)| shade_pixel(camera, scene, backplate, settings, x, y))
+        .collect()
+}
+
+/// Renders the radiance of every pixel, in row-major order, stored at the precision of the
+/// settings.
+fn ray_cast(
+    camera: &Camera,
+    scene: &Scene,
+    backplate: Option<&Backplate>,
+    settings: &RenderSettings,
+) -> RadianceBuffer {
+    // Generates samples for all film points, a tile at a time on each thread.
     // (0, 0) is the top left corner.
-    for (x, y, pixel) in image.enumerate_pixels_mut() {
-        let ray = camera.generate_ray(x, y);
-
-        let shade = scene.trace(&ray);
-        *pixel = image::Rgb(
-            [
-                (shade[Axis::X] * 255.0).min(255.0) as u8,
-                (shade[Axis::Y] * 255.0).min(255.0) as u8,
-                (shade[Axis::Z] * 255.0).min(255.0) as u8,
-            ],
-        );
+    let (width, height) = (camera.raster_width(), camera.raster_height());
+    let tiles = Tile::split(width, height, TILE_SIZE);
+    let framebuffer = Mutex::new(RadianceBuffer::new(
+        (width * height) as usize,
+        settings.precision,
+    ));
+    let progress = if settings.progress {
+        Some(ProgressBar::stderr(tiles.len()))
+    } else {
+        None
+    };
+    let preview = Mutex::new(settings.preview.map(|file_name| {
+        Preview::new(
+            Path::new(file_name),
+            width,
+            height,
+            Duration::from_millis(PREVIEW_INTERVAL_MILLISECONDS),
+        )
+    }));
+    let (window_tiles, finished_tiles) = mpsc::channel();
+    let window_tiles = Mutex::new(window_tiles);
+    let render = || tiles.par_iter().for_each(|tile| {
+        let radiance = render_tile(camera, scene, backplate, settings, tile);
+
+        {
+            let mut framebuffer = framebuffer.lock().unwrap();
+            for ((x, y), r) in tile_pixels(tile).zip(radiance.iter()) {
+                framebuffer.set((y * width + x) as usize, r);
+            }
+        }
+        if let Some(ref mut preview) = *preview.lock().unwrap() {
+            for ((x, y), r) in tile_pixels(tile).zip(radiance.iter()) {
+                preview.set_pixel(x, y, ViewTransform::Standard.apply(r, x, y));
+            }
+            preview.refresh().expect("Unable to write preview.");
+        }
+        if settings.window {
+            let colors = tile_pixels(tile)
+                .zip(radiance.iter())
+                .map(|((x, y), r)| ViewTransform::Standard.apply(r, x, y))
+                .collect::<Vec<_>>();
+            window_tiles.lock().unwrap().send((*tile, colors)).unwrap();
+        }
+        if let Some(ref progress) = progress {
+            progress.tick();
+        }
+    });
+    if settings.window {
+        show_in_window(width, height, &finished_tiles, render);
+    } else {
+        render();
     }
+    if let Some(ref mut preview) = *preview.lock().unwrap() {
+        preview.write().expect("Unable to write preview.");
+    }
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+    framebuffer.into_inner().unwrap()
+}
+
+/// Runs `render` on another thread, showing the colors of each tile it sends in a window.
+///
+/// The window shows the render in progress, so it closes once rendering finishes.  Closing it
+/// earlier leaves the render running without it.
+#[cfg(feature = "window")]
+fn show_in_window<F: FnOnce() + Send>(
+    width: u32,
+    height: u32,
+    tiles: &mpsc::Receiver<(Tile, Vec<[u8; 3]>)>,
+    render: F,
+) {
+    let mut window = PreviewWindow::new("rust_ray_tracer", width, height)
+        .expect("Unable to open a window.");
+    thread::scope(|scope| {
+        let rendering = scope.spawn(render);
+        while !rendering.is_finished() && window.is_open() {
+            for (tile, colors) in tiles.try_iter() {
+                for ((x, y), color) in tile_pixels(&tile).zip(colors) {
+                    window.set_pixel(x, y, color);
+                }
+            }
+            // Redrawing waits long enough between frames not to busy the thread.
+            window.refresh().expect("Unable to draw the window.");
+        }
+    });
+}
+
+#[cfg(not(feature = "window"))]
+fn show_in_window<F: FnOnce() + Send>(
+    _width: u32,
+    _height: u32,
+    _tiles: &mpsc::Receiver<(Tile, Vec<[u8; 3]>)>,
+    _render: F,
+) {
+    panic!("Showing renders in a window needs the window feature, see --preview for a file.");
+}
+
+/// Converts the radiance of every pixel into the colors of an image, in row-major order.
+///
+/// Statistics are of the radiance as rendered, before any auto exposure.
+fn develop(radiance: &RadianceBuffer, output: &OutputOptions, image: &mut ColorImage) {
+    let width = image.width();
+    let exposure = if output.auto_exposure {
+        let exposure = output::auto_exposure(radiance.iter());
+        println!("Auto exposure: x{:.4} ({:+.2} stops)", exposure, exposure.log2());
+        exposure
+    } else {
+        1.0
+    };
+    let mut statistics = ImageStatistics::new();
+    for (i, r) in radiance.iter().enumerate() {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        statistics.add(&r);
+        image.put_pixel(x, y, image::Rgb(output.display(&(exposure * r), x, y)));
+    }
+    output.report(&statistics);
+}
+
+/// Draws the world axes, ground grid, lights and entity bounds over a render.
+fn draw_overlay(camera: &Camera, scene: &Scene, image: &mut ColorImage) {
+    let (width, height) = (image.width(), image.height());
+    Overlay::for_scene(scene).draw(camera, |x, y, color| if x < width && y < height {
+        image.put_pixel(x, y, image::Rgb(color));
+    });
+}
+
+/// Renders one row at a time, handing off each to the writer as it completes so only a single row
+/// is ever held in memory.
+fn ray_cast_streamed<W: Write>(
+    camera: Camera,
+    scene: Scene,
+    backplate: Option<&Backplate>,
+    settings: &RenderSettings,
+    output: &OutputOptions,
+    mut writer: PpmStreamWriter<W>,
+) {
+    let (width, height) = (camera.raster_width(), camera.raster_height());
+    let mut row = Vec::with_capacity(width as usize);
+    let mut statistics = ImageStatistics::new();
+    for y in 0..height {
+        row.clear();
+        for x in 0..width {
+            let radiance = shade_pixel(&camera, &scene, backplate, settings, x, y);
+            statistics.add(&radiance);
+            row.push(output.display(&radiance, x, y));
+        }
+        writer.write_row(&row).expect("Unable to write image row.");
+    }
+    writer.finish().expect("Unable to finish writing image.");
+    output.report(&statistics);
 }
 
 fn write_image(image: ColorImage, file_name: &str) {
@@ -123,20 +1160,699 @@ fn write_image(image: ColorImage, file_name: &str) {
     let _ = image::ImageRgb8(image).save(fout, image::PNG);
 }
 
+/// Writes the radiance of a render to an HDR image, or its displayed colors to a PNG.
+fn save_image(image: ColorImage, radiance: &RadianceBuffer, file_name: &str) {
+    if is_hdr_file(file_name) {
+        let fout = File::create(&Path::new(file_name)).unwrap();
+        output::write_hdr(BufWriter::new(fout), image.width(), image.height(), radiance.iter())
+            .expect("Unable to write HDR image.");
+    } else {
+        write_image(image, file_name);
+    }
+}
+
 fn main() {
     let matches = App::new("Rust Ray Tracer")
         .version("1.0")
         .about("Basic ray tracing renderer, written in Rust.")
+        .arg(
+            Arg::with_name("threads")
+                .long("threads")
+                .value_name("COUNT")
+                .help("Number of threads to render with, defaults to one per CPU")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("spp")
+                .long("spp")
+                .value_name("COUNT")
+                .help("Number of samples to average in each pixel")
+                .default_value("1")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("sampler")
+                .long("sampler")
+                .value_name("SAMPLER")
+                .help("How to place samples within each pixel")
+                .possible_values(&["uniform", "jittered", "stratified", "halton"])
+                .default_value("uniform")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("bvh")
+                .long("bvh")
+                .value_name("LAYOUT")
+                .help(
+                    "Node layout of bounding volume hierarchies, quantized-wide uses less memory \
+                     for large meshes",
+                )
+                .possible_values(&["binary", "quantized-wide"])
+                .default_value("binary")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("half-precision")
+                .long("half-precision")
+                .help(
+                    "Store rendered radiance and environment maps as 16 bit floats, halving their \
+                     memory for very large renders",
+                ),
+        )
+        .arg(
+            Arg::with_name("light-cutoff")
+                .long("light-cutoff")
+                .value_name("RADIANCE")
+                .help(
+                    "Ignore lights too far away to light surfaces more than this, to speed up \
+                     scenes with many lights",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .value_name("SEED")
+                .help(
+                    "Seed for every random choice made while rendering, chosen randomly and \
+                     recorded in the render manifest if not given",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("view")
+                .long("view")
+                .value_name("VIEW")
+                .help(
+                    "How to display rendered radiance, false-color and zebra check exposure",
+                )
+                .possible_values(&["standard", "false-color", "zebra"])
+                .default_value("standard")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("width")
+                .long("width")
+                .value_name("PIXELS")
+                .help("Width of renders of built in scenes and models")
+                .default_value("800")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("height")
+                .long("height")
+                .value_name("PIXELS")
+                .help("Height of renders of built in scenes and models")
+                .default_value("600")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("fov")
+                .long("fov")
+                .value_name("DEGREES")
+                .help(
+                    "Angle the camera sees across built in scenes and models, scene files \
+                     describe their own cameras",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-bounces")
+                .long("max-bounces")
+                .alias("max-depth")
+                .value_name("COUNT")
+                .help("Most times a path may scatter from surfaces")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-diffuse-bounces")
+                .long("max-diffuse-bounces")
+                .value_name("COUNT")
+                .help("Most times a path may scatter diffusely")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-glossy-bounces")
+                .long("max-glossy-bounces")
+                .value_name("COUNT")
+                .help("Most times a path may reflect from glossy and mirror surfaces")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-transmission-bounces")
+                .long("max-transmission-bounces")
+                .value_name("COUNT")
+                .help("Most times a path may pass through transparent surfaces")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("override-material")
+                .long("override-material")
+                .value_name("MATERIAL")
+                .help(
+                    "Shade every entity with a debug material, uv-checker shows texture \
+                     coordinates",
+                )
+                .possible_values(&["uv-checker"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("clay")
+                .long("clay")
+                .conflicts_with("override-material")
+                .help("Shade every entity with neutral grey, keeping the lights"),
+        )
+        .arg(
+            Arg::with_name("sanitize")
+                .long("sanitize")
+                .help(
+                    "Check for NaN, infinite and negative radiance, reporting it and rendering \
+                     it as black.  Debug builds always check, but panic instead",
+                ),
+        )
+        .arg(
+            Arg::with_name("audit-energy")
+                .long("audit-energy")
+                .help(
+                    "Integrate the reflectance of each material before rendering, warning about \
+                     any reflecting more light than they receive",
+                ),
+        )
+        .arg(
+            Arg::with_name("highlight-invalid")
+                .long("highlight-invalid")
+                .help("Show pixels with NaN or infinite radiance in magenta"),
+        )
+        .arg(
+            Arg::with_name("auto-exposure")
+                .long("auto-exposure")
+                .help(
+                    "Scale radiance so the log-average luminance is middle grey, keeping \
+                     previews viewable whatever the strength of the lights.  Streamed and \
+                     distributed renders are not scaled",
+                ),
+        )
+        .arg(
+            Arg::with_name("progress")
+                .long("progress")
+                .help(
+                    "Show a progress bar while rendering tiles, then print the rays traced and \
+                     how long each stage took",
+                ),
+        )
+        .arg(
+            Arg::with_name("preview")
+                .long("preview")
+                .value_name("FILE")
+                .help(
+                    "Keep rewriting a PPM image with the tiles rendered so far.  Watch it live \
+                     with an image viewer which reloads changed files, such as \
+                     `feh --reload 1 FILE`.  Files need no display, so work on headless \
+                     machines and over shared filesystems",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("window")
+                .long("window")
+                .help(
+                    "Show the tiles rendered so far in a window, closed once rendering \
+                     finishes.  Only available when built with `--features window`",
+                ),
+        )
+        .arg(
+            Arg::with_name("stats")
+                .long("stats")
+                .help("Print luminance statistics and a histogram after rendering"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .value_name("FILE")
+                .help(
+                    "File to save the render to, ending in .hdr to keep highlights brighter \
+                     than the display can show",
+                )
+                .default_value("scene.png")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("histogram")
+                .long("histogram")
+                .value_name("FILE")
+                .help("Write a luminance histogram as CSV after rendering")
+                .takes_value(true),
+        )
         .subcommand(SubCommand::with_name("basic_sphere").about(
-            "Render simple sphere",
-        ))
-        .subcommand(SubCommand::with_name("scene").about(
-            "Render a simple directional light and multiple spheres!",
+            "Render a single sphere lit by a single light, to check rendering works",
         ))
+        .subcommand(
+            SubCommand::with_name("scene")
+                .about("Render a simple directional light and multiple spheres!")
+                .arg(
+                    Arg::with_name("background")
+                        .long("background")
+                        .value_name("FILE")
+                        .help("Image shown behind the scene, stretched to fit the render")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("stream")
+                        .long("stream")
+                        .value_name("FILE")
+                        .help(
+                            "Write a PPM image row by row as it renders, instead of the \
+                             output image",
+                        )
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("deep")
+                        .long("deep")
+                        .value_name("FILE")
+                        .help("Also write the depths of the nearest surfaces in each pixel")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("deep-samples")
+                        .long("deep-samples")
+                        .value_name("COUNT")
+                        .help("Most surfaces to record per pixel in the deep image")
+                        .default_value("4")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("overlay")
+                        .long("overlay")
+                        .conflicts_with("stream")
+                        .help("Draw world axes, a ground grid, lights and entity bounds"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("render")
+                .about("Render a scene described in a RON file")
+                .arg(
+                    Arg::with_name("scene")
+                        .long("scene")
+                        .value_name("FILE")
+                        .help("The scene file to render")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("overlay")
+                        .long("overlay")
+                        .help("Draw world axes, a ground grid, lights and entity bounds"),
+                )
+                .arg(
+                    Arg::with_name("toon")
+                        .long("toon")
+                        .help("Render with flat bands of shading and outlines"),
+                )
+                .arg(
+                    Arg::with_name("wireframe")
+                        .long("wireframe")
+                        .conflicts_with("toon")
+                        .help("Draw the edges of mesh triangles over direct lighting"),
+                )
+                .arg(
+                    Arg::with_name("aov")
+                        .long("aov")
+                        .value_name("AOV")
+                        .help(
+                            "Write an image of surface normals, depths, albedos or motion \
+                             across the screen while the shutter is open instead of the shaded \
+                             render, to debug cameras and intersections or to blur and denoise \
+                             in other tools",
+                        )
+                        .possible_values(&["normal", "depth", "albedo", "motion"])
+                        .conflicts_with_all(&["overlay", "toon", "wireframe", "passes", "split"])
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("passes")
+                        .long("passes")
+                        .help(
+                            "Also write direct, indirect, deeper bounce, diffuse and specular \
+                             light to separate images",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("split")
+                        .long("split")
+                        .help(
+                            "Also write two images each averaging half of the samples, and the \
+                             estimated error of their average, for denoisers and noise checks",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("line-width")
+                        .long("line-width")
+                        .value_name("PIXELS")
+                        .help("Width of wireframe lines")
+                        .requires("wireframe")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("obj")
+                .about("Render a model from a Wavefront OBJ file")
+                .arg(
+                    Arg::with_name("FILE")
+                        .help("The OBJ file to render")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("overlay")
+                        .long("overlay")
+                        .help("Draw world axes, a ground grid, lights and entity bounds"),
+                )
+                .arg(
+                    Arg::with_name("toon")
+                        .long("toon")
+                        .help("Render with flat bands of shading and outlines"),
+                )
+                .arg(
+                    Arg::with_name("wireframe")
+                        .long("wireframe")
+                        .conflicts_with("toon")
+                        .help("Draw the edges of mesh triangles over direct lighting"),
+                )
+                .arg(
+                    Arg::with_name("aov")
+                        .long("aov")
+                        .value_name("AOV")
+                        .help(
+                            "Write an image of surface normals, depths, albedos or motion \
+                             across the screen while the shutter is open instead of the shaded \
+                             render, to debug cameras and intersections or to blur and denoise \
+                             in other tools",
+                        )
+                        .possible_values(&["normal", "depth", "albedo", "motion"])
+                        .conflicts_with_all(&["overlay", "toon", "wireframe", "passes", "split"])
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("passes")
+                        .long("passes")
+                        .help(
+                            "Also write direct, indirect, deeper bounce, diffuse and specular \
+                             light to separate images",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("split")
+                        .long("split")
+                        .help(
+                            "Also write two images each averaging half of the samples, and the \
+                             estimated error of their average, for denoisers and noise checks",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("line-width")
+                        .long("line-width")
+                        .value_name("PIXELS")
+                        .help("Width of wireframe lines")
+                        .requires("wireframe")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("visibility")
+                .about("Find which of a set of points can see each other in a scene")
+                .arg(
+                    Arg::with_name("scene")
+                        .long("scene")
+                        .value_name("FILE")
+                        .help("The scene file to test visibility in")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .value_name("FILE")
+                        .help("Points to look from, one \"x y z\" per line")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("FILE")
+                        .help("Points to look at, instead of the points looked from")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("matrix")
+                        .long("matrix")
+                        .value_name("FILE")
+                        .help("Write the matrix to a file instead of standard output")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("inspect")
+                .about("Describe the camera, lights and entities of a scene file")
+                .arg(
+                    Arg::with_name("scene")
+                        .long("scene")
+                        .value_name("FILE")
+                        .help("The scene file to describe")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("coordinate")
+                .about("Render the multiple spheres scene by handing out tiles to workers")
+                .arg(
+                    Arg::with_name("bind")
+                        .long("bind")
+                        .value_name("ADDRESS")
+                        .help("Address to listen for workers on")
+                        .default_value("0.0.0.0:7878")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("worker-timeout")
+                        .long("worker-timeout")
+                        .value_name("SECONDS")
+                        .help(
+                            "How long a worker may take to render a tile before it is given to \
+                             another worker",
+                        )
+                        .default_value("600")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("worker")
+                .about("Render tiles for a coordinator")
+                .arg(
+                    Arg::with_name("connect")
+                        .long("connect")
+                        .value_name("ADDRESS")
+                        .help("Address of the coordinator")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
         .get_matches();
 
-    if let Some(_) = matches.subcommand_matches("scene") {
-        render_multiple_spheres();
+    if let Some(threads) = matches.value_of("threads") {
+        let threads = threads.parse::<usize>().expect(
+            "Thread count must be a positive integer.",
+        );
+        rayon::initialize(rayon::Configuration::new().num_threads(threads))
+            .expect("Unable to start rendering threads.");
+    }
+
+    let samples_per_pixel = match matches.value_of("spp").unwrap().parse::<u32>() {
+        Ok(spp) if spp > 0 => spp,
+        _ => panic!("Samples per pixel must be a positive integer."),
+    };
+    let sampler: Box<Sampler> = match matches.value_of("sampler").unwrap() {
+        "jittered" => Box::new(JitteredSampler::new(samples_per_pixel)),
+        "stratified" => Box::new(StratifiedSampler::new(samples_per_pixel)),
+        "halton" => Box::new(HaltonSampler::new(samples_per_pixel)),
+        _ => Box::new(UniformSampler::new(samples_per_pixel)),
+    };
+    let split = ["render", "obj"].iter().any(|name| {
+        matches
+            .subcommand_matches(name)
+            .map_or(false, |m| m.is_present("split"))
+    });
+    if split && sampler.samples_per_pixel() < 2 {
+        panic!("Split rendering needs at least two samples per pixel, set with --spp.");
+    }
+    let bounce_limit = |name: &str, default: u32| match matches.value_of(name) {
+        Some(count) => count.parse::<u32>().expect(
+            "Bounce limits must be non-negative integers.",
+        ),
+        None => default,
+    };
+    let default_limits = BounceLimits::default();
+    let bounce_limits = BounceLimits {
+        total: bounce_limit("max-bounces", default_limits.total),
+        diffuse: bounce_limit("max-diffuse-bounces", default_limits.diffuse),
+        glossy: bounce_limit("max-glossy-bounces", default_limits.glossy),
+        transmission: bounce_limit("max-transmission-bounces", default_limits.transmission),
+    };
+    let settings = RenderSettings {
+        sampler: sampler.as_ref(),
+        sanitize: matches.is_present("sanitize"),
+        override_material: matches.value_of("override-material"),
+        clay: matches.is_present("clay"),
+        bounce_limits: bounce_limits,
+        audit_energy: matches.is_present("audit-energy"),
+        progress: matches.is_present("progress"),
+        preview: matches.value_of("preview"),
+        window: matches.is_present("window"),
+        bvh_layout: match matches.value_of("bvh").unwrap() {
+            "quantized-wide" => BvhLayout::QuantizedWide,
+            _ => BvhLayout::Binary,
+        },
+        precision: if matches.is_present("half-precision") {
+            Precision::Half
+        } else {
+            Precision::Full
+        },
+        light_cutoff: matches.value_of("light-cutoff").map(|cutoff| {
+            match cutoff.parse::<f32>() {
+                Ok(c) if c > 0.0 => c,
+                _ => panic!("Light cutoff must be a positive number."),
+            }
+        }),
+        seed: match matches.value_of("seed") {
+            Some(seed) => seed.parse::<u32>().expect("Seed must be a non-negative integer."),
+            None => rand::random(),
+        },
+    };
+    let dimension = |name: &str| {
+        matches.value_of(name).unwrap().parse::<u32>().expect(
+            "Width and height must be positive integers.",
+        )
+    };
+    let film_options = FilmOptions {
+        width: dimension("width"),
+        height: dimension("height"),
+        fov: matches.value_of("fov").map(|fov| match fov.parse::<f32>() {
+            Ok(degrees) if degrees > 0.0 && degrees < 180.0 => PlanarAngle::Degrees(degrees),
+            _ => panic!("Field of view must be between 0 and 180 degrees."),
+        }),
+    };
+    let view = match matches.value_of("view").unwrap() {
+        "false-color" => ViewTransform::FalseColor,
+        "zebra" => ViewTransform::Zebra,
+        _ => ViewTransform::Standard,
+    };
+    let output = OutputOptions {
+        view: view,
+        auto_exposure: matches.is_present("auto-exposure"),
+        highlight_invalid: matches.is_present("highlight-invalid"),
+        print_statistics: matches.is_present("stats"),
+        histogram_file_name: matches.value_of("histogram"),
+        file_name: matches.value_of("output").unwrap(),
+        manifest: RenderManifest {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            command: matches.subcommand_name().unwrap_or("").to_string(),
+            scene_file: None,
+            scene_hash: None,
+            width: 0,
+            height: 0,
+            samples_per_pixel: settings.sampler.samples_per_pixel(),
+            sampler: matches.value_of("sampler").unwrap().to_string(),
+            seed: settings.seed,
+            integrator: String::new(),
+            bounce_limits: settings.bounce_limits,
+            material_override: if settings.clay {
+                Some("clay".to_string())
+            } else {
+                settings.override_material.map(|m| m.to_string())
+            },
+            view: matches.value_of("view").unwrap().to_string(),
+        },
+    };
+    if Path::new(output.file_name).extension().map_or(false, |e| e == "exr") {
+        panic!("OpenEXR output is not supported, save to a .hdr file instead.");
+    }
+
+    if matches.subcommand_matches("basic_sphere").is_some() {
+        render_basic_sphere(&film_options, &settings, &output);
+    } else if let Some(scene_matches) = matches.subcommand_matches("scene") {
+        let deep_samples = scene_matches
+            .value_of("deep-samples")
+            .unwrap()
+            .parse::<usize>()
+            .expect("Deep sample count must be a positive integer.");
+        render_multiple_spheres(
+            scene_matches.value_of("background"),
+            scene_matches.value_of("stream"),
+            scene_matches.value_of("deep").map(
+                |file_name| (file_name, deep_samples),
+            ),
+            scene_matches.is_present("overlay"),
+            &film_options,
+            &settings,
+            &output,
+        );
+    } else if let Some(render_matches) = matches.subcommand_matches("render") {
+        render_scene_file(
+            render_matches.value_of("scene").unwrap(),
+            render_matches.is_present("overlay"),
+            render_matches.is_present("toon"),
+            wireframe_settings(render_matches).as_ref(),
+            aov_setting(render_matches),
+            render_matches.is_present("passes"),
+            render_matches.is_present("split"),
+            &settings,
+            &output,
+        );
+    } else if let Some(obj_matches) = matches.subcommand_matches("obj") {
+        render_obj(
+            obj_matches.value_of("FILE").unwrap(),
+            obj_matches.is_present("overlay"),
+            obj_matches.is_present("toon"),
+            wireframe_settings(obj_matches).as_ref(),
+            aov_setting(obj_matches),
+            obj_matches.is_present("passes"),
+            obj_matches.is_present("split"),
+            &film_options,
+            &settings,
+            &output,
+        );
+    } else if let Some(visibility_matches) = matches.subcommand_matches("visibility") {
+        write_visibility(
+            visibility_matches.value_of("scene").unwrap(),
+            visibility_matches.value_of("from").unwrap(),
+            visibility_matches.value_of("to"),
+            visibility_matches.value_of("matrix"),
+        );
+    } else if let Some(inspect_matches) = matches.subcommand_matches("inspect") {
+        inspect_scene_file(inspect_matches.value_of("scene").unwrap());
+    } else if let Some(coordinate_matches) = matches.subcommand_matches("coordinate") {
+        let worker_timeout = coordinate_matches
+            .value_of("worker-timeout")
+            .unwrap()
+            .parse::<u64>()
+            .ok()
+            .filter(|&seconds| seconds > 0)
+            .expect("Worker timeout must be a positive number of seconds.");
+        coordinate_multiple_spheres(
+            coordinate_matches.value_of("bind").unwrap(),
+            Duration::from_secs(worker_timeout),
+            &film_options,
+            &output,
+        );
+    } else if let Some(worker_matches) = matches.subcommand_matches("worker") {
+        work_on_multiple_spheres(
+            worker_matches.value_of("connect").unwrap(),
+            &film_options,
+            &settings,
+            &output,
+        );
     } else {
         println!("Unhandled render command.");
     }
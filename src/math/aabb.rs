@@ -1,15 +1,68 @@
-use math::{Point, Ray, XYZ};
+use math::{Matrix4x4, Point, Ray, XYZ};
 use std::{f32, mem};
+use std::ops::Mul;
 
 /// Axis-Aligned Bounding Box (AABB).
 ///
 /// TODO: Checks to ensure that lower < upper
+#[derive(Clone, Copy, Debug)]
 pub struct AABB {
     pub lower: Point,
     pub upper: Point,
 }
 
 impl AABB {
+    pub fn new(lower: Point, upper: Point) -> AABB {
+        AABB {
+            lower: lower,
+            upper: upper,
+        }
+    }
+
+    /// A box containing all of space, for shapes which extend infinitely.
+    pub fn infinite() -> AABB {
+        AABB {
+            lower: Point::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+            upper: Point::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+        }
+    }
+
+    /// Determines if the box has a finite size.
+    pub fn is_bounded(&self) -> bool {
+        self.lower
+            .components()
+            .chain(self.upper.components())
+            .all(|(_, c)| c.is_finite())
+    }
+
+    /// The smallest box containing both boxes.
+    pub fn union(&self, other: &AABB) -> AABB {
+        AABB {
+            lower: self.lower.zip_with(&other.lower, f32::min),
+            upper: self.upper.zip_with(&other.upper, f32::max),
+        }
+    }
+
+    /// The smallest box containing the box and a point.
+    pub fn expand(&self, point: &Point) -> AABB {
+        self.union(&AABB::new(*point, *point))
+    }
+
+    /// The center of the box.
+    pub fn centroid(&self) -> Point {
+        self.lower + 0.5 * (self.upper - self.lower)
+    }
+
+    /// Surface area of the box, to which the likelihood of random rays hitting it is
+    /// proportional.  Infinite if the box is.
+    pub fn surface_area(&self) -> f32 {
+        if !self.is_bounded() {
+            return f32::INFINITY;
+        }
+        let extent = self.upper - self.lower;
+        2.0 * (extent.x * extent.y + extent.y * extent.z + extent.z * extent.x)
+    }
+
     pub fn intersection_time(&self, a_ray: Ray) -> Option<f32> {
         let mut r = a_ray;
         r.normalize().unwrap();
@@ -54,10 +107,77 @@ impl AABB {
     }
 }
 
+/// Bounds the transformed box, which may be larger than the box itself when rotated.
+impl Mul<AABB> for Matrix4x4 {
+    type Output = AABB;
+    fn mul(self, bb: AABB) -> Self::Output {
+        if !bb.is_bounded() {
+            return AABB::infinite();
+        }
+
+        let mut result: Option<AABB> = None;
+        for &x in [bb.lower.x, bb.upper.x].iter() {
+            for &y in [bb.lower.y, bb.upper.y].iter() {
+                for &z in [bb.lower.z, bb.upper.z].iter() {
+                    let corner = self * Point::new(x, y, z);
+                    result = Some(match result {
+                        Some(r) => r.expand(&corner),
+                        None => AABB::new(corner, corner),
+                    });
+                }
+            }
+        }
+        result.unwrap()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::AABB;
-    use math::{Point, Ray, Vector};
+    use math::{Matrix4x4, Point, Ray, Vector};
+    use std::f32;
+
+    #[test]
+    fn test_union_and_centroid() {
+        let a = AABB::new(Point::new(-1.0, 0.0, 0.0), Point::new(1.0, 1.0, 1.0));
+        let b = AABB::new(Point::new(0.0, -2.0, 0.5), Point::new(3.0, 0.5, 0.75));
+        let u = a.union(&b);
+        assert_relative_eq!(u.lower, Point::new(-1.0, -2.0, 0.0));
+        assert_relative_eq!(u.upper, Point::new(3.0, 1.0, 1.0));
+        assert_relative_eq!(u.centroid(), Point::new(1.0, -0.5, 0.5));
+
+        assert!(u.is_bounded());
+        assert!(!AABB::infinite().is_bounded());
+        assert!(!u.union(&AABB::infinite()).is_bounded());
+    }
+
+    #[test]
+    fn test_expand_and_surface_area() {
+        let point = Point::new(1.0, 2.0, 3.0);
+        let bb = AABB::new(point, point);
+        assert_relative_eq!(bb.surface_area(), 0.0);
+
+        let bb = bb.expand(&Point::new(0.0, 4.0, 3.5));
+        assert_relative_eq!(bb.lower, Point::new(0.0, 2.0, 3.0));
+        assert_relative_eq!(bb.upper, Point::new(1.0, 4.0, 3.5));
+        assert_relative_eq!(bb.surface_area(), 2.0 * (2.0 + 1.0 + 0.5));
+
+        // Points inside leave the box as it is.
+        let same = bb.expand(&Point::new(0.5, 3.0, 3.25));
+        assert_relative_eq!(same.lower, bb.lower);
+        assert_relative_eq!(same.upper, bb.upper);
+
+        assert_eq!(AABB::infinite().surface_area(), f32::INFINITY);
+    }
+
+    #[test]
+    fn test_transform() {
+        let bb = AABB::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let moved = Matrix4x4::translate(1.0, 2.0, 3.0) * Matrix4x4::scale(2.0, 1.0, 1.0) * bb;
+        assert_relative_eq!(moved.lower, Point::new(-1.0, 1.0, 2.0));
+        assert_relative_eq!(moved.upper, Point::new(3.0, 3.0, 4.0));
+        assert!(!(Matrix4x4::identity() * AABB::infinite()).is_bounded());
+    }
 
     #[test]
     fn test_misses_box() {
@@ -68,6 +188,7 @@ mod tests {
         let r = Ray {
             origin: Point::new(0.0, 4.0, 2.0),
             direction: Vector::new(0.218, -0.436, 0.873),
+            time: 0.0,
         };
         let intersection_time = bb.intersection_time(r);
         assert!(intersection_time.is_none());
@@ -82,6 +203,7 @@ mod tests {
         let r = Ray {
             origin: Point::new(0.0, 0.0, 50.0),
             direction: Vector::new(0.0, 0.0, -1.0),
+            time: 0.0,
         };
         let intersection_time = bb.intersection_time(r);
         assert!(intersection_time.is_some());
@@ -1,6 +1,8 @@
 /// Provides units for planar angles.
 ///
 /// We often want to be explicit about the units we're dealing with, so this lets us be explicit.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PlanarAngle<T = f32> {
     Radians(T),
     Degrees(T),
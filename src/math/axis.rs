@@ -2,7 +2,7 @@ use std::fmt;
 
 /// Provides a convenient mechanism to refer to values in `Point`s and `Vector`s by index without
 /// resorting to arbitrary numeric indices (e.g. 0, 1, 2).
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Axis {
     X,
     Y,
@@ -0,0 +1,592 @@
+use math::{AABB, Point, Ray, XYZ};
+use std::f32;
+
+/// Most items to keep in a single leaf before splitting it.
+const MAX_LEAF_SIZE: usize = 4;
+
+/// Most nodes waiting to be visited while traversing, which is kept on the call stack so
+/// traversal does not allocate.  Splitting at the median halves the items at each level, so
+/// traversal never holds more nodes than there are bits in an item count.
+const MAX_TRAVERSAL_STACK: usize = 64;
+
+/// Most children of a node in the quantized wide layout.
+const WIDE_WIDTH: usize = 8;
+
+/// Most children waiting to be visited while traversing the quantized wide layout, each level
+/// leaving at most all but one of its children behind.
+const MAX_WIDE_TRAVERSAL_STACK: usize = WIDE_WIDTH * MAX_TRAVERSAL_STACK;
+
+/// Largest quantized coordinate of a child's bounds within its parent's.
+const QUANTIZED_MAX: f32 = 65535.0;
+
+/// How the nodes of a hierarchy are laid out in memory.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BvhLayout {
+    /// Two children per node, with bounds at full precision.
+    Binary,
+    /// Up to eight children per node, with their bounds rounded outward to 16 bit steps within
+    /// their parent's.  Traversal reads fewer and smaller nodes, which suits large meshes.
+    QuantizedWide,
+}
+
+impl Default for BvhLayout {
+    fn default() -> BvhLayout {
+        BvhLayout::Binary
+    }
+}
+
+/// A node of the quantized wide layout, holding the bounds of its children rather than its own.
+struct WideNode {
+    /// Lower corner of the node, from which its children's bounds are measured.
+    origin: [f32; 3],
+    /// Size of one quantization step along each axis.
+    scale: [f32; 3],
+    /// Quantized corners of each child's bounds, indexed by axis and then child.
+    lower: [[u16; WIDE_WIDTH]; 3],
+    upper: [[u16; WIDE_WIDTH]; 3],
+    /// For leaf children, where their items start in `items`.  For the others, their index in
+    /// the wide nodes.
+    children: [u32; WIDE_WIDTH],
+    /// Number of items in each leaf child, 0 for the others.
+    counts: [u8; WIDE_WIDTH],
+    child_count: u8,
+}
+
+impl WideNode {
+    /// Bounds of a child, containing its exact bounds.
+    fn child_bounds(&self, child: usize) -> AABB {
+        let corner = |q: &[[u16; WIDE_WIDTH]; 3]| {
+            Point::new(
+                self.origin[0] + q[0][child] as f32 * self.scale[0],
+                self.origin[1] + q[1][child] as f32 * self.scale[1],
+                self.origin[2] + q[2][child] as f32 * self.scale[2],
+            )
+        };
+        AABB::new(corner(&self.lower), corner(&self.upper))
+    }
+}
+
+/// A bounding volume hierarchy (BVH) over a list of items, such as the entities of a scene or the
+/// triangles of a mesh.
+///
+/// The hierarchy only knows the bounds of each item, and refers to items by their index in the
+/// list it was built from.  Items with infinite bounds, such as planes, are kept aside and always
+/// tested.
+///
+/// Nodes are stored in flat arrays of each of their fields, indexed by node, in depth-first order
+/// so the left child of a branch directly follows it.  Traversal then walks through a few
+/// contiguous arrays instead of chasing boxed nodes around the heap.  The quantized wide layout
+/// is built by collapsing these binary nodes, which are then discarded.
+pub struct Bvh {
+    layout: BvhLayout,
+    bounds: Vec<AABB>,
+    /// For leaves, where their items start in `items`.  For branches, the index of their right
+    /// child.
+    offsets: Vec<u32>,
+    /// Number of items in each leaf, 0 for branches.
+    counts: Vec<u32>,
+    /// Items of every leaf, each leaf's together.
+    items: Vec<usize>,
+    unbounded: Vec<usize>,
+    /// Nodes of the quantized wide layout, each after all of its descendants.
+    wide: Vec<WideNode>,
+}
+
+impl Bvh {
+    /// Builds a hierarchy with the binary layout over items with the given bounds.
+    pub fn new(item_bounds: &[AABB]) -> Bvh {
+        Bvh::with_layout(item_bounds, BvhLayout::Binary)
+    }
+
+    /// Builds a hierarchy over items with the given bounds.
+    pub fn with_layout(item_bounds: &[AABB], layout: BvhLayout) -> Bvh {
+        let mut bounded = Vec::new();
+        let mut unbounded = Vec::new();
+        for (index, bounds) in item_bounds.iter().enumerate() {
+            if bounds.is_bounded() {
+                bounded.push(index);
+            } else {
+                unbounded.push(index);
+            }
+        }
+
+        let mut bvh = Bvh {
+            layout: layout,
+            bounds: Vec::new(),
+            offsets: Vec::new(),
+            counts: Vec::new(),
+            items: Vec::with_capacity(bounded.len()),
+            unbounded: unbounded,
+            wide: Vec::new(),
+        };
+        if !bounded.is_empty() {
+            bvh.build(item_bounds, bounded);
+        }
+        if layout == BvhLayout::QuantizedWide && !bvh.bounds.is_empty() {
+            bvh.build_wide(0);
+            bvh.bounds = Vec::new();
+            bvh.offsets = Vec::new();
+            bvh.counts = Vec::new();
+        }
+        bvh
+    }
+
+    pub fn layout(&self) -> BvhLayout {
+        self.layout
+    }
+
+    /// Number of nodes in the hierarchy.  Leaves of the wide layout are kept within their parents
+    /// rather than as nodes of their own.
+    pub fn node_count(&self) -> usize {
+        match self.layout {
+            BvhLayout::Binary => self.bounds.len(),
+            BvhLayout::QuantizedWide => self.wide.len(),
+        }
+    }
+
+    /// Recursively splits items at the median of their centroids along the widest axis, adding
+    /// nodes in depth-first order.
+    fn build(&mut self, item_bounds: &[AABB], mut items: Vec<usize>) {
+        let mut bounds = item_bounds[items[0]];
+        let mut centroid_bounds = AABB::new(bounds.centroid(), bounds.centroid());
+        for &i in items.iter() {
+            bounds = bounds.union(&item_bounds[i]);
+            centroid_bounds = centroid_bounds.expand(&item_bounds[i].centroid());
+        }
+
+        if items.len() <= MAX_LEAF_SIZE {
+            self.bounds.push(bounds);
+            self.offsets.push(self.items.len() as u32);
+            self.counts.push(items.len() as u32);
+            self.items.extend(items);
+            return;
+        }
+
+        let axis = (centroid_bounds.upper - centroid_bounds.lower).max_axis();
+
+        items.sort_by(|&a, &b| {
+            let ca = item_bounds[a].centroid()[axis];
+            let cb = item_bounds[b].centroid()[axis];
+            ca.partial_cmp(&cb).unwrap()
+        });
+        let right_items = items.split_off(items.len() / 2);
+
+        let node = self.bounds.len();
+        self.bounds.push(bounds);
+        self.offsets.push(0);
+        self.counts.push(0);
+        self.build(item_bounds, items);
+        self.offsets[node] = self.bounds.len() as u32;
+        self.build(item_bounds, right_items);
+    }
+
+    /// Collapses a binary node and its nearest descendants into a wide node, with the binary
+    /// nodes of the largest surface area opened up first until it has as many children as it can
+    /// hold.
+    ///
+    /// # Returns
+    /// The index of the wide node, which follows those of its descendants.
+    fn build_wide(&mut self, node: usize) -> u32 {
+        let mut binary_children = if self.counts[node] > 0 {
+            vec![node]
+        } else {
+            vec![node + 1, self.offsets[node] as usize]
+        };
+        while binary_children.len() < WIDE_WIDTH {
+            let largest_branch = binary_children
+                .iter()
+                .enumerate()
+                .filter(|&(_, &child)| self.counts[child] == 0)
+                .max_by(|&(_, &a), &(_, &b)| {
+                    self.bounds[a]
+                        .surface_area()
+                        .partial_cmp(&self.bounds[b].surface_area())
+                        .unwrap()
+                })
+                .map(|(slot, _)| slot);
+            let slot = match largest_branch {
+                Some(slot) => slot,
+                None => break,
+            };
+            let branch = binary_children[slot];
+            binary_children[slot] = branch + 1;
+            binary_children.push(self.offsets[branch] as usize);
+        }
+
+        let bounds = self.bounds[node];
+        let origin = [bounds.lower.x, bounds.lower.y, bounds.lower.z];
+        let extent = bounds.upper - bounds.lower;
+        // Stretched slightly so the last step still reaches the upper corner after rounding.
+        let step = |e: f32| e * (1.0 + 4.0 * f32::EPSILON) / QUANTIZED_MAX;
+        let scale = [step(extent.x), step(extent.y), step(extent.z)];
+        let quantize = |value: f32, axis: usize, round: fn(f32) -> f32, margin: f32| -> u16 {
+            if scale[axis] == 0.0 {
+                return 0;
+            }
+            let q = round((value - origin[axis]) / scale[axis]) + margin;
+            q.max(0.0).min(QUANTIZED_MAX) as u16
+        };
+
+        let mut wide = WideNode {
+            origin: origin,
+            scale: scale,
+            lower: [[0; WIDE_WIDTH]; 3],
+            upper: [[0; WIDE_WIDTH]; 3],
+            children: [0; WIDE_WIDTH],
+            counts: [0; WIDE_WIDTH],
+            child_count: binary_children.len() as u8,
+        };
+        for (slot, &child) in binary_children.iter().enumerate() {
+            let child_bounds = self.bounds[child];
+            for (axis, a) in XYZ.iter().enumerate() {
+                // Rounded outward a step further, so the quantized bounds always contain the
+                // exact ones.
+                wide.lower[axis][slot] = quantize(child_bounds.lower[*a], axis, f32::floor, -1.0);
+                wide.upper[axis][slot] = quantize(child_bounds.upper[*a], axis, f32::ceil, 1.0);
+            }
+            if self.counts[child] > 0 {
+                wide.children[slot] = self.offsets[child];
+                wide.counts[slot] = self.counts[child] as u8;
+            } else {
+                wide.children[slot] = self.build_wide(child);
+            }
+        }
+        self.wide.push(wide);
+        (self.wide.len() - 1) as u32
+    }
+
+    /// Visits the items whose bounds the ray enters before the closest hit found so far.
+    ///
+    /// # Arguments
+    /// * `ray` - ray to find the closest hit along
+    /// * `hit` - given the index of an item, returns the time the ray hits it at, if it does
+    pub fn traverse<F>(&self, ray: &Ray, mut hit: F)
+    where
+        F: FnMut(usize) -> Option<f32>,
+    {
+        let mut closest = f32::INFINITY;
+        for &index in self.unbounded.iter() {
+            if let Some(time) = hit(index) {
+                closest = closest.min(time);
+            }
+        }
+        self.traverse_bounded(ray, closest, false, hit);
+    }
+
+    /// Visits the items whose bounds the ray enters before a time, until one is hit.  Cheaper
+    /// than `traverse` when any hit will do, such as for shadow rays.
+    ///
+    /// # Arguments
+    /// * `ray` - ray to look for a hit along
+    /// * `max_time` - time past which hits are ignored
+    /// * `hit` - given the index of an item, returns whether the ray hits it before `max_time`
+    ///
+    /// # Returns
+    /// Whether any item was hit.
+    pub fn any_hit<F>(&self, ray: &Ray, max_time: f32, mut hit: F) -> bool
+    where
+        F: FnMut(usize) -> bool,
+    {
+        if self.unbounded.iter().any(|&index| hit(index)) {
+            return true;
+        }
+        self.traverse_bounded(ray, max_time, true, |index| if hit(index) {
+            Some(max_time)
+        } else {
+            None
+        })
+    }
+
+    /// Visits the items within bounds, returning whether it stopped at the first hit.
+    fn traverse_bounded<F>(&self, ray: &Ray, closest: f32, stop_at_hit: bool, hit: F) -> bool
+    where
+        F: FnMut(usize) -> Option<f32>,
+    {
+        match self.layout {
+            BvhLayout::Binary => self.traverse_binary(ray, closest, stop_at_hit, hit),
+            BvhLayout::QuantizedWide => self.traverse_wide(ray, closest, stop_at_hit, hit),
+        }
+    }
+
+    fn traverse_binary<F>(&self, ray: &Ray, mut closest: f32, stop_at_hit: bool, mut hit: F) -> bool
+    where
+        F: FnMut(usize) -> Option<f32>,
+    {
+        if self.bounds.is_empty() {
+            return false;
+        }
+
+        let mut stack = [0u32; MAX_TRAVERSAL_STACK];
+        let mut stack_size = 1;
+        while stack_size > 0 {
+            stack_size -= 1;
+            let node = stack[stack_size] as usize;
+            match self.bounds[node].intersection_time(*ray) {
+                Some(entry) if entry < closest => {}
+                _ => continue,
+            }
+
+            let count = self.counts[node] as usize;
+            if count > 0 {
+                let first = self.offsets[node] as usize;
+                for &index in self.items[first..(first + count)].iter() {
+                    if let Some(time) = hit(index) {
+                        if stop_at_hit {
+                            return true;
+                        }
+                        closest = closest.min(time);
+                    }
+                }
+            } else {
+                stack[stack_size] = self.offsets[node];
+                stack[stack_size + 1] = node as u32 + 1;
+                stack_size += 2;
+            }
+        }
+        false
+    }
+
+    /// Visits children nearest first, skipping any entered beyond the closest hit found so far.
+    fn traverse_wide<F>(&self, ray: &Ray, mut closest: f32, stop_at_hit: bool, mut hit: F) -> bool
+    where
+        F: FnMut(usize) -> Option<f32>,
+    {
+        if self.wide.is_empty() {
+            return false;
+        }
+
+        // Each entry is a child's index and item count as in `WideNode`, and the time the ray
+        // enters it.
+        let mut stack = [(0u32, 0u8, 0.0f32); MAX_WIDE_TRAVERSAL_STACK];
+        stack[0] = ((self.wide.len() - 1) as u32, 0, f32::NEG_INFINITY);
+        let mut stack_size = 1;
+        while stack_size > 0 {
+            stack_size -= 1;
+            let (child, count, entry) = stack[stack_size];
+            if entry >= closest {
+                continue;
+            }
+
+            if count > 0 {
+                let first = child as usize;
+                for &index in self.items[first..(first + count as usize)].iter() {
+                    if let Some(time) = hit(index) {
+                        if stop_at_hit {
+                            return true;
+                        }
+                        closest = closest.min(time);
+                    }
+                }
+                continue;
+            }
+
+            let node = &self.wide[child as usize];
+            let mut entered = [(0u32, 0u8, 0.0f32); WIDE_WIDTH];
+            let mut entered_count = 0;
+            for slot in 0..node.child_count as usize {
+                match node.child_bounds(slot).intersection_time(*ray) {
+                    Some(time) if time < closest => {
+                        entered[entered_count] = (node.children[slot], node.counts[slot], time);
+                        entered_count += 1;
+                    }
+                    _ => {}
+                }
+            }
+            // Farthest first onto the stack, so the nearest is visited next.
+            let entered = &mut entered[..entered_count];
+            entered.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap());
+            for &e in entered.iter() {
+                stack[stack_size] = e;
+                stack_size += 1;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bvh, BvhLayout};
+    use math::{AABB, Point, Ray, Vector};
+
+    /// Unit boxes spaced along the X axis.
+    fn row_of_boxes(count: usize) -> Vec<AABB> {
+        (0..count)
+            .map(|i| {
+                let x = 2.0 * i as f32;
+                AABB::new(Point::new(x, 0.0, 0.0), Point::new(x + 1.0, 1.0, 1.0))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_visits_hit_boxes() {
+        let boxes = row_of_boxes(20);
+        let bvh = Bvh::new(&boxes);
+        let ray = Ray {
+            origin: Point::new(12.5, 0.5, -10.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+
+        let mut visited = Vec::new();
+        bvh.traverse(&ray, |i| {
+            visited.push(i);
+            boxes[i].intersection_time(ray)
+        });
+        assert!(visited.contains(&6));
+        assert!(visited.len() <= 4);
+    }
+
+    #[test]
+    fn test_finds_closest() {
+        let boxes = row_of_boxes(50);
+        let mut with_plane = boxes.clone();
+        with_plane.push(AABB::infinite());
+        let bvh = Bvh::new(&with_plane);
+        let ray = Ray {
+            origin: Point::new(-5.0, 0.5, 0.5),
+            direction: Vector::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+
+        let mut closest = None;
+        bvh.traverse(&ray, |i| {
+            let time = if i == boxes.len() {
+                // Stands in for a plane at X=1000.
+                Some(1005.0)
+            } else {
+                boxes[i].intersection_time(ray)
+            };
+            if let Some(t) = time {
+                if closest.map_or(true, |(_, best)| t < best) {
+                    closest = Some((i, t));
+                }
+            }
+            time
+        });
+        let (index, time) = closest.unwrap();
+        assert_eq!(index, 0);
+        assert_relative_eq!(time, 5.0);
+    }
+
+    #[test]
+    fn test_any_hit() {
+        let boxes = row_of_boxes(50);
+        let ray = Ray {
+            origin: Point::new(-5.0, 0.5, 0.5),
+            direction: Vector::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        for &layout in [BvhLayout::Binary, BvhLayout::QuantizedWide].iter() {
+            let bvh = Bvh::with_layout(&boxes, layout);
+            let hit_before = |max_time: f32| {
+                let mut visited = 0;
+                let hit = bvh.any_hit(&ray, max_time, |i| {
+                    visited += 1;
+                    boxes[i].intersection_time(ray).map_or(false, |t| t < max_time)
+                });
+                (hit, visited)
+            };
+            // Stops at the first box hit, rather than visiting all fifty.
+            let (hit, visited) = hit_before(1000.0);
+            assert!(hit);
+            assert!(visited < 10);
+            // The first box is entered at 5, so nothing is hit before then.
+            assert_eq!(hit_before(4.0), (false, 0));
+        }
+    }
+
+    #[test]
+    fn test_flat_layout() {
+        let bvh = Bvh::new(&row_of_boxes(20));
+        // Every branch has two children, so there is one fewer branch than leaves.
+        let leaves = bvh.counts.iter().filter(|&&c| c > 0).count();
+        assert_eq!(bvh.node_count(), 2 * leaves - 1);
+
+        let mut items = bvh.items.clone();
+        items.sort();
+        assert_eq!(items, (0..20).collect::<Vec<usize>>());
+        for node in 0..bvh.node_count() {
+            if bvh.counts[node] == 0 {
+                let right = bvh.offsets[node] as usize;
+                assert!(right > node + 1 && right < bvh.node_count());
+            }
+        }
+    }
+
+    /// Small boxes scattered through a cube 100 across.
+    fn scattered_boxes(count: usize) -> Vec<AABB> {
+        let mut state = 12345u32;
+        let mut next = move || {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (state >> 8) as f32 / (1 << 24) as f32 * 100.0
+        };
+        (0..count)
+            .map(|_| {
+                let lower = Point::new(next(), next(), next());
+                let size = next() * 0.05;
+                AABB::new(lower, Point::new(lower.x + size, lower.y + size, lower.z + size))
+            })
+            .collect()
+    }
+
+    /// Index and time of the closest box hit by a ray.
+    fn closest_hit(bvh: &Bvh, boxes: &[AABB], ray: &Ray) -> Option<(usize, f32)> {
+        let mut closest: Option<(usize, f32)> = None;
+        bvh.traverse(ray, |i| {
+            let time = boxes[i].intersection_time(*ray);
+            if let Some(t) = time {
+                if closest.map_or(true, |(_, best)| t < best) {
+                    closest = Some((i, t));
+                }
+            }
+            time
+        });
+        closest
+    }
+
+    #[test]
+    fn test_quantized_wide_matches_binary() {
+        let boxes = scattered_boxes(2000);
+        let binary = Bvh::new(&boxes);
+        let wide = Bvh::with_layout(&boxes, BvhLayout::QuantizedWide);
+        assert!(wide.node_count() < binary.node_count() / 4);
+
+        let origin = Point::new(-10.0, 50.0, -10.0);
+        let mut hits = 0;
+        for i in 0..200 {
+            let toward = boxes[i].centroid() - origin;
+            let ray = Ray {
+                origin: origin,
+                direction: Vector::unit(toward.x, toward.y, toward.z).unwrap(),
+                time: 0.0,
+            };
+            let expected = closest_hit(&binary, &boxes, &ray);
+            assert_eq!(closest_hit(&wide, &boxes, &ray), expected);
+            hits += expected.is_some() as usize;
+        }
+        assert_eq!(hits, 200);
+    }
+
+    #[test]
+    fn test_quantized_bounds_are_conservative() {
+        let boxes = scattered_boxes(500);
+        let wide = Bvh::with_layout(&boxes, BvhLayout::QuantizedWide);
+        for node in wide.wide.iter() {
+            for child in 0..node.child_count as usize {
+                if node.counts[child] == 0 {
+                    continue;
+                }
+                let bounds = node.child_bounds(child);
+                let first = node.children[child] as usize;
+                for &item in wide.items[first..(first + node.counts[child] as usize)].iter() {
+                    let item = boxes[item];
+                    assert!(bounds.lower.x <= item.lower.x && item.upper.x <= bounds.upper.x);
+                    assert!(bounds.lower.y <= item.lower.y && item.upper.y <= bounds.upper.y);
+                    assert!(bounds.lower.z <= item.lower.z && item.upper.z <= bounds.upper.z);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use math::{AABB, Intersection, Point, Ray, Solid, Vector};
+use std::f32::consts::PI;
+
+/// A cone around the Z axis, with its base at the origin and its tip at a height along Z+,
+/// optionally closed by a flat cap over its base.
+pub struct Cone {
+    radius: f32,
+    height: f32,
+    capped: bool,
+}
+
+impl Cone {
+    /// # Arguments
+    /// * `radius` - radius of the base
+    /// * `height` - distance from the base to the tip along the Z axis
+    /// * `capped` - whether the base is closed, rather than leaving the cone hollow
+    ///
+    /// # Panics
+    /// If the radius or height are not positive.
+    pub fn new(radius: f32, height: f32, capped: bool) -> Cone {
+        assert!(
+            radius > 0.0 && height > 0.0,
+            "A cone's radius and height must be positive."
+        );
+        Cone {
+            radius: radius,
+            height: height,
+            capped: capped,
+        }
+    }
+
+    /// Hits of the sloped surface, which may be seen from inside an uncapped cone.
+    fn side_hits(&self, r: &Ray) -> Vec<Intersection> {
+        // Solve x^2 + y^2 = (k (height - z))^2, where k is the slope of the side.
+        let k = self.radius / self.height;
+        let k2 = k * k;
+        let (o, d) = (r.origin, r.direction);
+        let to_tip = self.height - o.z;
+        let a = d.x * d.x + d.y * d.y - k2 * d.z * d.z;
+        let b = 2.0 * (o.x * d.x + o.y * d.y + k2 * to_tip * d.z);
+        let c = o.x * o.x + o.y * o.y - k2 * to_tip * to_tip;
+
+        let roots = if a.abs() < 1e-7 {
+            // Parallel to the side, so it is crossed at most once.
+            if b == 0.0 { vec![] } else { vec![-c / b] }
+        } else {
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant < 0.0 {
+                vec![]
+            } else {
+                let root = discriminant.sqrt();
+                vec![(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+            }
+        };
+
+        roots
+            .into_iter()
+            .map(|t| (t, r.at(t)))
+            // Only the half of the double cone between the base and the tip.
+            .filter(|&(t, p)| t > 0.0 && p.z >= 0.0 && p.z <= self.height)
+            .filter_map(|(t, point)| {
+                let mut normal = Vector::new(point.x, point.y, k2 * (self.height - point.z));
+                // The normal is undefined at the tip.
+                normal.normalize().ok()?;
+                let angle = point.y.atan2(point.x);
+                let angle = if angle < 0.0 { angle + 2.0 * PI } else { angle };
+                let mut up_side = Vector::new(
+                    -self.radius * angle.cos(),
+                    -self.radius * angle.sin(),
+                    self.height,
+                );
+                up_side.normalize().ok()?;
+                Some(Intersection {
+                    time: t,
+                    point: point,
+                    normal: normal,
+                    uv: (angle / (2.0 * PI), point.z / self.height),
+                    tangent: Vector::new(-angle.sin(), angle.cos(), 0.0),
+                    bitangent: up_side,
+                    triangle: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Hit of the cap over the base, if the cone has one.
+    fn cap_hit(&self, r: &Ray) -> Option<Intersection> {
+        if !self.capped || r.direction.z == 0.0 {
+            return None;
+        }
+        let t = -r.origin.z / r.direction.z;
+        let point = r.at(t);
+        if t <= 0.0 || point.x * point.x + point.y * point.y > self.radius * self.radius {
+            return None;
+        }
+        Some(Intersection {
+            time: t,
+            point: point,
+            normal: Vector::new(0.0, 0.0, -1.0),
+            uv: (
+                0.5 + 0.5 * point.x / self.radius,
+                0.5 + 0.5 * point.y / self.radius,
+            ),
+            tangent: Vector::new(1.0, 0.0, 0.0),
+            bitangent: Vector::new(0.0, 1.0, 0.0),
+            triangle: None,
+        })
+    }
+}
+
+impl Solid for Cone {
+    fn intersect(&self, r: &Ray) -> Option<Intersection> {
+        debug_assert!(r.is_normalized());
+
+        let mut hits = self.side_hits(r);
+        hits.extend(self.cap_hit(r));
+        hits.into_iter().fold(None, |nearest, hit| match nearest {
+            Some(ref n) if n.time <= hit.time => nearest,
+            _ => Some(hit),
+        })
+    }
+
+    fn bounding_box(&self) -> AABB {
+        AABB::new(
+            Point::new(-self.radius, -self.radius, 0.0),
+            Point::new(self.radius, self.radius, self.height),
+        )
+    }
+
+    fn name(&self) -> &'static str {
+        "cone"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cone;
+    use math::{Point, Ray, Solid, Vector};
+
+    fn ray(origin: Point, direction: Vector) -> Ray {
+        let mut r = Ray {
+            origin: origin,
+            direction: direction,
+            time: 0.0,
+        };
+        r.normalize().unwrap();
+        r
+    }
+
+    #[test]
+    fn test_side() {
+        // A 45 degree cone, 1 wide at half its height.
+        let cone = Cone::new(2.0, 2.0, true);
+        let hit = cone
+            .intersect(&ray(Point::new(-5.0, 0.0, 1.0), Vector::new(1.0, 0.0, 0.0)))
+            .unwrap();
+        assert_relative_eq!(hit.time, 4.0, epsilon = 1e-5);
+        let slope = Vector::new(-1.0, 0.0, 1.0) / 2f32.sqrt();
+        assert_relative_eq!(hit.normal, slope, epsilon = 1e-5);
+        assert_relative_eq!(hit.uv.1, 0.5);
+
+        // Above the tip.
+        assert!(
+            cone.intersect(&ray(Point::new(-5.0, 0.0, 3.0), Vector::new(1.0, 0.0, 0.0)))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_base() {
+        let up_the_axis = ray(Point::new(0.5, 0.0, -4.0), Vector::new(0.0, 0.0, 1.0));
+
+        let capped = Cone::new(1.0, 3.0, true);
+        let hit = capped.intersect(&up_the_axis).unwrap();
+        assert_relative_eq!(hit.time, 4.0);
+        assert_relative_eq!(hit.normal, Vector::new(0.0, 0.0, -1.0));
+
+        // Without a cap, the inside of the far side is seen instead.
+        let hollow = Cone::new(1.0, 3.0, false);
+        let hit = hollow.intersect(&up_the_axis).unwrap();
+        assert_relative_eq!(hit.point, Point::new(0.5, 0.0, 1.5), epsilon = 1e-5);
+    }
+}
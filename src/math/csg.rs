@@ -0,0 +1,278 @@
+use math::{AABB, BvhLayout, Intersection, Point, Ray, Solid, Transform};
+
+/// Most surfaces of each child found along a ray, to bound the work for complex meshes.
+const MAX_CROSSINGS: usize = 64;
+
+/// Distance to move past a surface before looking for the next one along a ray.
+const CROSSING_OFFSET: f32 = 1e-4;
+
+/// How the two solids of a `Csg` are combined.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CsgOperation {
+    /// Everything inside either solid.
+    Union,
+    /// Only what is inside both solids.
+    Intersection,
+    /// What is inside the first solid but not the second, such as a sphere with a hole cut by a
+    /// cylinder.
+    Difference,
+}
+
+impl CsgOperation {
+    fn contains(&self, inside_a: bool, inside_b: bool) -> bool {
+        match *self {
+            CsgOperation::Union => inside_a || inside_b,
+            CsgOperation::Intersection => inside_a && inside_b,
+            CsgOperation::Difference => inside_a && !inside_b,
+        }
+    }
+}
+
+/// Two solids combined with a boolean operation (constructive solid geometry).
+///
+/// Each surface crossed along a ray is found for both solids, and the first crossing which moves
+/// the ray into or out of the combined solid is the one seen.  Whether a crossing enters or leaves
+/// a solid is determined from its outward facing normal, so both solids must be closed, like
+/// spheres and capped cylinders, rather than planes or open tubes.
+pub struct Csg {
+    operation: CsgOperation,
+    a: Box<Solid>,
+    b: Box<Solid>,
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, a: Box<Solid>, b: Box<Solid>) -> Csg {
+        Csg {
+            operation: operation,
+            a: a,
+            b: b,
+        }
+    }
+}
+
+/// A surface of one of the solids along a ray.
+struct Crossing {
+    intersection: Intersection,
+    /// Whether the ray is moving into the solid.
+    entering: bool,
+    from_a: bool,
+}
+
+/// Every surface of a solid along a ray, in order.
+fn crossings(solid: &Solid, r: &Ray, from_a: bool) -> Vec<Crossing> {
+    let mut found = Vec::new();
+    let mut ray = *r;
+    let mut traveled = 0.0;
+    while found.len() < MAX_CROSSINGS {
+        let mut intersection = match solid.intersect(&ray) {
+            Some(intersection) => intersection,
+            None => break,
+        };
+        traveled += intersection.time;
+        intersection.time = traveled;
+        ray.origin = intersection.point + CROSSING_OFFSET * r.direction;
+        traveled += CROSSING_OFFSET;
+        found.push(Crossing {
+            entering: intersection.normal.dot(&r.direction) < 0.0,
+            intersection: intersection,
+            from_a: from_a,
+        });
+    }
+    found
+}
+
+impl Solid for Csg {
+    fn intersect(&self, r: &Ray) -> Option<Intersection> {
+        let from_a = crossings(self.a.as_ref(), r, true);
+        let from_b = crossings(self.b.as_ref(), r, false);
+
+        // A ray first leaving a solid starts inside it.
+        let mut inside_a = from_a.first().map_or(false, |c| !c.entering);
+        let mut inside_b = from_b.first().map_or(false, |c| !c.entering);
+        let mut inside = self.operation.contains(inside_a, inside_b);
+
+        let mut all = from_a;
+        all.extend(from_b);
+        all.sort_by(|x, y| {
+            x.intersection.time.partial_cmp(&y.intersection.time).unwrap()
+        });
+
+        for crossing in all.into_iter() {
+            if crossing.from_a {
+                inside_a = crossing.entering;
+            } else {
+                inside_b = crossing.entering;
+            }
+            let now_inside = self.operation.contains(inside_a, inside_b);
+            if now_inside != inside {
+                let mut intersection = crossing.intersection;
+                // The second solid's surfaces face inward where they are cut out of the first.
+                if self.operation == CsgOperation::Difference && !crossing.from_a {
+                    intersection.normal = -intersection.normal;
+                }
+                return Some(intersection);
+            }
+            inside = now_inside;
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let (a, b) = (self.a.bounding_box(), self.b.bounding_box());
+        match self.operation {
+            CsgOperation::Union => a.union(&b),
+            CsgOperation::Intersection => {
+                let lower = Point::new(
+                    a.lower.x.max(b.lower.x),
+                    a.lower.y.max(b.lower.y),
+                    a.lower.z.max(b.lower.z),
+                );
+                let upper = Point::new(
+                    a.upper.x.min(b.upper.x),
+                    a.upper.y.min(b.upper.y),
+                    a.upper.z.min(b.upper.z),
+                );
+                // Solids which do not overlap have nothing in common.
+                AABB::new(
+                    lower,
+                    Point::new(
+                        upper.x.max(lower.x),
+                        upper.y.max(lower.y),
+                        upper.z.max(lower.z),
+                    ),
+                )
+            }
+            CsgOperation::Difference => a,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self.operation {
+            CsgOperation::Union => "CSG union",
+            CsgOperation::Intersection => "CSG intersection",
+            CsgOperation::Difference => "CSG difference",
+        }
+    }
+
+    fn set_bvh_layout(&mut self, layout: BvhLayout) {
+        self.a.set_bvh_layout(layout);
+        self.b.set_bvh_layout(layout);
+    }
+}
+
+/// A solid moved by a transform, to position the parts of a `Csg` relative to each other.
+pub struct Transformed {
+    solid: Box<Solid>,
+    transform: Transform,
+}
+
+impl Transformed {
+    pub fn new(solid: Box<Solid>, transform: Transform) -> Transformed {
+        Transformed {
+            solid: solid,
+            transform: transform,
+        }
+    }
+}
+
+impl Solid for Transformed {
+    fn intersect(&self, r: &Ray) -> Option<Intersection> {
+        let local_ray = *self.transform.to_local() * (*r);
+        self.solid.intersect(&local_ray).map(|intersection| {
+            let mut world = self.transform * intersection;
+            world.time = (world.point - r.origin).dot(&r.direction);
+            // Non-uniform scales change the length of normals.
+            let _ = world.normal.normalize();
+            world
+        })
+    }
+
+    fn bounding_box(&self) -> AABB {
+        *self.transform.to_world() * self.solid.bounding_box()
+    }
+
+    fn name(&self) -> &'static str {
+        self.solid.name()
+    }
+
+    fn set_bvh_layout(&mut self, layout: BvhLayout) {
+        self.solid.set_bvh_layout(layout);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Csg, CsgOperation, Transformed};
+    use math::{Cylinder, Point, Ray, Solid, Sphere, Transform, Vector};
+
+    fn ray(origin: Point, direction: Vector) -> Ray {
+        let mut r = Ray {
+            origin: origin,
+            direction: direction,
+            time: 0.0,
+        };
+        r.normalize().unwrap();
+        r
+    }
+
+    /// Two unit spheres, centered 1 apart along X.
+    fn overlapping(operation: CsgOperation) -> Csg {
+        Csg::new(
+            operation,
+            Box::new(Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0)),
+            Box::new(Sphere::new(Point::new(1.0, 0.0, 0.0), 1.0)),
+        )
+    }
+
+    #[test]
+    fn test_operations() {
+        let along_x = ray(Point::new(-5.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+        let cases = [
+            (CsgOperation::Union, -1.0),
+            (CsgOperation::Intersection, 0.0),
+            (CsgOperation::Difference, -1.0),
+        ];
+        for &(operation, x) in cases.iter() {
+            let hit = overlapping(operation).intersect(&along_x).unwrap();
+            assert_relative_eq!(hit.point, Point::new(x, 0.0, 0.0), epsilon = 1e-3);
+            assert_relative_eq!(hit.normal, Vector::new(-1.0, 0.0, 0.0), epsilon = 1e-3);
+        }
+
+        // From the far side, the difference is first reached where the second sphere cut it.
+        let back = ray(Point::new(5.0, 0.0, 0.0), Vector::new(-1.0, 0.0, 0.0));
+        let hit = overlapping(CsgOperation::Difference).intersect(&back).unwrap();
+        assert_relative_eq!(hit.point, Point::new(0.0, 0.0, 0.0), epsilon = 1e-3);
+        assert_relative_eq!(hit.normal, Vector::new(1.0, 0.0, 0.0), epsilon = 1e-3);
+    }
+
+    #[test]
+    fn test_sphere_with_hole() {
+        // A cylinder along Z drilled through a sphere.
+        let drilled = Csg::new(
+            CsgOperation::Difference,
+            Box::new(Sphere::new_with_radius(2.0)),
+            Box::new(Cylinder::new(0.5, -3.0, 3.0, true)),
+        );
+        let through_hole = ray(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(drilled.intersect(&through_hole).is_none());
+
+        let beside_hole = ray(Point::new(1.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(drilled.intersect(&beside_hole).is_some());
+    }
+
+    #[test]
+    fn test_transformed_parts() {
+        let moved = Csg::new(
+            CsgOperation::Union,
+            Box::new(Sphere::new_with_radius(1.0)),
+            Box::new(Transformed::new(
+                Box::new(Sphere::new_with_radius(1.0)),
+                Transform::translate(0.0, 5.0, 0.0),
+            )),
+        );
+        let above = ray(Point::new(0.0, 5.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let hit = moved.intersect(&above).unwrap();
+        assert_relative_eq!(hit.point, Point::new(0.0, 5.0, -1.0), epsilon = 1e-4);
+        assert_relative_eq!(moved.bounding_box().upper, Point::new(1.0, 6.0, 1.0));
+    }
+}
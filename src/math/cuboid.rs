@@ -0,0 +1,208 @@
+use math::{AABB, Axis, Intersection, Point, Ray, Solid, Vector, XYZ};
+
+/// A solid box, with faces aligned to the axes.
+///
+/// Rotate it with the transform of its entity to align it differently.
+pub struct Cuboid {
+    bounds: AABB,
+}
+
+impl Cuboid {
+    /// # Panics
+    /// If the box has no volume, since `lower` is not below `upper` along every axis.
+    pub fn new(lower: Point, upper: Point) -> Cuboid {
+        assert!(
+            XYZ.iter().all(|a| lower[*a] < upper[*a]),
+            "A cuboid's lower corner must be below its upper corner along every axis."
+        );
+        Cuboid { bounds: AABB::new(lower, upper) }
+    }
+
+    /// Creates a box of the given size centered at the origin.
+    pub fn new_with_size(width: f32, height: f32, depth: f32) -> Cuboid {
+        let half = Vector::new(0.5 * width, 0.5 * height, 0.5 * depth);
+        let center = Point::new(0.0, 0.0, 0.0);
+        Cuboid::new(center + -half, center + half)
+    }
+
+    /// Texture coordinates across a face, from 0 to 1 along the two axes the face spans.
+    fn face_uv(&self, point: &Point, axis: Axis) -> (f32, f32) {
+        let (u_axis, v_axis) = face_uv_axes(axis);
+        let fraction = |a: Axis| {
+            (point[a] - self.bounds.lower[a]) / (self.bounds.upper[a] - self.bounds.lower[a])
+        };
+        (fraction(u_axis), fraction(v_axis))
+    }
+}
+
+/// Where a ray crosses the planes of a pair of opposite faces.
+struct Crossing {
+    time: f32,
+    axis: Axis,
+    /// Whether the face is at the upper end of the axis.
+    upper: bool,
+}
+
+impl Solid for Cuboid {
+    fn intersect(&self, r: &Ray) -> Option<Intersection> {
+        debug_assert!(r.is_normalized());
+
+        let mut near: Option<Crossing> = None;
+        let mut far: Option<Crossing> = None;
+        for &axis in XYZ.iter() {
+            let (lower, upper) = (self.bounds.lower[axis], self.bounds.upper[axis]);
+            let (origin, direction) = (r.origin[axis], r.direction[axis]);
+
+            // Parallel to the faces, so the ray never enters or leaves through them.
+            if direction == 0.0 {
+                if origin < lower || upper < origin {
+                    return None;
+                }
+                continue;
+            }
+
+            let to_lower = Crossing {
+                time: (lower - origin) / direction,
+                axis: axis,
+                upper: false,
+            };
+            let to_upper = Crossing {
+                time: (upper - origin) / direction,
+                axis: axis,
+                upper: true,
+            };
+            let (enter, exit) = if direction > 0.0 {
+                (to_lower, to_upper)
+            } else {
+                (to_upper, to_lower)
+            };
+            if near.as_ref().map_or(true, |n| enter.time > n.time) {
+                near = Some(enter);
+            }
+            if far.as_ref().map_or(true, |f| exit.time < f.time) {
+                far = Some(exit);
+            }
+        }
+
+        // A ray must cross at least one pair of faces.
+        let (near, far) = (near.unwrap(), far.unwrap());
+        if near.time > far.time || far.time < 0.0 {
+            return None;
+        }
+
+        // Rays starting inside the box leave through the far face.
+        let face = if near.time >= 0.0 { near } else { far };
+        let point = r.at(face.time);
+        let (u_axis, v_axis) = face_uv_axes(face.axis);
+        Some(Intersection {
+            time: face.time,
+            point: point,
+            normal: face_normal(face.axis, face.upper),
+            uv: self.face_uv(&point, face.axis),
+            tangent: face_normal(u_axis, true),
+            bitangent: face_normal(v_axis, true),
+            triangle: None,
+        })
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bounds
+    }
+
+    fn name(&self) -> &'static str {
+        "cuboid"
+    }
+}
+
+/// Axes along which U and V increase across the faces at the ends of an axis.
+fn face_uv_axes(axis: Axis) -> (Axis, Axis) {
+    match axis {
+        Axis::X => (Axis::Z, Axis::Y),
+        Axis::Y => (Axis::X, Axis::Z),
+        Axis::Z => (Axis::X, Axis::Y),
+    }
+}
+
+/// Unit vector out of the face at one end of an axis.
+fn face_normal(axis: Axis, upper: bool) -> Vector {
+    let sign = if upper { 1.0 } else { -1.0 };
+    match axis {
+        Axis::X => Vector::new(sign, 0.0, 0.0),
+        Axis::Y => Vector::new(0.0, sign, 0.0),
+        Axis::Z => Vector::new(0.0, 0.0, sign),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cuboid;
+    use math::{Point, Ray, Solid, Vector};
+
+    fn ray(origin: Point, direction: Vector) -> Ray {
+        let mut r = Ray {
+            origin: origin,
+            direction: direction,
+            time: 0.0,
+        };
+        r.normalize().unwrap();
+        r
+    }
+
+    #[test]
+    fn test_face_normals() {
+        let cuboid = Cuboid::new_with_size(2.0, 4.0, 6.0);
+        let cases = [
+            (Point::new(-5.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0), 4.0),
+            (Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0), 3.0),
+            (Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0), 2.0),
+        ];
+        for &(origin, direction, time) in cases.iter() {
+            let hit = cuboid.intersect(&ray(origin, direction)).unwrap();
+            assert_relative_eq!(hit.time, time);
+            assert_relative_eq!(hit.normal, -direction);
+        }
+    }
+
+    #[test]
+    fn test_oblique_hit() {
+        let cuboid = Cuboid::new(Point::new(0.0, 0.0, 0.0), Point::new(1.0, 1.0, 1.0));
+        let hit = cuboid
+            .intersect(&ray(Point::new(0.5, 1.5, 0.0), Vector::new(0.0, -1.0, 1.0)))
+            .unwrap();
+        assert_relative_eq!(hit.point, Point::new(0.5, 1.0, 0.5), epsilon = 1e-5);
+        assert_relative_eq!(hit.normal, Vector::new(0.0, 1.0, 0.0));
+        assert_relative_eq!(hit.uv.0, 0.5, epsilon = 1e-5);
+        assert_relative_eq!(hit.uv.1, 0.5, epsilon = 1e-5);
+
+        // Passing above the box.
+        assert!(
+            cuboid
+                .intersect(&ray(Point::new(0.5, 3.0, 0.0), Vector::new(0.0, -1.0, 1.0)))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_misses_and_inside() {
+        let cuboid = Cuboid::new_with_size(2.0, 2.0, 2.0);
+        assert!(
+            cuboid
+                .intersect(&ray(Point::new(0.0, 5.0, -5.0), Vector::new(0.0, 0.0, 1.0)))
+                .is_none()
+        );
+        assert!(
+            cuboid
+                .intersect(&ray(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, 1.0)))
+                .is_none()
+        );
+
+        // From the center, leaving through the top.
+        let hit = cuboid
+            .intersect(&ray(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0)))
+            .unwrap();
+        assert_relative_eq!(hit.time, 1.0);
+        assert_relative_eq!(hit.normal, Vector::new(0.0, 1.0, 0.0));
+        assert_relative_eq!(hit.uv.0, 0.5);
+        assert_relative_eq!(hit.uv.1, 0.5);
+    }
+}
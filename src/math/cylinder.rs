@@ -0,0 +1,185 @@
+use math::{AABB, Intersection, Point, Ray, Solid, Vector};
+use std::f32::consts::PI;
+
+/// A finite cylinder around the Z axis, optionally closed by flat caps at each end.
+///
+/// Rotate it with the transform of its entity to stand it along another axis.
+pub struct Cylinder {
+    radius: f32,
+    z_min: f32,
+    z_max: f32,
+    capped: bool,
+}
+
+impl Cylinder {
+    /// # Arguments
+    /// * `radius` - distance of the curved surface from the Z axis
+    /// * `z_min`, `z_max` - where the cylinder starts and ends along the Z axis
+    /// * `capped` - whether the ends are closed, rather than leaving an open tube
+    ///
+    /// # Panics
+    /// If the radius is not positive, or the cylinder has no length.
+    pub fn new(radius: f32, z_min: f32, z_max: f32, capped: bool) -> Cylinder {
+        assert!(radius > 0.0, "A cylinder's radius must be positive.");
+        assert!(z_min < z_max, "A cylinder must start before it ends.");
+        Cylinder {
+            radius: radius,
+            z_min: z_min,
+            z_max: z_max,
+            capped: capped,
+        }
+    }
+
+    /// Hits of the curved surface, which may be seen from inside an open tube.
+    fn side_hits(&self, r: &Ray) -> Vec<Intersection> {
+        // Solve (ox + t dx)^2 + (oy + t dy)^2 = radius^2.
+        let a = r.direction.x * r.direction.x + r.direction.y * r.direction.y;
+        if a == 0.0 {
+            // Parallel to the axis, so only the caps can be hit.
+            return Vec::new();
+        }
+        let b = 2.0 * (r.origin.x * r.direction.x + r.origin.y * r.direction.y);
+        let c = r.origin.x * r.origin.x + r.origin.y * r.origin.y - self.radius * self.radius;
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return Vec::new();
+        }
+        let root = discriminant.sqrt();
+        [(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+            .iter()
+            .map(|&t| (t, r.at(t)))
+            .filter(|&(t, p)| t > 0.0 && p.z >= self.z_min && p.z <= self.z_max)
+            .map(|(t, point)| {
+                let normal = Vector::new(point.x / self.radius, point.y / self.radius, 0.0);
+                let angle = point.y.atan2(point.x);
+                let angle = if angle < 0.0 { angle + 2.0 * PI } else { angle };
+                Intersection {
+                    time: t,
+                    point: point,
+                    normal: normal,
+                    uv: (
+                        angle / (2.0 * PI),
+                        (point.z - self.z_min) / (self.z_max - self.z_min),
+                    ),
+                    tangent: Vector::new(-angle.sin(), angle.cos(), 0.0),
+                    bitangent: Vector::new(0.0, 0.0, 1.0),
+                    triangle: None,
+                }
+            })
+            .collect()
+    }
+
+    /// Hits of the caps, if the cylinder has them.
+    fn cap_hits(&self, r: &Ray) -> Vec<Intersection> {
+        if !self.capped || r.direction.z == 0.0 {
+            return Vec::new();
+        }
+        [(self.z_min, -1.0), (self.z_max, 1.0)]
+            .iter()
+            .map(|&(z, side)| ((z - r.origin.z) / r.direction.z, side))
+            .map(|(t, side)| (t, r.at(t), side))
+            .filter(|&(t, p, _)| {
+                t > 0.0 && p.x * p.x + p.y * p.y <= self.radius * self.radius
+            })
+            .map(|(t, point, side)| {
+                Intersection {
+                    time: t,
+                    point: point,
+                    normal: Vector::new(0.0, 0.0, side),
+                    uv: (
+                        0.5 + 0.5 * point.x / self.radius,
+                        0.5 + 0.5 * point.y / self.radius,
+                    ),
+                    tangent: Vector::new(1.0, 0.0, 0.0),
+                    bitangent: Vector::new(0.0, 1.0, 0.0),
+                    triangle: None,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Solid for Cylinder {
+    fn intersect(&self, r: &Ray) -> Option<Intersection> {
+        debug_assert!(r.is_normalized());
+
+        let mut hits = self.side_hits(r);
+        hits.extend(self.cap_hits(r));
+        hits.into_iter().fold(None, |nearest, hit| match nearest {
+            Some(ref n) if n.time <= hit.time => nearest,
+            _ => Some(hit),
+        })
+    }
+
+    fn bounding_box(&self) -> AABB {
+        AABB::new(
+            Point::new(-self.radius, -self.radius, self.z_min),
+            Point::new(self.radius, self.radius, self.z_max),
+        )
+    }
+
+    fn name(&self) -> &'static str {
+        "cylinder"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cylinder;
+    use math::{Point, Ray, Solid, Vector};
+
+    fn ray(origin: Point, direction: Vector) -> Ray {
+        let mut r = Ray {
+            origin: origin,
+            direction: direction,
+            time: 0.0,
+        };
+        r.normalize().unwrap();
+        r
+    }
+
+    #[test]
+    fn test_side() {
+        let cylinder = Cylinder::new(2.0, 0.0, 10.0, true);
+        let hit = cylinder
+            .intersect(&ray(Point::new(-5.0, 0.0, 3.0), Vector::new(1.0, 0.0, 0.0)))
+            .unwrap();
+        assert_relative_eq!(hit.time, 3.0);
+        assert_relative_eq!(hit.normal, Vector::new(-1.0, 0.0, 0.0));
+        assert_relative_eq!(hit.uv.0, 0.5);
+        assert_relative_eq!(hit.uv.1, 0.3);
+
+        // Passing beyond the end.
+        assert!(
+            cylinder
+                .intersect(&ray(Point::new(-5.0, 0.0, 11.0), Vector::new(1.0, 0.0, 0.0)))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_caps() {
+        let down_the_axis = ray(Point::new(0.5, 0.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let capped = Cylinder::new(1.0, 0.0, 4.0, true);
+        let hit = capped.intersect(&down_the_axis).unwrap();
+        assert_relative_eq!(hit.time, 5.0);
+        assert_relative_eq!(hit.normal, Vector::new(0.0, 0.0, -1.0));
+        assert_relative_eq!(hit.uv.0, 0.75);
+
+        // An open tube is seen straight through.
+        let open = Cylinder::new(1.0, 0.0, 4.0, false);
+        assert!(open.intersect(&down_the_axis).is_none());
+    }
+
+    #[test]
+    fn test_inside_open_tube() {
+        // Looking into the open end, the inside of the far wall is visible.
+        let open = Cylinder::new(1.0, 0.0, 4.0, false);
+        let hit = open
+            .intersect(&ray(Point::new(0.0, 0.0, -1.0), Vector::new(1.0, 0.0, 2.0)))
+            .unwrap();
+        assert_relative_eq!(hit.point, Point::new(1.0, 0.0, 1.0), epsilon = 1e-5);
+        assert_relative_eq!(hit.normal, Vector::new(1.0, 0.0, 0.0), epsilon = 1e-5);
+    }
+}
@@ -0,0 +1,130 @@
+use math::{AABB, Intersection, Point, Ray, Solid, Vector};
+use std::f32::consts::PI;
+
+/// A flat disk facing along Z+, with an optional hole in its center.
+///
+/// Unlike a `Plane`, disks have a finite size, so they can be bounded for the BVH, and used as
+/// the shapes of lights or small patches of ground.
+pub struct Disk {
+    radius: f32,
+    inner_radius: f32,
+    /// Position of the disk along the Z axis.
+    height: f32,
+}
+
+impl Disk {
+    /// # Arguments
+    /// * `radius` - outer edge of the disk, from its center
+    /// * `inner_radius` - edge of the hole in the center, 0 for no hole
+    /// * `height` - where the disk lies along the Z axis
+    ///
+    /// # Panics
+    /// If the radius is not positive, or the hole is larger than the disk.
+    pub fn new(radius: f32, inner_radius: f32, height: f32) -> Disk {
+        assert!(radius > 0.0, "A disk's radius must be positive.");
+        assert!(
+            inner_radius >= 0.0 && inner_radius < radius,
+            "A disk's hole must be smaller than the disk."
+        );
+        Disk {
+            radius: radius,
+            inner_radius: inner_radius,
+            height: height,
+        }
+    }
+
+    /// Creates a disk without a hole at the origin.
+    pub fn new_with_radius(radius: f32) -> Disk {
+        Disk::new(radius, 0.0, 0.0)
+    }
+}
+
+impl Solid for Disk {
+    fn intersect(&self, r: &Ray) -> Option<Intersection> {
+        debug_assert!(r.is_normalized());
+
+        // Parallel to the disk.
+        if r.direction.z == 0.0 {
+            return None;
+        }
+        let t = (self.height - r.origin.z) / r.direction.z;
+        if t <= 0.0 {
+            return None;
+        }
+        let point = r.at(t);
+        let distance = (point.x * point.x + point.y * point.y).sqrt();
+        if distance > self.radius || distance < self.inner_radius {
+            return None;
+        }
+
+        let angle = point.y.atan2(point.x);
+        let angle = if angle < 0.0 { angle + 2.0 * PI } else { angle };
+        Some(Intersection {
+            time: t,
+            point: point,
+            normal: Vector::new(0.0, 0.0, 1.0),
+            uv: (
+                angle / (2.0 * PI),
+                (self.radius - distance) / (self.radius - self.inner_radius),
+            ),
+            tangent: Vector::new(-angle.sin(), angle.cos(), 0.0),
+            // V increases toward the center.
+            bitangent: Vector::new(-angle.cos(), -angle.sin(), 0.0),
+            triangle: None,
+        })
+    }
+
+    fn bounding_box(&self) -> AABB {
+        AABB::new(
+            Point::new(-self.radius, -self.radius, self.height),
+            Point::new(self.radius, self.radius, self.height),
+        )
+    }
+
+    fn name(&self) -> &'static str {
+        "disk"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Disk;
+    use math::{Point, Ray, Solid, Vector};
+
+    fn ray(origin: Point, direction: Vector) -> Ray {
+        let mut r = Ray {
+            origin: origin,
+            direction: direction,
+            time: 0.0,
+        };
+        r.normalize().unwrap();
+        r
+    }
+
+    #[test]
+    fn test_intersection() {
+        let disk = Disk::new(2.0, 0.5, 1.0);
+        let hit = disk
+            .intersect(&ray(Point::new(1.0, 0.0, -3.0), Vector::new(0.0, 0.0, 1.0)))
+            .unwrap();
+        assert_relative_eq!(hit.time, 4.0);
+        assert_relative_eq!(hit.point, Point::new(1.0, 0.0, 1.0));
+        assert_relative_eq!(hit.normal, Vector::new(0.0, 0.0, 1.0));
+        assert_relative_eq!(hit.uv.1, 2.0 / 3.0);
+    }
+
+    #[test]
+    fn test_misses() {
+        let disk = Disk::new(2.0, 0.5, 0.0);
+        let down_z = Vector::new(0.0, 0.0, 1.0);
+
+        // Through the hole, past the edge, parallel and behind.
+        assert!(disk.intersect(&ray(Point::new(0.2, 0.0, -1.0), down_z)).is_none());
+        assert!(disk.intersect(&ray(Point::new(3.0, 0.0, -1.0), down_z)).is_none());
+        assert!(
+            disk.intersect(&ray(Point::new(-5.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0)))
+                .is_none()
+        );
+        assert!(disk.intersect(&ray(Point::new(1.0, 0.0, 1.0), down_z)).is_none());
+    }
+}
@@ -1,6 +1,6 @@
 use std::ops::Mul;
 
-use math::{Matrix4x4, Point, Ray, Vector};
+use math::{AABB, BvhLayout, Matrix4x4, Point, Ray, Transform, Vector};
 
 /// Provides intersection information for use by the renderer.
 /// In general, intersections need to provide the time, point, and surface normal.
@@ -9,6 +9,38 @@ pub struct Intersection {
     pub time: f32,
     pub point: Point,
     pub normal: Vector,
+    /// Texture coordinates of the intersection point on the surface.
+    pub uv: (f32, f32),
+    /// Directions along the surface in which U and V increase, to orient details such as normal
+    /// maps.
+    pub tangent: Vector,
+    pub bitangent: Vector,
+    /// Where on a triangle the intersection occurred, for solids made of triangles.
+    pub triangle: Option<TriangleHit>,
+}
+
+/// The position of an intersection on a triangle of a mesh.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TriangleHit {
+    /// Which triangle of the mesh was hit.
+    pub index: usize,
+    /// Weights of each of the triangle's vertices giving the intersection point, which sum to 1.
+    /// Each is 0 along the edge opposite its vertex.
+    pub barycentric: [f32; 3],
+}
+
+/// Unit vectors perpendicular to each other and to a unit normal, for surfaces with no natural
+/// direction along them.
+pub fn perpendicular_tangents(normal: &Vector) -> (Vector, Vector) {
+    let helper = if normal.x.abs() < 0.9 {
+        Vector::new(1.0, 0.0, 0.0)
+    } else {
+        Vector::new(0.0, 1.0, 0.0)
+    };
+    let mut tangent = helper - normal.dot(&helper) * *normal;
+    tangent.normalize().expect("Normal has no perpendicular tangent.");
+    let bitangent = tangent.cross(normal);
+    (tangent, bitangent)
 }
 
 
@@ -18,15 +50,43 @@ impl Mul<Intersection> for Matrix4x4 {
         Intersection {
             time: i.time,
             point: self * i.point,
-            normal: self.inverse().unwrap().transpose() * i.normal,
+            normal: self.affine_or_full_inverse().unwrap().transpose() * i.normal,
+            uv: i.uv,
+            tangent: self * i.tangent,
+            bitangent: self * i.bitangent,
+            triangle: i.triangle,
         }
     }
 }
 
-pub trait Solid {
+/// Converts an intersection in local coordinates to world coordinates, using the inverse kept by
+/// the transform to convert the normal.
+impl Mul<Intersection> for Transform {
+    type Output = Intersection;
+    fn mul(self, i: Intersection) -> Self::Output {
+        Intersection {
+            time: i.time,
+            point: *self.to_world() * i.point,
+            normal: self.to_local().transpose() * i.normal,
+            uv: i.uv,
+            tangent: *self.to_world() * i.tangent,
+            bitangent: *self.to_world() * i.bitangent,
+            triangle: i.triangle,
+        }
+    }
+}
+
+/// Solids are shared between rendering threads.
+pub trait Solid: Send + Sync {
     /// Provides intersection reporting against a ray.
     fn intersect(&self, r: &Ray) -> Option<Intersection>;
 
+    /// A box containing the entire solid, infinite if the solid extends forever.
+    fn bounding_box(&self) -> AABB;
+
+    /// Short name of the kind of solid, such as "sphere", for summaries of scenes.
+    fn name(&self) -> &'static str;
+
     /// A simpler interface for just seeing if an intersection exists.
     fn hits(&self, r: &Ray) -> bool {
         match self.intersect(r) {
@@ -34,4 +94,14 @@ pub trait Solid {
             None => false,
         }
     }
+
+    /// Rebuilds any hierarchy the solid keeps over its parts with another node layout.  Solids
+    /// without parts ignore this.
+    fn set_bvh_layout(&mut self, _layout: BvhLayout) {}
+
+    /// The corners of the triangles the solid is made of, so glowing ones can be sampled like
+    /// lights.  Solids with curved or infinite surfaces have none.
+    fn triangles(&self) -> Vec<[Point; 3]> {
+        Vec::new()
+    }
 }
@@ -19,6 +19,7 @@ use std::ops::Mul;
 /// current coordinate system will be transformed.
 ///
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Matrix4x4 {
     m: [[f32; 4]; 4],
 }
@@ -58,6 +59,113 @@ impl Matrix4x4 {
         }
     }
 
+    /// Generates a rotation about the X axis, turning Y+ toward Z+.
+    pub fn rotate_x(angle: PlanarAngle) -> Matrix4x4 {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Matrix4x4 {
+            m: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, cos, -sin, 0.0],
+                [0.0, sin, cos, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    /// Generates a rotation about the Y axis, turning Z+ toward X+.
+    pub fn rotate_y(angle: PlanarAngle) -> Matrix4x4 {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Matrix4x4 {
+            m: [
+                [cos, 0.0, sin, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [-sin, 0.0, cos, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    /// Generates a rotation about the Z axis, turning X+ toward Y+.
+    pub fn rotate_z(angle: PlanarAngle) -> Matrix4x4 {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Matrix4x4 {
+            m: [
+                [cos, -sin, 0.0, 0.0],
+                [sin, cos, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    /// Generates a rotation about an arbitrary axis through the origin, using Rodrigues' rotation
+    /// formula.  Rotations about the X, Y and Z axes match `rotate_x`, `rotate_y` and `rotate_z`.
+    ///
+    /// # Returns
+    /// `None` if the axis has no length.
+    pub fn rotate_axis_angle(axis: &Vector, angle: PlanarAngle) -> Option<Matrix4x4> {
+        let mut a = *axis;
+        if a.normalize().is_err() {
+            return None;
+        }
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let t = 1.0 - cos;
+        Some(Matrix4x4 {
+            m: [
+                [
+                    t * a.x * a.x + cos,
+                    t * a.x * a.y - sin * a.z,
+                    t * a.x * a.z + sin * a.y,
+                    0.0,
+                ],
+                [
+                    t * a.x * a.y + sin * a.z,
+                    t * a.y * a.y + cos,
+                    t * a.y * a.z - sin * a.x,
+                    0.0,
+                ],
+                [
+                    t * a.x * a.z - sin * a.y,
+                    t * a.y * a.z + sin * a.x,
+                    t * a.z * a.z + cos,
+                    0.0,
+                ],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        })
+    }
+
+    /// Creates a transform whose columns are the given basis vectors and origin.
+    pub fn from_basis(x: &Vector, y: &Vector, z: &Vector, origin: &Point) -> Matrix4x4 {
+        Matrix4x4 {
+            m: [
+                [x.x, y.x, z.x, origin.x],
+                [x.y, y.y, z.y, origin.y],
+                [x.z, y.z, z.z, origin.z],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    /// Generates a transform from a viewer's coordinate system, where Z+ goes into the screen, to
+    /// a viewer at `eye` looking at `target`.
+    ///
+    /// # Arguments
+    /// * `eye` - position of the viewer
+    /// * `target` - point the viewer looks at
+    /// * `up` - the upward direction of the viewer, which cannot be parallel to the view direction
+    ///
+    /// # Returns
+    /// `None` if the view direction cannot be determined, or is parallel to `up`.
+    pub fn look_at(eye: &Point, target: &Point, up: &Vector) -> Option<Matrix4x4> {
+        let mut forward = *target - *eye;
+        forward.normalize().ok()?;
+        let mut right = up.cross(&forward);
+        right.normalize().ok()?;
+        let true_up = forward.cross(&right);
+        Some(Matrix4x4::from_basis(&right, &true_up, &forward, eye))
+    }
+
     /// Generates a perspective transform, in a coordinate system with X+ going to the right,
     /// Y+ going up, and Z+ going into the screen.
     ///
@@ -105,6 +213,37 @@ impl Matrix4x4 {
         }
     }
 
+    /// Generates an orthographic transform, in a coordinate system with X+ going to the right,
+    /// Y+ going up, and Z+ going into the screen.
+    ///
+    /// Like `perspective`, the shorter dimension will scale to [-1, 1], and Z values from
+    /// [near, far] to [0, 1], but sizes do not change with distance.
+    ///
+    /// # Arguments
+    /// * `near` - Z value of the near plane
+    /// * `far` - Z value of far plane
+    /// * `half_extent` - distance from the center of the view to the edge of the shorter
+    ///   dimension
+    ///
+    /// # Preconditions
+    /// * `near < far`
+    /// * `0 < half_extent`
+    pub fn orthographic(near: f32, far: f32, half_extent: f32) -> Matrix4x4 {
+        assert!(near < far, "The near plane must be behind the far plane.");
+        assert!(
+            half_extent > 0.0,
+            "An orthographic view must have a positive size."
+        );
+        Matrix4x4 {
+            m: [
+                [1.0 / half_extent, 0.0, 0.0, 0.0],
+                [0.0, 1.0 / half_extent, 0.0, 0.0],
+                [0.0, 0.0, 1.0 / (far - near), -near / (far - near)],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
     /// Use Gauss-Jordan elimination to find the matrix inverse.
     /// After elimination is complete, reduce the left side to an identity matrix to get the result.
     pub fn inverse(&self) -> Option<Matrix4x4> {
@@ -186,6 +325,57 @@ impl Matrix4x4 {
         Some(inv)
     }
 
+    /// Whether the matrix keeps a bottom row of `[0, 0, 0, 1]`, only rotating, scaling, shearing
+    /// and translating, as matrices from every constructor other than `perspective` do.
+    pub fn is_affine(&self) -> bool {
+        self.m[3] == [0.0, 0.0, 0.0, 1.0]
+    }
+
+    /// Inverts an affine matrix directly, which is much cheaper than the elimination `inverse`
+    /// does.
+    ///
+    /// The upper left 3x3 part is inverted from its cofactors, so rotations are undone by their
+    /// transposes, and the translation is undone by moving back by it in the inverted frame.
+    ///
+    /// # Returns
+    /// `None` if the matrix cannot be inverted.
+    ///
+    /// # Panics
+    /// If the matrix is not affine, see `is_affine`.
+    pub fn fast_affine_inverse(&self) -> Option<Matrix4x4> {
+        assert!(self.is_affine(), "Only affine matrices can be inverted as affine.");
+        let a = &self.m;
+        let cofactor = |i: usize, j: usize| {
+            let (r0, r1) = ((i + 1) % 3, (i + 2) % 3);
+            let (c0, c1) = ((j + 1) % 3, (j + 2) % 3);
+            a[r0][c0] * a[r1][c1] - a[r0][c1] * a[r1][c0]
+        };
+        let det = a[0][0] * cofactor(0, 0) + a[0][1] * cofactor(0, 1) + a[0][2] * cofactor(0, 2);
+        if det == 0.0 {
+            return None;
+        }
+
+        let mut inv = Matrix4x4::identity();
+        for i in 0..3 {
+            for j in 0..3 {
+                inv.m[i][j] = cofactor(j, i) / det;
+            }
+        }
+        for i in 0..3 {
+            inv.m[i][3] = -(inv.m[i][0] * a[0][3] + inv.m[i][1] * a[1][3] + inv.m[i][2] * a[2][3]);
+        }
+        Some(inv)
+    }
+
+    /// Inverts the matrix, directly if it is affine and by elimination otherwise.
+    pub fn affine_or_full_inverse(&self) -> Option<Matrix4x4> {
+        if self.is_affine() {
+            self.fast_affine_inverse()
+        } else {
+            self.inverse()
+        }
+    }
+
     pub fn transpose(&self) -> Matrix4x4 {
         let mut n: [[f32; 4]; 4] = [[0.0; 4]; 4];
         for i in 0..4 {
@@ -195,6 +385,21 @@ impl Matrix4x4 {
         }
         Matrix4x4 { m: n }
     }
+
+    /// Blends each element with the corresponding one of another matrix.
+    ///
+    /// # Arguments
+    /// * `other` - matrix reached when `t` is 1
+    /// * `t` - fraction of the way from this matrix to `other`
+    pub fn lerp(&self, other: &Matrix4x4, t: f32) -> Matrix4x4 {
+        let mut n: [[f32; 4]; 4] = [[0.0; 4]; 4];
+        for i in 0..4 {
+            for j in 0..4 {
+                n[i][j] = self.m[i][j] + t * (other.m[i][j] - self.m[i][j]);
+            }
+        }
+        Matrix4x4 { m: n }
+    }
 }
 
 impl fmt::Debug for Matrix4x4 {
@@ -364,6 +569,7 @@ impl Mul<Ray> for Matrix4x4 {
         Ray {
             origin: self * r.origin,
             direction: new_direction,
+            time: r.time,
         }
     }
 }
@@ -371,7 +577,9 @@ impl Mul<Ray> for Matrix4x4 {
 #[cfg(test)]
 mod tests {
     use super::Matrix4x4;
-    use math::{PlanarAngle, Point};
+    use math::{PlanarAngle, Point, Vector};
+    use math::strategies::{matrix, point, vector};
+    use std::f32::consts::PI;
 
     #[test]
     pub fn test_identity() {
@@ -438,6 +646,41 @@ mod tests {
         assert_relative_eq!((p * center_far).z, 1.0);
     }
 
+    #[test]
+    pub fn test_orthographic_points() {
+        let o = Matrix4x4::orthographic(10.0, 100.0, 5.0);
+
+        // Sizes stay the same at any distance.
+        for &z in [10.0, 55.0, 100.0].iter() {
+            let corner = o * Point::new(5.0, -5.0, z);
+            assert_relative_eq!(corner.x, 1.0);
+            assert_relative_eq!(corner.y, -1.0);
+        }
+        assert_relative_eq!((o * Point::new(0.0, 0.0, 10.0)).z, 0.0);
+        assert_relative_eq!((o * Point::new(0.0, 0.0, 100.0)).z, 1.0);
+    }
+
+    #[test]
+    pub fn test_look_at() {
+        let eye = Point::new(1.0, 2.0, 3.0);
+        let m = Matrix4x4::look_at(&eye, &Point::new(1.0, 2.0, 10.0), &Vector::new(0.0, 1.0, 0.0))
+            .unwrap();
+        assert_relative_eq!(m, Matrix4x4::translate(1.0, 2.0, 3.0));
+
+        // Looking down -X, so the viewer's right is +Z.
+        let m = Matrix4x4::look_at(&eye, &Point::new(-5.0, 2.0, 3.0), &Vector::new(0.0, 1.0, 0.0))
+            .unwrap();
+        assert_relative_eq!(m * Point::new(0.0, 0.0, 1.0), Point::new(0.0, 2.0, 3.0));
+        assert_relative_eq!(m * Vector::new(1.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        assert_relative_eq!(m * Vector::new(0.0, 1.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+
+        assert!(Matrix4x4::look_at(&eye, &eye, &Vector::new(0.0, 1.0, 0.0)).is_none());
+        assert!(
+            Matrix4x4::look_at(&eye, &Point::new(1.0, 5.0, 3.0), &Vector::new(0.0, 1.0, 0.0))
+                .is_none()
+        );
+    }
+
     #[test]
     pub fn test_scale() {
         let p = Point::new(1.0, 2.0, 3.0);
@@ -458,4 +701,156 @@ mod tests {
         let zero_matrix = Matrix4x4 { m: [[0.0; 4]; 4] };
         assert!(zero_matrix.inverse().is_none());
     }
+
+    #[test]
+    pub fn test_rotate_axes() {
+        let x = Vector::new(1.0, 0.0, 0.0);
+        let y = Vector::new(0.0, 1.0, 0.0);
+        let z = Vector::new(0.0, 0.0, 1.0);
+        let quarter = PlanarAngle::Degrees(90.0);
+        let eps = 1e-6;
+        assert_relative_eq!(Matrix4x4::rotate_x(quarter) * y, z, epsilon = eps);
+        assert_relative_eq!(Matrix4x4::rotate_y(quarter) * z, x, epsilon = eps);
+        assert_relative_eq!(Matrix4x4::rotate_z(quarter) * x, y, epsilon = eps);
+
+        // Points on the axis stay put, and others keep their distance from it.
+        let p = Point::new(2.0, 3.0, 4.0);
+        let rotated = Matrix4x4::rotate_y(PlanarAngle::Radians(0.7)) * p;
+        assert_relative_eq!(rotated.y, 3.0);
+        assert_relative_eq!(rotated.x * rotated.x + rotated.z * rotated.z, 20.0, epsilon = 1e-4);
+    }
+
+    #[test]
+    pub fn test_rotate_axis_angle() {
+        let angle = PlanarAngle::Degrees(35.0);
+        let axes = [
+            (Vector::new(2.0, 0.0, 0.0), Matrix4x4::rotate_x(angle)),
+            (Vector::new(0.0, 1.0, 0.0), Matrix4x4::rotate_y(angle)),
+            (Vector::new(0.0, 0.0, 0.5), Matrix4x4::rotate_z(angle)),
+        ];
+        for &(ref axis, ref expected) in axes.iter() {
+            assert_relative_eq!(
+                Matrix4x4::rotate_axis_angle(axis, angle).unwrap(),
+                *expected,
+                epsilon = 1e-6
+            );
+        }
+
+        // A third of a turn about the diagonal cycles the axes.
+        let diagonal = Vector::new(1.0, 1.0, 1.0);
+        let m = Matrix4x4::rotate_axis_angle(&diagonal, PlanarAngle::Degrees(120.0)).unwrap();
+        assert_relative_eq!(
+            m * Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+            epsilon = 1e-6
+        );
+        assert_relative_eq!(m * diagonal, diagonal, epsilon = 1e-6);
+        assert_relative_eq!(m.inverse().unwrap(), m.transpose(), epsilon = 1e-6);
+
+        let zero = Vector::new(0.0, 0.0, 0.0);
+        assert!(Matrix4x4::rotate_axis_angle(&zero, angle).is_none());
+    }
+
+    #[test]
+    fn test_fast_affine_inverse() {
+        let eye = Point::new(1.0, 2.0, 3.0);
+        let target = Point::new(-4.0, 0.0, 5.0);
+        let matrices = [
+            Matrix4x4::identity(),
+            Matrix4x4::translate(7.0, -8.0, 9.0),
+            Matrix4x4::scale(2.0, -0.5, 4.0),
+            Matrix4x4::rotate_x(PlanarAngle::Radians(PI / 3.0)),
+            Matrix4x4::look_at(&eye, &target, &Vector::new(0.0, 1.0, 0.0)).unwrap(),
+            Matrix4x4::translate(1.0, 2.0, 3.0) * Matrix4x4::rotate_z(PlanarAngle::Degrees(30.0)) *
+                Matrix4x4::scale(3.0, 2.0, 1.0),
+        ];
+        for m in matrices.iter() {
+            assert!(m.is_affine());
+            assert_relative_eq!(
+                m.fast_affine_inverse().unwrap(),
+                m.inverse().unwrap(),
+                epsilon = 1e-5
+            );
+        }
+
+        // Rotations are undone exactly by their transposes.
+        let rotation = Matrix4x4::rotate_y(PlanarAngle::Degrees(40.0));
+        assert_relative_eq!(rotation.fast_affine_inverse().unwrap(), rotation.transpose());
+
+        assert!(Matrix4x4::scale(1.0, 0.0, 1.0).fast_affine_inverse().is_none());
+        let perspective = Matrix4x4::perspective(1.0, 10.0, PlanarAngle::Degrees(60.0));
+        assert!(!perspective.is_affine());
+        assert_relative_eq!(
+            perspective.affine_or_full_inverse().unwrap(),
+            perspective.inverse().unwrap()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_fast_affine_inverse(m in matrix()) {
+            assert_relative_eq!(
+                m.fast_affine_inverse().unwrap(),
+                m.inverse().unwrap(),
+                epsilon = 1e-3,
+                max_relative = 1e-3
+            );
+        }
+
+        #[test]
+        fn prop_inverse_undoes_matrix(m in matrix(), p in point(), v in vector()) {
+            let inverse = m.inverse().unwrap();
+            assert_relative_eq!(inverse * m, Matrix4x4::identity(), epsilon = 1e-3);
+            assert_relative_eq!(m * inverse, Matrix4x4::identity(), epsilon = 1e-3);
+            assert_relative_eq!(inverse * (m * p), p, epsilon = 1e-3);
+            assert_relative_eq!(inverse * (m * v), v, epsilon = 1e-3);
+        }
+
+        #[test]
+        fn prop_inverse_of_product(a in matrix(), b in matrix()) {
+            let product = (a * b).inverse().unwrap();
+            let inverses = b.inverse().unwrap() * a.inverse().unwrap();
+            assert_relative_eq!(product, inverses, epsilon = 1e-2, max_relative = 1e-3);
+        }
+
+        #[test]
+        fn prop_transpose(a in matrix(), b in matrix()) {
+            assert_relative_eq!(a.transpose().transpose(), a);
+            assert_relative_eq!(
+                (a * b).transpose(),
+                b.transpose() * a.transpose(),
+                epsilon = 1e-3,
+                max_relative = 1e-5
+            );
+            assert_relative_eq!(
+                a.transpose().inverse().unwrap(),
+                a.inverse().unwrap().transpose(),
+                epsilon = 1e-3,
+                max_relative = 1e-3
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        use ron;
+
+        let m = Matrix4x4::translate(1.0, 2.0, 3.0) * Matrix4x4::scale(0.5, 4.0, 2.0);
+        let text = ron::ser::to_string(&m).unwrap();
+        let read: Matrix4x4 = ron::de::from_str(&text).unwrap();
+        assert_relative_eq!(read, m);
+
+        let v = Vector::new(1.5, -2.0, 0.25);
+        let read: Vector = ron::de::from_str(&ron::ser::to_string(&v).unwrap()).unwrap();
+        assert_relative_eq!(read, v);
+
+        let p = Point::new(-3.0, 0.0, 7.5);
+        let read: Point = ron::de::from_str(&ron::ser::to_string(&p).unwrap()).unwrap();
+        assert_relative_eq!(read, p);
+
+        let angle = PlanarAngle::Degrees(30.0);
+        let read: PlanarAngle = ron::de::from_str(&ron::ser::to_string(&angle).unwrap()).unwrap();
+        assert_relative_eq!(read.to_radians(), angle.to_radians());
+    }
 }
@@ -3,21 +3,43 @@
 mod aabb;
 mod angle;
 mod axis;
+mod bvh;
+mod cone;
+mod csg;
+mod cuboid;
+mod cylinder;
+mod disk;
 mod intersection;
 mod matrix;
+mod noise;
 mod plane;
 mod point;
+mod quaternion;
 mod ray;
 mod sphere;
+#[cfg(test)]
+pub mod strategies;
+mod transform;
+mod triangle_mesh;
 mod vector;
 pub use self::aabb::AABB;
 pub use self::angle::PlanarAngle;
 pub use self::axis::Axis;
 pub use self::axis::XYZ;
-pub use self::intersection::{Intersection, Solid};
+pub use self::bvh::{Bvh, BvhLayout};
+pub use self::cone::Cone;
+pub use self::csg::{Csg, CsgOperation, Transformed};
+pub use self::cuboid::Cuboid;
+pub use self::cylinder::Cylinder;
+pub use self::disk::Disk;
+pub use self::intersection::{Intersection, Solid, TriangleHit, perpendicular_tangents};
 pub use self::matrix::Matrix4x4;
+pub use self::noise::{Perlin, Worley};
 pub use self::plane::Plane;
 pub use self::point::Point;
+pub use self::quaternion::Quaternion;
 pub use self::ray::Ray;
-pub use self::sphere::Sphere;
+pub use self::sphere::{Sphere, sphere_uv};
+pub use self::transform::{AnimatedTransform, Transform};
+pub use self::triangle_mesh::TriangleMesh;
 pub use self::vector::Vector;
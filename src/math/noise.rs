@@ -0,0 +1,202 @@
+//! Smoothly varying pseudo-random values throughout space, for procedural textures.
+use math::Point;
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+/// Number of lattice cells along each axis before the noise repeats.
+const PERIOD: usize = 256;
+
+/// A shuffled ordering of lattice indices, hashing integer coordinates to pseudo-random values.
+#[derive(Clone)]
+struct Permutation {
+    // Repeated twice, so sums of indices need no wrapping.
+    indices: Vec<usize>,
+}
+
+impl Permutation {
+    fn new(seed: u32) -> Permutation {
+        let mut rng = XorShiftRng::from_seed([
+            0x5851_f42d ^ seed,
+            0x4c95_7f2d,
+            0x1405_7b7e,
+            0xf767_814f,
+        ]);
+        let mut indices: Vec<usize> = (0..PERIOD).collect();
+        for i in (1..PERIOD).rev() {
+            let j = (rng.next_u32() as usize) % (i + 1);
+            indices.swap(i, j);
+        }
+        let repeated = indices.clone();
+        indices.extend(repeated);
+        Permutation { indices: indices }
+    }
+
+    /// A value from 0 to `PERIOD` for a lattice point.
+    fn hash(&self, x: i64, y: i64, z: i64) -> usize {
+        let wrap = |i: i64| (i & (PERIOD as i64 - 1)) as usize;
+        self.indices[self.indices[self.indices[wrap(x)] + wrap(y)] + wrap(z)]
+    }
+}
+
+/// Gradient noise, smooth at every scale above a lattice cell and ranging from about -1 to 1.
+///
+/// Noise is 0 at every lattice point, at integer coordinates.
+#[derive(Clone)]
+pub struct Perlin {
+    permutation: Permutation,
+}
+
+impl Perlin {
+    /// Noise which is the same for every use of the same seed.
+    pub fn new(seed: u32) -> Perlin {
+        Perlin { permutation: Permutation::new(seed) }
+    }
+
+    pub fn noise(&self, p: &Point) -> f32 {
+        let (x0, y0, z0) = (p.x.floor(), p.y.floor(), p.z.floor());
+        let (x, y, z) = (p.x - x0, p.y - y0, p.z - z0);
+        let (xi, yi, zi) = (x0 as i64, y0 as i64, z0 as i64);
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let corner = |dx: i64, dy: i64, dz: i64| {
+            gradient(
+                self.permutation.hash(xi + dx, yi + dy, zi + dz),
+                x - dx as f32,
+                y - dy as f32,
+                z - dz as f32,
+            )
+        };
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+                lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+            ),
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+                lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+            ),
+        )
+    }
+
+    /// Sum of the magnitude of noise at increasingly fine scales, each half the size and weight
+    /// of the last, for a rough, billowy look.
+    ///
+    /// # Arguments
+    /// * `octaves` - number of scales to sum
+    pub fn turbulence(&self, p: &Point, octaves: u32) -> f32 {
+        let mut sum = 0.0;
+        let mut weight = 1.0;
+        let mut scaled = *p;
+        for _ in 0..octaves {
+            sum += weight * self.noise(&scaled).abs();
+            weight *= 0.5;
+            scaled = Point::new(2.0 * scaled.x, 2.0 * scaled.y, 2.0 * scaled.z);
+        }
+        sum
+    }
+}
+
+/// Cellular noise, the distance to the nearest of a set of points scattered one per lattice
+/// cell.
+///
+/// Distances are 0 at the scattered points, and rarely more than 1.
+#[derive(Clone)]
+pub struct Worley {
+    permutation: Permutation,
+}
+
+impl Worley {
+    /// Noise which is the same for every use of the same seed.
+    pub fn new(seed: u32) -> Worley {
+        Worley { permutation: Permutation::new(seed) }
+    }
+
+    pub fn distance(&self, p: &Point) -> f32 {
+        let (xi, yi, zi) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+        let mut nearest_squared = ::std::f32::INFINITY;
+        for dz in -1..2 {
+            for dy in -1..2 {
+                for dx in -1..2 {
+                    let feature = self.feature_point(xi + dx, yi + dy, zi + dz);
+                    let offset = feature - *p;
+                    nearest_squared = nearest_squared.min(offset.dot(&offset));
+                }
+            }
+        }
+        nearest_squared.sqrt()
+    }
+
+    /// The scattered point within a lattice cell.
+    pub fn feature_point(&self, x: i64, y: i64, z: i64) -> Point {
+        let hash = |salt: i64| self.permutation.hash(x + salt, y, z) as f32 / PERIOD as f32;
+        Point::new(x as f32 + hash(0), y as f32 + hash(71), z as f32 + hash(163))
+    }
+}
+
+/// Smoothing curve with zero first and second derivatives at 0 and 1.
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+/// Dot product of an offset with one of twelve gradients along the edges of a cube.
+fn gradient(hash: usize, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Perlin, Worley};
+    use math::Point;
+
+    #[test]
+    fn test_perlin() {
+        let perlin = Perlin::new(1);
+        assert_eq!(perlin.noise(&Point::new(3.0, -2.0, 7.0)), 0.0);
+
+        let mut varies = false;
+        for i in 0..100 {
+            let p = Point::new(0.37 * i as f32, 0.11 * i as f32, -0.23 * i as f32);
+            let value = perlin.noise(&p);
+            assert!(value >= -1.5 && value <= 1.5);
+            varies = varies || value.abs() > 0.1;
+
+            // Nearby points have similar values.
+            let nearby = perlin.noise(&Point::new(p.x + 1e-3, p.y, p.z));
+            assert!((nearby - value).abs() < 0.01);
+
+            assert_eq!(value, Perlin::new(1).noise(&p));
+        }
+        assert!(varies);
+
+        let p = Point::new(0.5, 0.25, 0.75);
+        assert!(perlin.turbulence(&p, 4) >= perlin.noise(&p).abs());
+    }
+
+    #[test]
+    fn test_worley() {
+        let worley = Worley::new(7);
+        let feature = worley.feature_point(2, -3, 5);
+        assert_eq!(worley.distance(&feature), 0.0);
+
+        for i in 0..100 {
+            let p = Point::new(0.37 * i as f32, 0.11 * i as f32, -0.23 * i as f32);
+            let distance = worley.distance(&p);
+            assert!(distance >= 0.0 && distance < 3.0f32.sqrt());
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use math::{Intersection, Point, Ray, Solid, Vector};
+use math::{AABB, Intersection, Point, Ray, Solid, Vector, perpendicular_tangents};
+use std::f32;
 
 /// An infinitely stretching plane defined by a normal, and the distance from the coordinate system
 /// origin to the plane.
@@ -58,17 +59,51 @@ impl Solid for Plane {
         let vd = normal.dot(&r.direction);
         let t = v0 / vd;
 
+        let point = r.at(t);
+        // Planes facing along Y, such as the ground, are textured along X and Z.
+        let (tangent, bitangent) = perpendicular_tangents(&normal);
+        let position = Vector::from(point);
         Some(Intersection {
             time: t,
-            point: r.at(t),
+            point: point,
             normal: normal,
+            uv: (position.dot(&tangent), position.dot(&bitangent)),
+            tangent: tangent,
+            bitangent: bitangent,
+            triangle: None,
         })
     }
+
+    /// Planes facing along an axis are flat along it, though still infinite along the others.
+    fn bounding_box(&self) -> AABB {
+        let inf = f32::INFINITY;
+        let along = |n: f32| -self.d / n;
+        match (self.a != 0.0, self.b != 0.0, self.c != 0.0) {
+            (true, false, false) => AABB::new(
+                Point::new(along(self.a), -inf, -inf),
+                Point::new(along(self.a), inf, inf),
+            ),
+            (false, true, false) => AABB::new(
+                Point::new(-inf, along(self.b), -inf),
+                Point::new(inf, along(self.b), inf),
+            ),
+            (false, false, true) => AABB::new(
+                Point::new(-inf, -inf, along(self.c)),
+                Point::new(inf, inf, along(self.c)),
+            ),
+            _ => AABB::infinite(),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "plane"
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use math::{Ray, Point, Solid, Vector};
+    use std::f32;
     use super::Plane;
 
     #[test]
@@ -91,12 +126,32 @@ mod tests {
         assert_relative_eq!(p.distance_to_point(&Point::new(3.0, 20.0, 50.0)), 1.0);
     }
 
+    #[test]
+    fn test_bounding_box() {
+        let floor =
+            Plane::from_normal_and_point(&Vector::new(0.0, 1.0, 0.0), &Point::new(0.0, -2.0, 0.0));
+        let bounds = floor.bounding_box();
+        assert!(!bounds.is_bounded());
+        assert_relative_eq!(bounds.lower.y, -2.0);
+        assert_relative_eq!(bounds.upper.y, -2.0);
+        assert_eq!(bounds.lower.x, f32::NEG_INFINITY);
+        assert_eq!(bounds.upper.z, f32::INFINITY);
+
+        let tilted = Plane::new(1.0, 1.0, 0.0, 0.0).bounding_box();
+        assert_eq!(tilted.lower.y, f32::NEG_INFINITY);
+        assert_eq!(tilted.upper.y, f32::INFINITY);
+    }
+
     #[test]
     fn test_intersection() {
         let x_at_7 = Plane::new(1.0, 0.0, 0.0, -7.0);
         let origin = Point::new(2.0, 3.0, 4.0);
         let direction = Vector::new(0.577, 0.577, 0.577);
-        let ray = Ray { origin, direction };
+        let ray = Ray {
+            origin,
+            direction,
+            time: 0.0,
+        };
 
         let expected_t = 8.665511;
         let expected_p = Point::new(7.0, 8.0, 9.0);
@@ -108,4 +163,17 @@ mod tests {
             panic!("Couldn't find intersection of ray and plane!");
         }
     }
+
+    #[test]
+    fn test_ground_uv() {
+        let ground = Plane::new(0.0, 1.0, 0.0, 0.0);
+        let ray = Ray {
+            origin: Point::new(2.0, 5.0, 3.0),
+            direction: Vector::new(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        let intersection = ground.intersect(&ray).unwrap();
+        assert_relative_eq!(intersection.uv.0, 2.0);
+        assert_relative_eq!(intersection.uv.1, 3.0);
+    }
 }
@@ -1,10 +1,11 @@
 use approx::ApproxEq;
 use std::fmt;
 use std::convert::From;
-use std::ops::{Add, Sub, Mul, Index};
+use std::ops::{Add, Sub, Mul, Index, IndexMut};
 use math::{Axis, Vector, XYZ};
 
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Point {
     pub x: f32,
     pub y: f32,
@@ -19,6 +20,22 @@ impl Point {
     pub fn distance_to(self, p: Point) -> f32 {
         (self - p).length()
     }
+
+    /// Each coordinate with its axis, in the order X, Y, Z.
+    pub fn components(&self) -> impl Iterator<Item = (Axis, f32)> {
+        let p = *self;
+        XYZ.iter().map(move |&axis| (axis, p[axis]))
+    }
+
+    /// Applies a function to each coordinate.
+    pub fn map<F: Fn(f32) -> f32>(&self, f: F) -> Point {
+        Point::new(f(self.x), f(self.y), f(self.z))
+    }
+
+    /// Combines the matching coordinates of two points.
+    pub fn zip_with<F: Fn(f32, f32) -> f32>(&self, other: &Point, f: F) -> Point {
+        Point::new(f(self.x, other.x), f(self.y, other.y), f(self.z, other.z))
+    }
 }
 
 impl Add<Vector> for Point {
@@ -65,6 +82,15 @@ impl Index<Axis> for Point {
     }
 }
 
+impl IndexMut<Axis> for Point {
+    fn index_mut(&mut self, index: Axis) -> &mut f32 {
+        match index {
+            Axis::X => &mut self.x,
+            Axis::Y => &mut self.y,
+            Axis::Z => &mut self.z,
+        }
+    }
+}
 
 impl PartialEq for Point {
     fn eq(&self, other: &Point) -> bool {
@@ -129,6 +155,7 @@ impl fmt::Display for Point {
 #[cfg(test)]
 mod tests {
     use super::Point;
+    use math::Axis;
 
     #[test]
     fn test_equality() {
@@ -149,4 +176,18 @@ mod tests {
         assert_relative_eq!(p1.distance_to(p2), 5.0);
         assert_relative_eq!(p2.distance_to(p1), 5.0);
     }
+
+    #[test]
+    fn test_axes() {
+        let mut p = Point::new(1.0, 2.0, 3.0);
+        p[Axis::X] = -1.0;
+        p[Axis::Z] += 1.0;
+        assert_relative_eq!(p, Point::new(-1.0, 2.0, 4.0));
+        let components: Vec<(Axis, f32)> = p.components().collect();
+        assert_eq!(components, vec![(Axis::X, -1.0), (Axis::Y, 2.0), (Axis::Z, 4.0)]);
+
+        assert_relative_eq!(p.map(f32::abs), Point::new(1.0, 2.0, 4.0));
+        let q = Point::new(0.0, 3.0, 3.0);
+        assert_relative_eq!(p.zip_with(&q, f32::min), Point::new(-1.0, 2.0, 3.0));
+    }
 }
@@ -0,0 +1,218 @@
+use math::{Matrix4x4, Point, Vector};
+
+/// A rotation, as a unit quaternion `w + xi + yj + zk`.
+///
+/// Quaternions blend between rotations at a steady angular speed, unlike the matrices of the
+/// rotations, whose blends shrink what they rotate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quaternion {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+/// Blends of quaternions closer than this are normalized linear blends, since the sine of the
+/// angle between them is too small to divide by.
+const SLERP_LINEAR_THRESHOLD: f32 = 0.9995;
+
+impl Quaternion {
+    pub fn identity() -> Quaternion {
+        Quaternion {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            w: 1.0,
+        }
+    }
+
+    /// The rotation a matrix makes, with Shepperd's method.
+    ///
+    /// The upper left 3x3 part of the matrix must be a rotation, with orthonormal columns and a
+    /// determinant of 1.  Its translation is ignored.
+    pub fn from_rotation(m: &Matrix4x4) -> Quaternion {
+        // Columns of the rotation, so r[i][j] is row i of column j.
+        let columns = [
+            *m * Vector::new(1.0, 0.0, 0.0),
+            *m * Vector::new(0.0, 1.0, 0.0),
+            *m * Vector::new(0.0, 0.0, 1.0),
+        ];
+        let r = |i: usize, j: usize| match i {
+            0 => columns[j].x,
+            1 => columns[j].y,
+            _ => columns[j].z,
+        };
+
+        // Dividing by the largest of the four components keeps the others accurate.
+        let trace = r(0, 0) + r(1, 1) + r(2, 2);
+        let q = if trace > 0.0 {
+            let s = 2.0 * (1.0 + trace).sqrt();
+            Quaternion {
+                x: (r(2, 1) - r(1, 2)) / s,
+                y: (r(0, 2) - r(2, 0)) / s,
+                z: (r(1, 0) - r(0, 1)) / s,
+                w: 0.25 * s,
+            }
+        } else if r(0, 0) > r(1, 1) && r(0, 0) > r(2, 2) {
+            let s = 2.0 * (1.0 + r(0, 0) - r(1, 1) - r(2, 2)).sqrt();
+            Quaternion {
+                x: 0.25 * s,
+                y: (r(0, 1) + r(1, 0)) / s,
+                z: (r(0, 2) + r(2, 0)) / s,
+                w: (r(2, 1) - r(1, 2)) / s,
+            }
+        } else if r(1, 1) > r(2, 2) {
+            let s = 2.0 * (1.0 + r(1, 1) - r(0, 0) - r(2, 2)).sqrt();
+            Quaternion {
+                x: (r(0, 1) + r(1, 0)) / s,
+                y: 0.25 * s,
+                z: (r(1, 2) + r(2, 1)) / s,
+                w: (r(0, 2) - r(2, 0)) / s,
+            }
+        } else {
+            let s = 2.0 * (1.0 + r(2, 2) - r(0, 0) - r(1, 1)).sqrt();
+            Quaternion {
+                x: (r(0, 2) + r(2, 0)) / s,
+                y: (r(1, 2) + r(2, 1)) / s,
+                z: 0.25 * s,
+                w: (r(1, 0) - r(0, 1)) / s,
+            }
+        };
+        q.normalized()
+    }
+
+    /// The rotation as a matrix, whose inverse is its transpose.
+    pub fn to_matrix(&self) -> Matrix4x4 {
+        let Quaternion { x, y, z, w } = *self;
+        Matrix4x4::from_basis(
+            &Vector::new(
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y + w * z),
+                2.0 * (x * z - w * y),
+            ),
+            &Vector::new(
+                2.0 * (x * y - w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z + w * x),
+            ),
+            &Vector::new(
+                2.0 * (x * z + w * y),
+                2.0 * (y * z - w * x),
+                1.0 - 2.0 * (x * x + y * y),
+            ),
+            &Point::new(0.0, 0.0, 0.0),
+        )
+    }
+
+    pub fn dot(&self, other: &Quaternion) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    /// Angle in radians between this rotation and another, turning the shorter way.
+    pub fn angle_to(&self, other: &Quaternion) -> f32 {
+        2.0 * self.dot(other).abs().min(1.0).acos()
+    }
+
+    /// Blends toward another rotation at a steady angular speed, turning the shorter way.
+    ///
+    /// # Arguments
+    /// * `other` - rotation reached when `t` is 1
+    /// * `t` - fraction of the way from this rotation to `other`
+    pub fn slerp(&self, other: &Quaternion, t: f32) -> Quaternion {
+        // A quaternion and its negation are the same rotation, turning opposite ways to reach.
+        let cos_theta = self.dot(other);
+        let (other, cos_theta) = if cos_theta < 0.0 {
+            (other.scaled(-1.0), -cos_theta)
+        } else {
+            (*other, cos_theta)
+        };
+        if cos_theta > SLERP_LINEAR_THRESHOLD {
+            return self.scaled(1.0 - t).plus(&other.scaled(t)).normalized();
+        }
+        let theta = cos_theta.acos();
+        let sin_theta = theta.sin();
+        self.scaled(((1.0 - t) * theta).sin() / sin_theta)
+            .plus(&other.scaled((t * theta).sin() / sin_theta))
+    }
+
+    fn scaled(&self, s: f32) -> Quaternion {
+        Quaternion {
+            x: s * self.x,
+            y: s * self.y,
+            z: s * self.z,
+            w: s * self.w,
+        }
+    }
+
+    fn plus(&self, other: &Quaternion) -> Quaternion {
+        Quaternion {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+            w: self.w + other.w,
+        }
+    }
+
+    fn normalized(&self) -> Quaternion {
+        self.scaled(1.0 / self.dot(self).sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Quaternion;
+    use math::{Matrix4x4, PlanarAngle, Vector};
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_matrix_round_trip() {
+        let rotations = [
+            Matrix4x4::identity(),
+            Matrix4x4::rotate_x(PlanarAngle::Degrees(30.0)),
+            Matrix4x4::rotate_y(PlanarAngle::Degrees(180.0)),
+            Matrix4x4::rotate_z(PlanarAngle::Degrees(-120.0)),
+            Matrix4x4::rotate_axis_angle(&Vector::new(1.0, 2.0, 3.0), PlanarAngle::Degrees(170.0))
+                .unwrap(),
+        ];
+        for rotation in rotations.iter() {
+            let q = Quaternion::from_rotation(rotation);
+            assert_relative_eq!(q.dot(&q), 1.0, epsilon = 1e-6);
+            assert_relative_eq!(q.to_matrix(), *rotation, epsilon = 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_slerp() {
+        let start = Quaternion::identity();
+        let turn = Quaternion::from_rotation(&Matrix4x4::rotate_y(PlanarAngle::Degrees(170.0)));
+        assert_relative_eq!(start.angle_to(&turn), 170.0 * PI / 180.0, epsilon = 1e-5);
+
+        // Halfway through a large turn is half the angle, rather than the shrunken blend of the
+        // matrices.
+        assert_relative_eq!(
+            start.slerp(&turn, 0.5).to_matrix(),
+            Matrix4x4::rotate_y(PlanarAngle::Degrees(85.0)),
+            epsilon = 1e-5
+        );
+        assert_relative_eq!(start.slerp(&turn, 0.0).to_matrix(), start.to_matrix());
+
+        // Blends turn the shorter way, however the rotations are signed.
+        let small = Quaternion::from_rotation(&Matrix4x4::rotate_x(PlanarAngle::Degrees(40.0)));
+        let negated = Quaternion {
+            x: -small.x,
+            y: -small.y,
+            z: -small.z,
+            w: -small.w,
+        };
+        assert_relative_eq!(
+            start.slerp(&negated, 0.5).to_matrix(),
+            Matrix4x4::rotate_x(PlanarAngle::Degrees(20.0)),
+            epsilon = 1e-5
+        );
+
+        // Nearly equal rotations blend without dividing by nearly zero.
+        let tiny = Quaternion::from_rotation(&Matrix4x4::rotate_z(PlanarAngle::Degrees(0.01)));
+        let blend = start.slerp(&tiny, 0.5);
+        assert_relative_eq!(blend.dot(&blend), 1.0, epsilon = 1e-6);
+    }
+}
@@ -6,6 +6,9 @@ use std::fmt;
 pub struct Ray {
     pub origin: Point,
     pub direction: Vector,
+    /// When the ray is traced, within the interval the camera's shutter is open, so moving
+    /// entities are hit where they are at that moment.
+    pub time: f32,
 }
 
 impl Ray {
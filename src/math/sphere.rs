@@ -1,4 +1,4 @@
-use math::{Intersection, Point, Ray, Solid, Vector};
+use math::{AABB, Intersection, Point, Ray, Solid, Vector, perpendicular_tangents};
 
 /// A sphere type centered at a specific origin.
 pub struct Sphere {
@@ -76,22 +76,56 @@ impl Solid for Sphere {
             let point = r.at(time);
             let mut normal = point - self.origin;
             normal.normalize().unwrap();
+            let (tangent, bitangent) = sphere_tangents(&normal);
 
             Some(Intersection {
                 time: time,
                 point: point,
                 normal: normal,
+                uv: sphere_uv(&normal),
+                tangent: tangent,
+                bitangent: bitangent,
+                triangle: None,
             })
         } else {
             None
         }
     }
+
+    fn bounding_box(&self) -> AABB {
+        let r = Vector::new(self.radius, self.radius, self.radius);
+        AABB::new(self.origin + -r, self.origin + r)
+    }
+
+    fn name(&self) -> &'static str {
+        "sphere"
+    }
 }
 
+/// Texture coordinates of a point on a sphere, given its normal.
+///
+/// U increases in longitude around the Y axis, starting from the -X side, and V increases in
+/// latitude from the south pole to the north pole.
+pub fn sphere_uv(normal: &Vector) -> (f32, f32) {
+    use std::f32::consts::PI;
+    let u = 0.5 + normal.z.atan2(normal.x) / (2.0 * PI);
+    let v = 0.5 + normal.y.max(-1.0).min(1.0).asin() / PI;
+    (u, v)
+}
+
+/// Directions of increasing longitude and latitude at a point on a sphere, given its normal.
+fn sphere_tangents(normal: &Vector) -> (Vector, Vector) {
+    let mut tangent = Vector::new(-normal.z, 0.0, normal.x);
+    match tangent.normalize() {
+        Ok(_) => (tangent, tangent.cross(normal)),
+        // Longitude is undefined at the poles.
+        Err(_) => perpendicular_tangents(normal),
+    }
+}
 
 #[cfg(test)]
 mod tests {
-    use super::Sphere;
+    use super::{sphere_tangents, sphere_uv, Sphere};
     use math::{Point, Ray, Solid, Vector};
 
     #[test]
@@ -104,6 +138,7 @@ mod tests {
         let mut r = Ray {
             origin: Point::new(1.0, -2.0, -1.0),
             direction: Vector::new(1.0, 2.0, 4.0),
+            time: 0.0,
         };
         r.normalize().unwrap();
 
@@ -136,4 +171,44 @@ mod tests {
         assert!(intersection.normal.is_normalized());
         assert_relative_eq!(expected_normal, intersection.normal, max_relative = 0.01);
     }
+
+    #[test]
+    fn test_uv() {
+        let (u, v) = sphere_uv(&Vector::new(1.0, 0.0, 0.0));
+        assert_relative_eq!(u, 0.5);
+        assert_relative_eq!(v, 0.5);
+
+        let (u, v) = sphere_uv(&Vector::new(0.0, 0.0, 1.0));
+        assert_relative_eq!(u, 0.75);
+        assert_relative_eq!(v, 0.5);
+
+        assert_relative_eq!(sphere_uv(&Vector::new(0.0, 1.0, 0.0)).1, 1.0);
+        assert_relative_eq!(sphere_uv(&Vector::new(0.0, -1.0, 0.0)).1, 0.0);
+    }
+
+    #[test]
+    fn test_tangents() {
+        // Moving along the tangents increases U and V.
+        let mut normal = Vector::new(0.3, 0.4, -0.5);
+        normal.normalize().unwrap();
+        let (tangent, bitangent) = sphere_tangents(&normal);
+        let (u, v) = sphere_uv(&normal);
+        let step = |direction: Vector| {
+            let mut moved = normal + 1e-2 * direction;
+            moved.normalize().unwrap();
+            sphere_uv(&moved)
+        };
+        let along_u = step(tangent);
+        assert!(along_u.0 > u);
+        assert_relative_eq!(along_u.1, v, epsilon = 1e-4);
+        let along_v = step(bitangent);
+        assert!(along_v.1 > v);
+        assert_relative_eq!(along_v.0, u, epsilon = 1e-4);
+
+        // Both are perpendicular to the normal, even at the poles.
+        let pole = Vector::new(0.0, 1.0, 0.0);
+        let (tangent, bitangent) = sphere_tangents(&pole);
+        assert_relative_eq!(tangent.dot(&pole), 0.0);
+        assert_relative_eq!(bitangent.dot(&pole), 0.0);
+    }
 }
@@ -0,0 +1,86 @@
+//! Generators of math types for property tests.
+//!
+//! Each type is built only from its own constructors, so a failing property of one type points
+//! at that type rather than at whatever another was built from.  Values stay within ranges where
+//! single precision keeps a few digits through several operations, so properties can be checked
+//! with fixed tolerances.
+use math::{Matrix4x4, PlanarAngle, Point, Transform, Vector};
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+/// Largest coordinate of generated positions and offsets.
+pub const MAX_COORDINATE: f32 = 10.0;
+
+/// Most steps in a generated matrix or transform.
+const MAX_STEPS: usize = 3;
+
+pub fn coordinate() -> impl Strategy<Value = f32> {
+    -MAX_COORDINATE..MAX_COORDINATE
+}
+
+pub fn vector() -> impl Strategy<Value = Vector> {
+    (coordinate(), coordinate(), coordinate()).prop_map(|(x, y, z)| Vector::new(x, y, z))
+}
+
+pub fn point() -> impl Strategy<Value = Point> {
+    (coordinate(), coordinate(), coordinate()).prop_map(|(x, y, z)| Point::new(x, y, z))
+}
+
+/// Vectors of length one, in directions spread evenly over the sphere.
+pub fn unit_vector() -> impl Strategy<Value = Vector> {
+    (-1.0f32..1.0, 0.0..2.0 * ::std::f32::consts::PI).prop_map(|(z, phi)| {
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        Vector::new(r * phi.cos(), r * phi.sin(), z)
+    })
+}
+
+pub fn angle() -> impl Strategy<Value = PlanarAngle> {
+    (-180.0f32..180.0).prop_map(PlanarAngle::Degrees)
+}
+
+/// Scale factors far enough from zero that scaling can be undone accurately, either way around.
+pub fn scale_factor() -> impl Strategy<Value = f32> {
+    prop_oneof![-4.0f32..-0.25, 0.25f32..4.0]
+}
+
+/// A single invertible step: a translation, scale or rotation.
+pub fn matrix_step() -> impl Strategy<Value = Matrix4x4> {
+    prop_oneof![
+        (coordinate(), coordinate(), coordinate())
+            .prop_map(|(x, y, z)| Matrix4x4::translate(x, y, z)),
+        (scale_factor(), scale_factor(), scale_factor())
+            .prop_map(|(x, y, z)| Matrix4x4::scale(x, y, z)),
+        (unit_vector(), angle())
+            .prop_map(|(axis, angle)| Matrix4x4::rotate_axis_angle(&axis, angle).unwrap()),
+    ]
+}
+
+/// Invertible matrices made of a few steps applied in turn.
+pub fn matrix() -> impl Strategy<Value = Matrix4x4> {
+    vec(matrix_step(), 1..MAX_STEPS + 1).prop_map(|steps| {
+        steps
+            .iter()
+            .fold(Matrix4x4::identity(), |m, step| *step * m)
+    })
+}
+
+/// A single step: a translation, scale or rotation.
+pub fn transform_step() -> impl Strategy<Value = Transform> {
+    prop_oneof![
+        (coordinate(), coordinate(), coordinate())
+            .prop_map(|(x, y, z)| Transform::translate(x, y, z)),
+        (scale_factor(), scale_factor(), scale_factor())
+            .prop_map(|(x, y, z)| Transform::scale(x, y, z)),
+        (unit_vector(), angle())
+            .prop_map(|(axis, angle)| Transform::rotate_axis_angle(&axis, angle).unwrap()),
+    ]
+}
+
+/// Transforms made of a few steps applied in turn.
+pub fn transform() -> impl Strategy<Value = Transform> {
+    vec(transform_step(), 1..MAX_STEPS + 1).prop_map(|steps| {
+        steps
+            .iter()
+            .fold(Transform::identity(), |t, step| t.compose(step))
+    })
+}
@@ -0,0 +1,588 @@
+use approx::ApproxEq;
+use math::{AABB, Matrix4x4, PlanarAngle, Point, Quaternion, Vector};
+
+/// Converts into and out of a local coordinate space, such as that of an entity.
+///
+/// Inverting a general matrix is expensive, but the inverse of each simple step is not, such as
+/// rotating -30 degrees about X undoing a rotation of 30 degrees.  Transforms are built up from
+/// these steps, keeping the inverse alongside so it never needs to be calculated while rendering.
+///
+/// Steps are applied in the order they are added:
+///
+/// ```text
+/// Transform::scale(2.0, 2.0, 2.0)
+///     .then_rotate_y(PlanarAngle::Degrees(45.0))
+///     .then_translate(0.0, 0.0, 30.0)
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Transform {
+    to_world: Matrix4x4,
+    to_local: Matrix4x4,
+}
+
+impl Transform {
+    /// A transform leaving coordinates unchanged.
+    pub fn identity() -> Transform {
+        Transform {
+            to_world: Matrix4x4::identity(),
+            to_local: Matrix4x4::identity(),
+        }
+    }
+
+    /// Creates a transform from an arbitrary matrix, inverting it once.
+    ///
+    /// # Returns
+    /// `None` if the matrix cannot be inverted.
+    pub fn from_matrix(to_world: &Matrix4x4) -> Option<Transform> {
+        to_world.affine_or_full_inverse().map(|to_local| {
+            Transform {
+                to_world: *to_world,
+                to_local: to_local,
+            }
+        })
+    }
+
+    pub fn translate(x: f32, y: f32, z: f32) -> Transform {
+        Transform {
+            to_world: Matrix4x4::translate(x, y, z),
+            to_local: Matrix4x4::translate(-x, -y, -z),
+        }
+    }
+
+    /// Generates a possibly non-uniform scale.
+    ///
+    /// # Panics
+    /// If any of the factors are zero, since the scale could not be undone.
+    pub fn scale(x: f32, y: f32, z: f32) -> Transform {
+        assert!(
+            x != 0.0 && y != 0.0 && z != 0.0,
+            "Cannot scale by zero, since it cannot be inverted."
+        );
+        Transform {
+            to_world: Matrix4x4::scale(x, y, z),
+            to_local: Matrix4x4::scale(1.0 / x, 1.0 / y, 1.0 / z),
+        }
+    }
+
+    /// A rotation about the X axis, see `Matrix4x4::rotate_x`.
+    pub fn rotate_x(angle: PlanarAngle) -> Transform {
+        Transform::rotation(Matrix4x4::rotate_x(angle))
+    }
+
+    /// A rotation about the Y axis, see `Matrix4x4::rotate_y`.
+    pub fn rotate_y(angle: PlanarAngle) -> Transform {
+        Transform::rotation(Matrix4x4::rotate_y(angle))
+    }
+
+    /// A rotation about the Z axis, see `Matrix4x4::rotate_z`.
+    pub fn rotate_z(angle: PlanarAngle) -> Transform {
+        Transform::rotation(Matrix4x4::rotate_z(angle))
+    }
+
+    /// A rotation about an arbitrary axis through the origin.
+    ///
+    /// # Returns
+    /// `None` if the axis has no length.
+    pub fn rotate_axis_angle(axis: &Vector, angle: PlanarAngle) -> Option<Transform> {
+        Matrix4x4::rotate_axis_angle(axis, angle).map(Transform::rotation)
+    }
+
+    /// The inverse of a rotation is its transpose.
+    fn rotation(to_world: Matrix4x4) -> Transform {
+        Transform {
+            to_world: to_world,
+            to_local: to_world.transpose(),
+        }
+    }
+
+    /// Converts local coordinates to world coordinates.
+    pub fn to_world(&self) -> &Matrix4x4 {
+        &self.to_world
+    }
+
+    /// Converts world coordinates to local coordinates.
+    pub fn to_local(&self) -> &Matrix4x4 {
+        &self.to_local
+    }
+
+    /// The transform converting world coordinates back to local coordinates.
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            to_world: self.to_local,
+            to_local: self.to_world,
+        }
+    }
+
+    /// Applies this transform, followed by `next`.
+    pub fn compose(&self, next: &Transform) -> Transform {
+        Transform {
+            to_world: next.to_world * self.to_world,
+            to_local: self.to_local * next.to_local,
+        }
+    }
+
+    pub fn then_translate(&self, x: f32, y: f32, z: f32) -> Transform {
+        self.compose(&Transform::translate(x, y, z))
+    }
+
+    pub fn then_scale(&self, x: f32, y: f32, z: f32) -> Transform {
+        self.compose(&Transform::scale(x, y, z))
+    }
+
+    pub fn then_rotate_x(&self, angle: PlanarAngle) -> Transform {
+        self.compose(&Transform::rotate_x(angle))
+    }
+
+    pub fn then_rotate_y(&self, angle: PlanarAngle) -> Transform {
+        self.compose(&Transform::rotate_y(angle))
+    }
+
+    pub fn then_rotate_z(&self, angle: PlanarAngle) -> Transform {
+        self.compose(&Transform::rotate_z(angle))
+    }
+}
+
+/// A transform moving through keyframes over time, for motion blur.
+///
+/// Each keyframe is split into a translation, a rotation and a stretch, which are blended
+/// separately between neighbouring keyframes.  Rotations turn at a steady speed the shorter way
+/// around, so entities keep their shape while turning, and turns of half a revolution or more
+/// need keyframes in between.
+#[derive(Clone, Debug)]
+pub struct AnimatedTransform {
+    // Keyframes in order of time, with at least one.
+    keyframes: Vec<Keyframe>,
+}
+
+/// Largest angle in radians a rotation turns between the blends bounding its motion.
+const BOUND_STEP_ANGLE: f32 = 0.1;
+
+/// Most iterations spent splitting a rotation from a stretch, which usually takes a handful.
+const POLAR_ITERATIONS: usize = 100;
+
+#[derive(Clone, Debug)]
+struct Keyframe {
+    time: f32,
+    transform: Transform,
+    // `to_world` is the translation, after the rotation, after the stretch.
+    translation: Vector,
+    rotation: Quaternion,
+    stretch: Matrix4x4,
+}
+
+impl Keyframe {
+    fn new(time: f32, transform: Transform) -> Keyframe {
+        let to_world = transform.to_world;
+        let origin = Point::new(0.0, 0.0, 0.0);
+        let linear = Matrix4x4::from_basis(
+            &(to_world * Vector::new(1.0, 0.0, 0.0)),
+            &(to_world * Vector::new(0.0, 1.0, 0.0)),
+            &(to_world * Vector::new(0.0, 0.0, 1.0)),
+            &origin,
+        );
+        let (rotation, stretch) = match polar_rotation(&linear) {
+            Some(r) => (Quaternion::from_rotation(&r), r.transpose() * linear),
+            None => (Quaternion::identity(), linear),
+        };
+        Keyframe {
+            time: time,
+            transform: transform,
+            translation: to_world * origin - origin,
+            rotation: rotation,
+            stretch: stretch,
+        }
+    }
+}
+
+/// The rotation nearest a linear matrix, by averaging it with its inverse transpose until they
+/// agree.  Reflections are moved into the stretch left over, so the rotation is a proper one.
+///
+/// # Returns
+/// `None` if the matrix cannot be inverted.
+fn polar_rotation(linear: &Matrix4x4) -> Option<Matrix4x4> {
+    let mut r = *linear;
+    for _ in 0..POLAR_ITERATIONS {
+        let next = r.lerp(&r.fast_affine_inverse()?.transpose(), 0.5);
+        let done = next.relative_eq(&r, 1e-6, 1e-6);
+        r = next;
+        if done {
+            break;
+        }
+    }
+    let x = r * Vector::new(1.0, 0.0, 0.0);
+    let y = r * Vector::new(0.0, 1.0, 0.0);
+    let z = r * Vector::new(0.0, 0.0, 1.0);
+    if x.cross(&y).dot(&z) < 0.0 {
+        r = Matrix4x4::from_basis(&-x, &-y, &-z, &Point::new(0.0, 0.0, 0.0));
+    }
+    Some(r)
+}
+
+impl AnimatedTransform {
+    /// A transform which does not move.
+    pub fn still(transform: Transform) -> AnimatedTransform {
+        AnimatedTransform { keyframes: vec![Keyframe::new(0.0, transform)] }
+    }
+
+    /// # Arguments
+    /// * `start`, `start_time` - the first keyframe, kept before its time
+    /// * `end`, `end_time` - the last keyframe, kept after its time
+    ///
+    /// # Panics
+    /// If the end is before the start.
+    pub fn new(
+        start: Transform,
+        start_time: f32,
+        end: Transform,
+        end_time: f32,
+    ) -> AnimatedTransform {
+        AnimatedTransform::from_keyframes(vec![(start_time, start), (end_time, end)])
+    }
+
+    /// # Arguments
+    /// * `keyframes` - times and the transforms at them, with the first kept before its time and
+    ///   the last kept after its time
+    ///
+    /// # Panics
+    /// If there are no keyframes, or they are out of order.
+    pub fn from_keyframes(keyframes: Vec<(f32, Transform)>) -> AnimatedTransform {
+        assert!(!keyframes.is_empty(), "Animated transforms need a keyframe.");
+        assert!(
+            keyframes.windows(2).all(|pair| pair[0].0 <= pair[1].0),
+            "Animated transforms cannot end before they start."
+        );
+        AnimatedTransform {
+            keyframes: keyframes
+                .into_iter()
+                .map(|(time, transform)| Keyframe::new(time, transform))
+                .collect(),
+        }
+    }
+
+    /// Whether the transform changes over time.
+    pub fn is_animated(&self) -> bool {
+        self.keyframes[0].time < self.keyframes[self.keyframes.len() - 1].time
+    }
+
+    /// The transform at the first keyframe.
+    pub fn start(&self) -> &Transform {
+        &self.keyframes[0].transform
+    }
+
+    /// The transform at some time, blended between the keyframes around it.
+    ///
+    /// Blends whose stretch cannot be inverted, such as halfway through mirroring, are replaced
+    /// by the nearer keyframe.
+    pub fn at(&self, time: f32) -> Transform {
+        let (first, last) = (&self.keyframes[0], &self.keyframes[self.keyframes.len() - 1]);
+        if !self.is_animated() || time <= first.time {
+            return first.transform;
+        }
+        if time >= last.time {
+            return last.transform;
+        }
+        // The last keyframe at or before the time, which is followed by a later one.
+        let index = self.keyframes.iter().rposition(|k| k.time <= time).unwrap();
+        let (start, end) = (&self.keyframes[index], &self.keyframes[index + 1]);
+        let t = (time - start.time) / (end.time - start.time);
+        if t == 0.0 {
+            return start.transform;
+        }
+        self.blend(index, t).unwrap_or(if t < 0.5 { start.transform } else { end.transform })
+    }
+
+    /// Blends a keyframe with the next, inverting each part separately.
+    ///
+    /// # Returns
+    /// `None` if the blended stretch cannot be inverted.
+    fn blend(&self, index: usize, t: f32) -> Option<Transform> {
+        let (start, end) = (&self.keyframes[index], &self.keyframes[index + 1]);
+        let translation = start.translation * (1.0 - t) + end.translation * t;
+        let rotation = start.rotation.slerp(&end.rotation, t).to_matrix();
+        let stretch = start.stretch.lerp(&end.stretch, t);
+        let to_local = stretch.fast_affine_inverse()?
+            * rotation.transpose()
+            * Matrix4x4::translate(-translation.x, -translation.y, -translation.z);
+        Some(Transform {
+            to_world: Matrix4x4::translate(translation.x, translation.y, translation.z)
+                * rotation
+                * stretch,
+            to_local: to_local,
+        })
+    }
+
+    /// Bounds a local box over all time.
+    ///
+    /// Between keyframes, the box is bounded at steps small enough that each point strays only a
+    /// little from the straight line between steps, and the bounds are padded by that much.
+    pub fn bound(&self, local: &AABB) -> AABB {
+        let start = self.keyframes[0].transform.to_world * *local;
+        let keyframes = self.keyframes[1..]
+            .iter()
+            .fold(start, |bounds, k| bounds.union(&(k.transform.to_world * *local)));
+        if !local.is_bounded() {
+            return keyframes;
+        }
+        let pick = |bit: usize, lower: f32, upper: f32| if bit == 0 { lower } else { upper };
+        let corners: Vec<Vector> = (0..8)
+            .map(|i| {
+                Vector::new(
+                    pick(i & 1, local.lower.x, local.upper.x),
+                    pick(i & 2, local.lower.y, local.upper.y),
+                    pick(i & 4, local.lower.z, local.upper.z),
+                )
+            })
+            .collect();
+        (0..self.keyframes.len() - 1).fold(keyframes, |bounds, index| {
+            let (start, end) = (&self.keyframes[index], &self.keyframes[index + 1]);
+            let angle = start.rotation.angle_to(&end.rotation);
+            if !(start.time < end.time) || !(angle > 0.0) {
+                return bounds;
+            }
+            let steps = (angle / BOUND_STEP_ANGLE).ceil().max(1.0) as usize;
+            let step_angle = angle / steps as f32;
+            let bounds = (1..steps)
+                .filter_map(|step| self.blend(index, step as f32 / steps as f32))
+                .fold(bounds, |bounds, blend| bounds.union(&(blend.to_world * *local)));
+
+            // A point turning through a small angle strays from the chord by at most the
+            // curvature of its path, which grows with its distance from the centre and with how
+            // fast the stretch moves it.
+            let reach = corners.iter().fold(0.0f32, |reach, c| {
+                reach.max((start.stretch * *c).length()).max((end.stretch * *c).length())
+            });
+            let change = corners.iter().fold(0.0f32, |change, c| {
+                change.max((end.stretch * *c - start.stretch * *c).length())
+            }) / steps as f32;
+            let pad = reach * step_angle * step_angle / 8.0 + change * step_angle / 4.0;
+            let pad = Vector::new(pad, pad, pad);
+            AABB::new(bounds.lower + -pad, bounds.upper + pad)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnimatedTransform, Transform};
+    use math::{AABB, Matrix4x4, PlanarAngle, Point, Vector};
+    use math::strategies::{point, transform, vector};
+
+    #[test]
+    fn test_inverses() {
+        let transforms = [
+            Transform::translate(1.0, -2.0, 3.0),
+            Transform::scale(2.0, 0.5, -4.0),
+            Transform::rotate_x(PlanarAngle::Degrees(30.0)),
+            Transform::rotate_y(PlanarAngle::Degrees(-75.0)),
+            Transform::rotate_z(PlanarAngle::Radians(2.0)),
+            Transform::rotate_axis_angle(&Vector::new(1.0, 2.0, 3.0), PlanarAngle::Degrees(40.0))
+                .unwrap(),
+            Transform::scale(2.0, 3.0, 4.0)
+                .then_rotate_y(PlanarAngle::Degrees(45.0))
+                .then_translate(5.0, 6.0, 7.0),
+        ];
+        for t in transforms.iter() {
+            assert_relative_eq!(
+                *t.to_world() * *t.to_local(),
+                Matrix4x4::identity(),
+                epsilon = 1e-5
+            );
+            assert_relative_eq!(
+                *t.to_local(),
+                t.to_world().inverse().unwrap(),
+                epsilon = 1e-5
+            );
+        }
+    }
+
+    #[test]
+    fn test_steps_apply_in_order() {
+        let t = Transform::scale(2.0, 2.0, 2.0)
+            .then_rotate_z(PlanarAngle::Degrees(90.0))
+            .then_translate(0.0, 0.0, 10.0);
+        let p = *t.to_world() * Point::new(1.0, 0.0, 0.0);
+        assert_relative_eq!(p, Point::new(0.0, 2.0, 10.0), epsilon = 1e-6);
+        assert_relative_eq!(*t.to_local() * p, Point::new(1.0, 0.0, 0.0), epsilon = 1e-6);
+
+        let back = t.compose(&t.inverse());
+        assert_relative_eq!(*back.to_world(), Matrix4x4::identity(), epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_from_matrix() {
+        let m = Matrix4x4::translate(1.0, 2.0, 3.0) * Matrix4x4::scale(2.0, 2.0, 2.0);
+        let t = Transform::from_matrix(&m).unwrap();
+        assert_relative_eq!(*t.to_local() * m, Matrix4x4::identity(), epsilon = 1e-6);
+        assert!(Transform::from_matrix(&Matrix4x4::scale(1.0, 0.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn test_animated_transform() {
+        let motion = AnimatedTransform::new(
+            Transform::translate(0.0, 0.0, 0.0),
+            1.0,
+            Transform::translate(4.0, 0.0, 0.0).then_scale(1.0, 3.0, 1.0),
+            3.0,
+        );
+        assert!(motion.is_animated());
+        let origin = Point::new(0.0, 1.0, 0.0);
+        let at = |time: f32| *motion.at(time).to_world() * origin;
+        assert_relative_eq!(at(0.0), Point::new(0.0, 1.0, 0.0));
+        assert_relative_eq!(at(2.0), Point::new(2.0, 2.0, 0.0), epsilon = 1e-6);
+        assert_relative_eq!(at(5.0), Point::new(4.0, 3.0, 0.0), epsilon = 1e-6);
+        assert_relative_eq!(
+            *motion.at(2.0).to_local() * at(2.0),
+            origin,
+            epsilon = 1e-5
+        );
+
+        let cube = AABB::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let bounds = motion.bound(&cube);
+        assert_relative_eq!(bounds.lower, Point::new(-1.0, -3.0, -1.0), epsilon = 1e-6);
+        assert_relative_eq!(bounds.upper, Point::new(5.0, 3.0, 1.0), epsilon = 1e-6);
+
+        let still = AnimatedTransform::still(Transform::translate(1.0, 0.0, 0.0));
+        assert!(!still.is_animated());
+        assert_relative_eq!(*still.at(10.0).to_world() * origin, Point::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_keyframes() {
+        // Up, then across, then holding still before jumping back.
+        let motion = AnimatedTransform::from_keyframes(vec![
+            (0.0, Transform::identity()),
+            (1.0, Transform::translate(0.0, 2.0, 0.0)),
+            (3.0, Transform::translate(4.0, 2.0, 0.0)),
+            (4.0, Transform::translate(4.0, 2.0, 0.0)),
+            (4.0, Transform::identity()),
+        ]);
+        assert!(motion.is_animated());
+        let origin = Point::new(0.0, 0.0, 0.0);
+        let at = |time: f32| *motion.at(time).to_world() * origin;
+        assert_relative_eq!(at(-1.0), origin);
+        assert_relative_eq!(at(0.5), Point::new(0.0, 1.0, 0.0), epsilon = 1e-6);
+        assert_relative_eq!(at(1.0), Point::new(0.0, 2.0, 0.0), epsilon = 1e-6);
+        assert_relative_eq!(at(2.5), Point::new(3.0, 2.0, 0.0), epsilon = 1e-6);
+        assert_relative_eq!(at(3.5), Point::new(4.0, 2.0, 0.0), epsilon = 1e-6);
+        assert_relative_eq!(at(4.0), origin);
+        assert_relative_eq!(at(9.0), origin);
+
+        // Bounds reach the farthest keyframe.
+        let bounds = motion.bound(&AABB::new(origin, origin));
+        assert_relative_eq!(bounds.lower, origin);
+        assert_relative_eq!(bounds.upper, Point::new(4.0, 2.0, 0.0), epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_large_turn() {
+        let motion = AnimatedTransform::new(
+            Transform::translate(1.0, 0.0, 0.0),
+            0.0,
+            Transform::scale(2.0, 2.0, 2.0)
+                .then_rotate_y(PlanarAngle::Degrees(170.0))
+                .then_translate(1.0, 0.0, 0.0),
+            1.0,
+        );
+
+        // Halfway through, the turn and the scale are both halfway, rather than the shrunken
+        // blend of the matrices.
+        let halfway = motion.at(0.5);
+        let expected = Transform::scale(1.5, 1.5, 1.5)
+            .then_rotate_y(PlanarAngle::Degrees(85.0))
+            .then_translate(1.0, 0.0, 0.0);
+        assert_relative_eq!(*halfway.to_world(), *expected.to_world(), epsilon = 1e-5);
+        assert_relative_eq!(
+            *halfway.to_world() * *halfway.to_local(),
+            Matrix4x4::identity(),
+            epsilon = 1e-5
+        );
+
+        // Mirrored keyframes keep their shape too.
+        let mirrored = AnimatedTransform::new(
+            Transform::scale(-1.0, 1.0, 1.0),
+            0.0,
+            Transform::scale(-1.0, 1.0, 1.0).then_rotate_z(PlanarAngle::Degrees(90.0)),
+            1.0,
+        );
+        let expected = Transform::scale(-1.0, 1.0, 1.0).then_rotate_z(PlanarAngle::Degrees(45.0));
+        let halfway = mirrored.at(0.5);
+        assert_relative_eq!(*halfway.to_world(), *expected.to_world(), epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_bound_contains_arc() {
+        let motion = AnimatedTransform::new(
+            Transform::identity(),
+            0.0,
+            Transform::scale(1.0, 1.0, 3.0).then_rotate_y(PlanarAngle::Degrees(170.0)),
+            1.0,
+        );
+        let local = AABB::new(Point::new(0.5, -0.5, -0.5), Point::new(1.0, 0.5, 0.5));
+        let bounds = motion.bound(&local);
+        for i in 0..=100 {
+            let at = motion.at(i as f32 / 100.0);
+            for &x in [local.lower.x, local.upper.x].iter() {
+                for &y in [local.lower.y, local.upper.y].iter() {
+                    for &z in [local.lower.z, local.upper.z].iter() {
+                        let p = *at.to_world() * Point::new(x, y, z);
+                        assert!(bounds.lower.x <= p.x && p.x <= bounds.upper.x);
+                        assert!(bounds.lower.y <= p.y && p.y <= bounds.upper.y);
+                        assert!(bounds.lower.z <= p.z && p.z <= bounds.upper.z);
+                    }
+                }
+            }
+        }
+        // Swinging round, the box passes well beyond either keyframe.
+        assert!(bounds.lower.z < -0.9);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_keyframes_out_of_order() {
+        AnimatedTransform::from_keyframes(vec![
+            (1.0, Transform::identity()),
+            (0.0, Transform::identity()),
+        ]);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_round_trip(t in transform(), p in point(), v in vector()) {
+            assert_relative_eq!(*t.to_local() * (*t.to_world() * p), p, epsilon = 1e-3);
+            assert_relative_eq!(*t.to_local() * (*t.to_world() * v), v, epsilon = 1e-3);
+            assert_relative_eq!(*t.inverse().to_world() * (*t.to_world() * p), p, epsilon = 1e-3);
+        }
+
+        #[test]
+        fn prop_compose_applies_in_turn(a in transform(), b in transform(), p in point()) {
+            let composed = a.compose(&b);
+            let in_turn = *b.to_world() * (*a.to_world() * p);
+            assert_relative_eq!(
+                *composed.to_world() * p,
+                in_turn,
+                epsilon = 1e-2,
+                max_relative = 1e-4
+            );
+            assert_relative_eq!(*composed.to_local() * in_turn, p, epsilon = 1e-2);
+        }
+
+        #[test]
+        fn prop_bound_contains_corners(t in transform(), a in point(), b in point()) {
+            let local = AABB::new(a, a).expand(&b);
+            let bounds = AnimatedTransform::still(t).bound(&local);
+            for &x in [local.lower.x, local.upper.x].iter() {
+                for &y in [local.lower.y, local.upper.y].iter() {
+                    for &z in [local.lower.z, local.upper.z].iter() {
+                        let corner = *t.to_world() * Point::new(x, y, z);
+                        let inside = |lower: f32, c: f32, upper: f32| {
+                            lower - 1e-3 <= c && c <= upper + 1e-3
+                        };
+                        assert!(inside(bounds.lower.x, corner.x, bounds.upper.x));
+                        assert!(inside(bounds.lower.y, corner.y, bounds.upper.y));
+                        assert!(inside(bounds.lower.z, corner.z, bounds.upper.z));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use math::{AABB, Bvh, BvhLayout, Intersection, Point, Ray, Solid, TriangleHit, Vector};
+use std::f32;
+
+/// Triangles sharing a single buffer of vertices.
+///
+/// Triangles can be hit from either side.  The reported normal faces the side the triangle's
+/// vertices wind counterclockwise around, whichever side the ray came from, so closed meshes wound
+/// consistently report normals pointing out of them like other solids do.
+///
+/// Each coordinate of the vertices is kept in its own array, so meshes take no more memory than
+/// their coordinates and walking through them stays within a few contiguous arrays.
+pub struct TriangleMesh {
+    xs: Vec<f32>,
+    ys: Vec<f32>,
+    zs: Vec<f32>,
+    // Every three indices into the vertex coordinates describe a triangle.
+    indices: Vec<u32>,
+    bvh: Bvh,
+}
+
+impl TriangleMesh {
+    /// # Arguments
+    /// * `vertices` - the corners of all triangles
+    /// * `indices` - every three indices into `vertices` form a triangle
+    ///
+    /// # Panics
+    /// If the indices do not describe whole triangles, or refer to vertices which do not exist.
+    pub fn new(vertices: Vec<Point>, indices: Vec<u32>) -> TriangleMesh {
+        assert!(
+            indices.len() % 3 == 0,
+            "Triangle mesh indices must come in groups of three."
+        );
+        assert!(
+            indices.iter().all(|&i| (i as usize) < vertices.len()),
+            "Triangle mesh index refers to a vertex which does not exist."
+        );
+        let mut mesh = TriangleMesh {
+            xs: vertices.iter().map(|v| v.x).collect(),
+            ys: vertices.iter().map(|v| v.y).collect(),
+            zs: vertices.iter().map(|v| v.z).collect(),
+            indices: indices,
+            bvh: Bvh::new(&[]),
+        };
+        mesh.bvh = Bvh::new(&mesh.triangle_bounds());
+        mesh
+    }
+
+    /// The smallest box containing each triangle.
+    fn triangle_bounds(&self) -> Vec<AABB> {
+        (0..self.triangle_count())
+            .map(|index| {
+                let [a, b, c] = self.triangle(index);
+                AABB::new(a, a).expand(&b).expand(&c)
+            })
+            .collect()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.xs.len()
+    }
+
+    pub fn vertex(&self, index: usize) -> Point {
+        Point::new(self.xs[index], self.ys[index], self.zs[index])
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// The smallest box containing every vertex.
+    ///
+    /// # Panics
+    /// If the mesh has no vertices.
+    pub fn bounds(&self) -> AABB {
+        assert!(self.vertex_count() > 0, "Empty meshes have no bounds.");
+        let min = |c: &[f32]| c.iter().cloned().fold(f32::INFINITY, f32::min);
+        let max = |c: &[f32]| c.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        AABB {
+            lower: Point::new(min(&self.xs), min(&self.ys), min(&self.zs)),
+            upper: Point::new(max(&self.xs), max(&self.ys), max(&self.zs)),
+        }
+    }
+
+    /// The corners of a triangle.
+    pub fn triangle(&self, index: usize) -> [Point; 3] {
+        [
+            self.vertex(self.indices[3 * index] as usize),
+            self.vertex(self.indices[3 * index + 1] as usize),
+            self.vertex(self.indices[3 * index + 2] as usize),
+        ]
+    }
+
+    /// Möller–Trumbore ray and triangle intersection.
+    ///
+    /// # Returns
+    /// The time of the intersection, the unnormalized face normal, and the barycentric weights of
+    /// the second and third vertices.
+    fn intersect_triangle(&self, index: usize, r: &Ray) -> Option<(f32, Vector, f32, f32)> {
+        const PARALLEL_EPS: f32 = 1e-8;
+        let [a, b, c] = self.triangle(index);
+        let edge1 = b - a;
+        let edge2 = c - a;
+
+        let p = r.direction.cross(&edge2);
+        let determinant = edge1.dot(&p);
+        if determinant.abs() < PARALLEL_EPS {
+            return None;
+        }
+        let inv_determinant = 1.0 / determinant;
+
+        let to_origin = r.origin - a;
+        let u = to_origin.dot(&p) * inv_determinant;
+        if u < 0.0 || u > 1.0 {
+            return None;
+        }
+
+        let q = to_origin.cross(&edge1);
+        let v = r.direction.dot(&q) * inv_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = edge2.dot(&q) * inv_determinant;
+        if t <= 0.0 {
+            return None;
+        }
+        Some((t, edge1.cross(&edge2), u, v))
+    }
+}
+
+impl Solid for TriangleMesh {
+    fn intersect(&self, r: &Ray) -> Option<Intersection> {
+        let mut best_time = f32::INFINITY;
+        let mut best = None;
+        self.bvh.traverse(r, |index| match self.intersect_triangle(index, r) {
+            Some((time, normal, u, v)) => {
+                if time < best_time {
+                    best_time = time;
+                    best = Some((normal, index, u, v));
+                }
+                Some(time)
+            }
+            None => None,
+        });
+
+        best.map(|(mut normal, index, u, v)| {
+            normal.normalize().expect("Degenerate triangle in mesh.");
+            // U and V are the weights of the second and third vertices.
+            let [a, b, c] = self.triangle(index);
+            let (mut tangent, mut bitangent) = (b - a, c - a);
+            tangent.normalize().expect("Degenerate triangle in mesh.");
+            bitangent.normalize().expect("Degenerate triangle in mesh.");
+            Intersection {
+                time: best_time,
+                point: r.at(best_time),
+                normal: normal,
+                uv: (u, v),
+                tangent: tangent,
+                bitangent: bitangent,
+                triangle: Some(TriangleHit {
+                    index: index,
+                    barycentric: [1.0 - u - v, u, v],
+                }),
+            }
+        })
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bounds()
+    }
+
+    fn name(&self) -> &'static str {
+        "triangle mesh"
+    }
+
+    fn set_bvh_layout(&mut self, layout: BvhLayout) {
+        self.bvh = Bvh::with_layout(&self.triangle_bounds(), layout);
+    }
+
+    fn triangles(&self) -> Vec<[Point; 3]> {
+        (0..self.triangle_count()).map(|index| self.triangle(index)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TriangleMesh;
+    use math::{Point, Ray, Solid, Vector};
+
+    /// Two unit squares facing Z+, at Z=1 and Z=2.
+    fn two_squares() -> TriangleMesh {
+        TriangleMesh::new(
+            vec![
+                Point::new(-1.0, -1.0, 1.0),
+                Point::new(1.0, -1.0, 1.0),
+                Point::new(1.0, 1.0, 1.0),
+                Point::new(-1.0, 1.0, 1.0),
+                Point::new(-1.0, -1.0, 2.0),
+                Point::new(1.0, -1.0, 2.0),
+                Point::new(1.0, 1.0, 2.0),
+                Point::new(-1.0, 1.0, 2.0),
+            ],
+            vec![4, 5, 6, 4, 6, 7, 0, 1, 2, 0, 2, 3],
+        )
+    }
+
+    #[test]
+    fn test_hits_nearest_triangle() {
+        let mesh = two_squares();
+        assert_eq!(mesh.triangle_count(), 4);
+
+        let r = Ray {
+            origin: Point::new(0.25, 0.5, 0.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let intersection = mesh.intersect(&r).unwrap();
+        assert_relative_eq!(intersection.time, 1.0);
+        assert_relative_eq!(intersection.point, Point::new(0.25, 0.5, 1.0));
+        // The back of the square was hit, so the normal faces away from the ray.
+        assert_relative_eq!(intersection.normal, Vector::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_barycentric_coordinates() {
+        let mesh = two_squares();
+        let r = Ray {
+            origin: Point::new(0.5, -0.5, 0.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let hit = mesh.intersect(&r).unwrap().triangle.unwrap();
+        assert_eq!(hit.index, 2);
+
+        // Weighting the vertices by the barycentric coordinates gives the intersection point.
+        let [a, b, c] = mesh.triangle(hit.index);
+        let [wa, wb, wc] = hit.barycentric;
+        assert_relative_eq!(wa + wb + wc, 1.0);
+        let point = a + (wb * (b - a) + wc * (c - a));
+        assert_relative_eq!(point, Point::new(0.5, -0.5, 1.0), epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_normal_follows_winding() {
+        let mesh = two_squares();
+        let r = Ray {
+            origin: Point::new(0.0, 0.0, 5.0),
+            direction: Vector::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let intersection = mesh.intersect(&r).unwrap();
+        assert_relative_eq!(intersection.time, 3.0);
+        assert_relative_eq!(intersection.normal, Vector::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_bounds() {
+        let bounds = two_squares().bounds();
+        assert_relative_eq!(bounds.lower, Point::new(-1.0, -1.0, 1.0));
+        assert_relative_eq!(bounds.upper, Point::new(1.0, 1.0, 2.0));
+    }
+
+    #[test]
+    fn test_misses() {
+        let mesh = two_squares();
+        let beside = Ray {
+            origin: Point::new(1.5, 0.0, 0.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        assert!(mesh.intersect(&beside).is_none());
+
+        let away = Ray {
+            origin: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        assert!(mesh.intersect(&away).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_index() {
+        TriangleMesh::new(vec![Point::new(0.0, 0.0, 0.0)], vec![0, 0, 1]);
+    }
+}
@@ -1,5 +1,5 @@
 use approx::ApproxEq;
-use std::ops::{Add, AddAssign, Sub, Mul, Div, Neg, Index};
+use std::ops::{Add, AddAssign, Sub, Mul, Div, Neg, Index, IndexMut};
 use std::fmt;
 use math::{Axis, XYZ};
 
@@ -9,6 +9,7 @@ const NORMALIZED_EPS: f32 = 1e-6;
 
 /// A 3 dimensional vector.
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Vector {
     pub x: f32,
     pub y: f32,
@@ -55,11 +56,73 @@ impl Vector {
         self.x * v.x + self.y * v.y + self.z * v.z
     }
 
+    /// The vector perpendicular to both vectors, with length equal to the area of the
+    /// parallelogram they span.
+    pub fn cross(&self, v: &Vector) -> Vector {
+        Vector::new(
+            self.y * v.z - self.z * v.y,
+            self.z * v.x - self.x * v.z,
+            self.x * v.y - self.y * v.x,
+        )
+    }
+
+    /// Mirrors a vector pointing into a surface about the surface normal, so it points away.
+    ///
+    /// # Arguments
+    /// * `n` - unit vector perpendicular to the surface, on either side of it
+    ///
+    /// # Returns
+    /// A vector of the same length, with only its part along the normal reversed.
     pub fn reflect(&self, n: &Vector) -> Vector {
         let incident = self.clone();
         incident - 2.0 * n.dot(&incident) * (*n)
     }
 
+    /// Bends a unit vector pointing into a surface as it passes through, using Snell's law.
+    ///
+    /// # Arguments
+    /// * `n` - unit vector perpendicular to the surface, on the side the vector started from
+    /// * `eta` - ratio of the index of refraction being left to that being entered
+    ///
+    /// # Returns
+    /// The transmitted direction, or `None` if there is total internal reflection.
+    pub fn refract(&self, n: &Vector, eta: f32) -> Option<Vector> {
+        let cos_i = -n.dot(self);
+        let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+        if sin2_t > 1.0 {
+            return None;
+        }
+        let cos_t = (1.0 - sin2_t).sqrt();
+        Some(eta * *self + (eta * cos_i - cos_t) * *n)
+    }
+
+    /// Each component with its axis, in the order X, Y, Z.
+    pub fn components(&self) -> impl Iterator<Item = (Axis, f32)> {
+        let v = *self;
+        XYZ.iter().map(move |&axis| (axis, v[axis]))
+    }
+
+    /// Applies a function to each component.
+    pub fn map<F: Fn(f32) -> f32>(&self, f: F) -> Vector {
+        Vector::new(f(self.x), f(self.y), f(self.z))
+    }
+
+    /// Combines the matching components of two vectors.
+    pub fn zip_with<F: Fn(f32, f32) -> f32>(&self, other: &Vector, f: F) -> Vector {
+        Vector::new(f(self.x, other.x), f(self.y, other.y), f(self.z, other.z))
+    }
+
+    /// The axis of the largest component, the first of any tied.
+    pub fn max_axis(&self) -> Axis {
+        let mut largest = Axis::X;
+        for &axis in XYZ.iter() {
+            if self[axis] > self[largest] {
+                largest = axis;
+            }
+        }
+        largest
+    }
+
     /// The angle between two vectors in degrees in the range [0, 180].
     pub fn angle_with_in_degrees(&self, v: Vector) -> f32 {
         let mut a = self.clone();
@@ -140,6 +203,16 @@ impl Index<Axis> for Vector {
     }
 }
 
+impl IndexMut<Axis> for Vector {
+    fn index_mut(&mut self, index: Axis) -> &mut f32 {
+        match index {
+            Axis::X => &mut self.x,
+            Axis::Y => &mut self.y,
+            Axis::Z => &mut self.z,
+        }
+    }
+}
+
 impl ApproxEq for Vector {
     type Epsilon = <f32 as ApproxEq>::Epsilon;
 
@@ -190,6 +263,8 @@ impl fmt::Display for Vector {
 #[cfg(test)]
 mod tests {
     use super::Vector;
+    use math::{Axis, XYZ};
+    use math::strategies::{unit_vector, vector};
 
     #[test]
     fn test_relative_equality() {
@@ -265,6 +340,78 @@ mod tests {
         assert_relative_eq!(z.angle_with_in_degrees(-x), 90.0, max_relative = 0.001);
     }
 
+    #[test]
+    fn test_cross() {
+        let x = Vector::new(1.0, 0.0, 0.0);
+        let y = Vector::new(0.0, 1.0, 0.0);
+        let z = Vector::new(0.0, 0.0, 1.0);
+        assert_relative_eq!(x.cross(&y), z);
+        assert_relative_eq!(y.cross(&z), x);
+        assert_relative_eq!(z.cross(&x), y);
+        assert_relative_eq!(y.cross(&x), -z);
+
+        let a = Vector::new(1.0, 2.0, 3.0);
+        let b = Vector::new(-4.0, 0.5, 2.0);
+        let c = a.cross(&b);
+        assert_relative_eq!(c.dot(&a), 0.0);
+        assert_relative_eq!(c.dot(&b), 0.0);
+        assert_relative_eq!(a.cross(&a), Vector::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_reflect() {
+        let up = Vector::new(0.0, 1.0, 0.0);
+        assert_relative_eq!(Vector::new(1.0, -1.0, 0.0).reflect(&up), Vector::new(1.0, 1.0, 0.0));
+        assert_relative_eq!(Vector::new(0.0, -2.0, 0.0).reflect(&up), Vector::new(0.0, 2.0, 0.0));
+
+        // Only the part along the normal changes, whichever side the normal faces.
+        let v = Vector::new(0.3, -0.4, 0.5);
+        assert_relative_eq!(v.reflect(&-up), Vector::new(0.3, 0.4, 0.5));
+    }
+
+    #[test]
+    fn test_refract() {
+        let up = Vector::new(0.0, 1.0, 0.0);
+        let down = Vector::new(0.0, -1.0, 0.0);
+        assert_relative_eq!(down.refract(&up, 1.0 / 1.5).unwrap(), down);
+
+        // Matching indices leave the direction unchanged.
+        let mut incident = Vector::new(1.0, -1.0, 0.0);
+        incident.normalize().unwrap();
+        assert_relative_eq!(incident.refract(&up, 1.0).unwrap(), incident);
+
+        // Entering a denser material bends toward the normal, following Snell's law.
+        let transmitted = incident.refract(&up, 1.0 / 1.5).unwrap();
+        assert!(transmitted.is_normalized());
+        let sin_i = incident.cross(&up).length();
+        let sin_t = transmitted.cross(&up).length();
+        assert_relative_eq!(sin_i, 1.5 * sin_t, max_relative = 1e-5);
+
+        // Leaving at a grazing angle is totally internally reflected.
+        assert!(incident.refract(&up, 1.5).is_none());
+    }
+
+    #[test]
+    fn test_axes() {
+        let mut v = Vector::new(1.0, -2.0, 3.0);
+        v[Axis::Y] = 5.0;
+        v[Axis::Z] *= 2.0;
+        assert_relative_eq!(v, Vector::new(1.0, 5.0, 6.0));
+        let components: Vec<(Axis, f32)> = v.components().collect();
+        assert_eq!(components, vec![(Axis::X, 1.0), (Axis::Y, 5.0), (Axis::Z, 6.0)]);
+        for (&axis, (component_axis, component)) in XYZ.iter().zip(v.components()) {
+            assert_eq!(axis, component_axis);
+            assert_eq!(v[axis], component);
+        }
+
+        assert_relative_eq!(v.map(|c| c - 1.0), Vector::new(0.0, 4.0, 5.0));
+        let w = Vector::new(2.0, 2.0, 8.0);
+        assert_relative_eq!(v.zip_with(&w, f32::max), Vector::new(2.0, 5.0, 8.0));
+
+        assert_eq!(v.max_axis(), Axis::Z);
+        assert_eq!(Vector::new(1.0, 1.0, 0.0).max_axis(), Axis::X);
+    }
+
     #[test]
     fn test_vector_add() {
         let v1 = Vector::new(1.0, 2.0, 3.0);
@@ -305,4 +452,42 @@ mod tests {
         assert_relative_eq!(v1, Vector::new(1.0, -2.0, 0.0));
         assert_relative_eq!(v2, Vector::new(-1.0, 2.0, 0.0));
     }
+
+    proptest! {
+        #[test]
+        fn prop_reflect(v in vector(), n in unit_vector()) {
+            let reflected = v.reflect(&n);
+            // Length and the part across the surface are kept, and the part along the normal
+            // turns around.
+            assert_relative_eq!(reflected.length(), v.length(), epsilon = 1e-4);
+            assert_relative_eq!(reflected.dot(&n), -v.dot(&n), epsilon = 1e-4);
+            assert_relative_eq!(reflected.cross(&n), v.cross(&n), epsilon = 1e-4);
+            assert_relative_eq!(reflected.reflect(&n), v, epsilon = 1e-4);
+        }
+
+        #[test]
+        fn prop_refract(incident in unit_vector(), n in unit_vector(), eta in 0.25f32..4.0) {
+            prop_assume!(incident.dot(&n) < 0.0);
+            let cos_i = -incident.dot(&n);
+            let sin_i = (1.0 - cos_i * cos_i).max(0.0).sqrt();
+            match incident.refract(&n, eta) {
+                Some(transmitted) => {
+                    // Snell's law, with the light passing through the surface in the plane of
+                    // incidence.
+                    assert_relative_eq!(transmitted.length(), 1.0, epsilon = 1e-4);
+                    assert!(transmitted.dot(&n) <= 0.0);
+                    let sin_t = transmitted.cross(&n).length();
+                    assert_relative_eq!(eta * sin_i, sin_t, epsilon = 1e-3);
+                    assert_relative_eq!(transmitted.dot(&incident.cross(&n)), 0.0, epsilon = 1e-3);
+                    // Light follows the same path back, away from the critical angle where
+                    // small errors bend it a long way.
+                    if sin_t < 0.99 {
+                        let back = (-transmitted).refract(&-n, 1.0 / eta).unwrap();
+                        assert_relative_eq!(back, -incident, epsilon = 1e-2);
+                    }
+                }
+                None => assert!(eta * sin_i > 1.0 - 1e-4),
+            }
+        }
+    }
 }
@@ -0,0 +1,289 @@
+//! Splitting a render across several machines.
+//!
+//! A coordinator hands out tiles of the film to workers over TCP, and assembles the tiles they
+//! send back into the final image.  Every machine builds the same scene, so only tile assignments
+//! and pixels cross the network.
+//!
+//! # Protocol
+//! Messages are a single line of ASCII text, and pixels follow as raw RGB bytes.
+//!
+//! * Worker: `READY` - asks for a tile to render.
+//! * Coordinator: `TILE x y width height` - a tile to render, or `DONE` once every tile is
+//!   rendered.  While other workers hold the last tiles, the reply waits until one is returned
+//!   unrendered, or all are rendered.
+//! * Worker: `PIXELS x y width height` - followed by `width * height * 3` bytes of pixels, in
+//!   row-major order.
+#![allow(dead_code)]
+mod protocol;
+pub use self::protocol::Message;
+
+use scene::tile::Tile;
+use std::io::{self, BufReader, BufWriter};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Progress of a distributed render, shared between connections to the coordinator.
+struct Assembly {
+    width: u32,
+    pending: Vec<Tile>,
+    tiles_remaining: usize,
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Assembly {
+    fn store(&mut self, tile: &Tile, pixels: &[[u8; 3]]) {
+        for row in 0..tile.height {
+            for col in 0..tile.width {
+                let dst = ((tile.y + row) * self.width + tile.x + col) as usize;
+                self.pixels[dst] = pixels[(row * tile.width + col) as usize];
+            }
+        }
+        self.tiles_remaining -= 1;
+    }
+}
+
+/// Hands out tiles to workers which connect to `listener`, until the entire image is rendered.
+///
+/// Tiles held by a worker whose connection fails, or which takes longer than `worker_timeout` to
+/// return them, are given to the next worker which asks for one.  Workers asking while the last
+/// tiles are out wait for them, so there is always a worker to take a returned tile.
+///
+/// # Returns
+/// The pixels of the image in row-major order, with (0, 0) in the top left corner.
+pub fn coordinate(
+    listener: TcpListener,
+    width: u32,
+    height: u32,
+    tile_size: u32,
+    worker_timeout: Duration,
+) -> io::Result<Vec<[u8; 3]>> {
+    let mut pending = Tile::split(width, height, tile_size);
+    // Hand out tiles from the top of the image first.
+    pending.reverse();
+    let tiles_remaining = pending.len();
+
+    let state = Arc::new((
+        Mutex::new(Assembly {
+            width: width,
+            pending: pending,
+            tiles_remaining: tiles_remaining,
+            pixels: vec![[0, 0, 0]; (width * height) as usize],
+        }),
+        Condvar::new(),
+    ));
+
+    let accept_state = state.clone();
+    thread::spawn(move || for stream in listener.incoming() {
+        if let Ok(stream) = stream {
+            let connection_state = accept_state.clone();
+            thread::spawn(move || serve_worker(stream, &connection_state, worker_timeout));
+        }
+    });
+
+    let &(ref assembly, ref finished) = &*state;
+    let mut assembly = assembly.lock().unwrap();
+    while assembly.tiles_remaining > 0 {
+        assembly = finished.wait(assembly).unwrap();
+    }
+    Ok(assembly.pixels.clone())
+}
+
+/// Gives tiles to a single worker until every tile is rendered, or the connection fails.
+fn serve_worker(stream: TcpStream, state: &(Mutex<Assembly>, Condvar), timeout: Duration) {
+    let &(ref assembly, ref finished) = state;
+    let mut reader = match stream.try_clone() {
+        Ok(s) => BufReader::new(s),
+        Err(_) => return,
+    };
+    let mut writer = match stream.try_clone() {
+        Ok(s) => BufWriter::new(s),
+        Err(_) => return,
+    };
+
+    loop {
+        match Message::read(&mut reader) {
+            Ok(Message::Ready) => {}
+            _ => return,
+        }
+
+        let tile = {
+            let mut assembly = assembly.lock().unwrap();
+            loop {
+                if assembly.tiles_remaining == 0 {
+                    drop(assembly);
+                    let _ = Message::Done.write(&mut writer);
+                    return;
+                }
+                if let Some(tile) = assembly.pending.pop() {
+                    break tile;
+                }
+                // Other workers hold the remaining tiles, and may fail to return them.
+                assembly = finished.wait(assembly).unwrap();
+            }
+        };
+
+        // Only rendering is timed, workers may take as long as they like to ask for a tile.
+        let rendered = stream
+            .set_read_timeout(Some(timeout))
+            .and_then(|_| Message::Tile(tile).write(&mut writer))
+            .and_then(|_| Message::read(&mut reader))
+            .and_then(|message| match message {
+                Message::Pixels(t) if t == tile => protocol::read_pixels(&mut reader, &tile),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Worker returned pixels for the wrong tile.",
+                )),
+            })
+            .and_then(|pixels| stream.set_read_timeout(None).map(|_| pixels));
+
+        let mut assembly = assembly.lock().unwrap();
+        match rendered {
+            Ok(pixels) => {
+                assembly.store(&tile, &pixels);
+                finished.notify_all();
+            }
+            Err(_) => {
+                // Let another worker pick up this tile, including any waiting for one.
+                assembly.pending.push(tile);
+                finished.notify_all();
+                return;
+            }
+        }
+    }
+}
+
+/// Renders tiles handed out by a coordinator until it reports there are none left.
+///
+/// # Arguments
+/// * `stream` - connection to the coordinator
+/// * `render_tile` - gives the pixels of a tile in row-major order
+///
+/// # Returns
+/// The number of tiles rendered.
+pub fn work<F>(stream: TcpStream, mut render_tile: F) -> io::Result<usize>
+where
+    F: FnMut(&Tile) -> Vec<[u8; 3]>,
+{
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+    let mut tiles_rendered = 0;
+
+    loop {
+        Message::Ready.write(&mut writer)?;
+        match Message::read(&mut reader)? {
+            Message::Tile(tile) => {
+                let pixels = render_tile(&tile);
+                assert_eq!(pixels.len(), tile.pixel_count());
+                Message::Pixels(tile).write(&mut writer)?;
+                protocol::write_pixels(&mut writer, &pixels)?;
+                tiles_rendered += 1;
+            }
+            Message::Done => return Ok(tiles_rendered),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Unexpected message from coordinator.",
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{coordinate, work, Message};
+    use scene::tile::Tile;
+    use std::io::{BufReader, BufWriter};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::thread;
+    use std::time::Duration;
+
+    /// Pixels holding the coordinates of each pixel of a tile.
+    fn coordinate_pixels(tile: &Tile) -> Vec<[u8; 3]> {
+        let mut pixels = Vec::new();
+        for y in tile.y..(tile.y + tile.height) {
+            for x in tile.x..(tile.x + tile.width) {
+                pixels.push([x as u8, y as u8, 0]);
+            }
+        }
+        pixels
+    }
+
+    /// Connects as a worker and takes a tile, without rendering it.
+    fn take_tile(address: SocketAddr) -> TcpStream {
+        let stream = TcpStream::connect(address).unwrap();
+        Message::Ready.write(&mut BufWriter::new(&stream)).unwrap();
+        match Message::read(&mut BufReader::new(&stream)).unwrap() {
+            Message::Tile(_) => {}
+            message => panic!("Expected a tile, not {:?}.", message),
+        }
+        stream
+    }
+
+    /// Renders a single tile image with a worker which asks for a tile only once another worker
+    /// holds it, and then lets the other worker fail with `fail`.
+    fn render_after_failure<F: FnOnce(TcpStream)>(timeout: Duration, fail: F) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let coordinator = thread::spawn(move || coordinate(listener, 4, 4, 4, timeout).unwrap());
+
+        let failing = take_tile(address);
+        let worker = thread::spawn(move || {
+            work(TcpStream::connect(address).unwrap(), coordinate_pixels).unwrap()
+        });
+        // Give the worker time to ask for a tile while the failing worker holds it.
+        thread::sleep(Duration::from_millis(100));
+        fail(failing);
+
+        let pixels = coordinator.join().unwrap();
+        assert_eq!(worker.join().unwrap(), 1);
+        assert_eq!(pixels[4 * 3 + 2], [2, 3, 0]);
+    }
+
+    #[test]
+    fn test_dropped_tile() {
+        render_after_failure(Duration::from_secs(60), |stream| drop(stream));
+    }
+
+    #[test]
+    fn test_stalled_worker() {
+        render_after_failure(Duration::from_millis(200), |stream| {
+            // Hold the tile without rendering it, until it has been rendered by the other worker.
+            thread::spawn(move || {
+                thread::sleep(Duration::from_secs(5));
+                drop(stream);
+            });
+        });
+    }
+
+    #[test]
+    fn test_render_across_workers() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (width, height) = (10, 7);
+
+        let workers: Vec<_> = (0..2)
+            .map(|_| {
+                thread::spawn(move || {
+                    let stream = TcpStream::connect(address).unwrap();
+                    work(stream, coordinate_pixels)
+                })
+            })
+            .collect();
+
+        let pixels = coordinate(listener, width, height, 4, Duration::from_secs(60)).unwrap();
+        let tiles_rendered: usize = workers
+            .into_iter()
+            .map(|w| w.join().unwrap().unwrap())
+            .sum();
+        assert_eq!(tiles_rendered, 3 * 2);
+
+        for y in 0..height {
+            for x in 0..width {
+                assert_eq!(pixels[(y * width + x) as usize], [x as u8, y as u8, 0]);
+            }
+        }
+    }
+}
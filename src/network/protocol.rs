@@ -0,0 +1,129 @@
+use scene::tile::Tile;
+use std::io::{self, BufRead, Read, Write};
+
+/// A single line of the distributed rendering protocol.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Message {
+    /// A worker is ready for another tile.
+    Ready,
+    /// The coordinator assigns a tile to a worker.
+    Tile(Tile),
+    /// A worker is returning pixels for a tile, which follow this message.
+    Pixels(Tile),
+    /// There are no more tiles to render.
+    Done,
+}
+
+impl Message {
+    /// Writes and flushes this message.
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        match *self {
+            Message::Ready => write!(out, "READY\n")?,
+            Message::Tile(t) => write!(out, "TILE {} {} {} {}\n", t.x, t.y, t.width, t.height)?,
+            Message::Pixels(t) => {
+                write!(out, "PIXELS {} {} {} {}\n", t.x, t.y, t.width, t.height)?
+            }
+            Message::Done => write!(out, "DONE\n")?,
+        }
+        out.flush()
+    }
+
+    /// Reads the next message.
+    pub fn read<R: BufRead>(input: &mut R) -> io::Result<Message> {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Connection closed.",
+            ));
+        }
+
+        let mut words = line.split_whitespace();
+        let message = match words.next() {
+            Some("READY") => Some(Message::Ready),
+            Some("DONE") => Some(Message::Done),
+            Some("TILE") => parse_tile(words).map(Message::Tile),
+            Some("PIXELS") => parse_tile(words).map(Message::Pixels),
+            _ => None,
+        };
+        message.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unrecognized message: {}", line.trim()),
+            )
+        })
+    }
+}
+
+fn parse_tile<'a, I: Iterator<Item = &'a str>>(words: I) -> Option<Tile> {
+    let values: Vec<u32> = words.filter_map(|w| w.parse().ok()).collect();
+    if values.len() != 4 {
+        return None;
+    }
+    Some(Tile {
+        x: values[0],
+        y: values[1],
+        width: values[2],
+        height: values[3],
+    })
+}
+
+/// Writes the pixels which follow a `Message::Pixels`.
+pub fn write_pixels<W: Write>(out: &mut W, pixels: &[[u8; 3]]) -> io::Result<()> {
+    for pixel in pixels {
+        out.write_all(pixel)?;
+    }
+    out.flush()
+}
+
+/// Reads the pixels for a tile which follow a `Message::Pixels`.
+pub fn read_pixels<R: Read>(input: &mut R, tile: &Tile) -> io::Result<Vec<[u8; 3]>> {
+    let mut bytes = vec![0; tile.pixel_count() * 3];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes.chunks(3).map(|c| [c[0], c[1], c[2]]).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_pixels, write_pixels, Message};
+    use scene::tile::Tile;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_round_trip() {
+        let tile = Tile {
+            x: 32,
+            y: 64,
+            width: 2,
+            height: 1,
+        };
+        let messages = [
+            Message::Ready,
+            Message::Tile(tile),
+            Message::Pixels(tile),
+            Message::Done,
+        ];
+
+        let mut bytes = Vec::new();
+        for message in messages.iter() {
+            message.write(&mut bytes).unwrap();
+        }
+        write_pixels(&mut bytes, &[[1, 2, 3], [4, 5, 6]]).unwrap();
+
+        let mut input = Cursor::new(bytes);
+        for message in messages.iter() {
+            assert_eq!(Message::read(&mut input).unwrap(), *message);
+        }
+        assert_eq!(
+            read_pixels(&mut input, &tile).unwrap(),
+            vec![[1, 2, 3], [4, 5, 6]]
+        );
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(Message::read(&mut Cursor::new(b"TILE 1 2\n".to_vec())).is_err());
+        assert!(Message::read(&mut Cursor::new(b"HELLO\n".to_vec())).is_err());
+        assert!(Message::read(&mut Cursor::new(Vec::new())).is_err());
+    }
+}
@@ -0,0 +1,259 @@
+//! Radiance of many pixels, stored at full or half precision to trade accuracy for memory in very
+//! large renders and environment maps.
+use scene::Spectrum;
+
+/// Largest finite half precision value.
+pub const HALF_MAX: f32 = 65504.0;
+
+/// How many bits each component of stored radiance takes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Precision {
+    /// 32 bit floats.
+    Full,
+    /// 16 bit IEEE 754 half precision floats, which keep about three decimal digits and overflow
+    /// to infinity past `HALF_MAX`.
+    Half,
+}
+
+impl Default for Precision {
+    fn default() -> Precision {
+        Precision::Full
+    }
+}
+
+/// Converts a float to the bits of the nearest half precision float, rounding halfway values to
+/// even as hardware does.
+///
+/// Values too large for half precision become infinite, and NaN stays NaN.
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if half_exponent <= 0 {
+        // Too small for a normal half, so kept as a subnormal with the implicit leading bit
+        // shifted down into the mantissa.
+        if half_exponent < -10 {
+            return sign;
+        }
+        let shift = (14 - half_exponent) as u32;
+        let full = mantissa | 0x80_0000;
+        let rounded = round_shifted(full, shift);
+        return sign | rounded as u16;
+    }
+
+    // Rounding up may carry into the exponent, as far as infinity, which is the correct result.
+    let rounded = ((half_exponent as u32) << 10) + round_shifted(mantissa, 13);
+    sign | rounded as u16
+}
+
+/// Shifts bits right, rounding to the nearest result and halfway values to even.
+fn round_shifted(bits: u32, shift: u32) -> u32 {
+    let shifted = bits >> shift;
+    let remainder = bits & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    if remainder > halfway || (remainder == halfway && shifted & 1 == 1) {
+        shifted + 1
+    } else {
+        shifted
+    }
+}
+
+/// Converts the bits of a half precision float to a float, exactly.
+pub fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half & 0x8000) as u32) << 16;
+    let exponent = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x3ff) as u32;
+    match exponent {
+        0 => {
+            // Subnormals are multiples of 2^-24.
+            let magnitude = mantissa as f32 / 16_777_216.0;
+            if sign != 0 { -magnitude } else { magnitude }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exponent + 127 - 15) << 23) | (mantissa << 13)),
+    }
+}
+
+/// Radiance stored as a half precision float for each component.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HalfSpectrum([u16; 3]);
+
+impl HalfSpectrum {
+    pub fn new(spectrum: &Spectrum) -> HalfSpectrum {
+        HalfSpectrum([f32_to_f16(spectrum.r), f32_to_f16(spectrum.g), f32_to_f16(spectrum.b)])
+    }
+
+    pub fn to_spectrum(&self) -> Spectrum {
+        Spectrum::new(f16_to_f32(self.0[0]), f16_to_f32(self.0[1]), f16_to_f32(self.0[2]))
+    }
+}
+
+/// Radiance of each of a number of pixels, converted to and from the precision it is stored at
+/// as it is read and written.
+#[derive(Clone, Debug)]
+pub enum RadianceBuffer {
+    Full(Vec<Spectrum>),
+    Half(Vec<HalfSpectrum>),
+}
+
+impl RadianceBuffer {
+    /// A buffer of black pixels.
+    pub fn new(len: usize, precision: Precision) -> RadianceBuffer {
+        match precision {
+            Precision::Full => RadianceBuffer::Full(vec![Spectrum::black(); len]),
+            Precision::Half => {
+                RadianceBuffer::Half(vec![HalfSpectrum::new(&Spectrum::black()); len])
+            }
+        }
+    }
+
+    pub fn precision(&self) -> Precision {
+        match *self {
+            RadianceBuffer::Full(_) => Precision::Full,
+            RadianceBuffer::Half(_) => Precision::Half,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match *self {
+            RadianceBuffer::Full(ref pixels) => pixels.len(),
+            RadianceBuffer::Half(ref pixels) => pixels.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// # Panics
+    /// If the index is outside the buffer.
+    pub fn get(&self, index: usize) -> Spectrum {
+        match *self {
+            RadianceBuffer::Full(ref pixels) => pixels[index],
+            RadianceBuffer::Half(ref pixels) => pixels[index].to_spectrum(),
+        }
+    }
+
+    /// # Panics
+    /// If the index is outside the buffer.
+    pub fn set(&mut self, index: usize, radiance: &Spectrum) {
+        match *self {
+            RadianceBuffer::Full(ref mut pixels) => pixels[index] = *radiance,
+            RadianceBuffer::Half(ref mut pixels) => pixels[index] = HalfSpectrum::new(radiance),
+        }
+    }
+
+    /// The radiance of every pixel, in order.
+    pub fn iter<'a>(&'a self) -> RadianceIter<'a> {
+        RadianceIter {
+            buffer: self,
+            index: 0,
+        }
+    }
+
+    /// The same radiance stored at another precision, losing any precision the other lacks.
+    pub fn with_precision(self, precision: Precision) -> RadianceBuffer {
+        if self.precision() == precision {
+            return self;
+        }
+        match precision {
+            Precision::Full => RadianceBuffer::Full(self.iter().collect()),
+            Precision::Half => {
+                RadianceBuffer::Half(self.iter().map(|r| HalfSpectrum::new(&r)).collect())
+            }
+        }
+    }
+}
+
+impl From<Vec<Spectrum>> for RadianceBuffer {
+    fn from(pixels: Vec<Spectrum>) -> RadianceBuffer {
+        RadianceBuffer::Full(pixels)
+    }
+}
+
+/// Iterates over the radiance of each pixel of a buffer, at full precision.
+pub struct RadianceIter<'a> {
+    buffer: &'a RadianceBuffer,
+    index: usize,
+}
+
+impl<'a> Iterator for RadianceIter<'a> {
+    type Item = Spectrum;
+
+    fn next(&mut self) -> Option<Spectrum> {
+        if self.index < self.buffer.len() {
+            self.index += 1;
+            Some(self.buffer.get(self.index - 1))
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.buffer.len() - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for RadianceIter<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::{f16_to_f32, f32_to_f16, HALF_MAX, Precision, RadianceBuffer};
+    use scene::Spectrum;
+    use std::f32;
+
+    #[test]
+    fn test_half_conversion() {
+        // Including a third rounded to half precision, and the smallest normal half.
+        let smallest_normal = 2.0f32.powi(-14);
+        for &exact in [0.0, 1.0, -2.0, 0.5, 0.333251953125, HALF_MAX, smallest_normal].iter() {
+            assert_eq!(f16_to_f32(f32_to_f16(exact)), exact);
+        }
+        assert_eq!(f32_to_f16(1.0), 0x3c00);
+        assert_eq!(f32_to_f16(-0.0), 0x8000);
+
+        // Subnormals, down to the smallest.
+        let smallest = 2.0f32.powi(-24);
+        assert_eq!(f32_to_f16(smallest), 1);
+        assert_eq!(f16_to_f32(f32_to_f16(3.0 * smallest)), 3.0 * smallest);
+        assert_eq!(f32_to_f16(0.4 * smallest), 0);
+
+        // Halfway between 1 and the next half, 1 + 2^-10, rounds to even.
+        assert_eq!(f32_to_f16(1.0 + 2.0f32.powi(-11)), 0x3c00);
+        assert_eq!(f32_to_f16(1.0 + 3.0 * 2.0f32.powi(-11)), 0x3c02);
+
+        assert_eq!(f16_to_f32(f32_to_f16(70000.0)), f32::INFINITY);
+        assert_eq!(f16_to_f32(f32_to_f16(f32::NEG_INFINITY)), f32::NEG_INFINITY);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+    }
+
+    #[test]
+    fn test_radiance_buffer() {
+        let mut half = RadianceBuffer::new(3, Precision::Half);
+        half.set(1, &Spectrum::new(0.25, 1.5, 1000.0));
+        assert_relative_eq!(half.get(1), Spectrum::new(0.25, 1.5, 1000.0));
+        assert_relative_eq!(half.get(2), Spectrum::black());
+
+        // A third cannot be represented exactly, but is close.
+        half.set(0, &Spectrum::new(1.0 / 3.0, 0.0, 0.0));
+        assert_relative_eq!(half.get(0).r, 1.0 / 3.0, max_relative = 1e-3);
+
+        let full = half.clone().with_precision(Precision::Full);
+        assert_eq!(full.precision(), Precision::Full);
+        assert_eq!(full.iter().len(), 3);
+        for (a, b) in full.iter().zip(half.iter()) {
+            assert_relative_eq!(a, b);
+        }
+    }
+}
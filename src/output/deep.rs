@@ -0,0 +1,110 @@
+use scene::DeepSample;
+use std::io::{self, Write};
+
+/// Identifies a deep image file.
+pub const DEEP_MAGIC: &'static [u8; 8] = b"RRTDEEP1";
+
+/// Writes a deep image, with a variable number of depth and alpha samples per pixel, one pixel at
+/// a time.
+///
+/// # Layout
+/// All values are little endian.
+///
+/// * `DEEP_MAGIC`
+/// * width and height as `u32`
+/// * for each pixel in row-major order, with (0, 0) in the top left corner:
+///   * sample count as `u32`
+///   * for each sample from nearest to farthest, depth then alpha as `f32`
+pub struct DeepWriter<W: Write> {
+    out: W,
+    pixels_remaining: u64,
+}
+
+impl<W: Write> DeepWriter<W> {
+    /// Writes the header and prepares to receive pixels.
+    pub fn new(mut out: W, width: u32, height: u32) -> io::Result<DeepWriter<W>> {
+        out.write_all(DEEP_MAGIC)?;
+        write_u32(&mut out, width)?;
+        write_u32(&mut out, height)?;
+        Ok(DeepWriter {
+            out: out,
+            pixels_remaining: width as u64 * height as u64,
+        })
+    }
+
+    /// Appends the samples of the next pixel.
+    ///
+    /// # Panics
+    /// If every pixel has already been written.
+    pub fn write_pixel(&mut self, samples: &[DeepSample]) -> io::Result<()> {
+        assert!(self.pixels_remaining > 0, "All pixels have already been written.");
+        write_u32(&mut self.out, samples.len() as u32)?;
+        for sample in samples {
+            write_u32(&mut self.out, sample.depth.to_bits())?;
+            write_u32(&mut self.out, sample.alpha.to_bits())?;
+        }
+        self.pixels_remaining -= 1;
+        Ok(())
+    }
+
+    /// Flushes the output, and gives it back.
+    ///
+    /// # Returns
+    /// An error if not every pixel was written.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.pixels_remaining != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("{} pixels were never written.", self.pixels_remaining),
+            ));
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+fn write_u32<W: Write>(out: &mut W, value: u32) -> io::Result<()> {
+    out.write_all(
+        &[
+            value as u8,
+            (value >> 8) as u8,
+            (value >> 16) as u8,
+            (value >> 24) as u8,
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeepWriter, DEEP_MAGIC};
+    use scene::DeepSample;
+
+    #[test]
+    fn test_layout() {
+        let mut writer = DeepWriter::new(Vec::new(), 2, 1).unwrap();
+        writer
+            .write_pixel(
+                &[
+                    DeepSample {
+                        depth: 1.0,
+                        alpha: 1.0,
+                    },
+                ],
+            )
+            .unwrap();
+        writer.write_pixel(&[]).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let mut expected = DEEP_MAGIC.to_vec();
+        expected.extend_from_slice(&[2, 0, 0, 0, 1, 0, 0, 0]);
+        expected.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0x80, 0x3f, 0, 0, 0x80, 0x3f]);
+        expected.extend_from_slice(&[0, 0, 0, 0]);
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn test_missing_pixels() {
+        let writer = DeepWriter::new(Vec::new(), 1, 1).unwrap();
+        assert!(writer.finish().is_err());
+    }
+}
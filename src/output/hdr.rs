@@ -0,0 +1,160 @@
+use scene::Spectrum;
+use std::borrow::Borrow;
+use std::io::{self, Write};
+
+/// Widths which can be written as run-length encoded scanlines.
+const MIN_ENCODED_WIDTH: u32 = 8;
+const MAX_ENCODED_WIDTH: u32 = 0x7fff;
+
+/// Longest run of literal bytes in an encoded scanline.
+const MAX_LITERAL_RUN: usize = 128;
+
+/// Writes radiance to a Radiance HDR (.hdr) image, keeping highlights which would be clipped in
+/// an 8-bit image.
+///
+/// Each pixel is stored as a shared exponent and three 8-bit mantissas (RGBE), which keeps about
+/// two decimal digits of precision at any brightness.  Negative and invalid components are written
+/// as zero.
+///
+/// # Arguments
+/// * `pixels` - radiance of each pixel, in row-major order from the top left corner, such as a
+///   slice or the pixels of a `RadianceBuffer`
+///
+/// # Panics
+/// If there is not exactly one pixel for each position in the image.
+pub fn write_hdr<W, I, R>(mut out: W, width: u32, height: u32, pixels: I) -> io::Result<W>
+where
+    W: Write,
+    I: IntoIterator<Item = R>,
+    I::IntoIter: ExactSizeIterator,
+    R: Borrow<Spectrum>,
+{
+    let mut pixels = pixels.into_iter();
+    assert_eq!((width * height) as usize, pixels.len());
+    write!(
+        out,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        height,
+        width
+    )?;
+
+    let encoded = width >= MIN_ENCODED_WIDTH && width <= MAX_ENCODED_WIDTH;
+    for _ in 0..height {
+        let rgbe: Vec<[u8; 4]> = pixels
+            .by_ref()
+            .take(width as usize)
+            .map(|p| to_rgbe(p.borrow()))
+            .collect();
+        if encoded {
+            write_encoded_scanline(&mut out, &rgbe)?;
+        } else {
+            for pixel in rgbe.iter() {
+                out.write_all(pixel)?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(out)
+}
+
+/// Writes a scanline with each channel stored separately, as runs of literal bytes.
+fn write_encoded_scanline<W: Write>(out: &mut W, rgbe: &[[u8; 4]]) -> io::Result<()> {
+    let width = rgbe.len();
+    out.write_all(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8])?;
+    for channel in 0..4 {
+        let bytes: Vec<u8> = rgbe.iter().map(|p| p[channel]).collect();
+        for run in bytes.chunks(MAX_LITERAL_RUN) {
+            out.write_all(&[run.len() as u8])?;
+            out.write_all(run)?;
+        }
+    }
+    Ok(())
+}
+
+/// Converts radiance to three mantissas sharing the exponent of the largest component.
+fn to_rgbe(radiance: &Spectrum) -> [u8; 4] {
+    let component = |c: f32| if c.is_finite() && c > 0.0 { c } else { 0.0 };
+    let (r, g, b) = (
+        component(radiance.r),
+        component(radiance.g),
+        component(radiance.b),
+    );
+    let max = r.max(g).max(b);
+    if max < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    // max = mantissa * 2^exponent, with the mantissa in [0.5, 1).
+    let mut exponent = max.log2().floor() as i32 + 1;
+    if max >= 2f32.powi(exponent) {
+        exponent += 1;
+    }
+    let scale = 256.0 / 2f32.powi(exponent);
+    [
+        (r * scale) as u8,
+        (g * scale) as u8,
+        (b * scale) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_rgbe, write_hdr};
+    use scene::Spectrum;
+    use std::io::Cursor;
+
+    fn from_rgbe(rgbe: [u8; 4]) -> Spectrum {
+        if rgbe[3] == 0 {
+            return Spectrum::black();
+        }
+        let scale = 2f32.powi(rgbe[3] as i32 - 128 - 8);
+        Spectrum::new(
+            (rgbe[0] as f32 + 0.5) * scale,
+            (rgbe[1] as f32 + 0.5) * scale,
+            (rgbe[2] as f32 + 0.5) * scale,
+        )
+    }
+
+    #[test]
+    fn test_rgbe_keeps_highlights() {
+        for &radiance in [
+            Spectrum::new(1.0, 0.5, 0.25),
+            Spectrum::new(250.0, 10.0, 0.0),
+            Spectrum::new(0.001, 0.002, 0.003),
+        ].iter()
+        {
+            let decoded = from_rgbe(to_rgbe(&radiance));
+            let largest = radiance.max_channel();
+            assert_relative_eq!(decoded, radiance, epsilon = largest / 100.0);
+        }
+        assert_eq!(to_rgbe(&Spectrum::new(-1.0, 0.0, ::std::f32::NAN)), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_layout() {
+        let pixels = vec![Spectrum::uniform(1.0); 10 * 2];
+        let bytes = write_hdr(Cursor::new(Vec::new()), 10, 2, &pixels)
+            .unwrap()
+            .into_inner();
+        let header = "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 10\n";
+        assert!(bytes.starts_with(header.as_bytes()));
+
+        // Each scanline starts with a marker, followed by each channel as a run of 10 bytes.
+        let scanline = &bytes[header.len()..header.len() + 4 + 4 * 11];
+        assert_eq!(&scanline[..4], &[2, 2, 0, 10]);
+        assert_eq!(scanline[4], 10);
+        assert_eq!(bytes.len(), header.len() + 2 * (4 + 4 * 11));
+    }
+
+    #[test]
+    fn test_narrow_images_are_flat() {
+        let pixels = vec![Spectrum::uniform(1.0); 2 * 3];
+        let bytes = write_hdr(Cursor::new(Vec::new()), 2, 3, &pixels)
+            .unwrap()
+            .into_inner();
+        let header = "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 3 +X 2\n";
+        assert_eq!(bytes.len(), header.len() + 4 * 6);
+        assert_eq!(&bytes[header.len()..header.len() + 4], &to_rgbe(&pixels[0]));
+    }
+}
@@ -0,0 +1,196 @@
+use scene::BounceLimits;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Everything needed to render an image again exactly, written alongside it.
+#[derive(Clone, Debug)]
+pub struct RenderManifest {
+    /// Version of the renderer.
+    pub version: String,
+    /// Subcommand used to render, such as "render" or "obj".
+    pub command: String,
+    /// File the scene was loaded from, `None` for built in scenes.
+    pub scene_file: Option<String>,
+    /// Hash of the contents of the scene file and every file it reads, to notice when any has
+    /// changed since the render.
+    pub scene_hash: Option<u64>,
+    pub width: u32,
+    pub height: u32,
+    pub samples_per_pixel: u32,
+    pub sampler: String,
+    /// Seed for the random numbers used to place samples within pixels.
+    pub seed: u32,
+    /// How radiance was determined, such as "path", "toon" or "wireframe".
+    pub integrator: String,
+    pub bounce_limits: BounceLimits,
+    /// Material shading every entity in place of its own, if any.
+    pub material_override: Option<String>,
+    pub view: String,
+}
+
+impl RenderManifest {
+    /// Writes the manifest as a JSON object.
+    pub fn write_json<W: Write>(&self, mut out: W) -> io::Result<W> {
+        let limits = &self.bounce_limits;
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"version\": {},", json_string(&self.version))?;
+        writeln!(out, "  \"command\": {},", json_string(&self.command))?;
+        writeln!(
+            out,
+            "  \"scene_file\": {},",
+            json_option(self.scene_file.as_ref().map(|f| json_string(f)))
+        )?;
+        writeln!(
+            out,
+            "  \"scene_hash\": {},",
+            json_option(self.scene_hash.map(|h| format!("\"fnv1a64:{:016x}\"", h)))
+        )?;
+        writeln!(out, "  \"width\": {},", self.width)?;
+        writeln!(out, "  \"height\": {},", self.height)?;
+        writeln!(out, "  \"samples_per_pixel\": {},", self.samples_per_pixel)?;
+        writeln!(out, "  \"sampler\": {},", json_string(&self.sampler))?;
+        writeln!(out, "  \"seed\": {},", self.seed)?;
+        writeln!(out, "  \"integrator\": {},", json_string(&self.integrator))?;
+        writeln!(
+            out,
+            "  \"bounce_limits\": {{\"total\": {}, \"diffuse\": {}, \"glossy\": {}, \
+             \"transmission\": {}}},",
+            limits.total,
+            limits.diffuse,
+            limits.glossy,
+            limits.transmission
+        )?;
+        writeln!(
+            out,
+            "  \"material_override\": {},",
+            json_option(self.material_override.as_ref().map(|m| json_string(m)))
+        )?;
+        writeln!(out, "  \"view\": {}", json_string(&self.view))?;
+        writeln!(out, "}}")?;
+        out.flush()?;
+        Ok(out)
+    }
+}
+
+/// Hashes the contents of files, such as a scene file and the libraries, meshes and images it
+/// reads, by folding the hash of each file into those of the files before it.
+///
+/// Changing, adding, removing or reordering files changes the hash.
+pub fn hash_files<P: AsRef<Path>>(paths: &[P]) -> io::Result<u64> {
+    let mut hash = FNV_OFFSET_BASIS;
+    for path in paths.iter() {
+        hash = fnv1a_continue(hash, &hash_file(path)?.to_le_bytes());
+    }
+    Ok(hash)
+}
+
+/// Hashes the contents of a file with 64-bit FNV-1a, which is stable between releases unlike the
+/// standard library's hasher.
+fn hash_file<P: AsRef<Path>>(path: P) -> io::Result<u64> {
+    let mut contents = Vec::new();
+    File::open(path)?.read_to_end(&mut contents)?;
+    Ok(fnv1a(&contents))
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    fnv1a_continue(FNV_OFFSET_BASIS, bytes)
+}
+
+/// Continues hashing with FNV-1a from the hash of the bytes before these.
+fn fnv1a_continue(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn json_option(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "null".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fnv1a, hash_files, json_string, RenderManifest};
+    use scene::BounceLimits;
+    use std::fs::File;
+    use std::io::{Cursor, Write};
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn test_hash_files() {
+        let directory = ::std::env::temp_dir().join("rrt_test_hash_files");
+        ::std::fs::create_dir_all(&directory).unwrap();
+        let (scene, library) = (directory.join("scene.ron"), directory.join("library.ron"));
+        File::create(&scene).unwrap().write_all(b"(include: [\"library.ron\"])").unwrap();
+        File::create(&library).unwrap().write_all(b"()").unwrap();
+
+        let both = hash_files(&[&scene, &library]).unwrap();
+        assert_ne!(both, hash_files(&[&scene]).unwrap());
+        assert_ne!(both, hash_files(&[&library, &scene]).unwrap());
+
+        // Changing an included file changes the hash, even though the scene file is unchanged.
+        File::create(&library).unwrap().write_all(b"(lights: [])").unwrap();
+        assert_ne!(both, hash_files(&[&scene, &library]).unwrap());
+        assert!(hash_files(&[directory.join("missing.ron")]).is_err());
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("scenes/a.ron"), "\"scenes/a.ron\"");
+        assert_eq!(json_string("C:\\\"x\"\n"), "\"C:\\\\\\\"x\\\"\\n\"");
+    }
+
+    #[test]
+    fn test_write_json() {
+        let manifest = RenderManifest {
+            version: "0.1.0".to_string(),
+            command: "render".to_string(),
+            scene_file: Some("scenes/cornell.ron".to_string()),
+            scene_hash: Some(0x1234),
+            width: 800,
+            height: 600,
+            samples_per_pixel: 4,
+            sampler: "halton".to_string(),
+            seed: 42,
+            integrator: "path".to_string(),
+            bounce_limits: BounceLimits::default(),
+            material_override: None,
+            view: "standard".to_string(),
+        };
+        let json = String::from_utf8(
+            manifest.write_json(Cursor::new(Vec::new())).unwrap().into_inner(),
+        ).unwrap();
+        assert!(json.starts_with("{\n"));
+        assert!(json.ends_with("}\n"));
+        assert!(json.contains("\"scene_file\": \"scenes/cornell.ron\",\n"));
+        assert!(json.contains("\"scene_hash\": \"fnv1a64:0000000000001234\",\n"));
+        assert!(json.contains("\"seed\": 42,\n"));
+        assert!(json.contains("\"material_override\": null,\n"));
+        assert!(json.contains("\"view\": \"standard\"\n"));
+    }
+}
@@ -0,0 +1,27 @@
+//! Writing rendered images.
+#![allow(dead_code)]
+mod buffer;
+mod deep;
+mod hdr;
+mod manifest;
+mod ppm;
+mod preview;
+mod progress;
+mod split;
+mod statistics;
+mod view;
+#[cfg(feature = "window")]
+mod window;
+pub use self::buffer::{f16_to_f32, f32_to_f16, HalfSpectrum, Precision, RadianceBuffer,
+                       RadianceIter, HALF_MAX};
+pub use self::deep::DeepWriter;
+pub use self::hdr::write_hdr;
+pub use self::manifest::{hash_files, RenderManifest};
+pub use self::ppm::PpmStreamWriter;
+pub use self::preview::Preview;
+pub use self::progress::ProgressBar;
+pub use self::split::{relative_error, SplitRadiance};
+pub use self::statistics::ImageStatistics;
+pub use self::view::{auto_exposure, is_valid, INVALID_COLOR, ViewTransform};
+#[cfg(feature = "window")]
+pub use self::window::PreviewWindow;
@@ -0,0 +1,87 @@
+use std::io::{self, Write};
+
+/// Writes a binary PPM (P6) image one row at a time, so the full image never needs to be in
+/// memory.
+///
+/// Rows must be written from top to bottom.
+pub struct PpmStreamWriter<W: Write> {
+    out: W,
+    width: u32,
+    height: u32,
+    rows_written: u32,
+}
+
+impl<W: Write> PpmStreamWriter<W> {
+    /// Writes the PPM header and prepares to receive rows.
+    pub fn new(mut out: W, width: u32, height: u32) -> io::Result<PpmStreamWriter<W>> {
+        write!(out, "P6\n{} {}\n255\n", width, height)?;
+        Ok(PpmStreamWriter {
+            out: out,
+            width: width,
+            height: height,
+            rows_written: 0,
+        })
+    }
+
+    /// Appends the next row of pixels.
+    ///
+    /// # Panics
+    /// If the row is not exactly `width` pixels, or all rows have already been written.
+    pub fn write_row(&mut self, row: &[[u8; 3]]) -> io::Result<()> {
+        assert_eq!(row.len(), self.width as usize, "Row has the wrong number of pixels.");
+        assert!(self.rows_written < self.height, "All rows have already been written.");
+        for pixel in row {
+            self.out.write_all(pixel)?;
+        }
+        self.rows_written += 1;
+        Ok(())
+    }
+
+    /// Number of rows remaining until the image is complete.
+    pub fn rows_remaining(&self) -> u32 {
+        self.height - self.rows_written
+    }
+
+    /// Flushes the output, and gives it back.
+    ///
+    /// # Returns
+    /// An error if not every row was written, since the image would be truncated.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.rows_written != self.height {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("Only {} of {} rows written.", self.rows_written, self.height),
+            ));
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PpmStreamWriter;
+
+    #[test]
+    fn test_write_rows() {
+        let mut writer = PpmStreamWriter::new(Vec::new(), 2, 2).unwrap();
+        writer.write_row(&[[255, 0, 0], [0, 255, 0]]).unwrap();
+        assert_eq!(writer.rows_remaining(), 1);
+        writer.write_row(&[[0, 0, 255], [1, 2, 3]]).unwrap();
+
+        let bytes = writer.finish().unwrap();
+        let header = b"P6\n2 2\n255\n";
+        assert_eq!(&bytes[..header.len()], &header[..]);
+        assert_eq!(
+            &bytes[header.len()..],
+            &[255, 0, 0, 0, 255, 0, 0, 0, 255, 1, 2, 3][..]
+        );
+    }
+
+    #[test]
+    fn test_incomplete_image() {
+        let mut writer = PpmStreamWriter::new(Vec::new(), 1, 2).unwrap();
+        writer.write_row(&[[0, 0, 0]]).unwrap();
+        assert!(writer.finish().is_err());
+    }
+}
@@ -0,0 +1,98 @@
+//! Images of a render in progress, rewritten as tiles complete so an image viewer which reloads
+//! changed files shows the render filling in, and bad renders can be stopped early.
+//!
+//! Files need no display, so previews work the same on headless machines and can be watched from
+//! another machine sharing the file.  `feh --reload 1 preview.ppm` shows one live, as does any
+//! viewer which watches its file.  Builds with the `window` feature can show a `PreviewWindow`
+//! instead.
+use output::PpmStreamWriter;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// A partly rendered image, written as a binary PPM.  Pixels not yet rendered are black.
+pub struct Preview {
+    path: PathBuf,
+    width: u32,
+    height: u32,
+    pixels: Vec<[u8; 3]>,
+    /// Least time between writes, so slow disks do not hold up rendering.
+    interval: Duration,
+    last_written: Option<Instant>,
+}
+
+impl Preview {
+    pub fn new(path: &Path, width: u32, height: u32, interval: Duration) -> Preview {
+        Preview {
+            path: path.to_path_buf(),
+            width: width,
+            height: height,
+            pixels: vec![[0, 0, 0]; (width * height) as usize],
+            interval: interval,
+            last_written: None,
+        }
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: [u8; 3]) {
+        assert!(x < self.width && y < self.height, "Pixel is outside the preview.");
+        self.pixels[(y * self.width + x) as usize] = color;
+    }
+
+    /// Writes the preview if it has not been written within the interval.
+    ///
+    /// # Returns
+    /// Whether the preview was written.
+    pub fn refresh(&mut self) -> io::Result<bool> {
+        match self.last_written {
+            Some(last) if last.elapsed() < self.interval => Ok(false),
+            _ => self.write().map(|_| true),
+        }
+    }
+
+    /// Writes the preview as it is now.
+    ///
+    /// The image is written next to the preview and moved over it once complete, so viewers never
+    /// load a partly written file.
+    pub fn write(&mut self) -> io::Result<()> {
+        let partial = self.path.with_extension("partial");
+        {
+            let fout = File::create(&partial)?;
+            let mut writer = PpmStreamWriter::new(BufWriter::new(fout), self.width, self.height)?;
+            for row in self.pixels.chunks(self.width as usize) {
+                writer.write_row(row)?;
+            }
+            writer.finish()?;
+        }
+        fs::rename(&partial, &self.path)?;
+        self.last_written = Some(Instant::now());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Preview;
+    use std::fs;
+    use std::time::Duration;
+
+    #[test]
+    fn test_preview_refresh() {
+        let path = ::std::env::temp_dir().join("rrt_test_preview.ppm");
+        let mut preview = Preview::new(&path, 2, 1, Duration::from_secs(3600));
+        preview.set_pixel(1, 0, [255, 128, 0]);
+        assert!(preview.refresh().unwrap());
+
+        let header = b"P6\n2 1\n255\n";
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(&bytes[header.len()..], &[0, 0, 0, 255, 128, 0][..]);
+
+        // Too soon to write again, until written explicitly.
+        preview.set_pixel(0, 0, [1, 2, 3]);
+        assert!(!preview.refresh().unwrap());
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+        preview.write().unwrap();
+        assert_eq!(&fs::read(&path).unwrap()[header.len()..][..3], &[1, 2, 3]);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,98 @@
+//! Reporting how far along a render is, on a single line redrawn in place.
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Characters wide the bar itself is drawn.
+const BAR_WIDTH: usize = 40;
+
+/// A bar filling as pieces of a render, such as tiles, complete.  Pieces may complete on any
+/// thread.
+pub struct ProgressBar<W: Write> {
+    total: usize,
+    start: Instant,
+    state: Mutex<(usize, W)>,
+}
+
+impl ProgressBar<io::Stderr> {
+    /// A bar drawn to standard error, which does not mix with images streamed to standard output.
+    pub fn stderr(total: usize) -> ProgressBar<io::Stderr> {
+        ProgressBar::new(io::stderr(), total)
+    }
+}
+
+impl<W: Write> ProgressBar<W> {
+    /// # Arguments
+    /// * `total` - number of pieces the render is made of
+    pub fn new(out: W, total: usize) -> ProgressBar<W> {
+        ProgressBar {
+            total: total,
+            start: Instant::now(),
+            state: Mutex::new((0, out)),
+        }
+    }
+
+    /// Counts another piece as complete, and redraws the bar.
+    pub fn tick(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.0 += 1;
+        let line = self.line(state.0);
+        // Progress is only informative, so failing to show it must not stop the render.
+        let _ = write!(state.1, "\r{}", line).and_then(|_| state.1.flush());
+    }
+
+    /// Ends the line the bar is drawn on, so later output starts on its own line.
+    pub fn finish(self) {
+        let (_, mut out) = self.state.into_inner().unwrap();
+        let _ = writeln!(out);
+    }
+
+    /// The bar after `done` pieces, with an estimate of the time remaining.
+    fn line(&self, done: usize) -> String {
+        let fraction = if self.total == 0 {
+            1.0
+        } else {
+            done as f64 / self.total as f64
+        };
+        let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+        let bar: String = (0..BAR_WIDTH)
+            .map(|i| if i < filled { '#' } else { '-' })
+            .collect();
+
+        let elapsed = self.start.elapsed();
+        let elapsed = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9;
+        let remaining = if done > 0 {
+            format!("{:.0}s left", elapsed * (1.0 - fraction) / fraction)
+        } else {
+            "?s left".to_string()
+        };
+        format!(
+            "[{}] {:>3.0}% {}/{} {}",
+            bar,
+            100.0 * fraction,
+            done,
+            self.total,
+            remaining
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProgressBar;
+
+    #[test]
+    fn test_progress_bar() {
+        let bar = ProgressBar::new(Vec::new(), 4);
+        bar.tick();
+        bar.tick();
+        let drawn = {
+            let state = bar.state.lock().unwrap();
+            String::from_utf8(state.1.clone()).unwrap()
+        };
+        let last = drawn.rsplit('\r').next().unwrap();
+        assert!(last.starts_with(&format!("[{}{}]", "#".repeat(20), "-".repeat(20))));
+        assert!(last.contains(" 50% 2/4 "));
+        bar.finish();
+    }
+}
@@ -0,0 +1,97 @@
+//! Renders split into two buffers, each averaging half of the samples of every pixel.
+//!
+//! The halves are independent estimates of the same image, so how much they disagree estimates
+//! the noise left in their average.  Denoisers which compare two such buffers use them to tell
+//! noise from detail.
+use output::view::is_valid;
+use scene::Spectrum;
+
+/// The radiance of a pixel from two independent halves of its samples.
+#[derive(Clone, Copy, Debug)]
+pub struct SplitRadiance {
+    /// Average of the first half of the samples.
+    pub a: Spectrum,
+    /// Average of the second half of the samples.
+    pub b: Spectrum,
+}
+
+impl SplitRadiance {
+    /// Average of both halves, the pixel as it would be rendered without splitting.
+    pub fn combined(&self) -> Spectrum {
+        0.5 * (self.a + self.b)
+    }
+
+    /// Estimated standard error of the luminance of `combined`.
+    ///
+    /// Two estimates with variance σ² differ by 2σ² squared on average, and their average has a
+    /// variance of σ² / 2, so half of their difference estimates its standard deviation.
+    ///
+    /// # Returns
+    /// The estimated error, or NaN if either half is invalid.
+    pub fn estimated_error(&self) -> f32 {
+        if !is_valid(&self.a) || !is_valid(&self.b) {
+            return ::std::f32::NAN;
+        }
+        0.5 * (self.a.luminance() - self.b.luminance()).abs()
+    }
+}
+
+/// Average estimated error of the valid pixels, relative to their average luminance, as a single
+/// measure of how noisy a render is.
+///
+/// # Returns
+/// `None` if there are no valid pixels or they are all black.
+pub fn relative_error(pixels: &[SplitRadiance]) -> Option<f32> {
+    let (error, brightness) = pixels
+        .iter()
+        .filter(|p| p.estimated_error().is_finite())
+        .fold((0.0f64, 0.0f64), |(error, brightness), p| {
+            (
+                error + p.estimated_error() as f64,
+                brightness + p.combined().luminance().abs() as f64,
+            )
+        });
+    if brightness > 0.0 {
+        Some((error / brightness) as f32)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SplitRadiance, relative_error};
+    use scene::Spectrum;
+    use std::f32::NAN;
+
+    #[test]
+    fn test_estimated_error() {
+        let grey = Spectrum::uniform;
+        let agreeing = SplitRadiance {
+            a: grey(0.5),
+            b: grey(0.5),
+        };
+        assert_eq!(agreeing.estimated_error(), 0.0);
+
+        let noisy = SplitRadiance {
+            a: grey(0.2),
+            b: grey(0.6),
+        };
+        assert_relative_eq!(noisy.combined(), grey(0.4));
+        assert_relative_eq!(noisy.estimated_error(), 0.2, epsilon = 1e-5);
+
+        let broken = SplitRadiance {
+            a: grey(NAN),
+            b: grey(0.5),
+        };
+        assert!(broken.estimated_error().is_nan());
+
+        // Broken pixels are left out of the relative error of an image.
+        assert_relative_eq!(
+            relative_error(&[agreeing, noisy, broken]).unwrap(),
+            0.2 / 0.9,
+            epsilon = 1e-5
+        );
+        assert!(relative_error(&[broken]).is_none());
+    }
+}
@@ -0,0 +1,240 @@
+use scene::Spectrum;
+use std::f32::INFINITY;
+use std::fmt;
+use std::io::{self, Write};
+
+/// Luminance of the lower bound of the second histogram bin, as a power of two.  Anything darker
+/// is counted in the first bin.
+const HISTOGRAM_LOWEST_STOP: i32 = -12;
+/// Number of histogram bins, each spanning one stop.  Anything brighter than the last is counted
+/// in it.
+const HISTOGRAM_BINS: usize = 18;
+
+/// Summarizes the radiance of pixels in a render, to check exposure and find broken shading.
+#[derive(Clone, Debug)]
+pub struct ImageStatistics {
+    pixel_count: u64,
+    /// Pixels with a NaN or infinite channel, which are excluded from everything else.
+    invalid_count: u64,
+    clipped_count: u64,
+    min_luminance: f32,
+    max_luminance: f32,
+    luminance_sum: f64,
+    histogram: [u64; HISTOGRAM_BINS],
+}
+
+impl ImageStatistics {
+    pub fn new() -> ImageStatistics {
+        ImageStatistics {
+            pixel_count: 0,
+            invalid_count: 0,
+            clipped_count: 0,
+            min_luminance: INFINITY,
+            max_luminance: -INFINITY,
+            luminance_sum: 0.0,
+            histogram: [0; HISTOGRAM_BINS],
+        }
+    }
+
+    /// Includes a pixel in the statistics.
+    pub fn add(&mut self, radiance: &Spectrum) {
+        self.pixel_count += 1;
+        let channels = radiance.to_array();
+        if channels.iter().any(|c| !c.is_finite()) {
+            self.invalid_count += 1;
+            return;
+        }
+        if channels.iter().any(|&c| c >= 1.0) {
+            self.clipped_count += 1;
+        }
+
+        let l = radiance.luminance();
+        self.min_luminance = self.min_luminance.min(l);
+        self.max_luminance = self.max_luminance.max(l);
+        self.luminance_sum += l as f64;
+        self.histogram[ImageStatistics::bin(l)] += 1;
+    }
+
+    /// Combines statistics of separate parts of an image.
+    pub fn merge(&mut self, other: &ImageStatistics) {
+        self.pixel_count += other.pixel_count;
+        self.invalid_count += other.invalid_count;
+        self.clipped_count += other.clipped_count;
+        self.min_luminance = self.min_luminance.min(other.min_luminance);
+        self.max_luminance = self.max_luminance.max(other.max_luminance);
+        self.luminance_sum += other.luminance_sum;
+        for (bin, count) in self.histogram.iter_mut().zip(other.histogram.iter()) {
+            *bin += *count;
+        }
+    }
+
+    fn bin(luminance: f32) -> usize {
+        if luminance <= 0.0 {
+            return 0;
+        }
+        let stop = luminance.log2().floor() as i32 - HISTOGRAM_LOWEST_STOP + 1;
+        stop.max(0).min(HISTOGRAM_BINS as i32 - 1) as usize
+    }
+
+    /// Luminance range counted in a histogram bin, the first and last bins are open ended.
+    pub fn bin_range(bin: usize) -> (f32, f32) {
+        assert!(bin < HISTOGRAM_BINS);
+        let lower = if bin == 0 {
+            0.0
+        } else {
+            2.0_f32.powi(HISTOGRAM_LOWEST_STOP + bin as i32 - 1)
+        };
+        let upper = if bin == HISTOGRAM_BINS - 1 {
+            INFINITY
+        } else {
+            2.0_f32.powi(HISTOGRAM_LOWEST_STOP + bin as i32)
+        };
+        (lower, upper)
+    }
+
+    /// Pixel counts in each one stop range of luminance.
+    pub fn histogram(&self) -> &[u64] {
+        &self.histogram
+    }
+
+    pub fn pixel_count(&self) -> u64 {
+        self.pixel_count
+    }
+
+    /// Lowest luminance of the valid pixels, `None` if there are none.
+    pub fn min_luminance(&self) -> Option<f32> {
+        self.valid_luminance(self.min_luminance)
+    }
+
+    /// Highest luminance of the valid pixels, `None` if there are none.
+    pub fn max_luminance(&self) -> Option<f32> {
+        self.valid_luminance(self.max_luminance)
+    }
+
+    /// Average luminance of the valid pixels, `None` if there are none.
+    pub fn mean_luminance(&self) -> Option<f32> {
+        let valid = self.pixel_count - self.invalid_count;
+        self.valid_luminance((self.luminance_sum / valid as f64) as f32)
+    }
+
+    fn valid_luminance(&self, value: f32) -> Option<f32> {
+        if self.pixel_count > self.invalid_count {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    /// Fraction of pixels with a channel of 1 or more, which will clip when displayed.
+    pub fn clipped_fraction(&self) -> f32 {
+        self.fraction(self.clipped_count)
+    }
+
+    /// Fraction of pixels with a NaN or infinite channel.
+    pub fn invalid_fraction(&self) -> f32 {
+        self.fraction(self.invalid_count)
+    }
+
+    fn fraction(&self, count: u64) -> f32 {
+        if self.pixel_count == 0 {
+            0.0
+        } else {
+            count as f32 / self.pixel_count as f32
+        }
+    }
+
+    /// Writes the histogram as comma separated values, one bin per row.
+    pub fn write_histogram_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "min_luminance,max_luminance,pixels")?;
+        for (bin, count) in self.histogram.iter().enumerate() {
+            let (lower, upper) = ImageStatistics::bin_range(bin);
+            writeln!(out, "{},{},{}", lower, upper, count)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ImageStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Pixels: {}", self.pixel_count)?;
+        match (self.min_luminance(), self.max_luminance(), self.mean_luminance()) {
+            (Some(min), Some(max), Some(mean)) => {
+                writeln!(f, "Luminance: min {} max {} mean {}", min, max, mean)?
+            }
+            _ => writeln!(f, "Luminance: no valid pixels")?,
+        }
+        writeln!(f, "Clipped: {:.2}%", 100.0 * self.clipped_fraction())?;
+        writeln!(f, "NaN or infinite: {:.2}%", 100.0 * self.invalid_fraction())?;
+
+        const BAR_WIDTH: u64 = 50;
+        let most = self.histogram.iter().cloned().max().unwrap_or(0).max(1);
+        for (bin, &count) in self.histogram.iter().enumerate() {
+            let (lower, upper) = ImageStatistics::bin_range(bin);
+            let bar: String = (0..(count * BAR_WIDTH + most - 1) / most).map(|_| '#').collect();
+            writeln!(f, "[{:>10.6}, {:>10.6}) {:>9} {}", lower, upper, count, bar)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ImageStatistics;
+    use scene::Spectrum;
+    use std::f32::{INFINITY, NAN};
+
+    #[test]
+    fn test_statistics() {
+        let mut stats = ImageStatistics::new();
+        stats.add(&Spectrum::black());
+        stats.add(&Spectrum::uniform(0.5));
+        stats.add(&Spectrum::uniform(2.0));
+        stats.add(&Spectrum::new(NAN, 0.0, 0.0));
+        stats.add(&Spectrum::new(0.0, INFINITY, 0.0));
+
+        assert_eq!(stats.pixel_count(), 5);
+        assert_relative_eq!(stats.min_luminance().unwrap(), 0.0);
+        assert_relative_eq!(stats.max_luminance().unwrap(), 2.0, epsilon = 1e-5);
+        assert_relative_eq!(stats.mean_luminance().unwrap(), 2.5 / 3.0, epsilon = 1e-5);
+        assert_relative_eq!(stats.clipped_fraction(), 0.2);
+        assert_relative_eq!(stats.invalid_fraction(), 0.4);
+        assert_eq!(stats.histogram().iter().sum::<u64>(), 3);
+    }
+
+    #[test]
+    fn test_histogram_bins() {
+        let mut stats = ImageStatistics::new();
+        for &l in [0.0, 0.5, 0.75, 1.0, 1e6].iter() {
+            stats.add(&Spectrum::uniform(l));
+        }
+        let histogram = stats.histogram();
+        let last = histogram.len() - 1;
+        assert_eq!(histogram[0], 1);
+        assert_eq!(histogram[last], 1);
+
+        // 0.5 and 0.75 share a stop, which is just below 1.0's.
+        let half_bin = (0..last).find(|&b| ImageStatistics::bin_range(b).0 == 0.5).unwrap();
+        assert_eq!(histogram[half_bin], 2);
+        assert_eq!(histogram[half_bin + 1], 1);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut a = ImageStatistics::new();
+        a.add(&Spectrum::uniform(0.25));
+        let mut b = ImageStatistics::new();
+        b.add(&Spectrum::uniform(4.0));
+        a.merge(&b);
+        assert_eq!(a.pixel_count(), 2);
+        assert_relative_eq!(a.max_luminance().unwrap(), 4.0, epsilon = 1e-5);
+        assert_relative_eq!(a.clipped_fraction(), 0.5);
+    }
+
+    #[test]
+    fn test_empty_statistics() {
+        let stats = ImageStatistics::new();
+        assert!(stats.mean_luminance().is_none());
+        assert_eq!(stats.invalid_fraction(), 0.0);
+        assert!(format!("{}", stats).contains("no valid pixels"));
+    }
+}
@@ -0,0 +1,171 @@
+use scene::Spectrum;
+use std::borrow::Borrow;
+
+/// Luminance of the 18% grey card, the reference for a correct exposure.
+pub const MIDDLE_GREY: f32 = 0.18;
+
+/// Colors for ranges of luminance in the false color view, with the upper bound of each range.
+///
+/// Anything at or above 1.0 will be clipped in the final image.
+const FALSE_COLOR_BANDS: [(f32, [u8; 3]); 6] = [
+    (MIDDLE_GREY / 64.0, [64, 0, 96]),
+    (MIDDLE_GREY / 4.0, [0, 0, 255]),
+    (MIDDLE_GREY / 1.414, [0, 160, 160]),
+    (MIDDLE_GREY * 1.414, [128, 128, 128]),
+    (MIDDLE_GREY * 4.0, [0, 200, 0]),
+    (1.0, [255, 255, 0]),
+];
+const CLIPPED_COLOR: [u8; 3] = [255, 0, 0];
+
+/// Color of pixels with NaN or infinite radiance, when highlighted.
+pub const INVALID_COLOR: [u8; 3] = [255, 0, 255];
+
+/// Width in pixels of the stripes drawn over clipped pixels.
+const ZEBRA_STRIPE_WIDTH: u32 = 4;
+
+/// Luminance added before taking logarithms for auto exposure, so black pixels do not pull the
+/// average to zero.
+const LOG_AVERAGE_DELTA: f32 = 1e-4;
+
+/// How rendered radiance becomes the colors of an 8-bit image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ViewTransform {
+    /// Radiance is clamped to [0, 1] per channel.
+    Standard,
+    /// Pixels are colored by luminance relative to middle grey, to check exposure.  Middle grey is
+    /// shown in grey, darker ranges in teal, blue then purple, brighter ranges in green then
+    /// yellow, and clipped pixels in red.
+    FalseColor,
+    /// The standard view with diagonal stripes over pixels where any channel is clipped.
+    Zebra,
+}
+
+impl ViewTransform {
+    /// Determines the displayed color of a pixel.
+    ///
+    /// # Arguments
+    /// * `radiance` - linear radiance of the pixel
+    /// * `x`, `y` - raster position of the pixel, used to place patterns
+    pub fn apply(&self, radiance: &Spectrum, x: u32, y: u32) -> [u8; 3] {
+        match *self {
+            ViewTransform::Standard => to_bytes(radiance),
+            ViewTransform::FalseColor => {
+                let l = radiance.luminance();
+                FALSE_COLOR_BANDS
+                    .iter()
+                    .find(|&&(upper, _)| l < upper)
+                    .map(|&(_, color)| color)
+                    .unwrap_or(CLIPPED_COLOR)
+            }
+            ViewTransform::Zebra => {
+                let clipped = radiance.max_channel() >= 1.0;
+                if clipped && ((x + y) / ZEBRA_STRIPE_WIDTH) % 2 == 0 {
+                    [0, 0, 0]
+                } else {
+                    to_bytes(radiance)
+                }
+            }
+        }
+    }
+}
+
+/// Finds the scale bringing the log-average luminance of an image to middle grey, as a light
+/// meter would.  The log-average follows the overall brightness of a scene without being thrown
+/// off by a few very bright pixels, such as lights seen directly.
+///
+/// # Returns
+/// The factor to multiply radiance by before display, 1 for images with no valid pixels or which
+/// are entirely black.
+pub fn auto_exposure<I, R>(radiance: I) -> f32
+where
+    I: IntoIterator<Item = R>,
+    R: Borrow<Spectrum>,
+{
+    let (log_sum, count) = radiance
+        .into_iter()
+        .filter(|r| is_valid(r.borrow()))
+        .fold((0.0f64, 0u64), |(sum, count), r| {
+            let l = r.borrow().luminance().max(0.0);
+            (sum + ((l + LOG_AVERAGE_DELTA) as f64).ln(), count + 1)
+        });
+    if count == 0 {
+        return 1.0;
+    }
+    let log_average = (log_sum / count as f64).exp() as f32;
+    if log_average <= LOG_AVERAGE_DELTA * 1.01 {
+        return 1.0;
+    }
+    MIDDLE_GREY / log_average
+}
+
+/// Whether every channel of the radiance is a finite number.
+pub fn is_valid(radiance: &Spectrum) -> bool {
+    radiance.is_finite()
+}
+
+fn to_bytes(radiance: &Spectrum) -> [u8; 3] {
+    [
+        (radiance.r * 255.0).min(255.0) as u8,
+        (radiance.g * 255.0).min(255.0) as u8,
+        (radiance.b * 255.0).min(255.0) as u8,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MIDDLE_GREY, ViewTransform, auto_exposure};
+    use scene::Spectrum;
+    use std::f32::NAN;
+
+    #[test]
+    fn test_auto_exposure() {
+        let grey = Spectrum::uniform;
+
+        // A uniform image is brought to middle grey, whatever its brightness.
+        for &l in [0.001, 0.18, 50.0].iter() {
+            let exposure = auto_exposure(&[grey(l), grey(l)]);
+            assert_relative_eq!(exposure * l, MIDDLE_GREY, max_relative = 0.1);
+        }
+
+        // A few bright pixels barely change the exposure of a dim image.
+        let mut image = vec![grey(0.01); 99];
+        let dim = auto_exposure(&image);
+        image.push(grey(1000.0));
+        assert!(auto_exposure(&image) > 0.5 * dim);
+
+        // Broken and black images are left alone.
+        assert_eq!(auto_exposure(&[grey(NAN)]), 1.0);
+        assert_eq!(auto_exposure(&[grey(0.0)]), 1.0);
+    }
+
+    #[test]
+    fn test_standard_view_clamps() {
+        assert_eq!(
+            ViewTransform::Standard.apply(&Spectrum::new(0.0, 0.5, 2.0), 0, 0),
+            [0, 127, 255]
+        );
+    }
+
+    #[test]
+    fn test_false_color_bands() {
+        let grey = Spectrum::uniform;
+        let view = ViewTransform::FalseColor;
+        assert_eq!(view.apply(&grey(MIDDLE_GREY), 0, 0), [128, 128, 128]);
+        assert_eq!(view.apply(&grey(0.0), 0, 0), [64, 0, 96]);
+        assert_eq!(view.apply(&grey(0.9), 0, 0), [255, 255, 0]);
+        assert_eq!(view.apply(&grey(1.0), 0, 0), [255, 0, 0]);
+    }
+
+    #[test]
+    fn test_zebra_only_marks_clipped_pixels() {
+        let view = ViewTransform::Zebra;
+        let clipped = Spectrum::new(1.5, 0.2, 0.2);
+        let exposed = Spectrum::uniform(0.5);
+
+        let striped = (0..16).filter(|&x| view.apply(&clipped, x, 0) == [0, 0, 0]).count();
+        assert_eq!(striped, 8);
+        assert!((0..16).all(|x| {
+            view.apply(&exposed, x, 0) == ViewTransform::Standard.apply(&exposed, x, 0)
+        }));
+    }
+}
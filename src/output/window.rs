@@ -0,0 +1,77 @@
+//! A window showing a render in progress, filled in as tiles complete.
+//!
+//! Only built with the `window` feature, as it needs a display and a windowing library.  Without
+//! them, `Preview` writes the render in progress to a file instead.
+use minifb::{self, Window, WindowOptions};
+
+/// Most times a second the window is redrawn.
+const FRAMES_PER_SECOND: usize = 15;
+
+/// A window showing a partly rendered image.  Pixels not yet rendered are black.
+pub struct PreviewWindow {
+    window: Window,
+    width: u32,
+    height: u32,
+    /// Colors packed as 0RGB, as the window expects.
+    pixels: Vec<u32>,
+}
+
+impl PreviewWindow {
+    /// Opens a window exactly the size of the image.
+    pub fn new(title: &str, width: u32, height: u32) -> Result<PreviewWindow, minifb::Error> {
+        let mut window = Window::new(
+            title,
+            width as usize,
+            height as usize,
+            WindowOptions::default(),
+        )?;
+        window.set_target_fps(FRAMES_PER_SECOND);
+        Ok(PreviewWindow {
+            window: window,
+            width: width,
+            height: height,
+            pixels: vec![0; (width * height) as usize],
+        })
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: [u8; 3]) {
+        assert!(x < self.width && y < self.height, "Pixel is outside the window.");
+        self.pixels[(y * self.width + x) as usize] = pack(color);
+    }
+
+    /// Whether the window is still open, rather than closed by the user.
+    pub fn is_open(&self) -> bool {
+        self.window.is_open()
+    }
+
+    /// Redraws the window with the pixels set so far, waiting if it was redrawn too recently.
+    ///
+    /// Closed windows are left closed.
+    pub fn refresh(&mut self) -> Result<(), minifb::Error> {
+        if !self.window.is_open() {
+            return Ok(());
+        }
+        self.window.update_with_buffer(
+            &self.pixels,
+            self.width as usize,
+            self.height as usize,
+        )
+    }
+}
+
+/// Packs a color into the 0RGB layout of window pixels.
+fn pack(color: [u8; 3]) -> u32 {
+    ((color[0] as u32) << 16) | ((color[1] as u32) << 8) | color[2] as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pack;
+
+    #[test]
+    fn test_pack() {
+        assert_eq!(pack([0, 0, 0]), 0);
+        assert_eq!(pack([0x12, 0x34, 0x56]), 0x0012_3456);
+        assert_eq!(pack([255, 255, 255]), 0x00ff_ffff);
+    }
+}
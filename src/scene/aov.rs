@@ -0,0 +1,202 @@
+//! Diagnostic images of the surfaces seen through each pixel instead of their shading, to debug
+//! cameras, intersections and materials apart from lighting.
+use color::unit_vector_as_color;
+use math::Vector;
+use scene::{Camera, Scene, SurfaceHit};
+use scene::sampler::pixel_rng;
+
+/// Property of the first surface seen through each pixel to show.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Aov {
+    /// Surface normals, with each component mapped from [-1, 1] to a color channel.
+    Normal,
+    /// Distance to the surface, white for the nearest surface in view fading to black at the
+    /// farthest.
+    Depth,
+    /// Color of the surface without any lighting.
+    Albedo,
+    /// How far the surface moves across the raster while the shutter is open, for blurring
+    /// motion or denoising over time in other tools.  Red and green are the horizontal and
+    /// vertical motion in pixels, plus 128 so both directions fit, and blue is unused.
+    Motion,
+}
+
+/// Renders a property of the surfaces of a scene, through the center of each pixel.
+///
+/// Pixels where nothing is hit are black.
+///
+/// # Returns
+/// The color of each pixel, in row-major order from the top left corner.
+pub fn render(camera: &Camera, scene: &Scene, aov: Aov) -> Vec<[u8; 3]> {
+    let (width, height) = (camera.raster_width(), camera.raster_height());
+    let mut hits = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let ray = camera.generate_ray_sample(x as f32 + 0.5, y as f32 + 0.5);
+            hits.push(scene.first_hit(&ray, &mut pixel_rng(0, x, y)));
+        }
+    }
+
+    let depths = hits.iter().filter_map(|hit| hit.map(|h| h.depth));
+    let (near, far) = depths.fold((::std::f32::INFINITY, 0.0f32), |(near, far), depth| {
+        (near.min(depth), far.max(depth))
+    });
+
+    hits.iter()
+        .map(|hit| match *hit {
+            Some(ref hit) => color_of(camera, scene, hit, aov, near, far),
+            None => [0, 0, 0],
+        })
+        .collect()
+}
+
+/// Color showing a property of a surface.
+///
+/// # Arguments
+/// * `camera`, `scene` - what the surface was seen with, for motion images
+/// * `near`, `far` - range of depths in view, for depth images
+fn color_of(
+    camera: &Camera,
+    scene: &Scene,
+    hit: &SurfaceHit,
+    aov: Aov,
+    near: f32,
+    far: f32,
+) -> [u8; 3] {
+    match aov {
+        Aov::Normal => {
+            // Normals may be a rounding error longer than a unit vector.
+            let clamp = |c: f32| c.max(-1.0).min(1.0);
+            unit_vector_as_color(Vector::new(
+                clamp(hit.normal.x),
+                clamp(hit.normal.y),
+                clamp(hit.normal.z),
+            ))
+        }
+        Aov::Depth => {
+            let gray = depth_to_gray(hit.depth, near, far);
+            [gray, gray, gray]
+        }
+        Aov::Albedo => {
+            let channel = |c: f32| (c.max(0.0).min(1.0) * 255.0).round() as u8;
+            [
+                channel(hit.albedo.r),
+                channel(hit.albedo.g),
+                channel(hit.albedo.b),
+            ]
+        }
+        Aov::Motion => motion_to_color(motion_of(camera, scene, hit)),
+    }
+}
+
+/// How far a surface seen at the time the shutter opens moves across the raster by the time it
+/// closes, in pixels.
+///
+/// Surfaces which cannot be projected onto the raster at either time, such as through panoramic
+/// cameras, do not move.
+fn motion_of(camera: &Camera, scene: &Scene, hit: &SurfaceHit) -> (f32, f32) {
+    let (open, close) = (camera.shutter_time(0.0), camera.shutter_time(1.0));
+    let moved = scene.moved(hit.entity, &hit.point, open, close);
+    match (camera.world_to_raster(&hit.point), camera.world_to_raster(&moved)) {
+        (Some(start), Some(end)) => (end.0 - start.0, end.1 - start.1),
+        _ => (0.0, 0.0),
+    }
+}
+
+/// Color of a motion on the raster, clamped to the 128 pixels either way a channel can hold.
+fn motion_to_color(motion: (f32, f32)) -> [u8; 3] {
+    let channel = |pixels: f32| (128.0 + pixels.round()).max(0.0).min(255.0) as u8;
+    [channel(motion.0), channel(motion.1), 0]
+}
+
+/// Maps a depth linearly to a gray level, from white at `near` to black at `far`.
+///
+/// Everything is white if there is no range of depths.
+fn depth_to_gray(depth: f32, near: f32, far: f32) -> u8 {
+    if far <= near {
+        return 255;
+    }
+    let t = ((depth - near) / (far - near)).max(0.0).min(1.0);
+    ((1.0 - t) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{depth_to_gray, motion_to_color, render, Aov};
+    use math::{AnimatedTransform, PlanarAngle, Sphere, Transform};
+    use scene::{Camera, Film, Perspective, Scene, Spectrum};
+    use scene::material::LambertianMaterial;
+
+    #[test]
+    fn test_depth_to_gray() {
+        assert_eq!(depth_to_gray(1.0, 1.0, 3.0), 255);
+        assert_eq!(depth_to_gray(2.0, 1.0, 3.0), 128);
+        assert_eq!(depth_to_gray(3.0, 1.0, 3.0), 0);
+        assert_eq!(depth_to_gray(5.0, 5.0, 5.0), 255);
+    }
+
+    #[test]
+    fn test_sphere_aovs() {
+        let mut scene = Scene::new();
+        scene.add_entity(
+            Box::new(Sphere::new_with_radius(2.0)),
+            Box::new(LambertianMaterial::new(&Spectrum::new(0.5, 0.25, 1.0))),
+            Transform::translate(0.0, 0.0, 10.0),
+        );
+        let film = Film::new(21, 21);
+        let camera = Camera::new(&film, &Perspective::new(1.0, 100.0, PlanarAngle::Degrees(60.0)));
+        let center = 10 * 21 + 10;
+
+        // The center of the sphere faces straight back toward the camera, along -Z.
+        let normals = render(&camera, &scene, Aov::Normal);
+        assert_eq!(normals[center], [127, 127, 0]);
+        assert_eq!(normals[0], [0, 0, 0]);
+
+        let depths = render(&camera, &scene, Aov::Depth);
+        assert_eq!(depths[center], [255, 255, 255]);
+        assert_eq!(depths[0], [0, 0, 0]);
+
+        let albedos = render(&camera, &scene, Aov::Albedo);
+        assert_eq!(albedos[center], [128, 64, 255]);
+
+        // Nothing moves.
+        let motions = render(&camera, &scene, Aov::Motion);
+        assert_eq!(motions[center], [128, 128, 0]);
+        assert_eq!(motions[0], [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_motion_to_color() {
+        assert_eq!(motion_to_color((0.0, 0.0)), [128, 128, 0]);
+        assert_eq!(motion_to_color((2.4, -3.6)), [130, 124, 0]);
+        assert_eq!(motion_to_color((500.0, -500.0)), [255, 0, 0]);
+    }
+
+    #[test]
+    fn test_motion() {
+        // A sphere moving right by its radius while the shutter is open.
+        let mut scene = Scene::new();
+        scene.add_animated_entity(
+            Box::new(Sphere::new_with_radius(2.0)),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(0.5))),
+            AnimatedTransform::new(
+                Transform::translate(0.0, 0.0, 10.0),
+                0.0,
+                Transform::translate(2.0, 0.0, 10.0),
+                1.0,
+            ),
+        );
+        let film = Film::new(21, 21);
+        let mut camera =
+            Camera::new(&film, &Perspective::new(1.0, 100.0, PlanarAngle::Degrees(60.0)));
+        let center = 10 * 21 + 10;
+        let motions = render(&camera, &scene, Aov::Motion);
+        assert_eq!(motions[center], [128, 128, 0]);
+
+        // The front of the sphere is 8 units away, where the raster is 2 tan 30° 8 units wide.
+        camera.set_shutter(0.0, 1.0);
+        let pixels = 2.0 * 21.0 / (2.0 * (30.0f32).to_radians().tan() * 8.0);
+        let motions = render(&camera, &scene, Aov::Motion);
+        assert_eq!(motions[center], [128 + pixels.round() as u8, 128, 0]);
+    }
+}
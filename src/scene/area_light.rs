@@ -0,0 +1,610 @@
+//! Lights with a surface, which cast soft shadows and light nearby surfaces evenly.
+//!
+//! Area lights are sampled by choosing directions toward them.  Choosing directions in proportion
+//! to the solid angle a light covers, rather than choosing points across its area, keeps noise
+//! low on surfaces close to the light, where equal areas of the light cover very different solid
+//! angles.
+//!
+//! Like non-area lights, area lights are not seen by the camera, only their light on surfaces.
+//!
+//! Light given off may vary across a light with a texture, such as a stained glass window, and
+//! with the angle it leaves at with a profile, such as a lamp shining mostly straight down.
+use math::{Point, Vector, perpendicular_tangents, sphere_uv};
+use scene::Spectrum;
+use scene::sampler::sample_disk;
+use scene::texture::Texture;
+use std::f32::consts::PI;
+use std::fmt;
+
+/// Light arriving at a point from one direction toward an area light.
+pub struct AreaLightSample {
+    /// Radiance leaving the light toward the point.
+    pub radiance: Spectrum,
+    /// Unit vector pointing from the illuminated point to the light.
+    pub direction: Vector,
+    /// Distance from the illuminated point to the light along `direction`.
+    pub distance: f32,
+    /// Probability density of choosing `direction`, with respect to solid angle.
+    pub pdf: f32,
+}
+
+/// Lights are shared between rendering threads, and display as a short summary for inspecting
+/// scenes.
+pub trait AreaLight: Send + Sync + fmt::Display {
+    /// Chooses a direction from a point toward the light, ignoring any occluders.
+    ///
+    /// # Arguments
+    /// * `point` - point to illuminate with the light
+    /// * `u` - position in the unit square, which is mapped onto the light
+    ///
+    /// # Returns
+    /// `None` if the light cannot be seen from the point, such as from behind it.
+    fn sample(&self, point: &Point, u: (f32, f32)) -> Option<AreaLightSample>;
+}
+
+/// How the light given off varies across an area light and with the angle it leaves at.
+///
+/// By default, lights give off the same radiance everywhere and in every direction in front.
+#[derive(Default)]
+pub struct Emission {
+    texture: Option<Box<Texture>>,
+    profile: Vec<f32>,
+}
+
+impl Emission {
+    pub fn new() -> Emission {
+        Emission::default()
+    }
+
+    /// Scales the radiance at each point of the light by a texture, over the texture coordinates
+    /// of the light.
+    ///
+    /// Rectangles run from 0 to 1 along each edge, from the corner.  Disks run from 0 to 1 across
+    /// their width along the tangents of their normal.  Spheres are mapped like sphere solids,
+    /// around the Y axis.
+    pub fn with_texture(mut self, texture: Box<Texture>) -> Emission {
+        self.texture = Some(texture);
+        self
+    }
+
+    /// Scales the radiance by the angle it leaves the light at.
+    ///
+    /// # Arguments
+    /// * `profile` - relative intensity at evenly spaced angles from the normal, from 0° through
+    ///   90°, interpolated linearly between them.  Empty for the same intensity at every angle.
+    ///
+    /// # Panics
+    /// If any intensity is negative.
+    pub fn with_profile(mut self, profile: Vec<f32>) -> Emission {
+        assert!(
+            profile.iter().all(|i| *i >= 0.0),
+            "Light profile intensities must not be negative."
+        );
+        self.profile = profile;
+        self
+    }
+
+    /// Radiance given off from a point on a light.
+    ///
+    /// # Arguments
+    /// * `radiance` - radiance of the light, before the texture and profile
+    /// * `uv` - texture coordinates of the point on the light
+    /// * `point` - the point on the light
+    /// * `cos_light` - cosine of the angle between the light's normal and the light leaving it
+    fn radiance(
+        &self,
+        radiance: &Spectrum,
+        uv: (f32, f32),
+        point: &Point,
+        cos_light: f32,
+    ) -> Spectrum {
+        let mut result = *radiance;
+        if let Some(ref texture) = self.texture {
+            result = result * texture.eval(uv.0, uv.1, point);
+        }
+        if !self.profile.is_empty() {
+            result = result * profile_at(&self.profile, cos_light);
+        }
+        result
+    }
+}
+
+/// Relative intensity of a profile at an angle from the normal.
+fn profile_at(profile: &[f32], cos_light: f32) -> f32 {
+    let last = profile.len() - 1;
+    let angle = cos_light.max(0.0).min(1.0).acos() / (0.5 * PI) * last as f32;
+    let index = (angle as usize).min(last);
+    if index == last {
+        return profile[last];
+    }
+    let t = angle - index as f32;
+    profile[index] * (1.0 - t) + profile[index + 1] * t
+}
+
+/// A glowing ball, such as a light bulb.
+pub struct SphereLight {
+    center: Point,
+    radius: f32,
+    radiance: Spectrum,
+    emission: Emission,
+}
+
+impl SphereLight {
+    /// # Panics
+    /// If the radius is not positive.
+    pub fn new(center: Point, radius: f32, radiance: &Spectrum) -> SphereLight {
+        assert!(radius > 0.0, "Sphere light radius must be positive.");
+        SphereLight {
+            center: center,
+            radius: radius,
+            radiance: *radiance,
+            emission: Emission::new(),
+        }
+    }
+
+    /// Varies the light given off across the sphere and with the angle it leaves at.
+    pub fn with_emission(mut self, emission: Emission) -> SphereLight {
+        self.emission = emission;
+        self
+    }
+}
+
+impl fmt::Display for SphereLight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "sphere light at {}, radius {}, radiance {}",
+            self.center,
+            self.radius,
+            self.radiance
+        )
+    }
+}
+
+impl AreaLight for SphereLight {
+    /// Samples the cone of directions in which the sphere is seen, which no part of the sphere is
+    /// outside of.
+    fn sample(&self, point: &Point, u: (f32, f32)) -> Option<AreaLightSample> {
+        let distance = point.distance_to(self.center);
+        if distance <= self.radius {
+            return None;
+        }
+        let mut axis = self.center - *point;
+        axis.normalize().ok()?;
+
+        let sin_max_squared = (self.radius / distance) * (self.radius / distance);
+        let cos_max = (1.0 - sin_max_squared).sqrt();
+        // Avoids cancellation for small, distant spheres.
+        let one_minus_cos_max = sin_max_squared / (1.0 + cos_max);
+
+        let cos_theta = 1.0 - u.0 * one_minus_cos_max;
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * u.1;
+        let (tangent, bitangent) = perpendicular_tangents(&axis);
+        let direction = (sin_theta * phi.cos()) * tangent + (sin_theta * phi.sin()) * bitangent +
+            cos_theta * axis;
+
+        // The nearer of the two points where the direction crosses the sphere.
+        let offset = distance * sin_theta;
+        let depth = (self.radius * self.radius - offset * offset).max(0.0).sqrt();
+        let to_light = distance * cos_theta - depth;
+        let on_light = *point + to_light * direction;
+        let normal = (on_light - self.center) / self.radius;
+        Some(AreaLightSample {
+            radiance: self.emission.radiance(
+                &self.radiance,
+                sphere_uv(&normal),
+                &on_light,
+                -direction.dot(&normal),
+            ),
+            direction: direction,
+            distance: to_light,
+            pdf: 1.0 / (2.0 * PI * one_minus_cos_max),
+        })
+    }
+}
+
+/// A glowing parallelogram with perpendicular edges, such as a window or a softbox.
+///
+/// Light is given off the side that `edge_u.cross(&edge_v)` points toward.
+pub struct RectangleLight {
+    corner: Point,
+    edge_u: Vector,
+    edge_v: Vector,
+    radiance: Spectrum,
+    emission: Emission,
+}
+
+impl RectangleLight {
+    /// # Arguments
+    /// * `corner` - one corner of the rectangle
+    /// * `edge_u`, `edge_v` - the two edges leaving the corner, which must be perpendicular
+    /// * `radiance` - radiance given off in every direction in front of the rectangle
+    ///
+    /// # Panics
+    /// If either edge has no length, or the edges are not perpendicular.
+    pub fn new(
+        corner: Point,
+        edge_u: Vector,
+        edge_v: Vector,
+        radiance: &Spectrum,
+    ) -> RectangleLight {
+        assert!(
+            edge_u.length() > 0.0 && edge_v.length() > 0.0,
+            "Rectangle light edges must have a length."
+        );
+        assert!(
+            edge_u.dot(&edge_v).abs() <= 1e-4 * edge_u.length() * edge_v.length(),
+            "Rectangle light edges must be perpendicular."
+        );
+        RectangleLight {
+            corner: corner,
+            edge_u: edge_u,
+            edge_v: edge_v,
+            radiance: *radiance,
+            emission: Emission::new(),
+        }
+    }
+
+    /// Varies the light given off across the rectangle and with the angle it leaves at.
+    pub fn with_emission(mut self, emission: Emission) -> RectangleLight {
+        self.emission = emission;
+        self
+    }
+}
+
+impl fmt::Display for RectangleLight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "rectangle light from {} along {} and {}, radiance {}",
+            self.corner,
+            self.edge_u,
+            self.edge_v,
+            self.radiance
+        )
+    }
+}
+
+impl AreaLight for RectangleLight {
+    /// Samples the spherical rectangle the light covers, with the area preserving parametrization
+    /// of Ureña, Fajardo and King's "An Area-Preserving Parametrization for Spherical
+    /// Rectangles".
+    fn sample(&self, point: &Point, u: (f32, f32)) -> Option<AreaLightSample> {
+        let (width, height) = (self.edge_u.length(), self.edge_v.length());
+        let x = self.edge_u / width;
+        let y = self.edge_v / height;
+        let z = x.cross(&y);
+
+        // In the frame of the rectangle, the rectangle is below the point.  Only the front of the
+        // rectangle gives off light, so it is dark from points behind it.
+        let to_corner = self.corner - *point;
+        let z0 = to_corner.dot(&z);
+        if z0 >= 0.0 {
+            return None;
+        }
+        let (x0, y0) = (to_corner.dot(&x), to_corner.dot(&y));
+        let (x1, y1) = (x0 + width, y0 + height);
+
+        // Normals of the planes through the point and each edge.
+        let v00 = Vector::new(x0, y0, z0);
+        let v01 = Vector::new(x0, y1, z0);
+        let v10 = Vector::new(x1, y0, z0);
+        let v11 = Vector::new(x1, y1, z0);
+        let unit_cross = |a: &Vector, b: &Vector| {
+            let mut n = a.cross(b);
+            n.normalize().map(|_| n)
+        };
+        let n0 = unit_cross(&v00, &v10).ok()?;
+        let n1 = unit_cross(&v10, &v11).ok()?;
+        let n2 = unit_cross(&v11, &v01).ok()?;
+        let n3 = unit_cross(&v01, &v00).ok()?;
+
+        // Interior angles, whose excess over 2π is the solid angle.
+        let angle = |a: &Vector, b: &Vector| (-a.dot(b)).max(-1.0).min(1.0).acos();
+        let (g0, g1) = (angle(&n0, &n1), angle(&n1, &n2));
+        let (g2, g3) = (angle(&n2, &n3), angle(&n3, &n0));
+        let k = 2.0 * PI - g2 - g3;
+        let solid_angle = g0 + g1 - k;
+        if !(solid_angle > 1e-7) {
+            return None;
+        }
+        let (b0, b1) = (n0.z, n2.z);
+
+        // Choose a column of the rectangle by its share of the solid angle.
+        let au = u.0 * solid_angle + k;
+        let fu = (au.cos() * b0 - b1) / au.sin();
+        let cu = (1.0 / (fu * fu + b0 * b0).sqrt() * fu.signum()).max(-1.0).min(1.0);
+        let xu = (-(cu * z0) / (1.0 - cu * cu).max(1e-12).sqrt()).max(x0).min(x1);
+
+        // Then a point in the column.
+        let d = (xu * xu + z0 * z0).sqrt();
+        let h0 = y0 / (d * d + y0 * y0).sqrt();
+        let h1 = y1 / (d * d + y1 * y1).sqrt();
+        let hv = h0 + u.1 * (h1 - h0);
+        let yv = if hv * hv < 1.0 - 1e-6 {
+            (hv * d) / (1.0 - hv * hv).sqrt()
+        } else {
+            y1
+        };
+
+        let mut direction = xu * x + yv * y + z0 * z;
+        let distance = direction.length();
+        direction.normalize().ok()?;
+        Some(AreaLightSample {
+            radiance: self.emission.radiance(
+                &self.radiance,
+                ((xu - x0) / width, (yv - y0) / height),
+                &(*point + distance * direction),
+                -z0 / distance,
+            ),
+            direction: direction,
+            distance: distance,
+            pdf: 1.0 / solid_angle,
+        })
+    }
+}
+
+/// A glowing circle, such as a ceiling light.
+///
+/// Light is given off the side the normal points toward.
+pub struct DiskLight {
+    center: Point,
+    normal: Vector,
+    radius: f32,
+    radiance: Spectrum,
+    emission: Emission,
+}
+
+impl DiskLight {
+    /// # Panics
+    /// If the normal has no direction, or the radius is not positive.
+    pub fn new(center: Point, normal: &Vector, radius: f32, radiance: &Spectrum) -> DiskLight {
+        assert!(radius > 0.0, "Disk light radius must be positive.");
+        let mut n = *normal;
+        n.normalize().expect("Disk light needs a normal.");
+        DiskLight {
+            center: center,
+            normal: n,
+            radius: radius,
+            radiance: *radiance,
+            emission: Emission::new(),
+        }
+    }
+
+    /// Varies the light given off across the disk and with the angle it leaves at.
+    pub fn with_emission(mut self, emission: Emission) -> DiskLight {
+        self.emission = emission;
+        self
+    }
+}
+
+impl fmt::Display for DiskLight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "disk light at {} facing {}, radius {}, radiance {}",
+            self.center,
+            self.normal,
+            self.radius,
+            self.radiance
+        )
+    }
+}
+
+impl AreaLight for DiskLight {
+    /// Samples points evenly across the disk, converting their density to solid angle.
+    fn sample(&self, point: &Point, u: (f32, f32)) -> Option<AreaLightSample> {
+        let (tangent, bitangent) = perpendicular_tangents(&self.normal);
+        let (a, b) = sample_disk(u.0, u.1);
+        let on_light = self.center + (self.radius * a) * tangent + (self.radius * b) * bitangent;
+
+        let distance = point.distance_to(on_light);
+        let mut direction = on_light - *point;
+        direction.normalize().ok()?;
+        let cos_light = -direction.dot(&self.normal);
+        if cos_light <= 0.0 {
+            return None;
+        }
+        let area = PI * self.radius * self.radius;
+        Some(AreaLightSample {
+            radiance: self.emission.radiance(
+                &self.radiance,
+                (0.5 * (a + 1.0), 0.5 * (b + 1.0)),
+                &on_light,
+                cos_light,
+            ),
+            direction: direction,
+            distance: distance,
+            pdf: distance * distance / (area * cos_light),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AreaLight, DiskLight, Emission, RectangleLight, SphereLight, profile_at};
+    use math::{Point, Vector};
+    use math::strategies::point;
+    use rand::{Rng, XorShiftRng};
+    use scene::Spectrum;
+    use scene::texture::Texture;
+    use std::f32::consts::PI;
+
+    /// Red increasing with U and green with V.
+    struct Gradient;
+
+    impl Texture for Gradient {
+        fn eval(&self, u: f32, v: f32, _point: &Point) -> Spectrum {
+            Spectrum::new(u, v, 0.0)
+        }
+    }
+
+    /// Monte Carlo estimate of the irradiance at a point facing up, from light of radiance 1.
+    fn irradiance(light: &AreaLight, point: &Point) -> f32 {
+        let mut rng = XorShiftRng::new_unseeded();
+        let samples = 4000;
+        let mut total = 0.0;
+        for _ in 0..samples {
+            if let Some(sample) = light.sample(point, (rng.gen(), rng.gen())) {
+                total += sample.direction.y.max(0.0) / sample.pdf;
+            }
+        }
+        total / samples as f32
+    }
+
+    #[test]
+    fn test_sphere_light() {
+        let white = Spectrum::uniform(1.0);
+        let light = SphereLight::new(Point::new(0.0, 4.0, 0.0), 1.0, &white);
+        let origin = Point::new(0.0, 0.0, 0.0);
+
+        // A sphere directly above a surface lights it like a point light, π sin²θ.
+        assert_relative_eq!(irradiance(&light, &origin), PI / 16.0, max_relative = 0.01);
+
+        // Every sample lands on the sphere.
+        let sample = light.sample(&origin, (0.3, 0.7)).unwrap();
+        let on_light = origin + sample.distance * sample.direction;
+        assert_relative_eq!(on_light.distance_to(Point::new(0.0, 4.0, 0.0)), 1.0, epsilon = 1e-4);
+
+        assert!(light.sample(&Point::new(0.0, 4.5, 0.0), (0.5, 0.5)).is_none());
+    }
+
+    #[test]
+    fn test_rectangle_light() {
+        // A 2x2 square 1 above the origin, facing down.
+        let white = Spectrum::uniform(1.0);
+        let light = RectangleLight::new(
+            Point::new(-1.0, 1.0, -1.0),
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 2.0),
+            &white,
+        );
+        let origin = Point::new(0.0, 0.0, 0.0);
+
+        // Each sample has the density of the solid angle of the square, 4 asin(a² / (a² + h²)).
+        let solid_angle = 4.0 * (0.5f32).asin();
+        let sample = light.sample(&origin, (0.25, 0.8)).unwrap();
+        assert_relative_eq!(1.0 / sample.pdf, solid_angle, max_relative = 1e-4);
+
+        // Samples land on the square.
+        let mut rng = XorShiftRng::new_unseeded();
+        for _ in 0..100 {
+            let sample = light.sample(&origin, (rng.gen(), rng.gen())).unwrap();
+            let on_light = origin + sample.distance * sample.direction;
+            assert_relative_eq!(on_light.y, 1.0, epsilon = 1e-4);
+            assert!(on_light.x.abs() <= 1.0 + 1e-4 && on_light.z.abs() <= 1.0 + 1e-4);
+        }
+
+        // Each quarter of the square gives a / sqrt(a² + h²) atan(a / sqrt(a² + h²)) below its
+        // corner, for a square quarter of width a at height h.
+        let quarter = (0.5f32).sqrt() * (0.5f32).sqrt().atan();
+        assert_relative_eq!(irradiance(&light, &origin), 4.0 * quarter, max_relative = 0.01);
+
+        // The back of the light is dark.
+        assert!(light.sample(&Point::new(0.0, 2.0, 0.0), (0.5, 0.5)).is_none());
+    }
+
+    #[test]
+    fn test_disk_light() {
+        let white = Spectrum::uniform(1.0);
+        let light = DiskLight::new(
+            Point::new(0.0, 2.0, 0.0),
+            &Vector::new(0.0, -1.0, 0.0),
+            1.0,
+            &white,
+        );
+        // A disk of radius R at height h gives π R² / (h² + R²).
+        let origin = Point::new(0.0, 0.0, 0.0);
+        assert_relative_eq!(irradiance(&light, &origin), PI / 5.0, max_relative = 0.02);
+        assert!(light.sample(&Point::new(0.0, 3.0, 0.0), (0.5, 0.5)).is_none());
+    }
+
+    #[test]
+    fn test_profile_at() {
+        let profile = [1.0, 0.5, 0.0];
+        assert_relative_eq!(profile_at(&profile, 1.0), 1.0);
+        assert_relative_eq!(profile_at(&profile, (PI / 4.0).cos()), 0.5, epsilon = 1e-5);
+        assert_relative_eq!(profile_at(&profile, (PI / 8.0).cos()), 0.75, epsilon = 1e-5);
+        assert_relative_eq!(profile_at(&profile, 0.0), 0.0);
+        assert_relative_eq!(profile_at(&[2.0], 0.3), 2.0);
+    }
+
+    #[test]
+    fn test_emission() {
+        // A 2x2 square 1 above the origin, facing down, brightest straight down.
+        let light = RectangleLight::new(
+            Point::new(-1.0, 1.0, -1.0),
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 2.0),
+            &Spectrum::uniform(2.0),
+        ).with_emission(
+            Emission::new()
+                .with_texture(Box::new(Gradient))
+                .with_profile(vec![1.0, 0.0]),
+        );
+        let origin = Point::new(0.0, 0.0, 0.0);
+        let mut rng = XorShiftRng::new_unseeded();
+        for _ in 0..100 {
+            let sample = light.sample(&origin, (rng.gen(), rng.gen())).unwrap();
+            let on_light = origin + sample.distance * sample.direction;
+            let angle = sample.direction.y.acos();
+            let intensity = 2.0 * (1.0 - angle / (0.5 * PI));
+            assert_relative_eq!(
+                sample.radiance,
+                Spectrum::new(
+                    intensity * 0.5 * (on_light.x + 1.0),
+                    intensity * 0.5 * (on_light.z + 1.0),
+                    0.0,
+                ),
+                epsilon = 1e-3
+            );
+        }
+
+        // Points on disks and spheres are textured by where they are on the light.
+        let disk = DiskLight::new(
+            Point::new(0.0, 2.0, 0.0),
+            &Vector::new(0.0, -1.0, 0.0),
+            1.0,
+            &Spectrum::uniform(1.0),
+        ).with_emission(Emission::new().with_texture(Box::new(Gradient)));
+        let center = disk.sample(&origin, (0.5, 0.5)).unwrap();
+        assert_relative_eq!(center.radiance, Spectrum::new(0.5, 0.5, 0.0), epsilon = 1e-5);
+
+        let sphere = SphereLight::new(Point::new(0.0, 4.0, 0.0), 1.0, &Spectrum::uniform(1.0))
+            .with_emission(Emission::new().with_texture(Box::new(Gradient)));
+        // The south pole faces the origin.
+        let pole = sphere.sample(&origin, (0.0, 0.0)).unwrap();
+        assert_relative_eq!(pole.radiance.g, 0.0, epsilon = 1e-3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_negative_profile() {
+        Emission::new().with_profile(vec![1.0, -0.5]);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_sphere_light_samples(
+            center in point(),
+            radius in 0.1f32..2.0,
+            p in point(),
+            u in (0.0f32..1.0, 0.0f32..1.0),
+        ) {
+            prop_assume!(p.distance_to(center) > 1.01 * radius);
+            let light = SphereLight::new(center, radius, &Spectrum::uniform(1.0));
+            let sample = light.sample(&p, u).unwrap();
+            assert!(sample.direction.is_normalized());
+            assert!(sample.distance > 0.0);
+            let on_light = p + sample.distance * sample.direction;
+            assert_relative_eq!(on_light.distance_to(center), radius, epsilon = 1e-2);
+
+            // Directions are chosen evenly over the cone the sphere fills, so the density
+            // integrates to one over the cone's solid angle.
+            let sin_max = radius / p.distance_to(center);
+            let solid_angle = 2.0 * PI * (1.0 - (1.0 - sin_max * sin_max).sqrt());
+            assert_relative_eq!(sample.pdf * solid_angle, 1.0, max_relative = 1e-2);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use image;
+use std::path::Path;
+
+use scene::{Film, Spectrum};
+use scene::dimensions::Dimensions2;
+
+/// A background image stretched to fit the film, seen wherever camera rays escape the scene.
+///
+/// Backplates only replace what the camera sees directly.  Lighting still comes from the lights in
+/// the scene, so reflections and shading are unaffected.
+pub struct Backplate {
+    width: u32,
+    height: u32,
+    pixels: Vec<Spectrum>,
+
+    // Ratio of backplate pixels to film pixels along each axis.
+    x_scale: f32,
+    y_scale: f32,
+}
+
+impl Backplate {
+    /// Loads a backplate from an image file, fitting it to the given film.
+    pub fn open(path: &Path, film: &Film) -> image::ImageResult<Backplate> {
+        let image = image::open(path)?.to_rgb();
+        let (width, height) = image.dimensions();
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let rgb = image.get_pixel(x, y).data;
+                pixels.push(
+                    Spectrum::new(rgb[0] as f32, rgb[1] as f32, rgb[2] as f32) / 255.0,
+                );
+            }
+        }
+        Ok(Backplate::new(width, height, pixels, film))
+    }
+
+    /// Creates a backplate from row-major pixels, with (0, 0) in the top left corner.
+    pub fn new(width: u32, height: u32, pixels: Vec<Spectrum>, film: &Film) -> Backplate {
+        assert!(width > 0 && height > 0);
+        assert_eq!(pixels.len(), (width * height) as usize);
+        Backplate {
+            width: width,
+            height: height,
+            pixels: pixels,
+            x_scale: width as f32 / film.width() as f32,
+            y_scale: height as f32 / film.height() as f32,
+        }
+    }
+
+    /// The background color behind a position on the raster.
+    pub fn at(&self, x: u32, y: u32) -> Spectrum {
+        let bx = ((x as f32 * self.x_scale) as u32).min(self.width - 1);
+        let by = ((y as f32 * self.y_scale) as u32).min(self.height - 1);
+        self.pixels[(by * self.width + bx) as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Backplate;
+    use image::{ImageBuffer, Rgb};
+    use scene::{Film, Spectrum};
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn test_stretched_to_film() {
+        // A 2x1 backplate of red and blue, behind a 4x2 film.
+        let red = Spectrum::new(1.0, 0.0, 0.0);
+        let blue = Spectrum::new(0.0, 0.0, 1.0);
+        let backplate = Backplate::new(2, 1, vec![red, blue], &Film::new(4, 2));
+        assert_relative_eq!(backplate.at(0, 0), red);
+        assert_relative_eq!(backplate.at(1, 1), red);
+        assert_relative_eq!(backplate.at(2, 0), blue);
+        assert_relative_eq!(backplate.at(3, 1), blue);
+    }
+
+    #[test]
+    fn test_shrunk_to_film() {
+        // A 4x4 backplate behind a 2x2 film takes every other pixel.
+        let pixels = (0..16).map(|i| Spectrum::uniform(i as f32)).collect();
+        let backplate = Backplate::new(4, 4, pixels, &Film::new(2, 2));
+        assert_relative_eq!(backplate.at(0, 0), Spectrum::uniform(0.0));
+        assert_relative_eq!(backplate.at(1, 0), Spectrum::uniform(2.0));
+        assert_relative_eq!(backplate.at(0, 1), Spectrum::uniform(8.0));
+        assert_relative_eq!(backplate.at(1, 1), Spectrum::uniform(10.0));
+    }
+
+    #[test]
+    fn test_open() {
+        let directory = Path::new("target").join("rrt_test_backplate");
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("backplate.png");
+        let image = ImageBuffer::from_fn(2, 1, |x, _| {
+            if x == 0 {
+                Rgb { data: [255, 0, 0] }
+            } else {
+                Rgb { data: [0, 0, 255] }
+            }
+        });
+        image.save(&path).unwrap();
+
+        let backplate = Backplate::open(&path, &Film::new(2, 1)).unwrap();
+        assert_relative_eq!(backplate.at(0, 0), Spectrum::new(1.0, 0.0, 0.0));
+        assert_relative_eq!(backplate.at(1, 0), Spectrum::new(0.0, 0.0, 1.0));
+        assert!(Backplate::open(&directory.join("missing.png"), &Film::new(2, 1)).is_err());
+    }
+}
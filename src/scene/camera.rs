@@ -1,22 +1,71 @@
 #![allow(dead_code)]
-use math::{Matrix4x4, PlanarAngle, Point, Ray};
+use math::{AABB, Matrix4x4, PlanarAngle, Point, Ray, Vector};
+use std::f32::consts::PI;
 use scene::dimensions::{BasicDimensions2, Dimensions2};
+use scene::sampler::sample_disk;
+use std::fmt;
+
+/// The largest width or height of a film.
+///
+/// This keeps the total pixel count of a film, and offsets into buffers of its pixels, within
+/// `u32`.
+pub const MAX_FILM_DIMENSION: u32 = 32768;
+
+/// Reasons a film cannot be created.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FilmError {
+    /// A film must have at least one pixel in each direction.
+    Empty,
+    /// The width or height exceeds `MAX_FILM_DIMENSION`.
+    TooLarge,
+}
+
+impl fmt::Display for FilmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FilmError::Empty => write!(f, "film width and height must be non-zero"),
+            FilmError::TooLarge => write!(
+                f,
+                "film width and height cannot exceed {} pixels",
+                MAX_FILM_DIMENSION
+            ),
+        }
+    }
+}
 
 /// The mapping between the raster (film) and the image plane of the camera.
 pub struct Film {
-    size: BasicDimensions2<u16>,
+    size: BasicDimensions2<u32>,
     raster_to_screen: Matrix4x4,
     screen_to_raster: Matrix4x4,
 }
 
 impl Film {
+    /// Creates a film, panicking if the dimensions are invalid.
+    ///
+    /// # Panics
+    /// If either dimension is zero or larger than `MAX_FILM_DIMENSION`.
+    pub fn new(width: u32, height: u32) -> Film {
+        match Film::try_new(width, height) {
+            Ok(film) => film,
+            Err(e) => panic!("Cannot create a {}x{} film: {}", width, height, e),
+        }
+    }
+
     /// Converting from screen to raster:
     /// 1.) Flip along the Y-axis, so _top_ left will end up as (0, 0).
     /// 2.) Offset the screen by half its dimensions so all X,Y coordinates become non-negative.
     /// 3.) Squash the screen so X, Y range from [0, 1].
     /// 4.) Scale to the raster range X in [0, raster_width], Y in [0, raster_height].
-    pub fn new(width: u16, height: u16) -> Film {
-        let size = BasicDimensions2::<u16>::new(width, height);
+    pub fn try_new(width: u32, height: u32) -> Result<Film, FilmError> {
+        if width == 0 || height == 0 {
+            return Err(FilmError::Empty);
+        }
+        if width > MAX_FILM_DIMENSION || height > MAX_FILM_DIMENSION {
+            return Err(FilmError::TooLarge);
+        }
+
+        let size = BasicDimensions2::<u32>::new(width, height);
         let screen = Film::screen_space_from_aspect_ratio(size.aspect_ratio());
 
         let screen_to_raster = //Matrix4x4::scale(1.0, -1.0, 1.0) *
@@ -25,11 +74,11 @@ impl Film {
             Matrix4x4::translate(screen.width() / 2.0, screen.height() / 2.0, 0.0) *
             Matrix4x4::scale(1.0, -1.0, 1.0);
 
-        Film {
+        Ok(Film {
             size: size,
-            raster_to_screen: screen_to_raster.inverse().unwrap(),
+            raster_to_screen: screen_to_raster.fast_affine_inverse().unwrap(),
             screen_to_raster: screen_to_raster,
-        }
+        })
     }
 
     /// Gives the dimension of the image plane (screen space).
@@ -62,12 +111,12 @@ impl Film {
     }
 }
 
-impl Dimensions2<u16> for Film {
-    fn width(&self) -> u16 {
+impl Dimensions2<u32> for Film {
+    fn width(&self) -> u32 {
         self.size.width()
     }
 
-    fn height(&self) -> u16 {
+    fn height(&self) -> u32 {
         self.size.height()
     }
 }
@@ -75,6 +124,29 @@ impl Dimensions2<u16> for Film {
 pub trait Projection {
     fn screen_to_camera(&self) -> &Matrix4x4;
     fn camera_to_screen(&self) -> &Matrix4x4;
+
+    /// Whether rays travel in parallel down Z+ from the near plane, rather than spreading out from
+    /// the origin.
+    fn is_orthographic(&self) -> bool {
+        false
+    }
+
+    /// How the film wraps around the camera, for projections too wide for a matrix, whose screen
+    /// space is left unchanged by their matrices.
+    fn panorama(&self) -> Option<Panorama> {
+        None
+    }
+}
+
+/// Mappings of the film onto directions all around the camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Panorama {
+    /// The angle from the view direction grows evenly with the distance from the center of the
+    /// film, reaching half the field of view, in radians, at the edges of its shorter side.
+    Fisheye { half_fov: f32 },
+    /// Longitude across the width of the film and latitude down its height, covering every
+    /// direction, with the view direction at the center.
+    Equirectangular,
 }
 
 pub struct Perspective {
@@ -114,26 +186,254 @@ impl Projection for Perspective {
     }
 }
 
+/// A projection with parallel rays, so objects appear the same size at any distance.
+///
+/// Useful for technical drawings, isometric views, and checking the camera's transforms.
+pub struct Orthographic {
+    camera_to_screen: Matrix4x4,
+    screen_to_camera: Matrix4x4,
+}
+
+impl Orthographic {
+    /// Generates an orthographic transform.
+    ///
+    /// # Arguments
+    /// * `near` - distance to near plane, rays start here
+    /// * `far` - distance to far plane
+    /// * `half_extent` - distance from the center of the view to the edge of the shorter side of
+    ///   the film, in camera space units.
+    pub fn new(near: f32, far: f32, half_extent: f32) -> Orthographic {
+        assert!(far > near);
+        assert!(half_extent > 0.0);
+
+        let projection = Matrix4x4::orthographic(near, far, half_extent);
+        Orthographic {
+            camera_to_screen: projection,
+            screen_to_camera: projection.inverse().unwrap(),
+        }
+    }
+}
+
+impl Projection for Orthographic {
+    fn screen_to_camera(&self) -> &Matrix4x4 {
+        &self.screen_to_camera
+    }
+
+    fn camera_to_screen(&self) -> &Matrix4x4 {
+        &self.camera_to_screen
+    }
+
+    fn is_orthographic(&self) -> bool {
+        true
+    }
+}
+
+/// An equidistant fisheye lens, which can see wider than a perspective projection, even behind
+/// the camera.
+pub struct Fisheye {
+    half_fov: f32,
+    // Screen space is mapped onto directions by the camera, so is left unchanged.
+    identity: Matrix4x4,
+}
+
+impl Fisheye {
+    /// # Arguments
+    /// * `fov` - field of view across the shorter side of the film, up to a full turn
+    pub fn new(fov: PlanarAngle) -> Fisheye {
+        let fov_degrees = fov.to_degrees();
+        assert!(fov_degrees > 0.0 && fov_degrees <= 360.0);
+        Fisheye {
+            half_fov: fov.to_radians() / 2.0,
+            identity: Matrix4x4::identity(),
+        }
+    }
+}
+
+impl Projection for Fisheye {
+    fn screen_to_camera(&self) -> &Matrix4x4 {
+        &self.identity
+    }
+
+    fn camera_to_screen(&self) -> &Matrix4x4 {
+        &self.identity
+    }
+
+    fn panorama(&self) -> Option<Panorama> {
+        Some(Panorama::Fisheye { half_fov: self.half_fov })
+    }
+}
+
+/// A projection of every direction onto the film, for environment maps and VR panoramas.  Films
+/// twice as wide as they are tall keep the same scale in both directions.
+pub struct Equirectangular {
+    // Screen space is mapped onto directions by the camera, so is left unchanged.
+    identity: Matrix4x4,
+}
+
+impl Equirectangular {
+    pub fn new() -> Equirectangular {
+        Equirectangular { identity: Matrix4x4::identity() }
+    }
+}
+
+impl Projection for Equirectangular {
+    fn screen_to_camera(&self) -> &Matrix4x4 {
+        &self.identity
+    }
+
+    fn camera_to_screen(&self) -> &Matrix4x4 {
+        &self.identity
+    }
+
+    fn panorama(&self) -> Option<Panorama> {
+        Some(Panorama::Equirectangular)
+    }
+}
+
 /// A film and projection melded into a single functional component, providing ray casting from the
 /// viewing to the scene.
 ///
 pub struct Camera {
     raster_to_camera: Matrix4x4,
     camera_to_raster: Matrix4x4,
+    camera_to_world: Matrix4x4,
+    world_to_camera: Matrix4x4,
+    raster_size: BasicDimensions2<u32>,
+    orthographic: bool,
+    panorama: Option<Panorama>,
+
+    // Thin lens, in camera space.  Cameras with no lens radius are pinholes.
+    lens_radius: f32,
+    focal_distance: f32,
+
+    // Times the shutter opens and closes, which rays are spread between for motion blur.
+    shutter_open: f32,
+    shutter_close: f32,
 }
 
 impl Camera {
-    // TODO: #9 Add camera_to_world transform (be sure to normalize rays!)
+    /// Creates a camera at the world origin, looking down Z+.
     pub fn new(film: &Film, projection: &Projection) -> Camera {
+        Camera::new_with_transform(film, projection, Matrix4x4::identity())
+    }
+
+    /// Creates a camera placed into the world.
+    ///
+    /// # Arguments
+    /// * `camera_to_world` - transform from camera space, where the camera is at the origin
+    ///   looking down Z+, to world space.  See `Matrix4x4::look_at`.
+    pub fn new_with_transform(
+        film: &Film,
+        projection: &Projection,
+        camera_to_world: Matrix4x4,
+    ) -> Camera {
         let raster_to_camera = projection.screen_to_camera() * film.raster_to_screen();
         let camera_to_raster = raster_to_camera.inverse().unwrap();
 
         Camera {
             raster_to_camera: raster_to_camera,
             camera_to_raster: camera_to_raster,
+            camera_to_world: camera_to_world,
+            world_to_camera: camera_to_world.affine_or_full_inverse().expect(
+                "Uninvertible transform used for a camera.",
+            ),
+            raster_size: BasicDimensions2::new(film.width(), film.height()),
+            orthographic: projection.is_orthographic(),
+            panorama: projection.panorama(),
+            lens_radius: 0.0,
+            focal_distance: 1.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
         }
     }
 
+    /// Gives the camera a lens of some size, so only things at the focal distance are sharp and
+    /// the rest blur, like depth of field of a real camera.
+    ///
+    /// Panoramic cameras keep everything in focus.
+    ///
+    /// # Arguments
+    /// * `aperture_radius` - radius of the lens, or 0 for a pinhole camera with everything in
+    ///   focus
+    /// * `focal_distance` - distance along the view direction which is in focus
+    ///
+    /// # Panics
+    /// If the aperture radius is negative, or the focal distance is not positive.
+    pub fn set_thin_lens(&mut self, aperture_radius: f32, focal_distance: f32) {
+        assert!(aperture_radius >= 0.0, "Aperture radius must not be negative.");
+        assert!(focal_distance > 0.0, "Focal distance must be positive.");
+        self.lens_radius = aperture_radius;
+        self.focal_distance = focal_distance;
+    }
+
+    /// Keeps the shutter open for an interval, so entities moving during it blur.  Rays are traced
+    /// at the time the shutter opens, unless given another with `shutter_time`.
+    ///
+    /// # Panics
+    /// If the shutter closes before it opens.
+    pub fn set_shutter(&mut self, open: f32, close: f32) {
+        assert!(open <= close, "The shutter cannot close before it opens.");
+        self.shutter_open = open;
+        self.shutter_close = close;
+    }
+
+    /// Whether the shutter is open for an interval, so rays through a pixel must also be spread
+    /// over it.
+    pub fn has_motion_blur(&self) -> bool {
+        self.shutter_open < self.shutter_close
+    }
+
+    /// Maps a position in the unit interval onto the time the shutter is open.
+    pub fn shutter_time(&self, u: f32) -> f32 {
+        self.shutter_open + u * (self.shutter_close - self.shutter_open)
+    }
+
+    /// Whether the camera has a lens, so rays through a pixel must also be spread over the lens.
+    pub fn has_lens(&self) -> bool {
+        self.lens_radius > 0.0 && self.panorama.is_none()
+    }
+
+    /// How the film wraps around the camera, if its projection is panoramic.  The raster and
+    /// camera space transforms of panoramic cameras do not map between them.
+    pub fn panorama(&self) -> Option<Panorama> {
+        self.panorama
+    }
+
+    /// Creates a perspective camera looking along `direction`, just far enough away to see all of
+    /// the given bounds.
+    ///
+    /// # Arguments
+    /// * `film` - film to render to
+    /// * `bounds` - region which must be in view, see `Scene::bounds`
+    /// * `fov` - field of view along the smaller dimension of the film
+    /// * `direction` - direction the camera looks in
+    pub fn frame_scene(film: &Film, bounds: &AABB, fov: PlanarAngle, direction: &Vector) -> Camera {
+        assert!(bounds.is_bounded(), "Cannot frame infinite bounds.");
+        let mut forward = *direction;
+        forward.normalize().expect(
+            "Cannot frame a scene without a view direction.",
+        );
+
+        // Fit the bounding sphere of the region between the sides of the view frustum.
+        let center = bounds.centroid();
+        let radius = (0.5 * bounds.lower.distance_to(bounds.upper)).max(1e-3);
+        let half_fov = fov.to_radians() / 2.0;
+        let distance = radius / half_fov.sin();
+        let eye = center + -distance * forward;
+
+        let up = if forward.cross(&Vector::new(0.0, 1.0, 0.0)).length() > 1e-3 {
+            Vector::new(0.0, 1.0, 0.0)
+        } else {
+            Vector::new(0.0, 0.0, 1.0)
+        };
+        let camera_to_world = Matrix4x4::look_at(&eye, &center, &up).unwrap();
+
+        let near = (distance - radius).max(1e-3 * distance);
+        let far = distance + radius;
+        let projection = Perspective::new(near, far, PlanarAngle::Radians(2.0 * half_fov));
+        Camera::new_with_transform(film, &projection, camera_to_world)
+    }
+
     pub fn raster_to_camera(&self) -> Matrix4x4 {
         self.raster_to_camera
     }
@@ -142,6 +442,65 @@ impl Camera {
         self.camera_to_raster
     }
 
+    pub fn camera_to_world(&self) -> Matrix4x4 {
+        self.camera_to_world
+    }
+
+    pub fn world_to_camera(&self) -> Matrix4x4 {
+        self.world_to_camera
+    }
+
+    /// Where a point in the world appears on the raster, in pixels from the top left corner.
+    ///
+    /// # Returns
+    /// `None` for points behind the camera, and for panoramic cameras, which do not project
+    /// points through a matrix.
+    pub fn world_to_raster(&self, point: &Point) -> Option<(f32, f32)> {
+        if self.panorama.is_some() {
+            return None;
+        }
+        let in_camera = self.world_to_camera * *point;
+        if in_camera.z <= 0.0 {
+            return None;
+        }
+        let raster = self.camera_to_raster * in_camera;
+        Some((raster.x, raster.y))
+    }
+
+    /// Where the camera is in the world.
+    pub fn position(&self) -> Point {
+        self.camera_to_world * Point::new(0.0, 0.0, 0.0)
+    }
+
+    /// Unit vector along the center of the view, in the world.
+    pub fn view_direction(&self) -> Vector {
+        let mut forward = self.camera_to_world * Vector::new(0.0, 0.0, 1.0);
+        forward.normalize().expect(
+            "Camera transform must not collapse the view direction.",
+        );
+        forward
+    }
+
+    /// Short name of the camera's projection.
+    fn projection_name(&self) -> &'static str {
+        match self.panorama {
+            Some(Panorama::Fisheye { .. }) => "fisheye",
+            Some(Panorama::Equirectangular) => "equirectangular",
+            None if self.orthographic => "orthographic",
+            None => "perspective",
+        }
+    }
+
+    /// Width of the film this camera was created with, in pixels.
+    pub fn raster_width(&self) -> u32 {
+        self.raster_size.width()
+    }
+
+    /// Height of the film this camera was created with, in pixels.
+    pub fn raster_height(&self) -> u32 {
+        self.raster_size.height()
+    }
+
     /// Generates a ray for use in ray tracing.
     ///
     /// # Arguments
@@ -150,16 +509,343 @@ impl Camera {
     ///
     /// # Returns
     /// A ray going through (x, y) on the raster.
+    ///
+    /// # Panics
+    /// If (x, y) lies outside of the raster, which extends from (0, 0) to (width, height).
     pub fn generate_ray(&self, x: u32, y: u32) -> Ray {
-        let origin = Point::new(0.0, 0.0, 0.0);
-        let image_plane_pos = self.raster_to_camera * Point::new(x as f32, y as f32, 0.0);
-        let direction = image_plane_pos - origin;
+        self.generate_ray_sample(x as f32, y as f32)
+    }
+
+    /// Generates a ray through any position on the raster, such as a sample within a pixel.
+    ///
+    /// # Arguments
+    /// * `x` - x coordinate on the raster to trace
+    /// # `y` - y coordinate on the raster to trace
+    ///
+    /// # Returns
+    /// A ray going through (x, y) on the raster.
+    ///
+    /// # Panics
+    /// If (x, y) lies outside of the raster, which extends from (0, 0) to (width, height).
+    pub fn generate_ray_sample(&self, x: f32, y: f32) -> Ray {
+        self.generate_ray_through_lens(x, y, (0.5, 0.5))
+    }
+
+    /// Generates a ray through a position on the raster and a position on the lens, see
+    /// `set_thin_lens`.
+    ///
+    /// # Arguments
+    /// * `x` - x coordinate on the raster to trace
+    /// # `y` - y coordinate on the raster to trace
+    /// * `lens` - position in the unit square, mapped onto the lens, where (0.5, 0.5) is the
+    ///   center
+    ///
+    /// # Panics
+    /// If (x, y) lies outside of the raster, which extends from (0, 0) to (width, height).
+    pub fn generate_ray_through_lens(&self, x: f32, y: f32, lens: (f32, f32)) -> Ray {
+        assert!(
+            x >= 0.0 && y >= 0.0 && x <= self.raster_size.width() as f32 &&
+                y <= self.raster_size.height() as f32,
+            "Raster position ({}, {}) is outside of the {}x{} film.",
+            x,
+            y,
+            self.raster_size.width(),
+            self.raster_size.height()
+        );
+
+        if let Some(panorama) = self.panorama {
+            return self.generate_panoramic_ray(panorama, x, y);
+        }
+
+        // Raster positions are on the near plane.
+        let image_plane_pos = self.raster_to_camera * Point::new(x, y, 0.0);
+        let (mut origin, mut direction) = if self.orthographic {
+            (image_plane_pos, Vector::new(0.0, 0.0, 1.0))
+        } else {
+            let eye = Point::new(0.0, 0.0, 0.0);
+            (eye, image_plane_pos - eye)
+        };
+
+        // Rays through every point of the lens meet where the ray through its center crosses the
+        // plane of focus.
+        if self.lens_radius > 0.0 {
+            let focus = origin + ((self.focal_distance - origin.z) / direction.z) * direction;
+            let (u, v) = sample_disk(lens.0, lens.1);
+            origin = origin + Vector::new(self.lens_radius * u, self.lens_radius * v, 0.0);
+            direction = focus - origin;
+        }
 
-        let mut ray = Ray {
-            origin: origin,
-            direction: direction,
+        // Also normalizes the ray.
+        self.camera_to_world *
+            Ray {
+                origin: origin,
+                direction: direction,
+                time: self.shutter_open,
+            }
+    }
+
+    /// Generates a ray from the eye in the direction a panoramic film maps a raster position to.
+    fn generate_panoramic_ray(&self, panorama: Panorama, x: f32, y: f32) -> Ray {
+        let direction = match panorama {
+            Panorama::Fisheye { half_fov } => {
+                let screen = self.raster_to_camera * Point::new(x, y, 0.0);
+                let radius = (screen.x * screen.x + screen.y * screen.y).sqrt();
+                let theta = (radius * half_fov).min(PI);
+                let (cos_phi, sin_phi) = if radius > 0.0 {
+                    (screen.x / radius, screen.y / radius)
+                } else {
+                    (1.0, 0.0)
+                };
+                Vector::new(
+                    theta.sin() * cos_phi,
+                    theta.sin() * sin_phi,
+                    theta.cos(),
+                )
+            }
+            Panorama::Equirectangular => {
+                let longitude = (x / self.raster_size.width() as f32 - 0.5) * 2.0 * PI;
+                let latitude = (0.5 - y / self.raster_size.height() as f32) * PI;
+                Vector::new(
+                    latitude.cos() * longitude.sin(),
+                    latitude.sin(),
+                    latitude.cos() * longitude.cos(),
+                )
+            }
         };
-        ray.normalize().unwrap();
-        ray
+        self.camera_to_world *
+            Ray {
+                origin: Point::new(0.0, 0.0, 0.0),
+                direction: direction,
+                time: self.shutter_open,
+            }
+    }
+}
+
+impl fmt::Display for Camera {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}x{} {} camera at {} looking along {}",
+            self.raster_width(),
+            self.raster_height(),
+            self.projection_name(),
+            self.position(),
+            self.view_direction()
+        )?;
+        if self.has_lens() {
+            write!(
+                f,
+                ", lens radius {} focused at {}",
+                self.lens_radius,
+                self.focal_distance
+            )?;
+        }
+        if self.has_motion_blur() {
+            write!(f, ", shutter open from {} to {}", self.shutter_open, self.shutter_close)?;
+        }
+        Ok(())
+    }
+}
+
+/// Summarizes the camera by where it is and what it sees, rather than by its matrices.
+impl fmt::Debug for Camera {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Camera")
+            .field("width", &self.raster_width())
+            .field("height", &self.raster_height())
+            .field("projection", &self.projection_name())
+            .field("position", &self.position())
+            .field("view_direction", &self.view_direction())
+            .field("lens_radius", &self.lens_radius)
+            .field("focal_distance", &self.focal_distance)
+            .field("shutter", &(self.shutter_open, self.shutter_close))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Camera, Equirectangular, Film, FilmError, Fisheye, MAX_FILM_DIMENSION,
+                Orthographic, Perspective};
+    use math::{Matrix4x4, PlanarAngle, Point, Solid, Sphere, Vector};
+    use scene::dimensions::Dimensions2;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_frame_scene() {
+        let film = Film::new(100, 50);
+        let sphere = Sphere::new(Point::new(10.0, -5.0, 3.0), 2.0);
+        let bounds = sphere.bounding_box();
+        let camera = Camera::frame_scene(
+            &film,
+            &bounds,
+            PlanarAngle::Degrees(60.0),
+            &Vector::new(1.0, -1.0, 0.0),
+        );
+
+        // The center of the film looks at the center of the bounds.
+        let center_ray = camera.generate_ray(50, 25);
+        assert_relative_eq!(
+            center_ray.direction,
+            Vector::unit(1.0, -1.0, 0.0).unwrap(),
+            max_relative = 1e-3
+        );
+        assert!(sphere.hits(&center_ray));
+
+        // Every corner sees past the bounds.
+        for &(x, y) in [(0, 0), (100, 0), (0, 50), (100, 50)].iter() {
+            let corner_ray = camera.generate_ray(x, y);
+            assert!(bounds.intersection_time(corner_ray).is_none());
+        }
+    }
+
+    #[test]
+    fn test_thin_lens() {
+        let film = Film::new(100, 50);
+        let mut camera =
+            Camera::new(&film, &Perspective::new(1.0, 100.0, PlanarAngle::Degrees(90.0)));
+        let pinhole = camera.generate_ray_sample(20.0, 30.0);
+        camera.set_thin_lens(0.5, 10.0);
+        assert!(camera.has_lens());
+
+        // Rays through every part of the lens meet at the focal distance.
+        let in_focus = pinhole.at(10.0 / pinhole.direction.z);
+        for &lens in [(0.5, 0.5), (0.0, 0.0), (1.0, 0.25), (0.3, 0.9)].iter() {
+            let ray = camera.generate_ray_through_lens(20.0, 30.0, lens);
+            assert_relative_eq!(ray.direction.length(), 1.0, epsilon = 1e-5);
+            assert_relative_eq!(
+                ray.at((10.0 - ray.origin.z) / ray.direction.z),
+                in_focus,
+                epsilon = 1e-4
+            );
+        }
+        let edge = camera.generate_ray_through_lens(20.0, 30.0, (1.0, 0.5));
+        assert_relative_eq!(edge.origin, Point::new(0.5, 0.0, 0.0), epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_shutter() {
+        let film = Film::new(100, 50);
+        let mut camera =
+            Camera::new(&film, &Perspective::new(1.0, 100.0, PlanarAngle::Degrees(90.0)));
+        assert!(!camera.has_motion_blur());
+        assert_eq!(camera.generate_ray(20, 30).time, 0.0);
+
+        camera.set_shutter(0.5, 1.5);
+        assert!(camera.has_motion_blur());
+        assert_eq!(camera.generate_ray(20, 30).time, 0.5);
+        assert_relative_eq!(camera.shutter_time(0.25), 0.75);
+    }
+
+    #[test]
+    fn test_generate_ray_sample() {
+        let film = Film::new(100, 50);
+        let camera = Camera::new(&film, &Perspective::new(1.0, 10.0, PlanarAngle::Degrees(90.0)));
+        assert_relative_eq!(
+            camera.generate_ray_sample(20.0, 30.0).direction,
+            camera.generate_ray(20, 30).direction
+        );
+
+        // Samples on either side of the center of the film mirror each other.
+        let left = camera.generate_ray_sample(49.5, 25.0).direction;
+        let right = camera.generate_ray_sample(50.5, 25.0).direction;
+        assert!(left.x < 0.0);
+        assert_relative_eq!(left, Vector::new(-right.x, right.y, right.z), epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_orthographic_rays_are_parallel() {
+        let film = Film::new(100, 50);
+        let camera = Camera::new(&film, &Orthographic::new(1.0, 10.0, 2.0));
+        for &(x, y) in [(0, 0), (50, 25), (100, 50)].iter() {
+            assert_relative_eq!(camera.generate_ray(x, y).direction, Vector::new(0.0, 0.0, 1.0));
+        }
+
+        // The shorter side of the film spans the half extent in each direction.
+        let corner = camera.generate_ray(0, 0).origin;
+        assert_relative_eq!(corner, Point::new(-4.0, 2.0, 1.0), epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_fisheye() {
+        let film = Film::new(100, 50);
+        let camera = Camera::new(&film, &Fisheye::new(PlanarAngle::Degrees(180.0)));
+        assert_relative_eq!(
+            camera.generate_ray(50, 25).direction,
+            Vector::new(0.0, 0.0, 1.0),
+            epsilon = 1e-5
+        );
+
+        // The edges of the shorter side look sideways, at half the field of view.
+        assert_relative_eq!(
+            camera.generate_ray(50, 0).direction,
+            Vector::new(0.0, 1.0, 0.0),
+            epsilon = 1e-5
+        );
+
+        // Past them, rays look behind the camera.
+        let wide = camera.generate_ray(15, 25).direction;
+        let theta = 1.4 * PI / 2.0;
+        assert_relative_eq!(wide, Vector::new(-theta.sin(), 0.0, theta.cos()), epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_equirectangular() {
+        let film = Film::new(200, 100);
+        let camera = Camera::new(&film, &Equirectangular::new());
+        let cases = [
+            ((100, 50), Vector::new(0.0, 0.0, 1.0)),
+            ((150, 50), Vector::new(1.0, 0.0, 0.0)),
+            ((50, 50), Vector::new(-1.0, 0.0, 0.0)),
+            ((0, 50), Vector::new(0.0, 0.0, -1.0)),
+            ((100, 0), Vector::new(0.0, 1.0, 0.0)),
+            ((100, 100), Vector::new(0.0, -1.0, 0.0)),
+        ];
+        for &((x, y), expected) in cases.iter() {
+            let ray = camera.generate_ray(x, y);
+            assert_relative_eq!(ray.origin, Point::new(0.0, 0.0, 0.0));
+            assert_relative_eq!(ray.direction, expected, epsilon = 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_film_dimensions() {
+        let film = Film::new(3840, 2160);
+        assert_eq!(film.width(), 3840);
+        assert_eq!(film.height(), 2160);
+
+        let poster = Film::new(MAX_FILM_DIMENSION, MAX_FILM_DIMENSION);
+        assert_eq!(poster.width(), MAX_FILM_DIMENSION);
+    }
+
+    #[test]
+    fn test_invalid_film_dimensions() {
+        assert_eq!(Film::try_new(0, 600).err(), Some(FilmError::Empty));
+        assert_eq!(Film::try_new(800, 0).err(), Some(FilmError::Empty));
+        assert_eq!(
+            Film::try_new(MAX_FILM_DIMENSION + 1, 600).err(),
+            Some(FilmError::TooLarge)
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let eye = Point::new(0.0, 5.0, -10.0);
+        let to_world =
+            Matrix4x4::look_at(&eye, &Point::new(0.0, 5.0, 0.0), &Vector::new(0.0, 1.0, 0.0))
+                .unwrap();
+        let projection = Perspective::new(1.0, 100.0, PlanarAngle::Degrees(60.0));
+        let mut camera = Camera::new_with_transform(&Film::new(80, 60), &projection, to_world);
+        assert_relative_eq!(camera.position(), eye, epsilon = 1e-5);
+        assert_relative_eq!(camera.view_direction(), Vector::new(0.0, 0.0, 1.0), epsilon = 1e-5);
+
+        let summary = format!("{}", camera);
+        assert!(summary.starts_with("80x60 perspective camera at"));
+        assert!(!summary.contains("lens"));
+        camera.set_thin_lens(0.5, 10.0);
+        assert!(format!("{}", camera).contains("lens radius 0.5 focused at 10"));
+
+        let orthographic = Camera::new(&Film::new(10, 10), &Orthographic::new(1.0, 100.0, 5.0));
+        assert!(format!("{:?}", orthographic).contains("\"orthographic\""));
     }
 }
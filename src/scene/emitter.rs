@@ -0,0 +1,146 @@
+//! Glowing meshes lighting the scene like area lights, by choosing points on their triangles.
+//!
+//! Sampling the triangles themselves rather than the sphere bounding the mesh keeps thin shapes,
+//! such as neon signs, from wasting nearly every sample on directions which miss them.
+use math::{Point, Transform, Vector};
+use scene::environment::{cumulative, sample_cdf};
+
+/// A point chosen on a glowing mesh.
+#[derive(Clone, Copy, Debug)]
+pub struct TriangleSample {
+    pub point: Point,
+    /// Unit normal of the triangle, facing the side its vertices wind counterclockwise around like
+    /// those of hits on the mesh.
+    pub normal: Vector,
+    /// Probability density of choosing the point, per unit of area.
+    pub pdf: f32,
+}
+
+/// The triangles of a glowing mesh, each chosen in proportion to its area.
+pub struct EmissiveTriangles {
+    /// Corners of each triangle, in the coordinates of the mesh.
+    triangles: Vec<[Point; 3]>,
+    cdf: Vec<f32>,
+}
+
+impl EmissiveTriangles {
+    /// # Arguments
+    /// * `triangles` - corners of each triangle, in the coordinates of the mesh
+    /// * `transform` - places the mesh in the scene, to weigh triangles by the area they cover
+    ///
+    /// # Returns
+    /// `None` if there is no area to sample.
+    pub fn new(triangles: Vec<[Point; 3]>, transform: &Transform) -> Option<EmissiveTriangles> {
+        let (cdf, total) = cumulative(triangles.iter().map(|t| area(&to_world(t, transform))));
+        if !(total > 0.0) {
+            return None;
+        }
+        Some(EmissiveTriangles {
+            triangles: triangles,
+            cdf: cdf,
+        })
+    }
+
+    /// Chooses a point on one of the triangles.
+    ///
+    /// # Arguments
+    /// * `transform` - places the mesh in the scene at the time of the sample
+    /// * `choice` - uniform random number in [0, 1) choosing the triangle
+    /// * `u` - position in the unit square, which is mapped onto the triangle
+    ///
+    /// # Returns
+    /// `None` if the chosen triangle has no area where the mesh is at the time.
+    pub fn sample(
+        &self,
+        transform: &Transform,
+        choice: f32,
+        u: (f32, f32),
+    ) -> Option<TriangleSample> {
+        let (index, _) = sample_cdf(&self.cdf, choice);
+        let start = if index == 0 { 0.0 } else { self.cdf[index - 1] };
+        let corners = to_world(&self.triangles[index], transform);
+        let area = area(&corners);
+        if !(area > 0.0) {
+            return None;
+        }
+
+        // Taking the square root spreads points evenly rather than bunching them at the first
+        // corner.
+        let [a, b, c] = corners;
+        let s = u.0.sqrt();
+        let point = a + (s * (1.0 - u.1)) * (b - a) + (s * u.1) * (c - a);
+        let [la, lb, lc] = self.triangles[index];
+        let mut normal = transform.to_local().transpose() * (lb - la).cross(&(lc - la));
+        normal.normalize().ok()?;
+        Some(TriangleSample {
+            point: point,
+            normal: normal,
+            pdf: (self.cdf[index] - start) / area,
+        })
+    }
+}
+
+fn to_world(triangle: &[Point; 3], transform: &Transform) -> [Point; 3] {
+    let to_world = transform.to_world();
+    [
+        *to_world * triangle[0],
+        *to_world * triangle[1],
+        *to_world * triangle[2],
+    ]
+}
+
+fn area(triangle: &[Point; 3]) -> f32 {
+    0.5 * (triangle[1] - triangle[0])
+        .cross(&(triangle[2] - triangle[0]))
+        .length()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EmissiveTriangles;
+    use math::{Point, Transform, Vector};
+    use rand::{Rng, XorShiftRng};
+
+    #[test]
+    fn test_triangles_chosen_by_area() {
+        // Triangles of areas 2 and 8 once scaled.
+        let triangles = vec![
+            [
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+                Point::new(0.0, 1.0, 0.0),
+            ],
+            [
+                Point::new(5.0, 0.0, 0.0),
+                Point::new(7.0, 0.0, 0.0),
+                Point::new(5.0, 2.0, 0.0),
+            ],
+        ];
+        let transform = Transform::scale(2.0, 2.0, 1.0);
+        let emitter = EmissiveTriangles::new(triangles, &transform).unwrap();
+        let mut rng = XorShiftRng::new_unseeded();
+        let samples = 4000;
+        let mut first = 0;
+        for _ in 0..samples {
+            let sample = emitter
+                .sample(&transform, rng.gen(), (rng.gen(), rng.gen()))
+                .unwrap();
+            if sample.point.x < 5.0 {
+                first += 1;
+                assert!(sample.point.x + sample.point.y <= 2.0 + 1e-5);
+            }
+            assert_relative_eq!(sample.normal, Vector::new(0.0, 0.0, 1.0));
+            // Points are spread evenly over all 10 units of area.
+            assert_relative_eq!(sample.pdf, 0.1, max_relative = 1e-5);
+        }
+        assert_relative_eq!(first as f32 / samples as f32, 0.2, epsilon = 0.03);
+    }
+
+    #[test]
+    fn test_no_area() {
+        let point = Point::new(1.0, 2.0, 3.0);
+        let identity = Transform::identity();
+        assert!(EmissiveTriangles::new(vec![[point, point, point]], &identity).is_none());
+        assert!(EmissiveTriangles::new(Vec::new(), &identity).is_none());
+    }
+}
@@ -0,0 +1,163 @@
+//! Checks that materials do not reflect more light than they receive, which brightens every
+//! bounce and keeps paths from converging.
+//!
+//! The reflectance of a material seen from a direction, its directional albedo, is the BRDF
+//! integrated against the cosine of the light over the hemisphere above the surface.  Values
+//! above 1 create energy.
+use math::{Vector, perpendicular_tangents};
+use scene::{EntityHandle, Scene, Spectrum};
+use scene::material::Material;
+use std::f32::consts::PI;
+
+/// Divisions of the hemisphere from the normal to the horizon.
+const ELEVATION_STEPS: u32 = 128;
+
+/// Divisions of the hemisphere around the normal.
+const AZIMUTH_STEPS: u32 = 256;
+
+/// Cosines of the angles from the normal each material is viewed at, from head on to grazing.
+const VIEW_COSINES: [f32; 4] = [1.0, 0.7, 0.4, 0.1];
+
+/// How much more light than received may be reflected before warning, for the error of the
+/// numerical integration.  Narrow highlights of very smooth surfaces are integrated coarsely.
+pub const ALBEDO_TOLERANCE: f32 = 0.02;
+
+/// The most light a material reflects, where it reflects more than it receives.
+#[derive(Clone, Copy, Debug)]
+pub struct ExcessReflection {
+    /// Cosine of the angle from the normal of the view where the most light is reflected.
+    pub view_cosine: f32,
+    /// Fraction of the light received which is reflected toward that view.
+    pub albedo: Spectrum,
+}
+
+/// Integrates the BRDF of a material over the hemisphere, for light reflected toward `view`.
+///
+/// Only light reflected from lights is included, not rays the material scatters.
+///
+/// # Arguments
+/// * `view` - unit vector pointing to the viewer
+/// * `normal` - unit vector perpendicular to the surface
+pub fn directional_albedo(material: &Material, view: &Vector, normal: &Vector) -> Spectrum {
+    let (tangent, bitangent) = perpendicular_tangents(normal);
+    let mut total = Spectrum::black();
+    // Directions are spaced by projected solid angle, so the cosine term is part of the spacing
+    // and the albedo is the average of the π-scaled BRDF.
+    for i in 0..ELEVATION_STEPS {
+        let sin_squared = (i as f32 + 0.5) / ELEVATION_STEPS as f32;
+        let sin_theta = sin_squared.sqrt();
+        let cos_theta = (1.0 - sin_squared).sqrt();
+        for j in 0..AZIMUTH_STEPS {
+            let phi = 2.0 * PI * (j as f32 + 0.5) / AZIMUTH_STEPS as f32;
+            let light = (sin_theta * phi.cos()) * tangent + (sin_theta * phi.sin()) * bitangent +
+                cos_theta * *normal;
+            total = total + material.f(&light, view, normal);
+        }
+    }
+    (1.0 / (ELEVATION_STEPS * AZIMUTH_STEPS) as f32) * total
+}
+
+/// Views a material from several angles, looking for any where it reflects more than 1 plus
+/// `ALBEDO_TOLERANCE` of the light it receives in any color.
+///
+/// # Returns
+/// The view reflecting the most light, or `None` if energy is conserved.
+pub fn audit_material(material: &Material) -> Option<ExcessReflection> {
+    let normal = Vector::new(0.0, 1.0, 0.0);
+    let brightest = |albedo: &Spectrum| albedo.max_channel();
+    VIEW_COSINES
+        .iter()
+        .map(|&view_cosine| {
+            let sine = (1.0 - view_cosine * view_cosine).sqrt();
+            let view = Vector::new(sine, view_cosine, 0.0);
+            ExcessReflection {
+                view_cosine: view_cosine,
+                albedo: directional_albedo(material, &view, &normal),
+            }
+        })
+        .filter(|excess| brightest(&excess.albedo) > 1.0 + ALBEDO_TOLERANCE)
+        .fold(None, |worst: Option<ExcessReflection>, excess| match worst {
+            Some(w) if brightest(&w.albedo) >= brightest(&excess.albedo) => Some(w),
+            _ => Some(excess),
+        })
+}
+
+impl Scene {
+    /// Finds entities whose materials reflect more light than they receive, as they would be
+    /// shaded, including any material override.
+    ///
+    /// Materials which vary across their surface are checked where their texture coordinates are
+    /// zero.
+    pub fn audit_energy(&self) -> Vec<(EntityHandle, ExcessReflection)> {
+        self.entities
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entity)| {
+                audit_material(self.material_of(entity)).map(|e| (EntityHandle(index), e))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{audit_material, directional_albedo};
+    use math::{PlanarAngle, Sphere, Transform, Vector};
+    use scene::{EntityHandle, Scene, Spectrum};
+    use scene::material::{BlinnPhongMaterial, LambertianMaterial, MicrofacetMaterial,
+                          OrenNayarMaterial};
+
+    #[test]
+    fn test_lambertian_albedo() {
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let view = Vector::unit(1.0, 1.0, 0.0).unwrap();
+        let white = LambertianMaterial::new(&Spectrum::uniform(1.0));
+        assert_relative_eq!(
+            directional_albedo(&white, &view, &normal),
+            Spectrum::uniform(1.0),
+            epsilon = 1e-3
+        );
+        assert!(audit_material(&white).is_none());
+
+        let glowing = LambertianMaterial::new(&Spectrum::new(0.5, 1.5, 0.5));
+        let excess = audit_material(&glowing).unwrap();
+        assert_relative_eq!(excess.albedo.g, 1.5, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn test_materials_conserve_energy() {
+        let white = Spectrum::uniform(1.0);
+        let rough = OrenNayarMaterial::new(&white, PlanarAngle::Degrees(30.0));
+        assert!(audit_material(&rough).is_none());
+        let half = Spectrum::uniform(0.5);
+        for &shininess in [1.0, 20.0, 200.0].iter() {
+            assert!(audit_material(&BlinnPhongMaterial::new(&half, &half, shininess)).is_none());
+        }
+        for &roughness in [0.3, 0.6, 1.0].iter() {
+            for &metalness in [0.0, 1.0].iter() {
+                let material = MicrofacetMaterial::new(&white, roughness, metalness);
+                assert!(audit_material(&material).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn test_audit_scene() {
+        let mut scene = Scene::new();
+        for &diffuse in [0.8, 2.0].iter() {
+            scene.add_entity(
+                Box::new(Sphere::new_with_radius(1.0)),
+                Box::new(LambertianMaterial::new(&Spectrum::uniform(diffuse))),
+                Transform::identity(),
+            );
+        }
+        let audit = scene.audit_energy();
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].0, EntityHandle(1));
+
+        scene.set_material_override(Some(
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(0.5))),
+        ));
+        assert!(scene.audit_energy().is_empty());
+    }
+}
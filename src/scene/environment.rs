@@ -0,0 +1,488 @@
+//! Light arriving from infinitely far away in every direction, such as the sky.
+use image;
+use image::hdr::HDRDecoder;
+use math::{Point, Vector};
+use output::{Precision, RadianceBuffer};
+use scene::Spectrum;
+use scene::area_light::{AreaLight, RectangleLight};
+use std::f32::consts::PI;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Radiance surrounding the scene, looked up from an equirectangular image.
+///
+/// The top row of the image is straight up along Y+, the bottom row straight down, and the
+/// center of the image looks along X+.  Rays which miss every entity see the environment, and
+/// surfaces are lit by it through importance sampling of its brightest directions.
+pub struct EnvironmentLight {
+    width: usize,
+    height: usize,
+    pixels: RadianceBuffer,
+
+    // Cumulative distributions for choosing a row, and then a column within the row, in
+    // proportion to the light arriving through each pixel.
+    row_cdf: Vec<f32>,
+    column_cdfs: Vec<Vec<f32>>,
+    total_weight: f32,
+}
+
+/// Summarizes the environment rather than listing its pixels.
+impl fmt::Debug for EnvironmentLight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EnvironmentLight")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("precision", &self.precision())
+            .field("total_weight", &self.total_weight)
+            .finish()
+    }
+}
+
+impl fmt::Display for EnvironmentLight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.width * self.height == 1 {
+            return write!(f, "constant environment, radiance {}", self.pixels.get(0));
+        }
+        let precision = match self.precision() {
+            Precision::Full => "full",
+            Precision::Half => "half",
+        };
+        write!(
+            f,
+            "{}x{} environment map at {} precision",
+            self.width,
+            self.height,
+            precision
+        )
+    }
+}
+
+/// A direction chosen toward the environment.
+pub struct EnvironmentSample {
+    /// Unit vector toward the environment.
+    pub direction: Vector,
+    pub radiance: Spectrum,
+    /// Probability density of choosing the direction, with respect to solid angle.
+    pub pdf: f32,
+}
+
+impl EnvironmentLight {
+    /// An environment of the same radiance in every direction.
+    pub fn constant(radiance: &Spectrum) -> EnvironmentLight {
+        EnvironmentLight::from_pixels(1, 1, vec![*radiance])
+    }
+
+    /// Creates an environment from the pixels of an equirectangular image, in row-major order
+    /// from the top left corner.
+    ///
+    /// # Panics
+    /// If there is not exactly one pixel for each position in the image.
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Spectrum>) -> EnvironmentLight {
+        EnvironmentLight::from_buffer(width, height, RadianceBuffer::from(pixels))
+    }
+
+    /// Creates an environment from the pixels of an equirectangular image stored at any
+    /// precision, in row-major order from the top left corner.
+    ///
+    /// # Panics
+    /// If there is not exactly one pixel for each position in the image.
+    pub fn from_buffer(width: usize, height: usize, pixels: RadianceBuffer) -> EnvironmentLight {
+        assert!(width > 0 && height > 0, "An environment needs at least one pixel.");
+        assert_eq!(width * height, pixels.len());
+
+        // Rows near the poles cover less solid angle.
+        let mut column_cdfs = Vec::with_capacity(height);
+        let mut row_weights = Vec::with_capacity(height);
+        for row in 0..height {
+            let sin_theta = (PI * (row as f32 + 0.5) / height as f32).sin();
+            let weights = (row * width..(row + 1) * width).map(|i| {
+                pixels.get(i).luminance().max(0.0) * sin_theta
+            });
+            let (cdf, total) = cumulative(weights);
+            column_cdfs.push(cdf);
+            row_weights.push(total);
+        }
+        let (row_cdf, total_weight) = cumulative(row_weights.into_iter());
+
+        EnvironmentLight {
+            width: width,
+            height: height,
+            pixels: pixels,
+            row_cdf: row_cdf,
+            column_cdfs: column_cdfs,
+            total_weight: total_weight,
+        }
+    }
+
+    /// Precision the pixels of the environment are stored at.
+    pub fn precision(&self) -> Precision {
+        self.pixels.precision()
+    }
+
+    /// Stores the pixels of the environment at another precision.  Sampling is unchanged, other
+    /// than by any precision lost.
+    pub fn set_precision(&mut self, precision: Precision) {
+        if self.pixels.precision() == precision {
+            return;
+        }
+        let pixels = ::std::mem::replace(&mut self.pixels, RadianceBuffer::Full(Vec::new()));
+        *self = EnvironmentLight::from_buffer(
+            self.width,
+            self.height,
+            pixels.with_precision(precision),
+        );
+    }
+
+    /// Loads an environment from a Radiance HDR image.
+    pub fn load<P: AsRef<Path>>(path: P) -> image::ImageResult<EnvironmentLight> {
+        let decoder = HDRDecoder::new(BufReader::new(File::open(path)?))?;
+        let metadata = decoder.metadata();
+        let pixels = decoder
+            .read_image_hdr()?
+            .iter()
+            .map(|p| Spectrum::new(p.data[0], p.data[1], p.data[2]))
+            .collect();
+        Ok(EnvironmentLight::from_pixels(
+            metadata.width as usize,
+            metadata.height as usize,
+            pixels,
+        ))
+    }
+
+    /// Radiance arriving from the environment along the opposite of a unit vector.
+    pub fn radiance(&self, direction: &Vector) -> Spectrum {
+        let (column, row) = self.pixel(direction);
+        self.pixels.get(row * self.width + column)
+    }
+
+    /// Chooses a direction toward the environment, favoring brighter directions.
+    ///
+    /// # Arguments
+    /// * `u1`, `u2` - independent uniform random numbers in [0, 1)
+    ///
+    /// # Returns
+    /// `None` if the environment is black, so no light arrives from any direction.
+    pub fn sample(&self, u1: f32, u2: f32) -> Option<EnvironmentSample> {
+        if self.total_weight <= 0.0 {
+            return None;
+        }
+        let (row, v) = sample_cdf(&self.row_cdf, u1);
+        let (column, u) = sample_cdf(&self.column_cdfs[row], u2);
+
+        let phi = 2.0 * PI * (column as f32 + u) / self.width as f32;
+        let theta = PI * (row as f32 + v) / self.height as f32;
+        let sin_theta = theta.sin();
+        if sin_theta <= 0.0 {
+            return None;
+        }
+        let direction = Vector::new(-phi.cos() * sin_theta, theta.cos(), -phi.sin() * sin_theta);
+        Some(EnvironmentSample {
+            direction: direction,
+            radiance: self.pixels.get(row * self.width + column),
+            pdf: self.pixel_pdf(column, row, sin_theta),
+        })
+    }
+
+    /// Probability density of `sample` choosing a unit vector, with respect to solid angle.
+    pub fn pdf(&self, direction: &Vector) -> f32 {
+        if self.total_weight <= 0.0 {
+            return 0.0;
+        }
+        let sin_theta = (1.0 - direction.y * direction.y).max(0.0).sqrt();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let (column, row) = self.pixel(direction);
+        self.pixel_pdf(column, row, sin_theta)
+    }
+
+    /// Probability density with respect to solid angle of choosing a direction within a pixel,
+    /// given the sine of the angle between the direction and Y+.
+    fn pixel_pdf(&self, column: usize, row: usize, sin_theta: f32) -> f32 {
+        let row_sin_theta = (PI * (row as f32 + 0.5) / self.height as f32).sin();
+        let radiance = self.pixels.get(row * self.width + column);
+        let weight = radiance.luminance().max(0.0) * row_sin_theta;
+
+        // Each pixel covers 2π²/(width * height) in longitude and latitude, and sin(θ) of that
+        // in solid angle.
+        let pixel_area = 2.0 * PI * PI / (self.width * self.height) as f32;
+        weight / (self.total_weight * pixel_area * sin_theta)
+    }
+
+    /// The pixel seen along a unit vector.
+    fn pixel(&self, direction: &Vector) -> (usize, usize) {
+        let phi = (-direction.z).atan2(-direction.x);
+        let phi = if phi < 0.0 { phi + 2.0 * PI } else { phi };
+        let u = phi / (2.0 * PI);
+        let v = direction.y.max(-1.0).min(1.0).acos() / PI;
+        let column = ((u * self.width as f32) as usize).min(self.width - 1);
+        let row = ((v * self.height as f32) as usize).min(self.height - 1);
+        (column, row)
+    }
+}
+
+/// A window through which the environment lights an interior, such as a room lit only by the sky
+/// through its windows.
+///
+/// Most directions toward the environment from inside a room are blocked by its walls, so
+/// sampling the whole environment wastes nearly every sample.  Surfaces in scenes with portals
+/// are instead lit by choosing directions through the portals.  Light arriving other than
+/// through a portal is not sampled, so portals must cover every opening, and scenes seen from
+/// outside should not use them.
+pub struct Portal {
+    corner: Point,
+    edge_u: Vector,
+    edge_v: Vector,
+    /// The window, sampled like a rectangle light facing into the interior.
+    window: RectangleLight,
+}
+
+impl Portal {
+    /// # Arguments
+    /// * `corner` - one corner of the window
+    /// * `edge_u`, `edge_v` - the two edges leaving the corner, which must be perpendicular.
+    ///   Light enters toward the side `edge_u.cross(&edge_v)` points, into the interior.
+    ///
+    /// # Panics
+    /// If either edge has no length, or the edges are not perpendicular.
+    pub fn new(corner: Point, edge_u: Vector, edge_v: Vector) -> Portal {
+        Portal {
+            corner: corner,
+            edge_u: edge_u,
+            edge_v: edge_v,
+            window: RectangleLight::new(corner, edge_u, edge_v, &Spectrum::uniform(1.0)),
+        }
+    }
+
+    /// Chooses a direction from a point through the portal toward the environment, ignoring any
+    /// occluders.
+    ///
+    /// # Arguments
+    /// * `point` - point to illuminate through the portal
+    /// * `u` - position in the unit square, which is mapped onto the portal
+    ///
+    /// # Returns
+    /// `None` if the portal cannot be seen from the point, such as from outside.
+    pub fn sample(
+        &self,
+        environment: &EnvironmentLight,
+        point: &Point,
+        u: (f32, f32),
+    ) -> Option<EnvironmentSample> {
+        let sample = self.window.sample(point, u)?;
+        Some(EnvironmentSample {
+            direction: sample.direction,
+            radiance: environment.radiance(&sample.direction),
+            pdf: sample.pdf,
+        })
+    }
+}
+
+impl fmt::Display for Portal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "portal from {} along {} and {}",
+            self.corner,
+            self.edge_u,
+            self.edge_v
+        )
+    }
+}
+
+/// Running totals of weights, normalized to end at 1, and the total weight.
+pub fn cumulative<I: Iterator<Item = f32>>(weights: I) -> (Vec<f32>, f32) {
+    let mut total = 0.0;
+    let mut cdf: Vec<f32> = weights
+        .map(|w| {
+            total += w;
+            total
+        })
+        .collect();
+    if total > 0.0 {
+        for c in cdf.iter_mut() {
+            *c /= total;
+        }
+    }
+    (cdf, total)
+}
+
+/// Chooses an entry of a cumulative distribution.
+///
+/// # Returns
+/// The index chosen, and how far through its range `u` fell, in (0, 1) so that directions never
+/// fall exactly on a pole.
+pub fn sample_cdf(cdf: &[f32], u: f32) -> (usize, f32) {
+    let index = match cdf.iter().position(|&c| u < c) {
+        Some(index) => index,
+        None => cdf.len() - 1,
+    };
+    let start = if index == 0 { 0.0 } else { cdf[index - 1] };
+    let width = cdf[index] - start;
+    let offset = if width > 0.0 {
+        ((u - start) / width).max(0.0001).min(0.9999)
+    } else {
+        0.5
+    };
+    (index, offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EnvironmentLight, Portal};
+    use math::{Point, Vector};
+    use output::Precision;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use rand::{Rng, XorShiftRng};
+    use scene::Spectrum;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_lookup() {
+        // Bright sky above a dark ground.
+        let sky = Spectrum::new(1.0, 1.0, 2.0);
+        let ground = Spectrum::uniform(0.1);
+        let env = EnvironmentLight::from_pixels(2, 2, vec![sky, sky, ground, ground]);
+        assert_relative_eq!(env.radiance(&Vector::new(0.0, 1.0, 0.0)), sky);
+        assert_relative_eq!(env.radiance(&Vector::new(0.0, -1.0, 0.0)), ground);
+        assert_relative_eq!(env.radiance(&Vector::unit(1.0, 0.2, 0.3).unwrap()), sky);
+    }
+
+    #[test]
+    fn test_samples_match_lookup() {
+        let pixels = (0..32)
+            .map(|i| Spectrum::new(i as f32, 1.0, 0.5))
+            .collect();
+        let env = EnvironmentLight::from_pixels(8, 4, pixels);
+        let mut rng = XorShiftRng::new_unseeded();
+        for _ in 0..100 {
+            let sample = env.sample(rng.gen(), rng.gen()).unwrap();
+            assert!(sample.direction.is_normalized());
+            assert_relative_eq!(sample.radiance, env.radiance(&sample.direction));
+            assert!(sample.pdf > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_pdf_integrates_to_one() {
+        // Estimate the integral of the pdf over the sphere with uniformly chosen directions.
+        let pixels = (0..32)
+            .map(|i| Spectrum::new((i % 5) as f32, 1.0, 0.5))
+            .collect();
+        let env = EnvironmentLight::from_pixels(8, 4, pixels);
+        let mut rng = XorShiftRng::new_unseeded();
+        let count = 20000;
+        let mut total = 0.0;
+        for _ in 0..count {
+            let y = 2.0 * rng.gen::<f32>() - 1.0;
+            let phi = 2.0 * PI * rng.gen::<f32>();
+            let r = (1.0 - y * y).sqrt();
+            total += env.pdf(&Vector::new(r * phi.cos(), y, r * phi.sin()));
+        }
+        let integral = total * 4.0 * PI / count as f32;
+        assert_relative_eq!(integral, 1.0, epsilon = 0.05);
+    }
+
+    /// The width, height and luminance of each pixel of small environments, not all black.
+    fn environment_pixels() -> impl Strategy<Value = (usize, usize, Vec<f32>)> {
+        (1..8usize, 1..8usize)
+            .prop_flat_map(|(width, height)| {
+                (Just(width), Just(height), vec(0.0f32..10.0, width * height))
+            })
+            .prop_filter("black environment", |&(_, _, ref values)| {
+                values.iter().any(|&v| v > 0.0)
+            })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn prop_pdf_integrates_to_one((width, height, values) in environment_pixels()) {
+            let pixels = values.iter().map(|&v| Spectrum::uniform(v)).collect();
+            let env = EnvironmentLight::from_pixels(width, height, pixels);
+
+            // Sum over a grid of longitudes and angles from Y+ lining up with the pixels of
+            // every size of environment generated, so the sum is exact but for rounding.
+            let steps = 420;
+            let step = PI / steps as f32;
+            let mut total = 0.0f64;
+            for i in 0..steps {
+                let theta = (i as f32 + 0.5) * step;
+                for j in 0..steps {
+                    let phi = 2.0 * (j as f32 + 0.5) * step;
+                    let direction = Vector::new(
+                        phi.cos() * theta.sin(),
+                        theta.cos(),
+                        phi.sin() * theta.sin(),
+                    );
+                    total += (env.pdf(&direction) * theta.sin()) as f64;
+                }
+            }
+            let integral = total as f32 * step * 2.0 * step;
+            assert_relative_eq!(integral, 1.0, epsilon = 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_half_precision() {
+        let pixels: Vec<_> = (0..32)
+            .map(|i| Spectrum::new((i % 5) as f32, 1.0, 0.5))
+            .collect();
+        let full = EnvironmentLight::from_pixels(8, 4, pixels);
+        let mut half = EnvironmentLight::from_pixels(8, 4, full.pixels.iter().collect());
+        half.set_precision(Precision::Half);
+        assert_eq!(half.precision(), Precision::Half);
+        // Every pixel is exactly representable, so nothing changes.
+        let mut rng = XorShiftRng::new_unseeded();
+        for _ in 0..100 {
+            let (u1, u2) = (rng.gen(), rng.gen());
+            let (a, b) = (full.sample(u1, u2).unwrap(), half.sample(u1, u2).unwrap());
+            assert_relative_eq!(a.direction, b.direction);
+            assert_relative_eq!(a.radiance, b.radiance);
+            assert_relative_eq!(a.pdf, b.pdf);
+        }
+    }
+
+    #[test]
+    fn test_black_environment_is_not_sampled() {
+        let env = EnvironmentLight::constant(&Spectrum::black());
+        assert!(env.sample(0.5, 0.5).is_none());
+    }
+
+    #[test]
+    fn test_portal() {
+        // A 2 by 2 skylight in a ceiling 2 above the origin, facing down into the room.
+        let sky = Spectrum::new(1.0, 1.0, 2.0);
+        let ground = Spectrum::uniform(0.1);
+        let env = EnvironmentLight::from_pixels(2, 2, vec![sky, sky, ground, ground]);
+        let portal = Portal::new(
+            Point::new(-1.0, 2.0, -1.0),
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 2.0),
+        );
+        let below = Point::new(0.0, 0.0, 0.0);
+        let mut rng = XorShiftRng::new_unseeded();
+        let mut total_pdf = 0.0;
+        for _ in 0..100 {
+            let sample = portal.sample(&env, &below, (rng.gen(), rng.gen())).unwrap();
+            assert!(sample.direction.is_normalized());
+            let through = below + (2.0 / sample.direction.y) * sample.direction;
+            assert!(through.x.abs() <= 1.0 + 1e-4 && through.z.abs() <= 1.0 + 1e-4);
+            assert_relative_eq!(sample.radiance, sky);
+            total_pdf += sample.pdf;
+        }
+
+        // Directions are spread evenly over the solid angle of the skylight, which is 4 asin(1/5)
+        // for a square of side 2 at distance 2.
+        let solid_angle = 4.0 * (1.0f32 / 5.0).asin();
+        assert_relative_eq!(total_pdf / 100.0, 1.0 / solid_angle, epsilon = 1e-3);
+
+        // Points above the skylight are outside, and not lit through it.
+        assert!(portal.sample(&env, &Point::new(0.0, 3.0, 0.0), (0.5, 0.5)).is_none());
+        assert!(format!("{}", portal).starts_with("portal from"));
+    }
+}
@@ -0,0 +1,189 @@
+//! Lists of the lights reaching each region of a scene, so shading in scenes with many lights
+//! only considers those close enough to matter.
+use math::{AABB, Point, Vector, XYZ};
+use scene::nonarea_light::NonAreaLight;
+
+/// Cells along the longest side of the grid.  Other sides have as many cells as fit at the same
+/// size.
+const CELLS_ALONG_LONGEST_SIDE: usize = 16;
+
+/// A grid over a region of a scene, listing for each cell the lights whose light arriving within
+/// it may not be negligible.
+pub struct LightGrid {
+    bounds: AABB,
+    cell_size: Vector,
+    resolution: [usize; 3],
+    /// Where each cell's lights start in `lights`, with the end of the last cell's at the end.
+    offsets: Vec<u32>,
+    /// Lights of every cell, each cell's together.
+    lights: Vec<u32>,
+    /// Lights without a limited reach, which affect every point.
+    everywhere: Vec<u32>,
+    /// Lights with a limited reach, for points outside the grid.
+    reaching: Vec<u32>,
+}
+
+impl LightGrid {
+    /// # Arguments
+    /// * `bounds` - region to divide into cells, usually the bounds of the scene's entities
+    /// * `lights` - the lights of the scene, listed by their index
+    /// * `cutoff` - radiance below which light arriving from a light is negligible
+    ///
+    /// # Panics
+    /// If the bounds are infinite.
+    pub fn new(bounds: &AABB, lights: &[Box<NonAreaLight>], cutoff: f32) -> LightGrid {
+        assert!(bounds.is_bounded(), "Light grids cannot cover infinite regions.");
+        let extent = bounds.upper - bounds.lower;
+        let longest = extent[extent.max_axis()];
+        let side = if longest > 0.0 {
+            longest / CELLS_ALONG_LONGEST_SIDE as f32
+        } else {
+            1.0
+        };
+        let cells_along = |e: f32| ((e / side).ceil() as usize).max(1);
+        let mut resolution = [1; 3];
+        let mut cell_size = extent;
+        for (index, (axis, e)) in extent.components().enumerate() {
+            resolution[index] = cells_along(e);
+            cell_size[axis] /= resolution[index] as f32;
+        }
+
+        let mut everywhere = Vec::new();
+        let mut reaching = Vec::new();
+        for (index, light) in lights.iter().enumerate() {
+            match light.reach(cutoff) {
+                Some(reach) => reaching.push((index as u32, reach)),
+                None => everywhere.push(index as u32),
+            }
+        }
+
+        let mut grid = LightGrid {
+            bounds: *bounds,
+            cell_size: cell_size,
+            resolution: resolution,
+            offsets: Vec::with_capacity(resolution[0] * resolution[1] * resolution[2] + 1),
+            lights: Vec::new(),
+            everywhere: everywhere,
+            reaching: reaching.iter().map(|&(index, _)| index).collect(),
+        };
+        grid.offsets.push(0);
+        for z in 0..resolution[2] {
+            for y in 0..resolution[1] {
+                for x in 0..resolution[0] {
+                    let cell = grid.cell_bounds([x, y, z]);
+                    for &(index, ref reach) in reaching.iter() {
+                        if overlap(&cell, reach) {
+                            grid.lights.push(index);
+                        }
+                    }
+                    let end = grid.lights.len() as u32;
+                    grid.offsets.push(end);
+                }
+            }
+        }
+        grid
+    }
+
+    /// Number of cells along each axis.
+    pub fn resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+
+    /// Indices of the lights which may not be negligible at a point.
+    ///
+    /// Points outside the grid may be lit by any light.
+    pub fn lights_near<'a>(&'a self, point: &Point) -> impl Iterator<Item = usize> + 'a {
+        let listed = match self.cell_of(point) {
+            Some(cell) => {
+                let (start, end) = (self.offsets[cell], self.offsets[cell + 1]);
+                &self.lights[start as usize..end as usize]
+            }
+            None => &self.reaching[..],
+        };
+        self.everywhere
+            .iter()
+            .chain(listed.iter())
+            .map(|&index| index as usize)
+    }
+
+    /// Index of the cell containing a point, if the grid does.
+    fn cell_of(&self, point: &Point) -> Option<usize> {
+        let mut cell = [0; 3];
+        for (axis, a) in XYZ.iter().enumerate() {
+            let (lower, upper) = (self.bounds.lower[*a], self.bounds.upper[*a]);
+            if point[*a] < lower || point[*a] > upper {
+                return None;
+            }
+            let offset = if self.cell_size[*a] > 0.0 {
+                ((point[*a] - lower) / self.cell_size[*a]) as usize
+            } else {
+                0
+            };
+            // Points on the upper bounds belong to the last cell.
+            cell[axis] = offset.min(self.resolution[axis] - 1);
+        }
+        Some((cell[2] * self.resolution[1] + cell[1]) * self.resolution[0] + cell[0])
+    }
+
+    fn cell_bounds(&self, cell: [usize; 3]) -> AABB {
+        let corner = |offset: [usize; 3]| {
+            let mut corner = self.bounds.lower;
+            for (index, &axis) in XYZ.iter().enumerate() {
+                corner[axis] += offset[index] as f32 * self.cell_size[axis];
+            }
+            corner
+        };
+        AABB::new(corner(cell), corner([cell[0] + 1, cell[1] + 1, cell[2] + 1]))
+    }
+}
+
+/// Whether two boxes share any point, including touching at their sides.
+fn overlap(a: &AABB, b: &AABB) -> bool {
+    XYZ.iter()
+        .all(|&axis| a.lower[axis] <= b.upper[axis] && b.lower[axis] <= a.upper[axis])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LightGrid;
+    use math::{AABB, PlanarAngle, Point, Vector};
+    use scene::Spectrum;
+    use scene::nonarea_light::{DirectionalLight, NonAreaLight, SpotLight};
+
+    fn spot_at(x: f32) -> Box<NonAreaLight> {
+        Box::new(SpotLight::new(
+            Point::new(x, 1.0, 0.0),
+            &Vector::new(0.0, -1.0, 0.0),
+            Spectrum::uniform(1.0),
+            PlanarAngle::Degrees(30.0),
+            PlanarAngle::Degrees(45.0),
+            1.0,
+        ))
+    }
+
+    #[test]
+    fn test_lights_near() {
+        let lights = vec![
+            Box::new(DirectionalLight::new(
+                &Vector::new(0.0, -1.0, 0.0),
+                &Spectrum::uniform(1.0),
+            )) as Box<NonAreaLight>,
+            spot_at(0.0),
+            spot_at(100.0),
+        ];
+        let bounds = AABB::new(Point::new(-10.0, 0.0, -10.0), Point::new(110.0, 0.0, 10.0));
+        // Spot lights of unit intensity fall below 0.01 beyond 10 units.
+        let grid = LightGrid::new(&bounds, &lights, 0.01);
+        assert_eq!(grid.resolution(), [16, 1, 3]);
+
+        let near = |x: f32| {
+            let mut found: Vec<usize> = grid.lights_near(&Point::new(x, 0.0, 0.0)).collect();
+            found.sort();
+            found
+        };
+        assert_eq!(near(0.0), vec![0, 1]);
+        assert_eq!(near(50.0), vec![0]);
+        assert_eq!(near(105.0), vec![0, 2]);
+        assert_eq!(near(200.0), vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,2822 @@
+//! Scenes described in RON files, so they can be authored without recompiling.
+//!
+//! # Example
+//! ```text
+//! (
+//!     version: 3,
+//!     camera: (
+//!         width: 800,
+//!         height: 600,
+//!         fov: 90.0,
+//!         eye: Some((0.0, 5.0, -10.0)),
+//!         target: Some((0.0, 0.0, 30.0)),
+//!     ),
+//!     lights: [
+//!         Directional(direction: (0.0, -1.0, 0.0), radiance: (1.0, 1.0, 1.0)),
+//!         Point(position: (0.0, 20.0, 30.0), intensity: (1.0, 1.0, 1.0)),
+//!     ],
+//!     materials: {
+//!         "white": Lambertian(diffuse: (1.0, 1.0, 1.0)),
+//!         "mirror": Specular(reflectance: (0.9, 0.9, 0.9)),
+//!         "wood": Lambertian(texture: Some(Image(path: "wood.png"))),
+//!     },
+//!     entities: [
+//!         (
+//!             solid: Sphere(radius: 5.0),
+//!             material: "white",
+//!             transform: [Translate(0.0, 0.0, 30.0)],
+//!         ),
+//!         (
+//!             solid: Plane(normal: (0.0, 1.0, 0.0), point: (0.0, -5.0, 0.0)),
+//!             material: "mirror",
+//!         ),
+//!     ],
+//!     environment: Some(Image(path: "sky.hdr")),
+//! )
+//! ```
+//!
+//! Relative paths, such as those of OBJ meshes, textures and environment images, are relative to
+//! the scene file.
+//!
+//! Point lights fall off with the square of distance unless given another `attenuation`, such as
+//! one fading smoothly to nothing at a radius, which keeps the light out of distant rooms:
+//!
+//! ```text
+//! Point(position: (0.0, 3.0, 0.0), intensity: (20.0, 20.0, 20.0), attenuation: SmoothCutoff(8.0)),
+//! ```
+//!
+//! Area lights cast soft shadows, from the front of rectangles and disks, the side their edges
+//! or normal turn toward:
+//!
+//! ```text
+//! Rectangle(
+//!     corner: (-1.0, 4.0, -1.0),
+//!     edge_u: (2.0, 0.0, 0.0),
+//!     edge_v: (0.0, 0.0, 2.0),
+//!     radiance: (5.0, 5.0, 5.0),
+//! ),
+//! ```
+//!
+//! Their radiance may vary across them with a `texture`, and with the angle it leaves at with a
+//! `profile` of relative intensities from 0° through 90° from the normal, such as a lamp shining
+//! mostly straight down:
+//!
+//! ```text
+//! Disk(
+//!     center: (0.0, 4.0, 0.0),
+//!     normal: (0.0, -1.0, 0.0),
+//!     radius: 0.5,
+//!     radiance: (5.0, 5.0, 5.0),
+//!     texture: Some(Image(path: "lampshade.png")),
+//!     profile: [1.0, 0.8, 0.3, 0.0],
+//! ),
+//! ```
+//!
+//! Interiors lit by the environment through windows can mark each window as a portal, which
+//! guides sampling of the environment through it, for much less noise.  Only light through
+//! portals is sampled, so every opening needs one:
+//!
+//! ```text
+//! portals: [(corner: (-1.0, 3.0, -1.0), edge_u: (2.0, 0.0, 0.0), edge_v: (0.0, 0.0, 2.0))],
+//! ```
+//!
+//! Entities with `motion` move while the camera's `shutter` is open, blurring them, such as a ball
+//! falling one unit between times 0 and 1 with `shutter: (0.0, 1.0)` set on the camera:
+//!
+//! ```text
+//! (solid: Sphere(radius: 1.0), material: "white", motion: [Translate(0.0, -1.0, 0.0)]),
+//! ```
+//!
+//! Paths with turns use `keyframes` instead, such as a ball rising then rolling away:
+//!
+//! ```text
+//! (solid: Sphere(radius: 1.0), material: "white", keyframes: [
+//!     (time: 0.0),
+//!     (time: 0.5, transform: [Translate(0.0, 1.0, 0.0)]),
+//!     (time: 1.0, transform: [RotateZ(-90.0), Translate(2.0, 1.0, 0.0)]),
+//! ]),
+//! ```
+//!
+//! Lights which move together, such as spot lights hung from a truss, can be grouped into a rig
+//! and placed as a unit.  Rigs nest, each placed within the rig containing it, and can move with
+//! `motion` like entities.  Only lights without a surface can be rigged:
+//!
+//! ```text
+//! rigs: [(
+//!     transform: [RotateY(30.0), Translate(0.0, 6.0, 0.0)],
+//!     motion: [RotateY(10.0)],
+//!     lights: [Spot(
+//!         position: (-2.0, 0.0, 0.0),
+//!         direction: (0.0, -1.0, 0.0),
+//!         intensity: (30.0, 30.0, 30.0),
+//!         inner: 15.0,
+//!         outer: 25.0,
+//!     )],
+//!     rigs: [(transform: [Translate(4.0, 0.0, 0.0)], lights: [/* ... */])],
+//! )],
+//! ```
+//!
+//! Entities can be hidden from kinds of rays to control noise, such as a glass sphere which
+//! lets light through rather than casting a solid shadow:
+//!
+//! ```text
+//! (solid: Sphere(radius: 1.0), material: "glass", visibility: (shadows: false)),
+//! ```
+//!
+//! Groups of entities used many times, such as trees in a forest, can be defined once as a
+//! prototype and then placed by instances.  Instances may replace the materials of the prototype
+//! by name, and add to the transforms of its entities:
+//!
+//! ```text
+//! prototypes: {
+//!     "tree": [
+//!         (solid: Sphere(radius: 2.0), material: "leaves", transform: [Translate(0.0, 4.0, 0.0)]),
+//!         (solid: Cuboid(size: (0.5, 3.0, 0.5)), material: "bark"),
+//!     ],
+//! },
+//! instances: [
+//!     (prototype: "tree", transform: [Translate(10.0, 0.0, 5.0)]),
+//!     (
+//!         prototype: "tree",
+//!         materials: {"leaves": "autumn"},
+//!         transform: [Scale(1.5, 1.5, 1.5), Translate(-4.0, 0.0, 8.0)],
+//!     ),
+//! ],
+//! ```
+//!
+//! Instances can also be placed at random, see `scatter`.
+//!
+//! Scenes with many small image textures can set `pack_textures: true` to copy them into shared
+//! atlases as they load, which keeps the pixels materials sample closer together in memory.
+//!
+//! Materials, lights and entities shared between scenes can be kept in library files, which have
+//! the same fields as a scene file except for the camera and environment.  Their prototypes are
+//! available to the including file.  Scene files and
+//! libraries `include` libraries by path:
+//!
+//! ```text
+//! (
+//!     include: ["materials.ron", "furniture/chairs.ron"],
+//!     camera: (width: 800, height: 600),
+//! )
+//! ```
+//!
+//! Included lights and entities come before those of the including file, in the order they are
+//! included, and the including file's materials replace included materials of the same name.
+//! Paths in libraries are relative to the library.  A library included more than once is only
+//! added once, and a library including itself, directly or indirectly, is an error.
+//!
+//! Files record the version of the format they were written for.  When fields are renamed or
+//! their defaults change, older files are migrated to the current version as they are loaded,
+//! with a warning describing each change, rather than silently rendering differently.
+//!
+//! With the `serde` feature, camera and material descriptions can also be written back out in the
+//! same format.
+use math::{AnimatedTransform, Cone, Csg, CsgOperation, Cuboid, Cylinder, Disk, Matrix4x4,
+           PlanarAngle, Plane, Point, Solid, Sphere, Transform, Transformed, Vector};
+use image::ImageError;
+use ron;
+use scene::area_light::{AreaLight, DiskLight, Emission, RectangleLight, SphereLight};
+use scene::{Camera, Equirectangular, Film, Fisheye, Orthographic, Perspective, Projection,
+            RayVisibility, Scene, Spectrum};
+use scene::camera::FilmError;
+use scene::environment::{EnvironmentLight, Portal};
+use scene::loader::obj::{self, ObjError};
+use scene::loader::scatter::ScatterDescription;
+use scene::material::*;
+use scene::nonarea_light::{Attenuation, DirectionalLight, NonAreaLight, PointLight,
+                           SUN_ANGULAR_RADIUS, SpotLight};
+use scene::texture::{pack_atlases, CheckerTexture, ConstantTexture, ImageTexture, MarbleTexture,
+                     NormalMap, Texture, WoodTexture};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Problems encountered while loading a scene file.
+#[derive(Debug)]
+pub enum SceneFileError {
+    Io(io::Error),
+    /// The file is not a valid scene description.
+    Parse(String),
+    /// The description is well formed, but cannot be rendered.
+    Invalid(String),
+    /// A mesh referenced by the scene could not be loaded.
+    Mesh(String, ObjError),
+    /// An environment image referenced by the scene could not be loaded.
+    Environment(String, ImageError),
+    /// A texture image referenced by a material could not be loaded.
+    Texture(String, ImageError),
+}
+
+impl fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SceneFileError::Io(ref e) => write!(f, "{}", e),
+            SceneFileError::Parse(ref message) => write!(f, "{}", message),
+            SceneFileError::Invalid(ref message) => write!(f, "{}", message),
+            SceneFileError::Mesh(ref path, ref e) => write!(f, "{}: {}", path, e),
+            SceneFileError::Environment(ref path, ref e) => write!(f, "{}: {}", path, e),
+            SceneFileError::Texture(ref path, ref e) => write!(f, "{}: {}", path, e),
+        }
+    }
+}
+
+impl From<io::Error> for SceneFileError {
+    fn from(e: io::Error) -> SceneFileError {
+        SceneFileError::Io(e)
+    }
+}
+
+impl From<FilmError> for SceneFileError {
+    fn from(e: FilmError) -> SceneFileError {
+        SceneFileError::Invalid(format!("{}", e))
+    }
+}
+
+/// Version of the scene file format written by this release.
+pub const SCENE_FORMAT_VERSION: u32 = 3;
+
+/// Upgrades a description from the version before `to` to version `to`, describing each change
+/// made in a warning.
+type Migration = (u32, fn(&mut SceneDescription, &mut Vec<String>));
+
+/// Every change to the format since version 1, in order.
+const MIGRATIONS: &'static [Migration] = &[
+    (2, sharpen_directional_lights),
+    (3, keep_point_lights_constant),
+];
+
+/// Directional lights gained an angular radius, the size of the sun by default, in version 2.
+/// Older lights had none.
+fn sharpen_directional_lights(description: &mut SceneDescription, warnings: &mut Vec<String>) {
+    for light in description.lights.iter_mut() {
+        if let LightDescription::Directional {
+            ref mut angular_radius,
+            ..
+        } = *light
+        {
+            *angular_radius = 0.0;
+            warnings.push(
+                "directional light given no angular radius, to keep its shadows sharp".to_string(),
+            );
+        }
+    }
+}
+
+/// Point lights gained falloff with the square of distance in version 3.  Older lights were as
+/// bright at every distance beyond a unit away.
+fn keep_point_lights_constant(description: &mut SceneDescription, warnings: &mut Vec<String>) {
+    for light in description.lights.iter_mut() {
+        if let LightDescription::Point {
+            ref mut attenuation,
+            ..
+        } = *light
+        {
+            *attenuation = AttenuationDescription::None;
+            warnings.push(
+                "point light given no falloff with distance, to keep its brightness".to_string(),
+            );
+        }
+    }
+}
+
+/// Everything needed to render a scene file.
+pub struct LoadedScene {
+    pub camera: Camera,
+    pub scene: Scene,
+    /// Problems which did not stop the scene from loading, such as migrating from an older
+    /// version of the format.
+    pub warnings: Vec<String>,
+    /// Every file read to load the scene: those of its description, then the meshes and images
+    /// it uses, sorted by path.
+    pub files: Vec<PathBuf>,
+}
+
+/// A complete scene file.
+#[derive(Clone, Debug, Deserialize)]
+pub struct SceneDescription {
+    /// Version of the format the file was written for, 0 if missing, which is read as version 1.
+    #[serde(default)]
+    pub version: u32,
+    /// Paths of libraries to add to the scene.
+    #[serde(default)]
+    pub include: Vec<String>,
+    pub camera: CameraDescription,
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+    /// Groups of lights placed and moved as a unit.
+    #[serde(default)]
+    pub rigs: Vec<RigDescription>,
+    /// Materials, by the names entities refer to them with.
+    #[serde(default)]
+    pub materials: HashMap<String, MaterialDescription>,
+    #[serde(default)]
+    pub entities: Vec<EntityDescription>,
+    /// Groups of entities which instances place copies of, by name.
+    #[serde(default)]
+    pub prototypes: HashMap<String, Vec<EntityDescription>>,
+    #[serde(default)]
+    pub instances: Vec<InstanceDescription>,
+    /// Instances placed at random.
+    #[serde(default)]
+    pub scatter: Vec<ScatterDescription>,
+    /// Light arriving from every direction rays escape the scene in.
+    #[serde(default)]
+    pub environment: Option<EnvironmentDescription>,
+    /// Windows the environment lights interiors through.
+    #[serde(default)]
+    pub portals: Vec<PortalDescription>,
+    /// Copy small image textures into shared atlases.
+    #[serde(default)]
+    pub pack_textures: bool,
+    /// Files read to make the description: the scene file, then the libraries it includes in
+    /// the order they were merged.  Filled in by `read`, rather than written in scene files.
+    #[serde(skip)]
+    pub files: Vec<PathBuf>,
+}
+
+/// Materials, lights and entities to include in scenes.
+#[derive(Clone, Debug, Deserialize)]
+pub struct LibraryDescription {
+    /// Paths of other libraries to add to this one.
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+    #[serde(default)]
+    pub rigs: Vec<RigDescription>,
+    #[serde(default)]
+    pub materials: HashMap<String, MaterialDescription>,
+    #[serde(default)]
+    pub entities: Vec<EntityDescription>,
+    #[serde(default)]
+    pub prototypes: HashMap<String, Vec<EntityDescription>>,
+    #[serde(default)]
+    pub instances: Vec<InstanceDescription>,
+    #[serde(default)]
+    pub scatter: Vec<ScatterDescription>,
+}
+
+/// A camera, which uses a perspective projection unless `orthographic` or `panorama` is set.
+/// Without an `eye` and `target`, it sits at the origin looking down Z+.
+#[derive(Clone, Debug, Deserialize)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CameraDescription {
+    pub width: u32,
+    pub height: u32,
+    /// Field of view in degrees.
+    #[serde(default = "default_fov")]
+    pub fov: f32,
+    /// Half the width of the view along the shorter side of the film, for an orthographic
+    /// projection.
+    #[serde(default)]
+    pub orthographic: Option<f32>,
+    /// Projection onto directions all around the camera.
+    #[serde(default)]
+    pub panorama: Option<PanoramaDescription>,
+    #[serde(default = "default_near")]
+    pub near: f32,
+    #[serde(default = "default_far")]
+    pub far: f32,
+    #[serde(default)]
+    pub eye: Option<(f32, f32, f32)>,
+    #[serde(default)]
+    pub target: Option<(f32, f32, f32)>,
+    #[serde(default = "default_up")]
+    pub up: (f32, f32, f32),
+    /// Radius of the lens, blurring what is nearer or farther than the focal distance.  Cameras
+    /// without an aperture have everything in focus.
+    #[serde(default)]
+    pub aperture: f32,
+    /// Distance in focus, the distance from the eye to the target by default.
+    #[serde(default)]
+    pub focal_distance: Option<f32>,
+    /// Times the shutter opens and closes, blurring entities with `motion`.  Closed instantly by
+    /// default.
+    #[serde(default)]
+    pub shutter: (f32, f32),
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum PanoramaDescription {
+    /// Sees `fov` across the shorter side of the film, up to 360 degrees.
+    Fisheye,
+    /// Sees every direction, across a film twice as wide as it is tall.
+    Equirectangular,
+}
+
+fn default_fov() -> f32 {
+    90.0
+}
+
+fn default_near() -> f32 {
+    1.0
+}
+
+fn default_far() -> f32 {
+    1000.0
+}
+
+fn default_up() -> (f32, f32, f32) {
+    (0.0, 1.0, 0.0)
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum LightDescription {
+    /// The angular radius is in degrees, the size of the sun by default.  Lights with no angular
+    /// radius cast sharp shadows.
+    Directional {
+        direction: (f32, f32, f32),
+        radiance: (f32, f32, f32),
+        #[serde(default = "default_angular_radius")]
+        angular_radius: f32,
+    },
+    /// Falls off with the square of distance by default.
+    Point {
+        position: (f32, f32, f32),
+        intensity: (f32, f32, f32),
+        #[serde(default)]
+        attenuation: AttenuationDescription,
+    },
+    /// Cone angles are in degrees from the direction the light shines in.
+    Spot {
+        position: (f32, f32, f32),
+        direction: (f32, f32, f32),
+        intensity: (f32, f32, f32),
+        inner: f32,
+        outer: f32,
+        #[serde(default = "default_spot_falloff")]
+        falloff: f32,
+    },
+    /// Area lights may scale their radiance by a `texture` across the light, and by a `profile`
+    /// of relative intensities at evenly spaced angles from the normal, 0° through 90°.
+    Sphere {
+        center: (f32, f32, f32),
+        radius: f32,
+        radiance: (f32, f32, f32),
+        #[serde(default)]
+        texture: Option<TextureDescription>,
+        #[serde(default)]
+        profile: Vec<f32>,
+    },
+    /// Lit from the side `edge_u.cross(&edge_v)` points toward.  The edges must be perpendicular.
+    Rectangle {
+        corner: (f32, f32, f32),
+        edge_u: (f32, f32, f32),
+        edge_v: (f32, f32, f32),
+        radiance: (f32, f32, f32),
+        #[serde(default)]
+        texture: Option<TextureDescription>,
+        #[serde(default)]
+        profile: Vec<f32>,
+    },
+    /// Lit from the side the normal points toward.
+    Disk {
+        center: (f32, f32, f32),
+        normal: (f32, f32, f32),
+        radius: f32,
+        radiance: (f32, f32, f32),
+        #[serde(default)]
+        texture: Option<TextureDescription>,
+        #[serde(default)]
+        profile: Vec<f32>,
+    },
+}
+
+/// Lights placed and moved together as a unit, along with the rigs within it.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RigDescription {
+    /// Lights in the coordinates of the rig.  Area lights cannot be rigged.
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+    /// Rigs placed within this one, which move with it.
+    #[serde(default)]
+    pub rigs: Vec<RigDescription>,
+    /// Steps placing the rig within the rig containing it, or the world.
+    #[serde(default)]
+    pub transform: Vec<TransformDescription>,
+    /// Steps moving the rig within the rig containing it, after `transform`, by the time the
+    /// camera's shutter closes.
+    #[serde(default)]
+    pub motion: Vec<TransformDescription>,
+}
+
+/// Lights are added to scenes differently, depending on whether they have a surface.
+enum BuiltLight {
+    NonArea(Box<NonAreaLight>),
+    Area(Box<AreaLight>),
+}
+
+fn default_angular_radius() -> f32 {
+    SUN_ANGULAR_RADIUS.to_degrees()
+}
+
+fn default_spot_falloff() -> f32 {
+    1.0
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum AttenuationDescription {
+    None,
+    Linear,
+    Quadratic,
+    /// Fades to nothing at the radius given.
+    SmoothCutoff(f32),
+}
+
+impl Default for AttenuationDescription {
+    fn default() -> AttenuationDescription {
+        AttenuationDescription::Quadratic
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum MaterialDescription {
+    /// A matte surface, colored by `texture` if there is one and `diffuse` otherwise, and with
+    /// its shading normals perturbed by a tangent space `normal_map`.
+    Lambertian {
+        #[serde(default)]
+        diffuse: (f32, f32, f32),
+        #[serde(default)]
+        texture: Option<TextureDescription>,
+        #[serde(default)]
+        normal_map: Option<TextureDescription>,
+    },
+    /// Roughness `sigma` is in degrees.
+    OrenNayar {
+        diffuse: (f32, f32, f32),
+        sigma: f32,
+    },
+    Sheen {
+        diffuse: (f32, f32, f32),
+        sheen: (f32, f32, f32),
+        roughness: f32,
+    },
+    /// Diffuse reflection with glossy highlights, which narrow as `shininess` increases.
+    BlinnPhong {
+        diffuse: (f32, f32, f32),
+        specular: (f32, f32, f32),
+        shininess: f32,
+    },
+    /// Physically based GGX reflection, `roughness` and `metalness` in [0, 1].
+    Microfacet {
+        base_color: (f32, f32, f32),
+        roughness: f32,
+        #[serde(default)]
+        metalness: f32,
+    },
+    /// Glows with `radiance` from the front of its surfaces, lighting the scene.
+    Emissive { radiance: (f32, f32, f32) },
+    Specular { reflectance: (f32, f32, f32) },
+    /// Transparent, filling the overlap with entities of lower `priority` where they intersect.
+    /// Light travelling through it is dimmed by `absorption` per unit of distance, which is
+    /// clear by default.  Frosted glass has a `roughness` in [0, 1], or a `roughness_texture`
+    /// whose brightness is the roughness across it.
+    Dielectric {
+        index_of_refraction: f32,
+        #[serde(default)]
+        priority: u32,
+        #[serde(default)]
+        absorption: (f32, f32, f32),
+        #[serde(default)]
+        roughness: f32,
+        #[serde(default)]
+        roughness_texture: Option<TextureDescription>,
+    },
+}
+
+/// Colors varying across a surface.
+#[derive(Clone, Debug, Deserialize)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum TextureDescription {
+    Constant { color: (f32, f32, f32) },
+    /// An image repeated across texture coordinates.
+    Image { path: String },
+    /// Alternating squares of two textures, `cells` squares along each unit of texture
+    /// coordinates.
+    Checker {
+        even: Box<TextureDescription>,
+        odd: Box<TextureDescription>,
+        cells: f32,
+    },
+    /// Veined stone, with `scale` veins per unit of distance.
+    Marble {
+        base: (f32, f32, f32),
+        vein: (f32, f32, f32),
+        scale: f32,
+        #[serde(default)]
+        seed: u32,
+    },
+    /// Wood grain, with `rings` rings per unit of distance from the Y axis.
+    Wood {
+        light: (f32, f32, f32),
+        dark: (f32, f32, f32),
+        rings: f32,
+        #[serde(default)]
+        seed: u32,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum SolidDescription {
+    Sphere { radius: f32 },
+    /// A box centered at the origin, with the width, height and depth given.
+    Cuboid { size: (f32, f32, f32) },
+    /// A cylinder around the Z axis, with flat caps closing its ends unless `capped` is false.
+    Cylinder {
+        radius: f32,
+        z_min: f32,
+        z_max: f32,
+        #[serde(default = "default_capped")]
+        capped: bool,
+    },
+    /// A cone around the Z axis from its base at the origin to its tip at `height`, with a flat
+    /// cap closing the base unless `capped` is false.
+    Cone {
+        radius: f32,
+        height: f32,
+        #[serde(default = "default_capped")]
+        capped: bool,
+    },
+    /// A disk at the origin facing along Z+, with a hole in its center if `inner_radius` is
+    /// positive.
+    Disk {
+        radius: f32,
+        #[serde(default)]
+        inner_radius: f32,
+    },
+    Plane {
+        normal: (f32, f32, f32),
+        point: (f32, f32, f32),
+    },
+    /// A triangle mesh from a Wavefront OBJ file.
+    Obj { path: String },
+    /// Two solids combined by a boolean operation, such as a sphere with a hole drilled by a
+    /// cylinder.  Both parts must be closed for the combination to look solid.
+    Csg {
+        operation: CsgOperationDescription,
+        a: Box<CsgPartDescription>,
+        b: Box<CsgPartDescription>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum CsgOperationDescription {
+    Union,
+    Intersection,
+    /// The first part with the second part cut out of it.
+    Difference,
+}
+
+/// One of the solids of a CSG combination, positioned relative to the entity.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CsgPartDescription {
+    pub solid: SolidDescription,
+    #[serde(default)]
+    pub transform: Vec<TransformDescription>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum EnvironmentDescription {
+    /// The same radiance in every direction, like an overcast sky.
+    Constant { radiance: (f32, f32, f32) },
+    /// An equirectangular Radiance HDR image, whose center looks along X+.
+    Image { path: String },
+}
+
+/// A rectangular window letting the environment into the side `edge_u.cross(&edge_v)` points
+/// toward.  The edges must be perpendicular.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PortalDescription {
+    pub corner: (f32, f32, f32),
+    pub edge_u: (f32, f32, f32),
+    pub edge_v: (f32, f32, f32),
+}
+
+fn default_capped() -> bool {
+    true
+}
+
+/// One step of a transform.  Steps are applied in order to move an entity from its local
+/// coordinates into the world.
+#[derive(Clone, Debug, Deserialize)]
+pub enum TransformDescription {
+    Translate(f32, f32, f32),
+    Scale(f32, f32, f32),
+    /// Rotations about the X, Y and Z axes, in degrees.
+    RotateX(f32),
+    RotateY(f32),
+    RotateZ(f32),
+    /// A rotation in degrees about an axis through the origin.
+    Rotate { axis: (f32, f32, f32), degrees: f32 },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EntityDescription {
+    pub solid: SolidDescription,
+    /// Name of one of the scene's materials.
+    pub material: String,
+    #[serde(default)]
+    pub transform: Vec<TransformDescription>,
+    #[serde(default)]
+    pub visibility: RayVisibilityDescription,
+    /// Steps moving the entity in world space, after `transform`, by the time the camera's
+    /// shutter closes.
+    #[serde(default)]
+    pub motion: Vec<TransformDescription>,
+    /// Steps moving the entity in world space, after `transform`, at each of several times, for
+    /// paths with more than one segment.  Entities cannot have both these and `motion`.
+    #[serde(default)]
+    pub keyframes: Vec<KeyframeDescription>,
+}
+
+/// Where an entity is at one time along a path.
+///
+/// The entity is blended between neighbouring keyframes, holding the first before its time and
+/// the last after its time.
+#[derive(Clone, Debug, Deserialize)]
+pub struct KeyframeDescription {
+    pub time: f32,
+    #[serde(default)]
+    pub transform: Vec<TransformDescription>,
+}
+
+/// Which kinds of rays see an entity, every kind unless turned off.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct RayVisibilityDescription {
+    /// Seen in glossy reflections.
+    #[serde(default = "default_visible")]
+    pub glossy: bool,
+    /// Casts shadows, turned off for glass to let light through in place of caustics.
+    #[serde(default = "default_visible")]
+    pub shadows: bool,
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+impl Default for RayVisibilityDescription {
+    fn default() -> RayVisibilityDescription {
+        RayVisibilityDescription {
+            glossy: true,
+            shadows: true,
+        }
+    }
+}
+
+impl RayVisibilityDescription {
+    fn build(&self) -> RayVisibility {
+        RayVisibility {
+            glossy: self.glossy,
+            shadows: self.shadows,
+        }
+    }
+}
+
+/// A copy of the entities of a prototype.
+#[derive(Clone, Debug, Deserialize)]
+pub struct InstanceDescription {
+    /// Name of the prototype to copy.
+    pub prototype: String,
+    /// Materials to use in place of those named by the prototype's entities.
+    #[serde(default)]
+    pub materials: HashMap<String, String>,
+    /// Steps applied after those of each of the prototype's entities.
+    #[serde(default)]
+    pub transform: Vec<TransformDescription>,
+}
+
+fn vector(v: (f32, f32, f32)) -> Vector {
+    Vector::new(v.0, v.1, v.2)
+}
+
+fn point(p: (f32, f32, f32)) -> Point {
+    Point::new(p.0, p.1, p.2)
+}
+
+fn spectrum(c: (f32, f32, f32)) -> Spectrum {
+    Spectrum::new(c.0, c.1, c.2)
+}
+
+/// Reads a scene file.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<LoadedScene, SceneFileError> {
+    let path = path.as_ref();
+    let (description, warnings) = read(path)?;
+    let mut loaded = description.build(path.parent().unwrap_or(Path::new("")))?;
+    loaded.warnings = warnings;
+    Ok(loaded)
+}
+
+/// Reads a scene file and the libraries it includes, without building the scene.
+///
+/// # Returns
+/// The description with every library merged into it, and a warning for each change made
+/// migrating it to the current version of the format.
+pub fn read<P: AsRef<Path>>(path: P) -> Result<(SceneDescription, Vec<String>), SceneFileError> {
+    let path = path.as_ref();
+    let mut contents = String::new();
+    File::open(path)?.read_to_string(&mut contents)?;
+    let (mut description, warnings) = parse(&contents)?.migrate()?;
+    let base_directory = path.parent().unwrap_or(Path::new(""));
+
+    // Paths of the files being included, to find cycles.
+    let mut including = vec![canonical_path(path)?];
+    let mut included = HashSet::new();
+    let mut libraries: Vec<(PathBuf, LibraryDescription)> = Vec::new();
+    for include in description.include.iter() {
+        load_library(
+            base_directory,
+            Path::new(include),
+            &mut including,
+            &mut included,
+            &mut libraries,
+        )?;
+    }
+    description.files = Some(path.to_path_buf())
+        .into_iter()
+        .chain(libraries.iter().map(|l| l.0.clone()))
+        .collect();
+    description.merge(libraries.into_iter().map(|l| l.1).collect());
+    Ok((description, warnings))
+}
+
+/// Reads a scene description from RON.
+pub fn parse(contents: &str) -> Result<SceneDescription, SceneFileError> {
+    ron::de::from_str(contents).map_err(|e| SceneFileError::Parse(format!("{}", e)))
+}
+
+/// Reads a library, and those it includes, adding each to `libraries` with its path after those
+/// it includes.
+///
+/// # Arguments
+/// * `base_directory` - directory of the scene file, which paths in libraries are made relative to
+/// * `include` - path of the library, relative to `base_directory`
+/// * `including` - paths of the files currently being read, each including the next
+/// * `included` - paths of the libraries which have already been read
+fn load_library(
+    base_directory: &Path,
+    include: &Path,
+    including: &mut Vec<PathBuf>,
+    included: &mut HashSet<PathBuf>,
+    libraries: &mut Vec<(PathBuf, LibraryDescription)>,
+) -> Result<(), SceneFileError> {
+    let path = base_directory.join(include);
+    let canonical = canonical_path(&path)?;
+    if including.contains(&canonical) {
+        let cycle: Vec<String> = including
+            .iter()
+            .skip_while(|p| **p != canonical)
+            .chain(Some(&canonical))
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(SceneFileError::Invalid(
+            format!("include cycle: {}", cycle.join(" -> ")),
+        ));
+    }
+    if !included.insert(canonical.clone()) {
+        return Ok(());
+    }
+
+    let mut contents = String::new();
+    File::open(&path)?.read_to_string(&mut contents)?;
+    let mut library: LibraryDescription = ron::de::from_str(&contents).map_err(|e| {
+        SceneFileError::Parse(format!("{}: {}", path.display(), e))
+    })?;
+    // The library's directory, as seen from the scene file.
+    let library_directory = include.parent().unwrap_or(Path::new(""));
+    library.relative_to(library_directory);
+
+    including.push(canonical);
+    for nested in library.include.iter() {
+        load_library(
+            base_directory,
+            &library_directory.join(nested),
+            including,
+            included,
+            libraries,
+        )?;
+    }
+    including.pop();
+    libraries.push((path, library));
+    Ok(())
+}
+
+/// The absolute path to a file, to recognize it however it is referred to.
+fn canonical_path(path: &Path) -> Result<PathBuf, SceneFileError> {
+    path.canonicalize().map_err(|e| {
+        SceneFileError::Invalid(format!("{}: {}", path.display(), e))
+    })
+}
+
+impl LibraryDescription {
+    /// Makes the paths of meshes and textures relative to the directory of the including scene,
+    /// rather than the library.
+    ///
+    /// # Arguments
+    /// * `base_directory` - directory of the library, relative to that of the scene
+    fn relative_to(&mut self, base_directory: &Path) {
+        let prototype_entities = self.prototypes.values_mut().flat_map(|p| p.iter_mut());
+        for entity in self.entities.iter_mut().chain(prototype_entities) {
+            entity.solid.relative_to(base_directory);
+        }
+        for material in self.materials.values_mut() {
+            material.relative_to(base_directory);
+        }
+        for light in self.lights.iter_mut() {
+            light.relative_to(base_directory);
+        }
+    }
+}
+
+impl SceneDescription {
+    /// Adds the lights, materials, entities and prototypes of libraries, in the order they were
+    /// included.
+    ///
+    /// Lights, rigs, entities and instances are kept in order, each library's before those of the
+    /// libraries and scene after it.  Materials and prototypes of later libraries replace those
+    /// of the same name in earlier ones, and the scene's own replace them all.
+    pub fn merge(&mut self, libraries: Vec<LibraryDescription>) {
+        let mut lights = Vec::new();
+        let mut rigs = Vec::new();
+        let mut entities = Vec::new();
+        let mut instances = Vec::new();
+        let mut scatter = Vec::new();
+        let mut materials = HashMap::new();
+        let mut prototypes = HashMap::new();
+        for library in libraries.into_iter() {
+            lights.extend(library.lights);
+            rigs.extend(library.rigs);
+            entities.extend(library.entities);
+            instances.extend(library.instances);
+            scatter.extend(library.scatter);
+            materials.extend(library.materials);
+            prototypes.extend(library.prototypes);
+        }
+
+        lights.extend(self.lights.drain(..));
+        self.lights = lights;
+        rigs.extend(self.rigs.drain(..));
+        self.rigs = rigs;
+        entities.extend(self.entities.drain(..));
+        self.entities = entities;
+        instances.extend(self.instances.drain(..));
+        self.instances = instances;
+        scatter.extend(self.scatter.drain(..));
+        self.scatter = scatter;
+        materials.extend(self.materials.drain());
+        self.materials = materials;
+        prototypes.extend(self.prototypes.drain());
+        self.prototypes = prototypes;
+    }
+
+    /// Copies the entities of the prototype of each instance, including those scattered, with
+    /// the instance's materials and transform.
+    pub fn instantiate(&self) -> Result<Vec<EntityDescription>, SceneFileError> {
+        let mut scattered = Vec::new();
+        for scatter in self.scatter.iter() {
+            scattered.extend(scatter.instances()?);
+        }
+
+        let mut entities = Vec::new();
+        for instance in self.instances.iter().chain(scattered.iter()) {
+            let prototype = self.prototypes.get(&instance.prototype).ok_or_else(|| {
+                SceneFileError::Invalid(format!("no prototype named \"{}\"", instance.prototype))
+            })?;
+            for entity in prototype.iter() {
+                let mut copy = entity.clone();
+                if let Some(material) = instance.materials.get(&entity.material) {
+                    copy.material = material.clone();
+                }
+                copy.transform.extend(instance.transform.iter().cloned());
+                entities.push(copy);
+            }
+        }
+        Ok(entities)
+    }
+
+    /// Upgrades the description to the current version of the format.
+    ///
+    /// # Returns
+    /// The upgraded description and a warning for each change made, or an error if the file was
+    /// written for a newer version than this release understands.
+    pub fn migrate(mut self) -> Result<(SceneDescription, Vec<String>), SceneFileError> {
+        let mut warnings = Vec::new();
+        let version = match self.version {
+            0 => {
+                warnings.push(format!(
+                    "no scene format version given, assuming 1 (the latest is {})",
+                    SCENE_FORMAT_VERSION
+                ));
+                1
+            }
+            version if version > SCENE_FORMAT_VERSION => {
+                return Err(SceneFileError::Invalid(format!(
+                    "scene format version {} is newer than the latest supported, {}",
+                    version,
+                    SCENE_FORMAT_VERSION
+                )))
+            }
+            version => version,
+        };
+        for &(to, migrate) in MIGRATIONS.iter() {
+            if to > version {
+                migrate(&mut self, &mut warnings);
+            }
+        }
+        self.version = SCENE_FORMAT_VERSION;
+        Ok((self, warnings))
+    }
+
+    /// Creates the camera and scene described.
+    ///
+    /// # Arguments
+    /// * `base_directory` - directory relative paths are resolved from
+    pub fn build(&self, base_directory: &Path) -> Result<LoadedScene, SceneFileError> {
+        let camera = self.camera.build()?;
+
+        let mut scene = Scene::new();
+        let instanced = self.instantiate()?;
+        let mut images = HashMap::new();
+        if self.pack_textures {
+            // Every image must be loaded before any can be packed.
+            let mut paths = Vec::new();
+            for entity in self.entities.iter().chain(instanced.iter()) {
+                if let Some(material) = self.materials.get(&entity.material) {
+                    material.image_paths(&mut paths);
+                }
+            }
+            for path in paths.iter() {
+                load_image(base_directory, path, &mut images)?;
+            }
+            let mut loaded: Vec<(PathBuf, ImageTexture)> = images.drain().collect();
+            loaded.sort_by(|a, b| a.0.cmp(&b.0));
+            let textures: Vec<ImageTexture> = loaded.iter().map(|l| l.1.clone()).collect();
+            images = loaded
+                .into_iter()
+                .map(|l| l.0)
+                .zip(pack_atlases(&textures))
+                .collect();
+        }
+        for light in self.lights.iter() {
+            match light.build(base_directory, &mut images)? {
+                BuiltLight::NonArea(light) => scene.add_light(light),
+                BuiltLight::Area(light) => scene.add_area_light(light),
+            }
+        }
+        let world = Transform::identity();
+        for rig in self.rigs.iter() {
+            rig.build(
+                (&world, &world),
+                self.camera.shutter,
+                base_directory,
+                &mut images,
+                &mut scene,
+            )?;
+        }
+        for entity in self.entities.iter().chain(instanced.iter()) {
+            let material = match self.materials.get(&entity.material) {
+                Some(material) => material.build(base_directory, &mut images)?,
+                None => {
+                    return Err(SceneFileError::Invalid(
+                        format!("no material named \"{}\"", entity.material),
+                    ))
+                }
+            };
+            let transform = build_transform(&entity.transform)?;
+            let motion = if entity.keyframes.is_empty() {
+                let (open, close) = self.camera.shutter;
+                AnimatedTransform::new(
+                    transform,
+                    open,
+                    transform.compose(&build_transform(&entity.motion)?),
+                    close,
+                )
+            } else {
+                entity.keyframes(&transform)?
+            };
+
+            let handle = scene.add_animated_entity(
+                entity.solid.build(base_directory)?,
+                material,
+                motion,
+            );
+            scene.set_ray_visibility(handle, entity.visibility.build());
+        }
+        if let Some(ref environment) = self.environment {
+            scene.set_environment(Some(environment.build(base_directory)?));
+        }
+        for portal in self.portals.iter() {
+            scene.add_portal(portal.build()?);
+        }
+        scene.finalize();
+
+        // Sorted, so the order does not depend on that of the images in their hash map.
+        let mut meshes = Vec::new();
+        for entity in self.entities.iter().chain(instanced.iter()) {
+            entity.solid.mesh_paths(&mut meshes);
+        }
+        let mut assets: Vec<PathBuf> = images.keys().cloned().collect();
+        assets.extend(meshes.iter().map(|mesh| base_directory.join(mesh)));
+        if let Some(EnvironmentDescription::Image { ref path }) = self.environment {
+            assets.push(base_directory.join(path));
+        }
+        assets.sort();
+        assets.dedup();
+
+        Ok(LoadedScene {
+            camera: camera,
+            scene: scene,
+            warnings: Vec::new(),
+            files: self.files.iter().cloned().chain(assets).collect(),
+        })
+    }
+}
+
+impl EntityDescription {
+    /// The path of an entity with keyframes, placed by its transform.
+    fn keyframes(&self, transform: &Transform) -> Result<AnimatedTransform, SceneFileError> {
+        if !self.motion.is_empty() {
+            return Err(SceneFileError::Invalid(
+                "entities cannot have both motion and keyframes".to_string(),
+            ));
+        }
+        if !self.keyframes.windows(2).all(|pair| pair[0].time <= pair[1].time) {
+            return Err(SceneFileError::Invalid(
+                "keyframes must be in order of time".to_string(),
+            ));
+        }
+        let mut keyframes = Vec::with_capacity(self.keyframes.len());
+        for keyframe in self.keyframes.iter() {
+            let moved = transform.compose(&build_transform(&keyframe.transform)?);
+            keyframes.push((keyframe.time, moved));
+        }
+        Ok(AnimatedTransform::from_keyframes(keyframes))
+    }
+}
+
+impl RigDescription {
+    /// Adds the lights of the rig, and of the rigs within it, to a scene.
+    ///
+    /// # Arguments
+    /// * `parent` - transforms placing the rig containing this one at the times the camera's
+    ///   shutter opens and closes
+    /// * `shutter` - times the camera's shutter opens and closes
+    /// * `base_directory` - directory relative paths are resolved from
+    /// * `images` - images already loaded, by path
+    fn build(
+        &self,
+        parent: (&Transform, &Transform),
+        shutter: (f32, f32),
+        base_directory: &Path,
+        images: &mut HashMap<PathBuf, ImageTexture>,
+        scene: &mut Scene,
+    ) -> Result<(), SceneFileError> {
+        let transform = build_transform(&self.transform)?;
+        let open = transform.compose(parent.0);
+        let close = transform
+            .compose(&build_transform(&self.motion)?)
+            .compose(parent.1);
+        for light in self.lights.iter() {
+            match light.build(base_directory, images)? {
+                BuiltLight::NonArea(light) => {
+                    let motion = AnimatedTransform::new(open, shutter.0, close, shutter.1);
+                    scene.add_animated_light(light, motion)
+                }
+                BuiltLight::Area(_) => {
+                    return Err(SceneFileError::Invalid(
+                        "area lights cannot be placed in rigs".to_string(),
+                    ))
+                }
+            }
+        }
+        for rig in self.rigs.iter() {
+            rig.build((&open, &close), shutter, base_directory, images, scene)?;
+        }
+        Ok(())
+    }
+}
+
+impl SolidDescription {
+    /// # Arguments
+    /// * `base_directory` - directory the paths of meshes are relative to
+    fn build(&self, base_directory: &Path) -> Result<Box<Solid>, SceneFileError> {
+        match *self {
+            SolidDescription::Sphere { radius } => {
+                if radius <= 0.0 {
+                    return Err(SceneFileError::Invalid(
+                        "sphere radius must be positive".to_string(),
+                    ));
+                }
+                Ok(Box::new(Sphere::new_with_radius(radius)))
+            }
+            SolidDescription::Cuboid { size } => {
+                if !(size.0 > 0.0 && size.1 > 0.0 && size.2 > 0.0) {
+                    return Err(SceneFileError::Invalid(
+                        "cuboid size must be positive along every axis".to_string(),
+                    ));
+                }
+                Ok(Box::new(Cuboid::new_with_size(size.0, size.1, size.2)))
+            }
+            SolidDescription::Cylinder {
+                radius,
+                z_min,
+                z_max,
+                capped,
+            } => {
+                if !(radius > 0.0 && z_min < z_max) {
+                    return Err(SceneFileError::Invalid(
+                        "cylinder needs a positive radius and z_min < z_max".to_string(),
+                    ));
+                }
+                Ok(Box::new(Cylinder::new(radius, z_min, z_max, capped)))
+            }
+            SolidDescription::Cone {
+                radius,
+                height,
+                capped,
+            } => {
+                if !(radius > 0.0 && height > 0.0) {
+                    return Err(SceneFileError::Invalid(
+                        "cone radius and height must be positive".to_string(),
+                    ));
+                }
+                Ok(Box::new(Cone::new(radius, height, capped)))
+            }
+            SolidDescription::Disk {
+                radius,
+                inner_radius,
+            } => {
+                if !(inner_radius >= 0.0 && inner_radius < radius) {
+                    return Err(SceneFileError::Invalid(
+                        "disk needs 0 <= inner_radius < radius".to_string(),
+                    ));
+                }
+                Ok(Box::new(Disk::new(radius, inner_radius, 0.0)))
+            }
+            SolidDescription::Plane { normal, point: p } => {
+                if vector(normal).length() == 0.0 {
+                    return Err(SceneFileError::Invalid(
+                        "plane normal cannot be zero".to_string(),
+                    ));
+                }
+                Ok(Box::new(Plane::from_normal_and_point(&vector(normal), &point(p))))
+            }
+            SolidDescription::Obj { ref path } => {
+                let mesh = obj::load(base_directory.join(path))
+                    .map_err(|e| SceneFileError::Mesh(path.clone(), e))?;
+                Ok(Box::new(mesh))
+            }
+            SolidDescription::Csg {
+                operation,
+                ref a,
+                ref b,
+            } => {
+                Ok(Box::new(Csg::new(
+                    operation.build(),
+                    a.build(base_directory)?,
+                    b.build(base_directory)?,
+                )))
+            }
+        }
+    }
+
+    /// Makes the paths of meshes, including those within CSG parts, relative to another
+    /// directory.
+    fn relative_to(&mut self, base_directory: &Path) {
+        match *self {
+            SolidDescription::Obj { ref mut path } => {
+                *path = base_directory.join(&*path).to_string_lossy().into_owned();
+            }
+            SolidDescription::Csg {
+                ref mut a,
+                ref mut b,
+                ..
+            } => {
+                a.solid.relative_to(base_directory);
+                b.solid.relative_to(base_directory);
+            }
+            _ => {}
+        }
+    }
+
+    /// Adds the paths of meshes, including those within CSG parts, to `paths`.
+    fn mesh_paths(&self, paths: &mut Vec<String>) {
+        match *self {
+            SolidDescription::Obj { ref path } => paths.push(path.clone()),
+            SolidDescription::Csg { ref a, ref b, .. } => {
+                a.solid.mesh_paths(paths);
+                b.solid.mesh_paths(paths);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl CsgPartDescription {
+    fn build(&self, base_directory: &Path) -> Result<Box<Solid>, SceneFileError> {
+        Ok(Box::new(Transformed::new(
+            self.solid.build(base_directory)?,
+            build_transform(&self.transform)?,
+        )))
+    }
+}
+
+impl AttenuationDescription {
+    fn build(&self) -> Result<Attenuation, SceneFileError> {
+        Ok(match *self {
+            AttenuationDescription::None => Attenuation::None,
+            AttenuationDescription::Linear => Attenuation::Linear,
+            AttenuationDescription::Quadratic => Attenuation::Quadratic,
+            AttenuationDescription::SmoothCutoff(radius) => {
+                if !(radius > 0.0) {
+                    return Err(SceneFileError::Invalid(
+                        "point light cutoff radius must be positive".to_string(),
+                    ));
+                }
+                Attenuation::SmoothCutoff(radius)
+            }
+        })
+    }
+}
+
+impl CsgOperationDescription {
+    fn build(&self) -> CsgOperation {
+        match *self {
+            CsgOperationDescription::Union => CsgOperation::Union,
+            CsgOperationDescription::Intersection => CsgOperation::Intersection,
+            CsgOperationDescription::Difference => CsgOperation::Difference,
+        }
+    }
+}
+
+impl CameraDescription {
+    fn build(&self) -> Result<Camera, SceneFileError> {
+        let film = Film::try_new(self.width, self.height)?;
+        if !(self.near > 0.0 && self.far > self.near) {
+            return Err(SceneFileError::Invalid(
+                "camera needs 0 < near < far".to_string(),
+            ));
+        }
+        let projection: Box<Projection> = match (self.orthographic, self.panorama) {
+            (Some(_), Some(_)) => {
+                return Err(SceneFileError::Invalid(
+                    "camera cannot be both orthographic and panoramic".to_string(),
+                ))
+            }
+            (None, Some(PanoramaDescription::Fisheye)) => {
+                if !(self.fov > 0.0 && self.fov <= 360.0) {
+                    return Err(SceneFileError::Invalid(
+                        "fisheye field of view must be between 0 and 360 degrees".to_string(),
+                    ));
+                }
+                Box::new(Fisheye::new(PlanarAngle::Degrees(self.fov)))
+            }
+            (None, Some(PanoramaDescription::Equirectangular)) => Box::new(Equirectangular::new()),
+            (Some(half_extent), None) => {
+                if !(half_extent > 0.0) {
+                    return Err(SceneFileError::Invalid(
+                        "orthographic camera must have a positive size".to_string(),
+                    ));
+                }
+                Box::new(Orthographic::new(self.near, self.far, half_extent))
+            }
+            (None, None) => {
+                if !(self.fov > 0.0 && self.fov < 180.0) {
+                    return Err(SceneFileError::Invalid(
+                        "camera field of view must be between 0 and 180 degrees".to_string(),
+                    ));
+                }
+                Box::new(Perspective::new(
+                    self.near,
+                    self.far,
+                    PlanarAngle::Degrees(self.fov),
+                ))
+            }
+        };
+
+        let camera_to_world = match (self.eye, self.target) {
+            (Some(eye), Some(target)) => {
+                Matrix4x4::look_at(&point(eye), &point(target), &vector(self.up)).ok_or_else(
+                    || {
+                        SceneFileError::Invalid(
+                            "camera eye and target must differ, and not be along up".to_string(),
+                        )
+                    },
+                )?
+            }
+            (None, None) => Matrix4x4::identity(),
+            _ => {
+                return Err(SceneFileError::Invalid(
+                    "camera needs both an eye and target, or neither".to_string(),
+                ))
+            }
+        };
+        let mut camera = Camera::new_with_transform(&film, projection.as_ref(), camera_to_world);
+
+        if self.aperture != 0.0 {
+            let focal_distance = match (self.focal_distance, self.eye, self.target) {
+                (Some(distance), _, _) => distance,
+                (None, Some(eye), Some(target)) => point(eye).distance_to(point(target)),
+                _ => {
+                    return Err(SceneFileError::Invalid(
+                        "camera with an aperture needs a focal distance or target".to_string(),
+                    ))
+                }
+            };
+            if !(self.aperture > 0.0 && focal_distance > 0.0) {
+                return Err(SceneFileError::Invalid(
+                    "camera aperture and focal distance must be positive".to_string(),
+                ));
+            }
+            camera.set_thin_lens(self.aperture, focal_distance);
+        }
+
+        let (open, close) = self.shutter;
+        if !(open <= close) {
+            return Err(SceneFileError::Invalid(
+                "camera shutter cannot close before it opens".to_string(),
+            ));
+        }
+        camera.set_shutter(open, close);
+        Ok(camera)
+    }
+}
+
+impl LightDescription {
+    /// # Arguments
+    /// * `base_directory` - directory the paths of texture images are relative to
+    /// * `images` - images already loaded, by path, so lights and materials share them
+    fn build(
+        &self,
+        base_directory: &Path,
+        images: &mut HashMap<PathBuf, ImageTexture>,
+    ) -> Result<BuiltLight, SceneFileError> {
+        Ok(match *self {
+            LightDescription::Directional {
+                direction,
+                radiance,
+                angular_radius,
+            } => {
+                if vector(direction).length() == 0.0 {
+                    return Err(SceneFileError::Invalid(
+                        "directional light needs a direction".to_string(),
+                    ));
+                }
+                if !(0.0 <= angular_radius && angular_radius < 90.0) {
+                    return Err(SceneFileError::Invalid(
+                        "directional light angular radius must be at least 0 and less than 90"
+                            .to_string(),
+                    ));
+                }
+                BuiltLight::NonArea(Box::new(DirectionalLight::new_with_angular_radius(
+                    &vector(direction),
+                    &spectrum(radiance),
+                    PlanarAngle::Degrees(angular_radius),
+                )))
+            }
+            LightDescription::Point {
+                position,
+                intensity,
+                attenuation,
+            } => BuiltLight::NonArea(Box::new(PointLight::new_with_attenuation(
+                point(position),
+                spectrum(intensity),
+                attenuation.build()?,
+            ))),
+            LightDescription::Spot {
+                position,
+                direction,
+                intensity,
+                inner,
+                outer,
+                falloff,
+            } => {
+                if vector(direction).length() == 0.0 {
+                    return Err(SceneFileError::Invalid(
+                        "spot light needs a direction".to_string(),
+                    ));
+                }
+                if !(0.0 <= inner && inner <= outer && outer <= 180.0) {
+                    return Err(SceneFileError::Invalid(
+                        "spot light cone angles must satisfy 0 <= inner <= outer <= 180"
+                            .to_string(),
+                    ));
+                }
+                if falloff <= 0.0 {
+                    return Err(SceneFileError::Invalid(
+                        "spot light falloff must be positive".to_string(),
+                    ));
+                }
+                BuiltLight::NonArea(Box::new(SpotLight::new(
+                    point(position),
+                    &vector(direction),
+                    spectrum(intensity),
+                    PlanarAngle::Degrees(inner),
+                    PlanarAngle::Degrees(outer),
+                    falloff,
+                )))
+            }
+            LightDescription::Sphere {
+                center,
+                radius,
+                radiance,
+                ref texture,
+                ref profile,
+            } => {
+                if !(radius > 0.0) {
+                    return Err(SceneFileError::Invalid(
+                        "sphere light radius must be positive".to_string(),
+                    ));
+                }
+                BuiltLight::Area(Box::new(
+                    SphereLight::new(point(center), radius, &spectrum(radiance))
+                        .with_emission(emission(texture, profile, base_directory, images)?),
+                ))
+            }
+            LightDescription::Rectangle {
+                corner,
+                edge_u,
+                edge_v,
+                radiance,
+                ref texture,
+                ref profile,
+            } => {
+                let (u, v) = (vector(edge_u), vector(edge_v));
+                if u.length() == 0.0 || v.length() == 0.0 {
+                    return Err(SceneFileError::Invalid(
+                        "rectangle light edges must have a length".to_string(),
+                    ));
+                }
+                if u.dot(&v).abs() > 1e-4 * u.length() * v.length() {
+                    return Err(SceneFileError::Invalid(
+                        "rectangle light edges must be perpendicular".to_string(),
+                    ));
+                }
+                BuiltLight::Area(Box::new(
+                    RectangleLight::new(point(corner), u, v, &spectrum(radiance))
+                        .with_emission(emission(texture, profile, base_directory, images)?),
+                ))
+            }
+            LightDescription::Disk {
+                center,
+                normal,
+                radius,
+                radiance,
+                ref texture,
+                ref profile,
+            } => {
+                if vector(normal).length() == 0.0 {
+                    return Err(SceneFileError::Invalid(
+                        "disk light needs a normal".to_string(),
+                    ));
+                }
+                if !(radius > 0.0) {
+                    return Err(SceneFileError::Invalid(
+                        "disk light radius must be positive".to_string(),
+                    ));
+                }
+                BuiltLight::Area(Box::new(
+                    DiskLight::new(point(center), &vector(normal), radius, &spectrum(radiance))
+                        .with_emission(emission(texture, profile, base_directory, images)?),
+                ))
+            }
+        })
+    }
+
+    /// The texture describing how an area light varies across it, if any.
+    fn texture(&self) -> Option<&TextureDescription> {
+        match *self {
+            LightDescription::Sphere { ref texture, .. } |
+            LightDescription::Rectangle { ref texture, .. } |
+            LightDescription::Disk { ref texture, .. } => texture.as_ref(),
+            LightDescription::Directional { .. } |
+            LightDescription::Point { .. } |
+            LightDescription::Spot { .. } => None,
+        }
+    }
+
+    /// Adds the paths of the light's texture images to `paths`.
+    fn image_paths(&self, paths: &mut Vec<String>) {
+        if let Some(texture) = self.texture() {
+            texture.image_paths(paths);
+        }
+    }
+
+    /// Makes the paths of the light's texture images relative to another directory.
+    fn relative_to(&mut self, base_directory: &Path) {
+        match *self {
+            LightDescription::Sphere { ref mut texture, .. } |
+            LightDescription::Rectangle { ref mut texture, .. } |
+            LightDescription::Disk { ref mut texture, .. } => {
+                for texture in texture.iter_mut() {
+                    texture.relative_to(base_directory);
+                }
+            }
+            LightDescription::Directional { .. } |
+            LightDescription::Point { .. } |
+            LightDescription::Spot { .. } => {}
+        }
+    }
+}
+
+/// Creates how an area light varies across it and with the angle light leaves at.
+fn emission(
+    texture: &Option<TextureDescription>,
+    profile: &[f32],
+    base_directory: &Path,
+    images: &mut HashMap<PathBuf, ImageTexture>,
+) -> Result<Emission, SceneFileError> {
+    if profile.iter().any(|i| !(*i >= 0.0)) {
+        return Err(SceneFileError::Invalid(
+            "light profile intensities must not be negative".to_string(),
+        ));
+    }
+    let emission = Emission::new().with_profile(profile.to_vec());
+    Ok(match *texture {
+        Some(ref texture) => emission.with_texture(texture.build(base_directory, images)?),
+        None => emission,
+    })
+}
+
+impl MaterialDescription {
+    /// # Arguments
+    /// * `base_directory` - directory the paths of texture images are relative to
+    /// * `images` - images already loaded, by path, so materials share them
+    fn build(
+        &self,
+        base_directory: &Path,
+        images: &mut HashMap<PathBuf, ImageTexture>,
+    ) -> Result<Box<Material>, SceneFileError> {
+        Ok(match *self {
+            MaterialDescription::Lambertian {
+                diffuse,
+                ref texture,
+                ref normal_map,
+            } => {
+                let mut material = match *texture {
+                    Some(ref texture) => {
+                        LambertianMaterial::new_with_texture(texture.build(base_directory, images)?)
+                    }
+                    None => LambertianMaterial::new(&spectrum(diffuse)),
+                };
+                if let Some(ref normal_map) = *normal_map {
+                    material.set_normal_map(Some(
+                        NormalMap::new(normal_map.build(base_directory, images)?),
+                    ));
+                }
+                Box::new(material)
+            }
+            MaterialDescription::OrenNayar { diffuse, sigma } => {
+                if sigma < 0.0 {
+                    return Err(SceneFileError::Invalid(
+                        "Oren-Nayar roughness cannot be negative".to_string(),
+                    ));
+                }
+                Box::new(OrenNayarMaterial::new(
+                    &spectrum(diffuse),
+                    PlanarAngle::Degrees(sigma),
+                ))
+            }
+            MaterialDescription::Sheen {
+                diffuse,
+                sheen,
+                roughness,
+            } => {
+                if !(roughness >= 0.0 && roughness <= 1.0) {
+                    return Err(SceneFileError::Invalid(
+                        "sheen roughness must be between 0 and 1".to_string(),
+                    ));
+                }
+                Box::new(SheenMaterial::new(
+                    &spectrum(diffuse),
+                    &spectrum(sheen),
+                    roughness,
+                ))
+            }
+            MaterialDescription::BlinnPhong {
+                diffuse,
+                specular,
+                shininess,
+            } => {
+                if !(shininess >= 0.0) {
+                    return Err(SceneFileError::Invalid(
+                        "Blinn-Phong shininess must not be negative".to_string(),
+                    ));
+                }
+                Box::new(BlinnPhongMaterial::new(
+                    &spectrum(diffuse),
+                    &spectrum(specular),
+                    shininess,
+                ))
+            }
+            MaterialDescription::Microfacet {
+                base_color,
+                roughness,
+                metalness,
+            } => {
+                if !(roughness >= 0.0 && roughness <= 1.0 && metalness >= 0.0 &&
+                         metalness <= 1.0)
+                {
+                    return Err(SceneFileError::Invalid(
+                        "microfacet roughness and metalness must be between 0 and 1".to_string(),
+                    ));
+                }
+                Box::new(MicrofacetMaterial::new(
+                    &spectrum(base_color),
+                    roughness,
+                    metalness,
+                ))
+            }
+            MaterialDescription::Emissive { radiance } => {
+                Box::new(EmissiveMaterial::new(&spectrum(radiance)))
+            }
+            MaterialDescription::Specular { reflectance } => {
+                Box::new(SpecularMaterial::new(&spectrum(reflectance)))
+            }
+            MaterialDescription::Dielectric {
+                index_of_refraction,
+                priority,
+                absorption,
+                roughness,
+                ref roughness_texture,
+            } => {
+                if index_of_refraction <= 0.0 {
+                    return Err(SceneFileError::Invalid(
+                        "index of refraction must be positive".to_string(),
+                    ));
+                }
+                if absorption.0 < 0.0 || absorption.1 < 0.0 || absorption.2 < 0.0 {
+                    return Err(SceneFileError::Invalid(
+                        "absorption must not be negative".to_string(),
+                    ));
+                }
+                if !(roughness >= 0.0 && roughness <= 1.0) {
+                    return Err(SceneFileError::Invalid(
+                        "dielectric roughness must be between 0 and 1".to_string(),
+                    ));
+                }
+                let material = DielectricMaterial::new(index_of_refraction)
+                    .with_priority(priority)
+                    .with_absorption(&spectrum(absorption))
+                    .with_roughness(roughness);
+                Box::new(match *roughness_texture {
+                    Some(ref texture) => {
+                        material.with_roughness_texture(texture.build(base_directory, images)?)
+                    }
+                    None => material,
+                })
+            }
+        })
+    }
+
+    /// Adds the paths of the texture, normal map and roughness images of the material to
+    /// `paths`.
+    fn image_paths(&self, paths: &mut Vec<String>) {
+        match *self {
+            MaterialDescription::Lambertian {
+                ref texture,
+                ref normal_map,
+                ..
+            } => {
+                for texture in texture.iter().chain(normal_map.iter()) {
+                    texture.image_paths(paths);
+                }
+            }
+            MaterialDescription::Dielectric { ref roughness_texture, .. } => {
+                for texture in roughness_texture.iter() {
+                    texture.image_paths(paths);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Makes the paths of texture, normal map and roughness images relative to another
+    /// directory.
+    fn relative_to(&mut self, base_directory: &Path) {
+        match *self {
+            MaterialDescription::Lambertian {
+                ref mut texture,
+                ref mut normal_map,
+                ..
+            } => {
+                for texture in texture.iter_mut().chain(normal_map.iter_mut()) {
+                    texture.relative_to(base_directory);
+                }
+            }
+            MaterialDescription::Dielectric { ref mut roughness_texture, .. } => {
+                for texture in roughness_texture.iter_mut() {
+                    texture.relative_to(base_directory);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl TextureDescription {
+    fn build(
+        &self,
+        base_directory: &Path,
+        images: &mut HashMap<PathBuf, ImageTexture>,
+    ) -> Result<Box<Texture>, SceneFileError> {
+        Ok(match *self {
+            TextureDescription::Constant { color } => {
+                Box::new(ConstantTexture::new(&spectrum(color)))
+            }
+            TextureDescription::Image { ref path } => {
+                Box::new(load_image(base_directory, path, images)?.clone())
+            }
+            TextureDescription::Checker {
+                ref even,
+                ref odd,
+                cells,
+            } => {
+                if !(cells > 0.0) {
+                    return Err(SceneFileError::Invalid(
+                        "checker texture cells must be positive".to_string(),
+                    ));
+                }
+                Box::new(CheckerTexture::new(
+                    even.build(base_directory, images)?,
+                    odd.build(base_directory, images)?,
+                    cells,
+                ))
+            }
+            TextureDescription::Marble {
+                base,
+                vein,
+                scale,
+                seed,
+            } => {
+                if !(scale > 0.0) {
+                    return Err(SceneFileError::Invalid(
+                        "marble scale must be positive".to_string(),
+                    ));
+                }
+                Box::new(MarbleTexture::new(&spectrum(base), &spectrum(vein), scale, seed))
+            }
+            TextureDescription::Wood {
+                light,
+                dark,
+                rings,
+                seed,
+            } => {
+                if !(rings > 0.0) {
+                    return Err(SceneFileError::Invalid(
+                        "wood rings must be positive".to_string(),
+                    ));
+                }
+                Box::new(WoodTexture::new(&spectrum(light), &spectrum(dark), rings, seed))
+            }
+        })
+    }
+
+    fn relative_to(&mut self, base_directory: &Path) {
+        match *self {
+            TextureDescription::Image { ref mut path } => {
+                *path = base_directory.join(&*path).to_string_lossy().into_owned();
+            }
+            TextureDescription::Checker {
+                ref mut even,
+                ref mut odd,
+                ..
+            } => {
+                even.relative_to(base_directory);
+                odd.relative_to(base_directory);
+            }
+            TextureDescription::Constant { .. } |
+            TextureDescription::Marble { .. } |
+            TextureDescription::Wood { .. } => {}
+        }
+    }
+
+    /// Adds the paths of the images making up the texture to `paths`.
+    fn image_paths(&self, paths: &mut Vec<String>) {
+        match *self {
+            TextureDescription::Image { ref path } => paths.push(path.clone()),
+            TextureDescription::Checker {
+                ref even,
+                ref odd,
+                ..
+            } => {
+                even.image_paths(paths);
+                odd.image_paths(paths);
+            }
+            TextureDescription::Constant { .. } |
+            TextureDescription::Marble { .. } |
+            TextureDescription::Wood { .. } => {}
+        }
+    }
+}
+
+/// Loads an image texture, unless it already has been.
+///
+/// # Arguments
+/// * `images` - images already loaded, by path, which the image is added to
+fn load_image<'a>(
+    base_directory: &Path,
+    path: &str,
+    images: &'a mut HashMap<PathBuf, ImageTexture>,
+) -> Result<&'a ImageTexture, SceneFileError> {
+    let full_path = base_directory.join(path);
+    if !images.contains_key(&full_path) {
+        let image = ImageTexture::open(&full_path)
+            .map_err(|e| SceneFileError::Texture(path.to_string(), e))?;
+        images.insert(full_path.clone(), image);
+    }
+    Ok(&images[&full_path])
+}
+
+impl PortalDescription {
+    fn build(&self) -> Result<Portal, SceneFileError> {
+        let (u, v) = (vector(self.edge_u), vector(self.edge_v));
+        if u.length() == 0.0 || v.length() == 0.0 {
+            return Err(SceneFileError::Invalid(
+                "portal edges must have a length".to_string(),
+            ));
+        }
+        if u.dot(&v).abs() > 1e-4 * u.length() * v.length() {
+            return Err(SceneFileError::Invalid(
+                "portal edges must be perpendicular".to_string(),
+            ));
+        }
+        Ok(Portal::new(point(self.corner), u, v))
+    }
+}
+
+impl EnvironmentDescription {
+    fn build(&self, base_directory: &Path) -> Result<EnvironmentLight, SceneFileError> {
+        match *self {
+            EnvironmentDescription::Constant { radiance } => {
+                Ok(EnvironmentLight::constant(&spectrum(radiance)))
+            }
+            EnvironmentDescription::Image { ref path } => {
+                EnvironmentLight::load(base_directory.join(path))
+                    .map_err(|e| SceneFileError::Environment(path.clone(), e))
+            }
+        }
+    }
+}
+
+/// Combines the steps of a transform, in order.
+fn build_transform(steps: &[TransformDescription]) -> Result<Transform, SceneFileError> {
+    let mut transform = Transform::identity();
+    for step in steps.iter() {
+        transform = transform.compose(&step.build()?);
+    }
+    Ok(transform)
+}
+
+impl TransformDescription {
+    fn build(&self) -> Result<Transform, SceneFileError> {
+        match *self {
+            TransformDescription::Translate(x, y, z) => Ok(Transform::translate(x, y, z)),
+            TransformDescription::Scale(x, y, z) => {
+                if x == 0.0 || y == 0.0 || z == 0.0 {
+                    return Err(SceneFileError::Invalid(
+                        "entities cannot be scaled by zero".to_string(),
+                    ));
+                }
+                Ok(Transform::scale(x, y, z))
+            }
+            TransformDescription::RotateX(degrees) => {
+                Ok(Transform::rotate_x(PlanarAngle::Degrees(degrees)))
+            }
+            TransformDescription::RotateY(degrees) => {
+                Ok(Transform::rotate_y(PlanarAngle::Degrees(degrees)))
+            }
+            TransformDescription::RotateZ(degrees) => {
+                Ok(Transform::rotate_z(PlanarAngle::Degrees(degrees)))
+            }
+            TransformDescription::Rotate { axis, degrees } => {
+                Transform::rotate_axis_angle(&vector(axis), PlanarAngle::Degrees(degrees))
+                    .ok_or_else(|| {
+                        SceneFileError::Invalid("rotation axis must have a length".to_string())
+                    })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use math::Ray;
+    use std::io::Write;
+    use std::path::Path;
+
+    fn single_sphere() -> SceneDescription {
+        let mut materials = HashMap::new();
+        materials.insert(
+            "white".to_string(),
+            MaterialDescription::Lambertian {
+                diffuse: (1.0, 1.0, 1.0),
+                texture: None,
+                normal_map: None,
+            },
+        );
+        SceneDescription {
+            version: SCENE_FORMAT_VERSION,
+            include: Vec::new(),
+            camera: CameraDescription {
+                width: 40,
+                height: 30,
+                fov: default_fov(),
+                orthographic: None,
+                panorama: None,
+                near: default_near(),
+                far: default_far(),
+                eye: Some((0.0, 0.0, -10.0)),
+                target: Some((0.0, 0.0, 0.0)),
+                up: default_up(),
+                aperture: 0.0,
+                focal_distance: None,
+                shutter: (0.0, 0.0),
+            },
+            lights: vec![
+                LightDescription::Point {
+                    position: (0.0, 0.0, -10.0),
+                    intensity: (1.0, 1.0, 1.0),
+                    attenuation: AttenuationDescription::Quadratic,
+                },
+            ],
+            rigs: Vec::new(),
+            materials: materials,
+            entities: vec![
+                EntityDescription {
+                    solid: SolidDescription::Sphere { radius: 1.0 },
+                    material: "white".to_string(),
+                    transform: vec![TransformDescription::Translate(0.0, 0.0, 5.0)],
+                    visibility: RayVisibilityDescription::default(),
+                    motion: Vec::new(),
+                    keyframes: Vec::new(),
+                },
+            ],
+            prototypes: HashMap::new(),
+            instances: Vec::new(),
+            scatter: Vec::new(),
+            environment: None,
+            portals: Vec::new(),
+            pack_textures: false,
+            files: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_build() {
+        let loaded = single_sphere().build(Path::new("")).unwrap();
+        assert_eq!(loaded.camera.raster_width(), 40);
+
+        // The camera looks at the sphere.
+        let center_ray = loaded.camera.generate_ray(20, 15);
+        assert!(loaded.scene.hits(&center_ray));
+        let corner_ray = loaded.camera.generate_ray(0, 0);
+        assert!(!loaded.scene.hits(&corner_ray));
+
+        let behind_sphere = Ray {
+            origin: Point::new(0.0, 0.0, 3.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        assert!(loaded.scene.hits(&behind_sphere));
+    }
+
+    #[test]
+    fn test_orthographic_camera() {
+        let mut description = single_sphere();
+        description.camera.orthographic = Some(2.0);
+        let loaded = description.build(Path::new("")).unwrap();
+        assert_relative_eq!(
+            loaded.camera.generate_ray(0, 0).direction,
+            loaded.camera.generate_ray(40, 30).direction
+        );
+    }
+
+    #[test]
+    fn test_panoramic_camera() {
+        let mut description = single_sphere();
+        description.camera.panorama = Some(PanoramaDescription::Equirectangular);
+        let loaded = description.build(Path::new("")).unwrap();
+        assert!(loaded.camera.panorama().is_some());
+        // The left edge of the film looks away from the target.
+        assert_relative_eq!(
+            loaded.camera.generate_ray(0, 15).direction,
+            Vector::new(0.0, 0.0, -1.0),
+            epsilon = 1e-5
+        );
+
+        description.camera.panorama = Some(PanoramaDescription::Fisheye);
+        description.camera.fov = 270.0;
+        assert!(description.build(Path::new("")).is_ok());
+        description.camera.orthographic = Some(2.0);
+        assert!(description.build(Path::new("")).is_err());
+    }
+
+    #[test]
+    fn test_motion_blur() {
+        let mut description = single_sphere();
+        description.entities[0].motion = vec![TransformDescription::Translate(0.0, 100.0, 0.0)];
+        let center = Ray {
+            origin: Point::new(0.0, 0.0, -10.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+
+        // With the shutter closed instantly, entities do not move.
+        let loaded = description.build(Path::new("")).unwrap();
+        assert!(!loaded.camera.has_motion_blur());
+        assert!(loaded.scene.cast_ray(&Ray { time: 1.0, ..center }).is_some());
+
+        description.camera.shutter = (0.0, 1.0);
+        let loaded = description.build(Path::new("")).unwrap();
+        assert!(loaded.camera.has_motion_blur());
+        assert!(loaded.scene.cast_ray(&center).is_some());
+        assert!(loaded.scene.cast_ray(&Ray { time: 1.0, ..center }).is_none());
+
+        description.camera.shutter = (1.0, 0.0);
+        assert!(description.build(Path::new("")).is_err());
+    }
+
+    #[test]
+    fn test_keyframes() {
+        let mut description = single_sphere();
+        let keyframe = |time: f32, y: f32| {
+            KeyframeDescription {
+                time: time,
+                transform: vec![TransformDescription::Translate(0.0, y, 0.0)],
+            }
+        };
+        // Away and back again while the shutter is open.
+        description.entities[0].keyframes =
+            vec![keyframe(0.0, 0.0), keyframe(0.5, 100.0), keyframe(1.0, 0.0)];
+        description.camera.shutter = (0.0, 1.0);
+        let center = |time: f32| {
+            Ray {
+                origin: Point::new(0.0, 0.0, -10.0),
+                direction: Vector::new(0.0, 0.0, 1.0),
+                time: time,
+            }
+        };
+        let loaded = description.build(Path::new("")).unwrap();
+        assert!(loaded.scene.cast_ray(&center(0.0)).is_some());
+        assert!(loaded.scene.cast_ray(&center(0.5)).is_none());
+        assert!(loaded.scene.cast_ray(&center(1.0)).is_some());
+
+        description.entities[0].keyframes.reverse();
+        description.entities[0].keyframes[0].time = 2.0;
+        assert!(description.build(Path::new("")).is_err());
+
+        description.entities[0].keyframes = vec![keyframe(0.0, 0.0)];
+        description.entities[0].motion = vec![TransformDescription::Translate(0.0, 1.0, 0.0)];
+        assert!(description.build(Path::new("")).is_err());
+    }
+
+    #[test]
+    fn test_depth_of_field() {
+        let mut description = single_sphere();
+        assert!(!description.build(Path::new("")).unwrap().camera.has_lens());
+
+        // Focused on the target by default.
+        description.camera.aperture = 0.5;
+        let camera = description.build(Path::new("")).unwrap().camera;
+        assert!(camera.has_lens());
+        let ray = camera.generate_ray_through_lens(20.0, 15.0, (1.0, 0.5));
+        assert_relative_eq!(
+            ray.at(10.0 / ray.direction.z),
+            Point::new(0.0, 0.0, 0.0),
+            epsilon = 1e-4
+        );
+
+        description.camera.aperture = -1.0;
+        assert!(description.build(Path::new("")).is_err());
+    }
+
+    #[test]
+    fn test_constant_environment() {
+        let mut description = single_sphere();
+        description.environment = Some(EnvironmentDescription::Constant {
+            radiance: (0.5, 0.5, 1.0),
+        });
+        let loaded = description.build(Path::new("")).unwrap();
+        let sky = loaded.camera.generate_ray(0, 0);
+        assert_relative_eq!(loaded.scene.trace(&sky), Spectrum::new(0.5, 0.5, 1.0));
+    }
+
+    #[test]
+    fn test_portals() {
+        let mut description = single_sphere();
+        description.environment = Some(EnvironmentDescription::Constant {
+            radiance: (1.0, 1.0, 1.0),
+        });
+        description.portals.push(PortalDescription {
+            corner: (-1.0, 10.0, -1.0),
+            edge_u: (2.0, 0.0, 0.0),
+            edge_v: (0.0, 0.0, 2.0),
+        });
+        let loaded = description.build(Path::new("")).unwrap();
+        assert!(loaded.scene.dump().contains("portal 0: portal from"));
+
+        description.portals[0].edge_v = (1.0, 0.0, 2.0);
+        match description.build(Path::new("")) {
+            Err(SceneFileError::Invalid(_)) => {}
+            _ => panic!("Expected skewed portal edges to be reported."),
+        }
+    }
+
+    #[test]
+    fn test_textured_material() {
+        let constant = |color| Box::new(TextureDescription::Constant { color: color });
+        let checker = MaterialDescription::Lambertian {
+            diffuse: (0.0, 0.0, 0.0),
+            texture: Some(TextureDescription::Checker {
+                even: constant((1.0, 0.0, 0.0)),
+                odd: constant((0.0, 0.0, 1.0)),
+                cells: 2.0,
+            }),
+            normal_map: None,
+        };
+        let material = checker.build(Path::new(""), &mut HashMap::new()).unwrap();
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let origin = Point::new(0.0, 0.0, 0.0);
+        assert_relative_eq!(
+            material.f_at(&normal, &normal, &normal, (0.25, 0.25), &origin),
+            Spectrum::new(1.0, 0.0, 0.0)
+        );
+        assert_relative_eq!(
+            material.f_at(&normal, &normal, &normal, (0.75, 0.25), &origin),
+            Spectrum::new(0.0, 0.0, 1.0)
+        );
+
+        // Images in libraries are found relative to the library.
+        let mut image = MaterialDescription::Lambertian {
+            diffuse: (0.0, 0.0, 0.0),
+            texture: Some(TextureDescription::Image { path: "wood.png".to_string() }),
+            normal_map: Some(TextureDescription::Image { path: "bumps.png".to_string() }),
+        };
+        image.relative_to(Path::new("textures"));
+        match image {
+            MaterialDescription::Lambertian {
+                texture: Some(TextureDescription::Image { ref path }), ..
+            } => assert_eq!(Path::new(path), Path::new("textures").join("wood.png")),
+            _ => panic!("Expected the texture to be kept."),
+        }
+        match image {
+            MaterialDescription::Lambertian {
+                normal_map: Some(TextureDescription::Image { ref path }), ..
+            } => assert_eq!(Path::new(path), Path::new("textures").join("bumps.png")),
+            _ => panic!("Expected the normal map to be kept."),
+        }
+
+        let mut frosted = MaterialDescription::Dielectric {
+            index_of_refraction: 1.5,
+            priority: 0,
+            absorption: (0.0, 0.0, 0.0),
+            roughness: 0.0,
+            roughness_texture: Some(TextureDescription::Image { path: "frost.png".to_string() }),
+        };
+        frosted.relative_to(Path::new("textures"));
+        let mut paths = Vec::new();
+        frosted.image_paths(&mut paths);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(Path::new(&paths[0]), Path::new("textures").join("frost.png"));
+    }
+
+    #[test]
+    fn test_textured_light() {
+        // A disk facing down, tinted red and shining only straight down.
+        let mut description = LightDescription::Disk {
+            center: (0.0, 2.0, 0.0),
+            normal: (0.0, -1.0, 0.0),
+            radius: 1.0,
+            radiance: (2.0, 2.0, 2.0),
+            texture: Some(TextureDescription::Constant { color: (1.0, 0.0, 0.0) }),
+            profile: vec![1.0, 0.0],
+        };
+        let light = match description.build(Path::new(""), &mut HashMap::new()).unwrap() {
+            BuiltLight::Area(light) => light,
+            BuiltLight::NonArea(_) => panic!("Expected a disk to be an area light."),
+        };
+        let below = light.sample(&Point::new(0.0, 0.0, 0.0), (0.5, 0.5)).unwrap();
+        assert_relative_eq!(below.radiance, Spectrum::new(2.0, 0.0, 0.0), epsilon = 1e-5);
+        let aside = light.sample(&Point::new(2.0, 0.0, 0.0), (0.5, 0.5)).unwrap();
+        assert_relative_eq!(aside.radiance, Spectrum::new(1.0, 0.0, 0.0), epsilon = 1e-4);
+
+        if let LightDescription::Disk { ref mut profile, .. } = description {
+            profile[1] = -1.0;
+        }
+        match description.build(Path::new(""), &mut HashMap::new()) {
+            Err(SceneFileError::Invalid(_)) => {}
+            _ => panic!("Expected a negative profile to be reported."),
+        }
+
+        // Images in libraries are found relative to the library.
+        let mut image = LightDescription::Rectangle {
+            corner: (0.0, 5.0, 0.0),
+            edge_u: (1.0, 0.0, 0.0),
+            edge_v: (0.0, 0.0, 1.0),
+            radiance: (1.0, 1.0, 1.0),
+            texture: Some(TextureDescription::Image { path: "glass.png".to_string() }),
+            profile: Vec::new(),
+        };
+        image.relative_to(Path::new("windows"));
+        let mut paths = Vec::new();
+        image.image_paths(&mut paths);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(Path::new(&paths[0]), Path::new("windows").join("glass.png"));
+    }
+
+    #[test]
+    fn test_merge_library() {
+        let mut materials = HashMap::new();
+        materials.insert(
+            "white".to_string(),
+            MaterialDescription::Specular { reflectance: (1.0, 1.0, 1.0) },
+        );
+        materials.insert(
+            "glass".to_string(),
+            MaterialDescription::Dielectric {
+                index_of_refraction: 1.5,
+                priority: 0,
+                absorption: (0.0, 0.0, 0.0),
+                roughness: 0.0,
+                roughness_texture: None,
+            },
+        );
+        let mut library = LibraryDescription {
+            include: Vec::new(),
+            lights: Vec::new(),
+            rigs: Vec::new(),
+            materials: materials,
+            entities: vec![
+                EntityDescription {
+                    solid: SolidDescription::Obj { path: "chair.obj".to_string() },
+                    material: "glass".to_string(),
+                    transform: Vec::new(),
+                    visibility: RayVisibilityDescription::default(),
+                    motion: Vec::new(),
+                    keyframes: Vec::new(),
+                },
+            ],
+            prototypes: HashMap::new(),
+            instances: Vec::new(),
+            scatter: Vec::new(),
+        };
+        library.relative_to(Path::new("furniture"));
+
+        let mut description = single_sphere();
+        description.merge(vec![library]);
+        assert_eq!(description.entities.len(), 2);
+        match description.entities[0].solid {
+            SolidDescription::Obj { ref path } => {
+                assert_eq!(Path::new(path), Path::new("furniture").join("chair.obj"))
+            }
+            _ => panic!("Expected the library's mesh first."),
+        }
+
+        // The scene's own material is kept.
+        match description.materials["white"] {
+            MaterialDescription::Lambertian { .. } => {}
+            _ => panic!("Expected the scene's material to replace the library's."),
+        }
+        assert!(description.materials.contains_key("glass"));
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let directory = ::std::env::temp_dir().join("rrt_test_include_cycle");
+        ::std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("library.ron");
+        File::create(&path).unwrap().write_all(b"()").unwrap();
+        let canonical = path.canonicalize().unwrap();
+
+        // A library being read includes itself.
+        let mut libraries = Vec::new();
+        match load_library(
+            &directory,
+            Path::new("library.ron"),
+            &mut vec![canonical.clone()],
+            &mut HashSet::new(),
+            &mut libraries,
+        ) {
+            Err(SceneFileError::Invalid(ref message)) => assert!(message.contains("cycle")),
+            _ => panic!("Expected an include cycle to be reported."),
+        }
+
+        // A library which was already read is not added again.
+        let mut included = HashSet::new();
+        included.insert(canonical);
+        load_library(
+            &directory,
+            Path::new("library.ron"),
+            &mut Vec::new(),
+            &mut included,
+            &mut libraries,
+        ).unwrap();
+        assert!(libraries.is_empty());
+    }
+
+    #[test]
+    fn test_load_includes() {
+        // Loaded by a path relative to the working directory, as from the command line.
+        let directory = Path::new("target").join("rrt_test_load_includes");
+        let library_directory = directory.join("scenes").join("lib");
+        ::std::fs::create_dir_all(&library_directory).unwrap();
+        let write = |path: PathBuf, contents: &str| {
+            File::create(path).unwrap().write_all(contents.as_bytes()).unwrap();
+        };
+        write(
+            directory.join("scenes").join("room.ron"),
+            r#"(
+                version: 3,
+                include: ["lib/chairs.ron"],
+                camera: (width: 4, height: 4),
+                lights: [Directional(direction: (0.0, -1.0, 0.0), radiance: (3.0, 3.0, 3.0))],
+            )"#,
+        );
+        write(
+            library_directory.join("chairs.ron"),
+            r#"(
+                include: ["materials.ron"],
+                lights: [Directional(direction: (0.0, -1.0, 0.0), radiance: (2.0, 2.0, 2.0))],
+                materials: {"white": Lambertian(diffuse: (1.0, 1.0, 1.0))},
+                entities: [(solid: Obj(path: "chair.obj"), material: "white")],
+            )"#,
+        );
+        write(
+            library_directory.join("materials.ron"),
+            r#"(
+                lights: [Directional(direction: (0.0, -1.0, 0.0), radiance: (1.0, 1.0, 1.0))],
+                materials: {
+                    "white": Specular(reflectance: (1.0, 1.0, 1.0)),
+                    "red": Lambertian(diffuse: (1.0, 0.0, 0.0)),
+                },
+            )"#,
+        );
+        write(
+            library_directory.join("chair.obj"),
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n",
+        );
+
+        let room = directory.join("scenes").join("room.ron");
+        assert!(room.is_relative());
+        let loaded = load(&room).unwrap();
+        assert_eq!(loaded.scene.entity_bounds().len(), 1);
+
+        // Every file read is listed, libraries after the scene in the order they were merged.
+        let files: Vec<PathBuf> = vec![
+            room.clone(),
+            library_directory.join("materials.ron"),
+            library_directory.join("chairs.ron"),
+            library_directory.join("chair.obj"),
+        ];
+        assert_eq!(loaded.files, files);
+
+        // Lights come in the order they were included, and including files replace materials.
+        let (description, _) = read(&room).unwrap();
+        let radiance: Vec<f32> = description
+            .lights
+            .iter()
+            .map(|light| match *light {
+                LightDescription::Directional { radiance, .. } => radiance.0,
+                _ => panic!("Expected only directional lights."),
+            })
+            .collect();
+        assert_eq!(radiance, vec![1.0, 2.0, 3.0]);
+        match description.materials["white"] {
+            MaterialDescription::Lambertian { .. } => {}
+            _ => panic!("Expected the including library's material to replace the included."),
+        }
+        assert!(description.materials.contains_key("red"));
+    }
+
+    #[test]
+    fn test_instances() {
+        let mut description = single_sphere();
+        description.materials.insert(
+            "mirror".to_string(),
+            MaterialDescription::Specular { reflectance: (1.0, 1.0, 1.0) },
+        );
+        description.prototypes.insert(
+            "pair".to_string(),
+            vec![
+                EntityDescription {
+                    solid: SolidDescription::Sphere { radius: 1.0 },
+                    material: "white".to_string(),
+                    transform: vec![TransformDescription::Translate(-1.0, 0.0, 0.0)],
+                    visibility: RayVisibilityDescription::default(),
+                    motion: Vec::new(),
+                    keyframes: Vec::new(),
+                },
+                EntityDescription {
+                    solid: SolidDescription::Cuboid { size: (1.0, 1.0, 1.0) },
+                    material: "white".to_string(),
+                    transform: vec![TransformDescription::Translate(1.0, 0.0, 0.0)],
+                    visibility: RayVisibilityDescription::default(),
+                    motion: Vec::new(),
+                    keyframes: Vec::new(),
+                },
+            ],
+        );
+        let mut materials = HashMap::new();
+        materials.insert("white".to_string(), "mirror".to_string());
+        description.instances = vec![
+            InstanceDescription {
+                prototype: "pair".to_string(),
+                materials: HashMap::new(),
+                transform: vec![TransformDescription::Translate(0.0, 10.0, 0.0)],
+            },
+            InstanceDescription {
+                prototype: "pair".to_string(),
+                materials: materials,
+                transform: vec![TransformDescription::Translate(0.0, 20.0, 0.0)],
+            },
+        ];
+
+        let entities = description.instantiate().unwrap();
+        assert_eq!(entities.len(), 4);
+        assert_eq!(entities[1].material, "white");
+        assert_eq!(entities[3].material, "mirror");
+        assert_eq!(entities[3].transform.len(), 2);
+
+        // The copies are placed in the scene.
+        let loaded = description.build(Path::new("")).unwrap();
+        let through_copy = Ray {
+            origin: Point::new(1.0, 20.0, -10.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        assert!(loaded.scene.hits(&through_copy));
+
+        let mut missing = single_sphere();
+        missing.instances = vec![
+            InstanceDescription {
+                prototype: "tree".to_string(),
+                materials: HashMap::new(),
+                transform: Vec::new(),
+            },
+        ];
+        assert!(missing.build(Path::new("")).is_err());
+    }
+
+    #[test]
+    fn test_rigs() {
+        let light = |x: f32| {
+            LightDescription::Point {
+                position: (x, 0.0, 0.0),
+                intensity: (1.0, 1.0, 1.0),
+                attenuation: AttenuationDescription::Quadratic,
+            }
+        };
+        // A truss lifted and sliding along X while the shutter is open, holding a light and a bar
+        // turned a quarter turn about Y with a light along it.
+        let bar = RigDescription {
+            lights: vec![light(2.0)],
+            rigs: Vec::new(),
+            transform: vec![TransformDescription::RotateY(90.0)],
+            motion: Vec::new(),
+        };
+        let mut description = single_sphere();
+        description.camera.shutter = (0.0, 1.0);
+        description.rigs = vec![
+            RigDescription {
+                lights: vec![light(0.0)],
+                rigs: vec![bar],
+                transform: vec![TransformDescription::Translate(0.0, 10.0, 0.0)],
+                motion: vec![TransformDescription::Translate(10.0, 0.0, 0.0)],
+            },
+        ];
+        let loaded = description.build(Path::new("")).unwrap();
+        let lights = &loaded.scene.lights;
+        assert_eq!(lights.len(), 3);
+
+        let origin = Point::new(0.0, 0.0, 0.0);
+        let position_at = |light: usize, time: f32| {
+            let sample = lights[light].incident_at(&origin, time);
+            origin + sample.distance * sample.direction
+        };
+        assert_relative_eq!(position_at(1, 0.0), Point::new(0.0, 10.0, 0.0), epsilon = 1e-4);
+        assert_relative_eq!(position_at(1, 0.5), Point::new(5.0, 10.0, 0.0), epsilon = 1e-4);
+
+        // The bar's light moves with the truss.
+        let on_bar = *Transform::rotate_y(PlanarAngle::Degrees(90.0)).to_world() *
+            Point::new(2.0, 0.0, 0.0);
+        let lifted = Vector::new(0.0, 10.0, 0.0);
+        assert_relative_eq!(position_at(2, 0.0), on_bar + lifted, epsilon = 1e-4);
+        let slid = Vector::new(10.0, 10.0, 0.0);
+        assert_relative_eq!(position_at(2, 1.0), on_bar + slid, epsilon = 1e-4);
+
+        // Lights with a surface cannot be rigged.
+        let mut area = single_sphere();
+        area.rigs = vec![
+            RigDescription {
+                lights: vec![
+                    LightDescription::Sphere {
+                        center: (0.0, 0.0, 0.0),
+                        radius: 1.0,
+                        radiance: (1.0, 1.0, 1.0),
+                        texture: None,
+                        profile: Vec::new(),
+                    },
+                ],
+                rigs: Vec::new(),
+                transform: Vec::new(),
+                motion: Vec::new(),
+            },
+        ];
+        assert!(area.build(Path::new("")).is_err());
+    }
+
+    #[test]
+    fn test_csg() {
+        // A sphere at Z = 5 with a hole drilled along Z.
+        let mut description = single_sphere();
+        description.entities[0].solid = SolidDescription::Csg {
+            operation: CsgOperationDescription::Difference,
+            a: Box::new(CsgPartDescription {
+                solid: SolidDescription::Sphere { radius: 1.0 },
+                transform: Vec::new(),
+            }),
+            b: Box::new(CsgPartDescription {
+                solid: SolidDescription::Cylinder {
+                    radius: 0.25,
+                    z_min: -1.0,
+                    z_max: 1.0,
+                    capped: true,
+                },
+                transform: vec![TransformDescription::Scale(1.0, 1.0, 2.0)],
+            }),
+        };
+        let loaded = description.build(Path::new("")).unwrap();
+        let through_hole = Ray {
+            origin: Point::new(0.0, 0.0, -10.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        assert!(!loaded.scene.hits(&through_hole));
+        let beside_hole = Ray {
+            origin: Point::new(0.5, 0.0, -10.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        assert!(loaded.scene.hits(&beside_hole));
+    }
+
+    #[test]
+    fn test_migrate() {
+        let (current, warnings) = single_sphere().migrate().unwrap();
+        assert_eq!(current.version, SCENE_FORMAT_VERSION);
+        assert!(warnings.is_empty());
+
+        let mut unversioned = single_sphere();
+        unversioned.version = 0;
+        let (migrated, warnings) = unversioned.migrate().unwrap();
+        assert_eq!(migrated.version, SCENE_FORMAT_VERSION);
+        // The missing version and the point light.
+        assert_eq!(warnings.len(), 2);
+
+        let mut newer = single_sphere();
+        newer.version = SCENE_FORMAT_VERSION + 1;
+        assert!(newer.migrate().is_err());
+
+        // Directional lights of version 1 stay sharp.
+        let mut version_1 = single_sphere();
+        version_1.version = 1;
+        version_1.lights.push(LightDescription::Directional {
+            direction: (0.0, -1.0, 0.0),
+            radiance: (1.0, 1.0, 1.0),
+            angular_radius: default_angular_radius(),
+        });
+        let (migrated, warnings) = version_1.migrate().unwrap();
+        assert_eq!(warnings.len(), 2);
+        match migrated.lights[1] {
+            LightDescription::Directional { angular_radius, .. } => {
+                assert_eq!(angular_radius, 0.0)
+            }
+            _ => panic!("Expected the directional light to be kept."),
+        }
+
+        // Point lights of version 2 keep their brightness at every distance.
+        let mut version_2 = single_sphere();
+        version_2.version = 2;
+        let (migrated, warnings) = version_2.migrate().unwrap();
+        assert_eq!(warnings.len(), 1);
+        match migrated.lights[0] {
+            LightDescription::Point {
+                attenuation: AttenuationDescription::None,
+                ..
+            } => {}
+            _ => panic!("Expected the point light to have no falloff."),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_write_descriptions() {
+        let camera = single_sphere().camera;
+        let text = ron::ser::to_string(&camera).unwrap();
+        let read: CameraDescription = ron::de::from_str(&text).unwrap();
+        assert_eq!(ron::ser::to_string(&read).unwrap(), text);
+
+        let material = MaterialDescription::Lambertian {
+            diffuse: (0.0, 0.0, 0.0),
+            texture: Some(TextureDescription::Checker {
+                even: Box::new(TextureDescription::Constant { color: (1.0, 1.0, 1.0) }),
+                odd: Box::new(TextureDescription::Image { path: "wood.png".to_string() }),
+                cells: 4.0,
+            }),
+            normal_map: None,
+        };
+        let text = ron::ser::to_string(&material).unwrap();
+        let read: MaterialDescription = ron::de::from_str(&text).unwrap();
+        assert_eq!(ron::ser::to_string(&read).unwrap(), text);
+    }
+
+    #[test]
+    fn test_invalid_descriptions() {
+        let mut missing_material = single_sphere();
+        missing_material.entities[0].material = "chrome".to_string();
+        match missing_material.build(Path::new("")) {
+            Err(SceneFileError::Invalid(_)) => {}
+            _ => panic!("Expected a missing material to be reported."),
+        }
+
+        let mut no_target = single_sphere();
+        no_target.camera.target = None;
+        assert!(no_target.build(Path::new("")).is_err());
+
+        let mut flat = single_sphere();
+        flat.entities[0].transform.push(TransformDescription::Scale(1.0, 0.0, 1.0));
+        assert!(flat.build(Path::new("")).is_err());
+
+        let mut no_axis = single_sphere();
+        no_axis.entities[0].transform.push(TransformDescription::Rotate {
+            axis: (0.0, 0.0, 0.0),
+            degrees: 30.0,
+        });
+        assert!(no_axis.build(Path::new("")).is_err());
+
+        let mut missing_sky = single_sphere();
+        missing_sky.environment = Some(EnvironmentDescription::Image {
+            path: "no_such_sky.hdr".to_string(),
+        });
+        match missing_sky.build(Path::new("")) {
+            Err(SceneFileError::Environment(_, _)) => {}
+            _ => panic!("Expected a missing environment image to be reported."),
+        }
+
+        let mut empty_film = single_sphere();
+        empty_film.camera.width = 0;
+        assert!(empty_film.build(Path::new("")).is_err());
+
+        let mut skewed_light = single_sphere();
+        skewed_light.lights.push(LightDescription::Rectangle {
+            corner: (0.0, 5.0, 0.0),
+            edge_u: (1.0, 0.0, 0.0),
+            edge_v: (1.0, 0.0, 1.0),
+            radiance: (1.0, 1.0, 1.0),
+            texture: None,
+            profile: Vec::new(),
+        });
+        assert!(skewed_light.build(Path::new("")).is_err());
+
+        let mut no_reach = single_sphere();
+        no_reach.lights.push(LightDescription::Point {
+            position: (0.0, 5.0, 0.0),
+            intensity: (1.0, 1.0, 1.0),
+            attenuation: AttenuationDescription::SmoothCutoff(0.0),
+        });
+        assert!(no_reach.build(Path::new("")).is_err());
+
+        let mut dark_glass = single_sphere();
+        dark_glass.materials.insert(
+            "white".to_string(),
+            MaterialDescription::Dielectric {
+                index_of_refraction: 1.5,
+                priority: 0,
+                absorption: (0.0, -1.0, 0.0),
+                roughness: 0.0,
+                roughness_texture: None,
+            },
+        );
+        assert!(dark_glass.build(Path::new("")).is_err());
+
+        let mut rougher_than_rough = single_sphere();
+        rougher_than_rough.materials.insert(
+            "white".to_string(),
+            MaterialDescription::Dielectric {
+                index_of_refraction: 1.5,
+                priority: 0,
+                absorption: (0.0, 0.0, 0.0),
+                roughness: 1.5,
+                roughness_texture: None,
+            },
+        );
+        assert!(rougher_than_rough.build(Path::new("")).is_err());
+    }
+}
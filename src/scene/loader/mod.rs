@@ -0,0 +1,4 @@
+//! Creating scene contents from files.
+pub mod description;
+pub mod obj;
+pub mod scatter;
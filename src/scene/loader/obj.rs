@@ -0,0 +1,182 @@
+//! Wavefront OBJ meshes.
+//!
+//! Only vertex positions and faces are read, other statements such as normals, texture
+//! coordinates, groups, and materials are ignored.  Faces with more than three vertices are split
+//! into a fan of triangles.
+use math::{Point, Transform, TriangleMesh};
+use scene::Scene;
+use scene::material::Material;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// Problems encountered while loading an OBJ file.
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::Error),
+    /// A malformed statement, and the line number it occurred on, starting at 1.
+    Parse(usize, String),
+    /// The file has no faces, so there is nothing to render.
+    Empty,
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ObjError::Io(ref e) => write!(f, "{}", e),
+            ObjError::Parse(line, ref message) => write!(f, "line {}: {}", line, message),
+            ObjError::Empty => write!(f, "no faces"),
+        }
+    }
+}
+
+impl From<io::Error> for ObjError {
+    fn from(e: io::Error) -> ObjError {
+        ObjError::Io(e)
+    }
+}
+
+/// Reads a triangle mesh from an OBJ file.
+///
+/// OBJ files use a right-handed coordinate system, so Z is negated to convert into the
+/// left-handed world space.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<TriangleMesh, ObjError> {
+    let file = File::open(path)?;
+    parse(BufReader::new(file))
+}
+
+/// Loads an OBJ file, and adds it to a scene as a single entity.
+///
+/// # Arguments
+/// * `scene` - scene to add the mesh to
+/// * `path` - the OBJ file
+/// * `material` - material to apply to the whole mesh
+/// * `transform` - converts the mesh's coordinates to world coordinates
+pub fn add_to_scene<P: AsRef<Path>>(
+    scene: &mut Scene,
+    path: P,
+    material: Box<Material>,
+    transform: Transform,
+) -> Result<(), ObjError> {
+    let mesh = load(path)?;
+    scene.add_entity(Box::new(mesh), material, transform);
+    Ok(())
+}
+
+/// Reads a triangle mesh from OBJ statements.
+///
+/// Files without any faces are an error, rather than an empty mesh with no bounds.
+pub fn parse<R: BufRead>(input: R) -> Result<TriangleMesh, ObjError> {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for (line_index, line) in input.lines().enumerate() {
+        let line = line?;
+        let line_number = line_index + 1;
+        let mut words = line.split_whitespace();
+
+        match words.next() {
+            Some("v") => {
+                let coordinates: Vec<f32> = words.take(3).filter_map(|w| w.parse().ok()).collect();
+                if coordinates.len() != 3 {
+                    return Err(ObjError::Parse(
+                        line_number,
+                        "vertex needs three coordinates".to_string(),
+                    ));
+                }
+                vertices.push(Point::new(coordinates[0], coordinates[1], -coordinates[2]));
+            }
+            Some("f") => {
+                let mut face = Vec::new();
+                for word in words {
+                    face.push(parse_face_index(word, vertices.len()).ok_or_else(|| {
+                        ObjError::Parse(line_number, format!("invalid face vertex '{}'", word))
+                    })?);
+                }
+                if face.len() < 3 {
+                    return Err(ObjError::Parse(
+                        line_number,
+                        "face needs at least three vertices".to_string(),
+                    ));
+                }
+                // Negating Z mirrors the mesh, so the winding is reversed to keep faces
+                // counterclockwise around their outward normals.
+                for i in 1..(face.len() - 1) {
+                    indices.push(face[0]);
+                    indices.push(face[i + 1]);
+                    indices.push(face[i]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if indices.is_empty() {
+        return Err(ObjError::Empty);
+    }
+    Ok(TriangleMesh::new(vertices, indices))
+}
+
+/// Converts a face vertex such as `3`, `3/1`, `3//2` or `-1` into an index into the vertices
+/// read so far.
+fn parse_face_index(word: &str, vertex_count: usize) -> Option<u32> {
+    let position: i64 = word.split('/').next()?.parse().ok()?;
+    let index = if position > 0 {
+        position - 1
+    } else {
+        // Negative indices count back from the most recent vertex.
+        vertex_count as i64 + position
+    };
+
+    if index >= 0 && index < vertex_count as i64 {
+        Some(index as u32)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, ObjError};
+    use math::{Point, Vector};
+
+    #[test]
+    fn test_parse_quad() {
+        let obj = "# A unit square\n\
+                   v 0 0 0\n\
+                   v 1 0 0\n\
+                   v 1 1 0\n\
+                   v 0 1 -2.5\n\
+                   vn 0 0 1\n\
+                   f 1//1 2//1 3//1 -1//1\n";
+        let mesh = parse(obj.as_bytes()).unwrap();
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.indices(), &[0, 2, 1, 0, 3, 2]);
+        assert_relative_eq!(mesh.vertex(3), Point::new(0.0, 1.0, 2.5));
+
+        // The face is counterclockwise around Z+ in the file, which is Z- once mirrored.
+        let [a, b, c] = mesh.triangle(0);
+        let mut normal = (b - a).cross(&(c - a));
+        normal.normalize().unwrap();
+        assert_relative_eq!(normal, Vector::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        match parse("v 0 0\n".as_bytes()) {
+            Err(ObjError::Parse(1, _)) => {}
+            _ => panic!("Short vertex was accepted."),
+        }
+        match parse("v 0 0 0\nv 1 0 0\nf 1 2 3\n".as_bytes()) {
+            Err(ObjError::Parse(3, _)) => {}
+            _ => panic!("Face referring to a missing vertex was accepted."),
+        }
+        for obj in ["", "# Nothing but a comment\n", "v 0 0 0\nv 1 0 0\n"].iter() {
+            match parse(obj.as_bytes()) {
+                Err(ObjError::Empty) => {}
+                _ => panic!("Mesh without faces was accepted."),
+            }
+        }
+    }
+}
@@ -0,0 +1,280 @@
+//! Placing many instances of a prototype at random, for clutter and stress-test scenes.
+//!
+//! ```text
+//! scatter: [
+//!     (
+//!         prototype: "tree",
+//!         count: 500,
+//!         seed: 7,
+//!         region: Surface(center: (0.0, 0.0, 50.0), size: (200.0, 200.0)),
+//!         jitter: 0.8,
+//!         scale: (0.7, 1.3),
+//!         rotate: true,
+//!     ),
+//! ],
+//! ```
+//!
+//! The same seed always places instances in the same positions.
+use rand::{Rng, SeedableRng, XorShiftRng};
+use scene::loader::description::{InstanceDescription, SceneFileError, TransformDescription};
+use std::collections::HashMap;
+
+/// Where scattered instances are placed.
+#[derive(Clone, Debug, Deserialize)]
+pub enum RegionDescription {
+    /// A horizontal rectangle, such as the ground, centered at a point with a width along X and
+    /// depth along Z.
+    Surface {
+        center: (f32, f32, f32),
+        size: (f32, f32),
+    },
+    /// Anywhere within a box.
+    Box {
+        lower: (f32, f32, f32),
+        upper: (f32, f32, f32),
+    },
+}
+
+/// Copies of a prototype placed at random within a region.
+///
+/// Positions are spread over a grid with a cell for each instance, so they neither clump nor
+/// leave large gaps, and each is moved within its cell by up to `jitter` of the cell's size.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ScatterDescription {
+    /// Name of the prototype to copy.
+    pub prototype: String,
+    pub count: u32,
+    #[serde(default)]
+    pub seed: u32,
+    pub region: RegionDescription,
+    /// How far instances may move from the centers of their cells, from 0 for a regular grid
+    /// to 1 for anywhere within their cells.
+    #[serde(default = "default_jitter")]
+    pub jitter: f32,
+    /// Smallest and largest uniform scale of the instances.
+    #[serde(default = "default_scale")]
+    pub scale: (f32, f32),
+    /// Whether to turn each instance to a random angle around the Y axis.
+    #[serde(default)]
+    pub rotate: bool,
+    /// Materials to use in place of those named by the prototype's entities.
+    #[serde(default)]
+    pub materials: HashMap<String, String>,
+}
+
+fn default_jitter() -> f32 {
+    1.0
+}
+
+fn default_scale() -> (f32, f32) {
+    (1.0, 1.0)
+}
+
+impl ScatterDescription {
+    /// Chooses where each copy of the prototype goes.
+    pub fn instances(&self) -> Result<Vec<InstanceDescription>, SceneFileError> {
+        if !(self.jitter >= 0.0 && self.jitter <= 1.0) {
+            return Err(SceneFileError::Invalid(
+                "scatter jitter must be between 0 and 1".to_string(),
+            ));
+        }
+        if !(self.scale.0 > 0.0 && self.scale.0 <= self.scale.1) {
+            return Err(SceneFileError::Invalid(
+                "scatter scale must be a positive range, smallest first".to_string(),
+            ));
+        }
+
+        let mut rng = seeded_rng(self.seed);
+        let positions = match self.region {
+            RegionDescription::Surface { center, size } => {
+                if !(size.0 >= 0.0 && size.1 >= 0.0) {
+                    return Err(SceneFileError::Invalid(
+                        "scatter surface size cannot be negative".to_string(),
+                    ));
+                }
+                let lower = (center.0 - 0.5 * size.0, center.1, center.2 - 0.5 * size.1);
+                let upper = (center.0 + 0.5 * size.0, center.1, center.2 + 0.5 * size.1);
+                self.positions(lower, upper, false, &mut rng)
+            }
+            RegionDescription::Box { lower, upper } => {
+                if !(lower.0 <= upper.0 && lower.1 <= upper.1 && lower.2 <= upper.2) {
+                    return Err(SceneFileError::Invalid(
+                        "scatter box lower corner must be below its upper corner".to_string(),
+                    ));
+                }
+                self.positions(lower, upper, true, &mut rng)
+            }
+        };
+
+        Ok(
+            positions
+                .into_iter()
+                .map(|(x, y, z)| {
+                    let scale = self.scale.0 + rng.next_f32() * (self.scale.1 - self.scale.0);
+                    let mut transform = vec![TransformDescription::Scale(scale, scale, scale)];
+                    if self.rotate {
+                        transform.push(TransformDescription::RotateY(360.0 * rng.next_f32()));
+                    }
+                    transform.push(TransformDescription::Translate(x, y, z));
+                    InstanceDescription {
+                        prototype: self.prototype.clone(),
+                        materials: self.materials.clone(),
+                        transform: transform,
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Jittered positions in the cells of a grid covering a box, flat if `volume` is false.
+    fn positions(
+        &self,
+        lower: (f32, f32, f32),
+        upper: (f32, f32, f32),
+        volume: bool,
+        rng: &mut XorShiftRng,
+    ) -> Vec<(f32, f32, f32)> {
+        let count = self.count as usize;
+        let cells_for = |per_side: usize| if volume {
+            per_side * per_side * per_side
+        } else {
+            per_side * per_side
+        };
+        let mut per_side = 1;
+        while cells_for(per_side) < count {
+            per_side += 1;
+        }
+        let layers = if volume { per_side } else { 1 };
+
+        // Visit the cells in a random order, so a partially filled grid is still spread out.
+        let cell_count = per_side * per_side * layers;
+        let mut cells: Vec<usize> = (0..cell_count).collect();
+        for i in (1..cell_count).rev() {
+            let j = (rng.next_u32() as usize) % (i + 1);
+            cells.swap(i, j);
+        }
+
+        let mut place = |cell: usize, lower: f32, upper: f32, divisions: usize| {
+            let offset = 0.5 + self.jitter * (rng.next_f32() - 0.5);
+            lower + (upper - lower) * (cell as f32 + offset) / divisions as f32
+        };
+        cells
+            .into_iter()
+            .take(count)
+            .map(|cell| {
+                let (column, row, layer) = (
+                    cell % per_side,
+                    (cell / per_side) % per_side,
+                    cell / (per_side * per_side),
+                );
+                (
+                    place(column, lower.0, upper.0, per_side),
+                    place(layer, lower.1, upper.1, layers),
+                    place(row, lower.2, upper.2, per_side),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Random numbers which are the same for every load of a scene.
+fn seeded_rng(seed: u32) -> XorShiftRng {
+    let mut rng = XorShiftRng::from_seed([
+        0x193a_6754 ^ seed,
+        0xa8a7_d469,
+        0x9783_0e05,
+        0x113b_a7bb,
+    ]);
+    // Similar seeds start with similar numbers, so move past them.
+    for _ in 0..16 {
+        rng.next_u32();
+    }
+    rng
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RegionDescription, ScatterDescription};
+    use scene::loader::description::TransformDescription;
+    use std::collections::HashMap;
+
+    fn forest(count: u32, seed: u32) -> ScatterDescription {
+        ScatterDescription {
+            prototype: "tree".to_string(),
+            count: count,
+            seed: seed,
+            region: RegionDescription::Surface {
+                center: (0.0, 1.0, 10.0),
+                size: (20.0, 10.0),
+            },
+            jitter: 1.0,
+            scale: (0.5, 2.0),
+            rotate: true,
+            materials: HashMap::new(),
+        }
+    }
+
+    fn translation(transform: &[TransformDescription]) -> (f32, f32, f32) {
+        match transform.last() {
+            Some(&TransformDescription::Translate(x, y, z)) => (x, y, z),
+            _ => panic!("Expected instances to be moved into place last."),
+        }
+    }
+
+    #[test]
+    fn test_surface_placement() {
+        let instances = forest(50, 3).instances().unwrap();
+        assert_eq!(instances.len(), 50);
+        for instance in instances.iter() {
+            assert_eq!(instance.transform.len(), 3);
+            match instance.transform[0] {
+                TransformDescription::Scale(s, _, _) => assert!(s >= 0.5 && s <= 2.0),
+                _ => panic!("Expected instances to be scaled first."),
+            }
+            let (x, y, z) = translation(&instance.transform);
+            assert!(x >= -10.0 && x <= 10.0);
+            assert_eq!(y, 1.0);
+            assert!(z >= 5.0 && z <= 15.0);
+        }
+    }
+
+    #[test]
+    fn test_seeds() {
+        let position = |seed: u32| translation(&forest(10, seed).instances().unwrap()[0].transform);
+        assert_eq!(position(1), position(1));
+        assert!(position(1) != position(2));
+    }
+
+    #[test]
+    fn test_box_grid() {
+        let grid = ScatterDescription {
+            region: RegionDescription::Box {
+                lower: (0.0, 0.0, 0.0),
+                upper: (2.0, 2.0, 2.0),
+            },
+            jitter: 0.0,
+            scale: (1.0, 1.0),
+            rotate: false,
+            ..forest(8, 0)
+        };
+        let mut positions: Vec<(f32, f32, f32)> = grid.instances()
+            .unwrap()
+            .iter()
+            .map(|i| translation(&i.transform))
+            .collect();
+        positions.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(positions[0], (0.5, 0.5, 0.5));
+        assert_eq!(positions[7], (1.5, 1.5, 1.5));
+    }
+
+    #[test]
+    fn test_invalid_scatter() {
+        let mut backwards = forest(5, 0);
+        backwards.scale = (2.0, 1.0);
+        assert!(backwards.instances().is_err());
+
+        let mut shaky = forest(5, 0);
+        shaky.jitter = 1.5;
+        assert!(shaky.instances().is_err());
+    }
+}
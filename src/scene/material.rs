@@ -1,44 +1,1306 @@
-use math::Vector;
+use math::{Intersection, PlanarAngle, Point, Vector, perpendicular_tangents};
 use scene::Spectrum;
+use scene::medium::Medium;
+use scene::texture::{ConstantTexture, NormalMap, Texture};
+use std::f32::consts::PI;
+
+/// A ray continuing on from a surface after reflection or transmission.
+#[derive(Clone, Copy, Debug)]
+pub struct ScatteredRay {
+    /// Unit vector the ray continues along.
+    pub direction: Vector,
+    /// Fraction of the radiance arriving along the ray which is carried back toward the viewer.
+    pub weight: Spectrum,
+    pub kind: ScatterKind,
+}
+
+/// How a scattered ray left a surface, which limits how many times paths may scatter that way.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScatterKind {
+    /// Reflected in a widely spread direction, such as from a matte surface.
+    Diffuse,
+    /// Reflected in or near the mirror direction.
+    Glossy,
+    /// Transmitted through the surface, such as into glass.
+    Transmission,
+}
 
 /// Materials determine the next ray direction of travel, as well as the describing the surface
 /// properties of the object.
-pub trait Material {
-    /// Materials are perfectly reflective by default.
+///
+/// Materials are shared between rendering threads.
+pub trait Material: Send + Sync {
+    /// Determines the rays which continue on from a surface, such as reflections and refractions.
+    ///
+    /// Materials only reflect light directly from lights by default, and scatter no rays.
     ///
     /// # Arguments
-    /// * `incident` - vector pointing into the material whose next direction must be determined.
-    /// * `normal` - vector perpendicular to the surface
+    /// * `incident` - unit vector pointing into the material whose next direction must be
+    ///   determined.
+    /// * `normal` - unit vector perpendicular to the surface
+    /// * `outside_index` - index of refraction of the medium bordering the surface, on the other
+    ///   side from the material's `interior`, 1 for a vacuum.
+    /// * `sample` - uniformly distributed in [0, 1), for materials which randomly choose among
+    ///   the ways they scatter rather than tracing every one.
+    /// * `scattered` - rays to trace further are added to this, with the fraction of their
+    ///   radiance to add to this surface's.  It is reused between surfaces so tracing does not
+    ///   allocate, and may already hold rays from other surfaces which must be kept.
+    #[allow(unused_variables)]
+    fn scatter(
+        &self,
+        incident: &Vector,
+        normal: &Vector,
+        outside_index: f32,
+        sample: f32,
+        scattered: &mut Vec<ScatteredRay>,
+    ) {
+    }
+
+    /// Rays continuing on from a point on the surface, see `scatter`.
     ///
-    /// # Return
-    /// Either a reflected or refracted vector pointing in the new direction.
-    fn next_ray_direction(&self, incident: &Vector, normal: &Vector) -> Vector {
-        2.0 * (normal.dot(incident) * (*normal))
+    /// Materials scatter the same way everywhere on their surface by default, choosing among the
+    /// ways they scatter with only the first of the `samples`.
+    ///
+    /// # Arguments
+    /// * `surface` - where on the surface the rays scatter from
+    /// * `samples` - uniformly distributed in [0, 1).  The first chooses among the ways the
+    ///   material scatters like the `sample` of `scatter`, and the others are a position in the
+    ///   unit square, for materials which spread rays across many directions, such as frosted
+    ///   glass.
+    #[allow(unused_variables)]
+    fn scatter_at(
+        &self,
+        incident: &Vector,
+        normal: &Vector,
+        outside_index: f32,
+        surface: &Intersection,
+        samples: [f32; 3],
+        scattered: &mut Vec<ScatteredRay>,
+    ) {
+        self.scatter(incident, normal, outside_index, samples[0], scattered)
     }
 
     /// BRDF function giving ratio of differential outgoing radiance (dependent upon the view
     /// vector) to differential irradiance, dependent upon the light direction.
     ///
+    /// The cosine term between the light and the normal is not included, the integrator applies
+    /// it.  Values are scaled by π, so a white Lambertian surface has a BRDF of 1.
+    ///
     /// # Arguments
     /// * `light` - light vector, points to the light
     /// * `view` - view vector, points to the viewer.
-    fn f(&self, light: &Vector, view: &Vector) -> Spectrum;
+    /// * `normal` - vector perpendicular to the surface
+    fn f(&self, light: &Vector, view: &Vector, normal: &Vector) -> Spectrum;
+
+    /// BRDF at a point on the surface with texture coordinates `uv`, see `f`.
+    ///
+    /// Materials are the same everywhere on their surface by default.
+    #[allow(unused_variables)]
+    fn f_at(
+        &self,
+        light: &Vector,
+        view: &Vector,
+        normal: &Vector,
+        uv: (f32, f32),
+        point: &Point,
+    ) -> Spectrum {
+        self.f(light, view, normal)
+    }
+
+    /// The medium filling entities made of the material, if rays pass into them.
+    ///
+    /// Materials are opaque by default.
+    fn interior(&self) -> Option<Medium> {
+        None
+    }
+
+    /// Texture perturbing the normals used to shade the surface, if the material has one.
+    fn normal_map(&self) -> Option<&NormalMap> {
+        None
+    }
+
+    /// Radiance the surface gives off itself, toward `view`.
+    ///
+    /// Materials do not glow by default.
+    ///
+    /// # Arguments
+    /// * `view` - unit vector pointing to the viewer
+    /// * `normal` - unit vector perpendicular to the surface
+    #[allow(unused_variables)]
+    fn emitted(&self, view: &Vector, normal: &Vector) -> Spectrum {
+        Spectrum::black()
+    }
+
+    /// Whether the material ever gives off light, so entities made of it light the scene.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// Short name of the kind of material, such as "Lambertian", for summaries of scenes.
+    fn name(&self) -> &'static str;
 }
 
-/// Lambertian material consisting of a single diffuse color.
+/// Lambertian material, whose diffuse color may vary across its surface.
 pub struct LambertianMaterial {
-    diffuse: Spectrum,
+    diffuse: Box<Texture>,
+    normal_map: Option<NormalMap>,
 }
 
 impl LambertianMaterial {
+    /// A material of a single diffuse color.
     pub fn new(diffuse: &Spectrum) -> LambertianMaterial {
-        LambertianMaterial { diffuse: *diffuse }
+        LambertianMaterial::new_with_texture(Box::new(ConstantTexture::new(diffuse)))
+    }
+
+    pub fn new_with_texture(diffuse: Box<Texture>) -> LambertianMaterial {
+        LambertianMaterial {
+            diffuse: diffuse,
+            normal_map: None,
+        }
+    }
+
+    /// Perturbs the normals the surface is shaded with, or shades with the surface's own normals
+    /// again if `None`.
+    pub fn set_normal_map(&mut self, normal_map: Option<NormalMap>) {
+        self.normal_map = normal_map;
     }
 }
 
 impl Material for LambertianMaterial {
+    /// The color of the texture at the origin, since no point on the surface is given.
+    #[allow(unused_variables)]
+    fn f(&self, light: &Vector, view: &Vector, normal: &Vector) -> Spectrum {
+        self.diffuse.eval(0.0, 0.0, &Point::new(0.0, 0.0, 0.0))
+    }
+
     #[allow(unused_variables)]
-    fn f(&self, light: &Vector, view: &Vector) -> Spectrum {
-        self.diffuse
+    fn f_at(
+        &self,
+        light: &Vector,
+        view: &Vector,
+        normal: &Vector,
+        uv: (f32, f32),
+        point: &Point,
+    ) -> Spectrum {
+        self.diffuse.eval(uv.0, uv.1, point)
+    }
+
+    fn normal_map(&self) -> Option<&NormalMap> {
+        self.normal_map.as_ref()
+    }
+
+    fn name(&self) -> &'static str {
+        "Lambertian"
+    }
+}
+
+/// A mirror, which reflects a fraction of the incoming light of each color.
+pub struct SpecularMaterial {
+    reflectance: Spectrum,
+}
+
+impl SpecularMaterial {
+    /// # Arguments
+    /// * `reflectance` - fraction of light reflected in each channel, white is a perfect mirror.
+    pub fn new(reflectance: &Spectrum) -> SpecularMaterial {
+        SpecularMaterial { reflectance: *reflectance }
+    }
+}
+
+impl Material for SpecularMaterial {
+    #[allow(unused_variables)]
+    fn scatter(
+        &self,
+        incident: &Vector,
+        normal: &Vector,
+        outside_index: f32,
+        sample: f32,
+        scattered: &mut Vec<ScatteredRay>,
+    ) {
+        scattered.push(ScatteredRay {
+            direction: incident.reflect(normal),
+            weight: self.reflectance,
+            kind: ScatterKind::Glossy,
+        });
+    }
+
+    /// Light only arrives from the mirrored direction, which is traced by the scattered ray.
+    #[allow(unused_variables)]
+    fn f(&self, light: &Vector, view: &Vector, normal: &Vector) -> Spectrum {
+        Spectrum::black()
+    }
+
+    fn name(&self) -> &'static str {
+        "specular"
+    }
+}
+
+/// Transparent material such as glass or water, which reflects and refracts, and may absorb light
+/// travelling through it.
+///
+/// Each surface either reflects or refracts a path, chosen at random in proportion to the
+/// reflectance, rather than tracing both.  Tracing both doubles the rays at every surface of the
+/// glass, so a path through a few panes would trace hundreds of rays; choosing one keeps each
+/// path a single ray, and the noise averages out over the samples of a pixel.
+///
+/// Lights are not seen through dielectrics when shading, since only direct rays to lights are
+/// traced.
+///
+/// Rough dielectrics, such as frosted glass, reflect and refract off microfacets with the GGX
+/// distribution of `MicrofacetMaterial`, spreading each ray across nearby directions.
+pub struct DielectricMaterial {
+    interior: Medium,
+    /// Perceptual roughness at each point of the surface, from its brightness, or `None` for
+    /// smooth glass.
+    roughness: Option<Box<Texture>>,
+}
+
+impl DielectricMaterial {
+    /// # Arguments
+    /// * `index_of_refraction` - ratio of the speed of light in a vacuum to that in the material,
+    ///   such as 1.33 for water or 1.5 for glass.
+    pub fn new(index_of_refraction: f32) -> DielectricMaterial {
+        assert!(
+            index_of_refraction > 0.0,
+            "Index of refraction must be positive."
+        );
+        DielectricMaterial {
+            interior: Medium {
+                index_of_refraction: index_of_refraction,
+                priority: 0,
+                absorption: Spectrum::black(),
+            },
+            roughness: None,
+        }
+    }
+
+    /// The same material with a rough surface, which blurs what is seen through and reflected in
+    /// it.
+    ///
+    /// # Arguments
+    /// * `roughness` - in [0, 1], from smooth to fully rough
+    ///
+    /// # Panics
+    /// If roughness is outside of [0, 1].
+    pub fn with_roughness(mut self, roughness: f32) -> DielectricMaterial {
+        assert!(roughness >= 0.0 && roughness <= 1.0, "Roughness must be in [0, 1].");
+        self.roughness = if roughness > 0.0 {
+            Some(Box::new(ConstantTexture::new(&Spectrum::uniform(roughness))))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// The same material with a roughness varying across its surface, such as glass frosted
+    /// in a pattern.
+    ///
+    /// # Arguments
+    /// * `roughness` - texture whose brightness, clamped to [0, 1], is the roughness at each point
+    pub fn with_roughness_texture(mut self, roughness: Box<Texture>) -> DielectricMaterial {
+        self.roughness = Some(roughness);
+        self
+    }
+
+    /// The same material filling the space where entities of it overlap entities of lower
+    /// priority, see `scene::medium`.
+    pub fn with_priority(mut self, priority: u32) -> DielectricMaterial {
+        self.interior.priority = priority;
+        self
+    }
+
+    /// The same material absorbing light travelling through it, so thicker parts are darker.
+    ///
+    /// # Arguments
+    /// * `absorption` - fraction of light absorbed in each channel per unit of distance, so a
+    ///   channel of `a` transmits `exp(-a d)` of light through a thickness `d`.
+    ///
+    /// # Panics
+    /// If any channel of `absorption` is negative.
+    pub fn with_absorption(mut self, absorption: &Spectrum) -> DielectricMaterial {
+        assert!(
+            absorption.r >= 0.0 && absorption.g >= 0.0 && absorption.b >= 0.0,
+            "Absorption must not be negative."
+        );
+        self.interior.absorption = *absorption;
+        self
+    }
+}
+
+/// Fraction of unpolarized light reflected from a boundary between two media, by the Fresnel
+/// equations.
+///
+/// Schlick's approximation is not used, since it still reflects at grazing angles between media
+/// with the same index of refraction, which nested media often border.
+///
+/// # Arguments
+/// * `eta` - index of refraction on the incident side over that on the transmitted side.
+/// * `cos_incident` - cosine of the angle between the incident ray and the normal.
+///
+/// # Returns
+/// 1 if all light is reflected by total internal reflection.
+pub fn fresnel_reflectance(eta: f32, cos_incident: f32) -> f32 {
+    let sin_transmitted_squared = eta * eta * (1.0 - cos_incident * cos_incident);
+    if sin_transmitted_squared >= 1.0 {
+        return 1.0;
+    }
+    let cos_transmitted = (1.0 - sin_transmitted_squared).sqrt();
+    let perpendicular = (eta * cos_incident - cos_transmitted) /
+        (eta * cos_incident + cos_transmitted);
+    let parallel = (eta * cos_transmitted - cos_incident) / (eta * cos_transmitted + cos_incident);
+    0.5 * (perpendicular * perpendicular + parallel * parallel)
+}
+
+impl Material for DielectricMaterial {
+    /// Rough dielectrics scatter like smooth ones here, without a position on the surface to
+    /// find their roughness at, or to choose microfacets with.  See `scatter_at`.
+    fn scatter(
+        &self,
+        incident: &Vector,
+        normal: &Vector,
+        outside_index: f32,
+        sample: f32,
+        scattered: &mut Vec<ScatteredRay>,
+    ) {
+        // Flip the normal to face the incident ray when leaving the material.
+        let relative_index = self.interior.index_of_refraction / outside_index;
+        let entering = incident.dot(normal) < 0.0;
+        let (normal, eta) = if entering {
+            (*normal, 1.0 / relative_index)
+        } else {
+            (-*normal, relative_index)
+        };
+
+        let reflected = incident.reflect(&normal);
+        let transmitted = match incident.refract(&normal, eta) {
+            Some(transmitted) => transmitted,
+            None => {
+                scattered.push(ScatteredRay {
+                    direction: reflected,
+                    weight: Spectrum::uniform(1.0),
+                    kind: ScatterKind::Glossy,
+                });
+                return;
+            }
+        };
+
+        let reflectance = fresnel_reflectance(eta, -normal.dot(incident));
+        // Choosing each ray with probability equal to the fraction of light it carries cancels
+        // that fraction from its weight.
+        if sample < reflectance {
+            scattered.push(ScatteredRay {
+                direction: reflected,
+                weight: Spectrum::uniform(1.0),
+                kind: ScatterKind::Glossy,
+            });
+        } else {
+            scattered.push(ScatteredRay {
+                direction: transmitted,
+                weight: Spectrum::uniform(1.0),
+                kind: ScatterKind::Transmission,
+            });
+        }
+    }
+
+    /// Reflects or refracts off a single microfacet, chosen in proportion to the GGX density of
+    /// microfacets facing each direction, with the weights of Walter et al.'s "Microfacet Models
+    /// for Refraction through Rough Surfaces".
+    fn scatter_at(
+        &self,
+        incident: &Vector,
+        normal: &Vector,
+        outside_index: f32,
+        surface: &Intersection,
+        samples: [f32; 3],
+        scattered: &mut Vec<ScatteredRay>,
+    ) {
+        let roughness = match self.roughness {
+            Some(ref roughness) => {
+                roughness
+                    .eval(surface.uv.0, surface.uv.1, &surface.point)
+                    .luminance()
+                    .max(0.0)
+                    .min(1.0)
+            }
+            None => 0.0,
+        };
+        if roughness == 0.0 {
+            return self.scatter(incident, normal, outside_index, samples[0], scattered);
+        }
+        let alpha = (roughness * roughness).max(MIN_ALPHA);
+
+        let relative_index = self.interior.index_of_refraction / outside_index;
+        let entering = incident.dot(normal) < 0.0;
+        let (normal, eta) = if entering {
+            (*normal, 1.0 / relative_index)
+        } else {
+            (-*normal, relative_index)
+        };
+        let cos_view = -incident.dot(&normal);
+        if cos_view <= 0.0 {
+            return;
+        }
+
+        let local = ggx_half_vector(alpha, (samples[1], samples[2]));
+        let (tangent, bitangent) = perpendicular_tangents(&normal);
+        let half = local.x * tangent + local.y * normal + local.z * bitangent;
+        let v_dot_h = -incident.dot(&half);
+        if v_dot_h <= 0.0 {
+            return;
+        }
+
+        let (direction, kind) = match incident.refract(&half, eta) {
+            Some(transmitted) if samples[0] >= fresnel_reflectance(eta, v_dot_h) => {
+                (transmitted, ScatterKind::Transmission)
+            }
+            _ => (incident.reflect(&half), ScatterKind::Glossy),
+        };
+        // Reflections below the surface and refractions above it are blocked by other
+        // microfacets.
+        let cos_out = direction.dot(&normal);
+        if (kind == ScatterKind::Glossy) != (cos_out > 0.0) {
+            return;
+        }
+
+        // Choosing microfacets in proportion to their density and projected area, then
+        // reflecting or refracting in proportion to the Fresnel reflectance, cancels all but the
+        // shadowing from the weight.
+        let weight = v_dot_h * ggx_smith_g1(alpha, cos_view) * ggx_smith_g1(alpha, cos_out.abs()) /
+            (cos_view * local.y);
+        scattered.push(ScatteredRay {
+            direction: direction,
+            weight: Spectrum::uniform(weight),
+            kind: kind,
+        });
+    }
+
+    fn interior(&self) -> Option<Medium> {
+        Some(self.interior)
+    }
+
+    /// All light is reflected or transmitted along the scattered rays.
+    #[allow(unused_variables)]
+    fn f(&self, light: &Vector, view: &Vector, normal: &Vector) -> Spectrum {
+        Spectrum::black()
+    }
+
+    fn name(&self) -> &'static str {
+        "dielectric"
+    }
+}
+
+/// Rough diffuse material, from Oren and Nayar's "Generalization of Lambert's Reflectance
+/// Model".
+///
+/// Models the surface as V-shaped microfacets, which makes matte materials like clay and concrete
+/// flatter and brighter toward the viewer than Lambertian surfaces.
+pub struct OrenNayarMaterial {
+    diffuse: Spectrum,
+    a: f32,
+    b: f32,
+}
+
+impl OrenNayarMaterial {
+    /// # Arguments
+    /// * `diffuse` - the diffuse color
+    /// * `sigma` - standard deviation of the microfacet slope angles, 0 is Lambertian
+    pub fn new(diffuse: &Spectrum, sigma: PlanarAngle) -> OrenNayarMaterial {
+        let sigma = sigma.to_radians();
+        assert!(sigma >= 0.0, "Oren-Nayar roughness cannot be negative.");
+        let sigma2 = sigma * sigma;
+        OrenNayarMaterial {
+            diffuse: *diffuse,
+            a: 1.0 - 0.5 * sigma2 / (sigma2 + 0.33),
+            b: 0.45 * sigma2 / (sigma2 + 0.09),
+        }
+    }
+}
+
+impl Material for OrenNayarMaterial {
+    fn f(&self, light: &Vector, view: &Vector, normal: &Vector) -> Spectrum {
+        let cos_theta_i = normal.dot(light).min(1.0);
+        let cos_theta_r = normal.dot(view).min(1.0);
+        if cos_theta_i <= 0.0 || cos_theta_r <= 0.0 {
+            return Spectrum::black();
+        }
+
+        // Cosine of the difference in azimuth, from the projections onto the surface.
+        let mut light_tangent = *light - cos_theta_i * (*normal);
+        let mut view_tangent = *view - cos_theta_r * (*normal);
+        let cos_phi_difference = match (light_tangent.normalize(), view_tangent.normalize()) {
+            (Ok(_), Ok(_)) => light_tangent.dot(&view_tangent).max(0.0),
+            _ => 0.0,
+        };
+
+        let sin_theta_i = (1.0 - cos_theta_i * cos_theta_i).sqrt();
+        let sin_theta_r = (1.0 - cos_theta_r * cos_theta_r).sqrt();
+        // sin(alpha) * tan(beta), where alpha = max(theta_i, theta_r) and beta is the minimum.
+        let sin_alpha_tan_beta = if cos_theta_i < cos_theta_r {
+            sin_theta_i * sin_theta_r / cos_theta_r
+        } else {
+            sin_theta_r * sin_theta_i / cos_theta_i
+        };
+
+        (self.a + self.b * cos_phi_difference * sin_alpha_tan_beta) * self.diffuse
+    }
+
+    fn name(&self) -> &'static str {
+        "Oren-Nayar"
+    }
+}
+
+/// Diffuse surface with glossy highlights, from the Blinn-Phong model.
+///
+/// Highlights are cheap to evaluate, but only light arriving from lights and the environment is
+/// reflected, like `MicrofacetMaterial`.  The specular lobe is normalized, so raising the
+/// shininess narrows highlights without dimming them.
+pub struct BlinnPhongMaterial {
+    diffuse: Spectrum,
+    specular: Spectrum,
+    shininess: f32,
+}
+
+impl BlinnPhongMaterial {
+    /// The sum of `diffuse` and `specular` should be at most 1 in each color to reflect no more
+    /// light than received.
+    ///
+    /// # Arguments
+    /// * `diffuse` - color reflected equally in every direction
+    /// * `specular` - color of highlights
+    /// * `shininess` - exponent of the highlight, larger values give smaller, sharper highlights
+    ///
+    /// # Panics
+    /// If shininess is negative.
+    pub fn new(diffuse: &Spectrum, specular: &Spectrum, shininess: f32) -> BlinnPhongMaterial {
+        assert!(shininess >= 0.0, "Shininess must not be negative.");
+        BlinnPhongMaterial {
+            diffuse: *diffuse,
+            specular: *specular,
+            shininess: shininess,
+        }
+    }
+}
+
+impl Material for BlinnPhongMaterial {
+    fn f(&self, light: &Vector, view: &Vector, normal: &Vector) -> Spectrum {
+        if normal.dot(light) <= 0.0 || normal.dot(view) <= 0.0 {
+            return Spectrum::black();
+        }
+        let mut half = *light + *view;
+        if half.normalize().is_err() {
+            return self.diffuse;
+        }
+        // Normalization of the lobe from Fabian Giesen's "Derivation of Phong and Blinn-Phong
+        // BRDF normalization factors", scaled by π like every BRDF.
+        let n = self.shininess;
+        let normalization = (n + 2.0) * (n + 4.0) / (8.0 * ((-n / 2.0).exp2() + n));
+        let highlight = normal.dot(&half).max(0.0).powf(self.shininess);
+        self.diffuse + (normalization * highlight) * self.specular
+    }
+
+    fn name(&self) -> &'static str {
+        "Blinn-Phong"
+    }
+}
+
+/// A glowing surface, such as a light panel or bulb, which lights the scene like a light.
+///
+/// Light is only given off the front of the surface, the side its normals point out of, and no
+/// light is reflected.
+pub struct EmissiveMaterial {
+    radiance: Spectrum,
+}
+
+impl EmissiveMaterial {
+    /// # Arguments
+    /// * `radiance` - radiance given off in every direction in front of the surface
+    pub fn new(radiance: &Spectrum) -> EmissiveMaterial {
+        EmissiveMaterial { radiance: *radiance }
+    }
+}
+
+impl Material for EmissiveMaterial {
+    #[allow(unused_variables)]
+    fn f(&self, light: &Vector, view: &Vector, normal: &Vector) -> Spectrum {
+        Spectrum::black()
+    }
+
+    fn emitted(&self, view: &Vector, normal: &Vector) -> Spectrum {
+        if view.dot(normal) > 0.0 {
+            self.radiance
+        } else {
+            Spectrum::black()
+        }
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
+
+    fn name(&self) -> &'static str {
+        "emissive"
+    }
+}
+
+/// Cloth-like material with a diffuse base and a sheen lobe, which brightens edges viewed at
+/// grazing angles like velvet.
+///
+/// Sheen uses the "Charlie" distribution from Estevez and Kulla's "Production Friendly
+/// Microfacet Sheen BRDF", with the visibility term of Neubelt and Pettineo.
+pub struct SheenMaterial {
+    diffuse: Spectrum,
+    sheen: Spectrum,
+    roughness: f32,
+}
+
+impl SheenMaterial {
+    /// # Arguments
+    /// * `diffuse` - color of the base, black for a standalone sheen lobe
+    /// * `sheen` - color of the sheen
+    /// * `roughness` - in [0, 1], larger values spread the sheen further from the edges
+    pub fn new(diffuse: &Spectrum, sheen: &Spectrum, roughness: f32) -> SheenMaterial {
+        assert!(roughness >= 0.0 && roughness <= 1.0);
+        SheenMaterial {
+            diffuse: *diffuse,
+            sheen: *sheen,
+            // The distribution degenerates as roughness approaches zero.
+            roughness: roughness.max(0.07),
+        }
+    }
+
+    /// Amount of the sheen lobe in the BRDF, before scaling by the sheen color.
+    fn sheen_weight(&self, light: &Vector, view: &Vector, normal: &Vector) -> f32 {
+        let n_dot_l = normal.dot(light);
+        let n_dot_v = normal.dot(view);
+        if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+            return 0.0;
+        }
+
+        let mut half = *light + *view;
+        if half.normalize().is_err() {
+            return 0.0;
+        }
+        let cos_theta_h = normal.dot(&half).min(1.0);
+        let sin_theta_h = (1.0 - cos_theta_h * cos_theta_h).sqrt();
+
+        let inv_roughness = 1.0 / self.roughness;
+        let distribution = (2.0 + inv_roughness) * sin_theta_h.powf(inv_roughness) / (2.0 * PI);
+        let visibility = 1.0 / (4.0 * (n_dot_l + n_dot_v - n_dot_l * n_dot_v));
+        PI * distribution * visibility
+    }
+}
+
+impl Material for SheenMaterial {
+    fn f(&self, light: &Vector, view: &Vector, normal: &Vector) -> Spectrum {
+        self.diffuse + self.sheen_weight(light, view, normal) * self.sheen
+    }
+
+    fn name(&self) -> &'static str {
+        "sheen"
+    }
+}
+
+/// Physically based Cook-Torrance material, covering plastics through metals.
+///
+/// Specular reflection uses the GGX (Trowbridge-Reitz) distribution of microfacet normals, the
+/// separable Smith shadowing and masking term for GGX, and Schlick's approximation of Fresnel
+/// reflectance.  Dielectrics reflect 4% of light head on, with the rest diffusely reflected in
+/// the base color, while metals tint their reflections by the base color and have no diffuse
+/// reflection.
+///
+/// Light bouncing between microfacets more than once is restored with the energy compensation
+/// of Kulla and Conty's "Revisiting Physically Based Shading at Imageworks", so rough metals do
+/// not darken.
+///
+/// Only light arriving from lights and the environment is reflected, so other surfaces are not
+/// seen in the reflections of smooth materials.
+pub struct MicrofacetMaterial {
+    base_color: Spectrum,
+    // GGX width, the square of the perceptual roughness.
+    alpha: f32,
+    metalness: f32,
+    // Fraction of light reflected after a single bounce, ignoring Fresnel, for views spaced
+    // evenly in the cosine of their angle from the normal.
+    single_scatter_albedo: Vec<f32>,
+    // Single bounce albedo averaged over the hemisphere, weighted by the cosine.
+    average_albedo: f32,
+}
+
+/// Reflectance at normal incidence of common dielectrics.
+const DIELECTRIC_REFLECTANCE: f32 = 0.04;
+
+/// Narrowest GGX width, since the distribution becomes a delta function as it approaches zero.
+const MIN_ALPHA: f32 = 1e-3;
+
+/// Views at which the single bounce albedo of microfacet materials is tabulated.
+const ALBEDO_TABLE_SIZE: usize = 32;
+
+/// Half vectors sampled along each axis of a grid when tabulating single bounce albedo.
+const ALBEDO_SAMPLES: usize = 32;
+
+impl MicrofacetMaterial {
+    /// # Arguments
+    /// * `base_color` - diffuse color of dielectrics, and reflectance of metals
+    /// * `roughness` - in [0, 1], from mirror-like to fully rough
+    /// * `metalness` - in [0, 1], from a dielectric to a metal
+    ///
+    /// # Panics
+    /// If roughness or metalness are outside of [0, 1].
+    pub fn new(base_color: &Spectrum, roughness: f32, metalness: f32) -> MicrofacetMaterial {
+        assert!(roughness >= 0.0 && roughness <= 1.0, "Roughness must be in [0, 1].");
+        assert!(metalness >= 0.0 && metalness <= 1.0, "Metalness must be in [0, 1].");
+        let mut material = MicrofacetMaterial {
+            base_color: *base_color,
+            alpha: (roughness * roughness).max(MIN_ALPHA),
+            metalness: metalness,
+            single_scatter_albedo: Vec::new(),
+            average_albedo: 1.0,
+        };
+        material.single_scatter_albedo = (0..ALBEDO_TABLE_SIZE)
+            .map(|i| {
+                material.integrate_single_scatter((i as f32 + 0.5) / ALBEDO_TABLE_SIZE as f32)
+            })
+            .collect();
+        material.average_albedo = 2.0 *
+            material
+                .single_scatter_albedo
+                .iter()
+                .enumerate()
+                .map(|(i, e)| e * (i as f32 + 0.5) / ALBEDO_TABLE_SIZE as f32)
+                .sum::<f32>() / ALBEDO_TABLE_SIZE as f32;
+        material
+    }
+
+    /// Integrates the single bounce specular lobe, with a Fresnel reflectance of 1, for a view at
+    /// `cos_view` from the normal.
+    ///
+    /// Half vectors are placed in proportion to the GGX distribution, so narrow lobes are
+    /// integrated as accurately as wide ones.
+    fn integrate_single_scatter(&self, cos_view: f32) -> f32 {
+        let view = Vector::new((1.0 - cos_view * cos_view).sqrt(), cos_view, 0.0);
+        let mut total = 0.0;
+        for i in 0..ALBEDO_SAMPLES {
+            for j in 0..ALBEDO_SAMPLES {
+                let u = (
+                    (i as f32 + 0.5) / ALBEDO_SAMPLES as f32,
+                    (j as f32 + 0.5) / ALBEDO_SAMPLES as f32,
+                );
+                let half = ggx_half_vector(self.alpha, u);
+                let cos_half = half.y;
+                let v_dot_h = view.dot(&half);
+                let cos_light = (-view).reflect(&half).y;
+                if v_dot_h <= 0.0 || cos_light <= 0.0 {
+                    continue;
+                }
+                total += self.smith_g1(cos_light) * self.smith_g1(cos_view) * v_dot_h /
+                    (cos_half * cos_view);
+            }
+        }
+        (total / (ALBEDO_SAMPLES * ALBEDO_SAMPLES) as f32).min(1.0)
+    }
+
+    /// Single bounce albedo for a view at `cos_theta` from the normal, see
+    /// `single_scatter_albedo`.
+    fn single_scatter(&self, cos_theta: f32) -> f32 {
+        let position = (cos_theta * ALBEDO_TABLE_SIZE as f32 - 0.5)
+            .max(0.0)
+            .min((ALBEDO_TABLE_SIZE - 1) as f32);
+        let lower = position.floor() as usize;
+        let upper = (lower + 1).min(ALBEDO_TABLE_SIZE - 1);
+        let t = position - lower as f32;
+        (1.0 - t) * self.single_scatter_albedo[lower] + t * self.single_scatter_albedo[upper]
+    }
+
+    /// Light reflected after bouncing between microfacets more than once, scaled by π.
+    fn multiple_scatter(&self, n_dot_l: f32, n_dot_v: f32) -> Spectrum {
+        let missing = 1.0 - self.average_albedo;
+        if missing <= 0.0 {
+            return Spectrum::black();
+        }
+        let lobe = (1.0 - self.single_scatter(n_dot_l)) * (1.0 - self.single_scatter(n_dot_v)) /
+            missing;
+
+        // Each further bounce is tinted by the Fresnel reflectance averaged over the hemisphere.
+        let tint = |f0: f32| {
+            let average_fresnel = f0 + (1.0 - f0) / 21.0;
+            average_fresnel * average_fresnel * self.average_albedo /
+                (1.0 - average_fresnel * missing)
+        };
+        lobe * self.f0().map(tint)
+    }
+
+    /// Reflectance at normal incidence.
+    fn f0(&self) -> Spectrum {
+        Spectrum::uniform(DIELECTRIC_REFLECTANCE).lerp(&self.base_color, self.metalness)
+    }
+
+    /// GGX density of microfacets facing along a direction at `cos_theta` from the normal.
+    fn distribution(&self, cos_theta: f32) -> f32 {
+        let alpha2 = self.alpha * self.alpha;
+        let denominator = cos_theta * cos_theta * (alpha2 - 1.0) + 1.0;
+        alpha2 / (PI * denominator * denominator)
+    }
+
+    /// Fraction of microfacets visible from a direction at `cos_theta` from the normal.
+    fn smith_g1(&self, cos_theta: f32) -> f32 {
+        ggx_smith_g1(self.alpha, cos_theta)
+    }
+}
+
+/// Fraction of microfacets of GGX width `alpha` visible from a direction at `cos_theta` from the
+/// normal.
+fn ggx_smith_g1(alpha: f32, cos_theta: f32) -> f32 {
+    let alpha2 = alpha * alpha;
+    2.0 * cos_theta / (cos_theta + (alpha2 + (1.0 - alpha2) * cos_theta * cos_theta).sqrt())
+}
+
+/// Maps a position in the unit square onto the direction a microfacet of GGX width `alpha`
+/// faces, in proportion to the density of microfacets facing each direction times their
+/// projected area, in a frame with the normal along Y.
+fn ggx_half_vector(alpha: f32, u: (f32, f32)) -> Vector {
+    let cos_half = ((1.0 - u.0) / (1.0 + u.0 * (alpha * alpha - 1.0))).sqrt();
+    let sin_half = (1.0 - cos_half * cos_half).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    Vector::new(sin_half * phi.cos(), cos_half, sin_half * phi.sin())
+}
+
+/// Schlick's approximation of Fresnel reflectance, given the reflectance at normal incidence.
+fn schlick_fresnel(f0: &Spectrum, cos_theta: f32) -> Spectrum {
+    let weight = (1.0 - cos_theta).max(0.0).powi(5);
+    f0.lerp(&Spectrum::uniform(1.0), weight)
+}
+
+impl Material for MicrofacetMaterial {
+    fn f(&self, light: &Vector, view: &Vector, normal: &Vector) -> Spectrum {
+        let n_dot_l = normal.dot(light).min(1.0);
+        let n_dot_v = normal.dot(view).min(1.0);
+        if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+            return Spectrum::black();
+        }
+        let mut half = *light + *view;
+        if half.normalize().is_err() {
+            return Spectrum::black();
+        }
+
+        let fresnel = schlick_fresnel(&self.f0(), view.dot(&half).max(0.0));
+        let shadowing = self.smith_g1(n_dot_l) * self.smith_g1(n_dot_v);
+        let specular = (self.distribution(normal.dot(&half).min(1.0)) * shadowing /
+                            (4.0 * n_dot_l * n_dot_v)) * fresnel;
+
+        // Light not reflected on the way in or out of dielectrics scatters diffusely.  Weighting
+        // by the transmission at both the light and the view keeps the BRDF reciprocal, without
+        // reflecting more than received at grazing angles.
+        let transmitted = |cos_theta: f32| {
+            1.0 - DIELECTRIC_REFLECTANCE -
+                (1.0 - DIELECTRIC_REFLECTANCE) * (1.0 - cos_theta).powi(5)
+        };
+        let diffuse = ((1.0 - self.metalness) * transmitted(n_dot_l) * transmitted(n_dot_v)) *
+            self.base_color;
+
+        // BRDFs are scaled by π.
+        diffuse + PI * specular + self.multiple_scatter(n_dot_l, n_dot_v)
+    }
+
+    fn name(&self) -> &'static str {
+        "microfacet"
+    }
+}
+
+/// Diffuse grid of colored squares showing texture coordinates, for finding surfaces with
+/// missing, stretched or flipped coordinates.
+///
+/// Each unit of texture coordinates is split into a grid of `UV_CHECKER_CELLS` squares.  Red
+/// increases with U and green with V across the grid, and alternate squares are darker.
+pub struct UvCheckerMaterial;
+
+/// Number of squares along each side of a unit of texture coordinates.
+pub const UV_CHECKER_CELLS: u32 = 8;
+
+impl UvCheckerMaterial {
+    pub fn new() -> UvCheckerMaterial {
+        UvCheckerMaterial
+    }
+
+    /// Color of the grid at texture coordinates `uv`.
+    pub fn color(&self, uv: (f32, f32)) -> Spectrum {
+        let cells = UV_CHECKER_CELLS as i64;
+        let cell = |t: f32| (((t * cells as f32).floor() as i64 % cells) + cells) % cells;
+        let (column, row) = (cell(uv.0), cell(uv.1));
+        let level = |i: i64| (i as f32 + 0.5) / cells as f32;
+        let (red, green) = (level(column), level(row));
+        let color = Spectrum::new(red, green, 1.0 - 0.5 * (red + green));
+        if (column + row) % 2 == 0 {
+            color
+        } else {
+            0.5 * color
+        }
+    }
+}
+
+impl Material for UvCheckerMaterial {
+    #[allow(unused_variables)]
+    fn f(&self, light: &Vector, view: &Vector, normal: &Vector) -> Spectrum {
+        self.color((0.0, 0.0))
+    }
+
+    #[allow(unused_variables)]
+    fn f_at(
+        &self,
+        light: &Vector,
+        view: &Vector,
+        normal: &Vector,
+        uv: (f32, f32),
+        point: &Point,
+    ) -> Spectrum {
+        self.color(uv)
+    }
+
+    fn name(&self) -> &'static str {
+        "UV checker"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlinnPhongMaterial, DielectricMaterial, LambertianMaterial, Material,
+                MicrofacetMaterial, OrenNayarMaterial, ScatterKind, ScatteredRay, SheenMaterial,
+                SpecularMaterial, UvCheckerMaterial, UV_CHECKER_CELLS, fresnel_reflectance};
+    use math::{Intersection, PlanarAngle, Point, Vector};
+    use rand::{Rng, XorShiftRng};
+    use scene::Spectrum;
+    use scene::energy::directional_albedo;
+    use scene::texture::{CheckerTexture, ConstantTexture};
+
+    /// The rays a material scatters from a surface, given a random `sample`.
+    fn scatter(
+        material: &Material,
+        incident: &Vector,
+        normal: &Vector,
+        sample: f32,
+    ) -> Vec<ScatteredRay> {
+        let mut scattered = Vec::new();
+        material.scatter(incident, normal, 1.0, sample, &mut scattered);
+        scattered
+    }
+
+    #[test]
+    fn test_only_specular_reflects() {
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let incident = Vector::unit(1.0, -1.0, 0.0).unwrap();
+        let lambertian = LambertianMaterial::new(&Spectrum::uniform(1.0));
+        assert!(scatter(&lambertian, &incident, &normal, 0.5).is_empty());
+
+        let gold = Spectrum::new(1.0, 0.8, 0.3);
+        let mirror = SpecularMaterial::new(&gold);
+        let scattered = scatter(&mirror, &incident, &normal, 0.5);
+        assert_eq!(scattered.len(), 1);
+        assert_relative_eq!(scattered[0].direction, Vector::unit(1.0, 1.0, 0.0).unwrap());
+        assert_relative_eq!(scattered[0].weight, gold);
+    }
+
+    #[test]
+    fn test_dielectric_straight_through() {
+        let glass = DielectricMaterial::new(1.5);
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let down = Vector::new(0.0, -1.0, 0.0);
+
+        // 4% of light is reflected from glass head on, so 4% of samples reflect.
+        let reflected = scatter(&glass, &down, &normal, 0.039);
+        assert_eq!(reflected.len(), 1);
+        assert_relative_eq!(reflected[0].direction, Vector::new(0.0, 1.0, 0.0));
+        assert_relative_eq!(reflected[0].weight, Spectrum::uniform(1.0));
+        assert_eq!(reflected[0].kind, ScatterKind::Glossy);
+
+        let transmitted = scatter(&glass, &down, &normal, 0.041);
+        assert_eq!(transmitted.len(), 1);
+        assert_relative_eq!(transmitted[0].direction, down);
+        assert_relative_eq!(transmitted[0].weight, Spectrum::uniform(1.0));
+        assert_eq!(transmitted[0].kind, ScatterKind::Transmission);
+    }
+
+    #[test]
+    fn test_dielectric_conserves_energy() {
+        let glass = DielectricMaterial::new(1.5);
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let incident = Vector::unit(1.0, -0.5, 0.0).unwrap();
+
+        // Averaged over samples, the weights of all scattered rays sum to one.
+        const SAMPLES: usize = 1000;
+        let total = (0..SAMPLES)
+            .flat_map(|i| scatter(&glass, &incident, &normal, (i as f32 + 0.5) / SAMPLES as f32))
+            .fold(0.0, |total, ray| total + ray.weight.r);
+        assert_relative_eq!(total / SAMPLES as f32, 1.0, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_dielectric_snells_law() {
+        let glass = DielectricMaterial::new(1.5);
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let incident = Vector::unit(1.0, -1.0, 0.0).unwrap();
+
+        // Entering bends toward the normal.
+        let inside = scatter(&glass, &incident, &normal, 0.99)[0].direction;
+        assert_relative_eq!(inside.length(), 1.0, epsilon = 1e-6);
+        assert_relative_eq!(inside.x, incident.x / 1.5, epsilon = 1e-6);
+
+        // Leaving through the parallel far side of a slab restores the original direction.
+        let outside = scatter(&glass, &inside, &-normal, 0.99)[0].direction;
+        assert_relative_eq!(outside, incident, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_dielectric_relative_index() {
+        let glass = DielectricMaterial::new(1.5);
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let incident = Vector::unit(1.0, -1.0, 0.0).unwrap();
+        let scatter_from = |outside_index: f32, sample: f32| {
+            let mut scattered = Vec::new();
+            glass.scatter(&incident, &normal, outside_index, sample, &mut scattered);
+            scattered[0]
+        };
+
+        // Glass in a liquid of the same index of refraction neither reflects nor bends light.
+        let matched = scatter_from(1.5, 0.0);
+        assert_eq!(matched.kind, ScatterKind::Transmission);
+        assert_relative_eq!(matched.direction, incident, epsilon = 1e-6);
+
+        // From water, light bends by the ratio of the indices.
+        let from_water = scatter_from(1.33, 0.99).direction;
+        assert_relative_eq!(from_water.x, incident.x * 1.33 / 1.5, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_fresnel_reflectance() {
+        // 4% of light is reflected head on from either side of glass, none between media of the
+        // same index, and all of it beyond the critical angle inside the glass.
+        assert_relative_eq!(fresnel_reflectance(1.0 / 1.5, 1.0), 0.04, epsilon = 1e-6);
+        assert_relative_eq!(fresnel_reflectance(1.5, 1.0), 0.04, epsilon = 1e-6);
+        assert_relative_eq!(fresnel_reflectance(1.0, 0.3), 0.0);
+        assert_relative_eq!(fresnel_reflectance(1.5, 0.5), 1.0);
+        // Reflection grows toward grazing angles.
+        assert!(fresnel_reflectance(1.0 / 1.5, 0.1) > fresnel_reflectance(1.0 / 1.5, 0.9));
+    }
+
+    #[test]
+    fn test_dielectric_total_internal_reflection() {
+        let glass = DielectricMaterial::new(1.5);
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let grazing = Vector::unit(1.0, 0.2, 0.0).unwrap();
+        let scattered = scatter(&glass, &grazing, &normal, 0.99);
+        assert_eq!(scattered.len(), 1);
+        assert_relative_eq!(scattered[0].direction, Vector::unit(1.0, -0.2, 0.0).unwrap());
+    }
+
+    #[test]
+    fn test_rough_dielectric() {
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let incident = Vector::unit(1.0, -1.0, 0.0).unwrap();
+        let surface = Intersection {
+            time: 0.0,
+            point: Point::new(0.0, 0.0, 0.0),
+            normal: normal,
+            uv: (0.25, 0.25),
+            tangent: Vector::new(1.0, 0.0, 0.0),
+            bitangent: Vector::new(0.0, 0.0, 1.0),
+            triangle: None,
+        };
+        let scatter_at = |glass: &DielectricMaterial, samples: [f32; 3]| {
+            let mut scattered = Vec::new();
+            glass.scatter_at(&incident, &normal, 1.0, &surface, samples, &mut scattered);
+            scattered
+        };
+
+        // Smooth glass ignores the position in the unit square.
+        let smooth = DielectricMaterial::new(1.5).with_roughness(0.0);
+        let refracted = scatter(&smooth, &incident, &normal, 0.99)[0].direction;
+        assert_relative_eq!(scatter_at(&smooth, [0.99, 0.3, 0.7])[0].direction, refracted);
+
+        // Frosted glass spreads light around the directions smooth glass reflects and refracts
+        // it in, losing little to shadowing between microfacets.
+        let frosted = DielectricMaterial::new(1.5).with_roughness(0.5);
+        let mut rng = XorShiftRng::new_unseeded();
+        const SAMPLES: usize = 4000;
+        let mut total = 0.0;
+        let mut transmitted = Vector::new(0.0, 0.0, 0.0);
+        let mut spread = false;
+        for _ in 0..SAMPLES {
+            for ray in scatter_at(&frosted, rng.gen()) {
+                total += ray.weight.r;
+                match ray.kind {
+                    ScatterKind::Transmission => {
+                        assert!(ray.direction.y < 0.0);
+                        transmitted = transmitted + ray.weight.r * ray.direction;
+                        spread = spread || (ray.direction - refracted).length() > 0.1;
+                    }
+                    _ => assert!(ray.direction.y > 0.0),
+                }
+            }
+        }
+        let average = total / SAMPLES as f32;
+        assert!(average > 0.9 && average <= 1.01, "Average weight {}", average);
+        assert!(spread);
+        transmitted.normalize().unwrap();
+        assert!(transmitted.dot(&refracted) > 0.95);
+
+        // Roughness textures frost only part of the surface.
+        let checker = CheckerTexture::new(
+            Box::new(ConstantTexture::new(&Spectrum::black())),
+            Box::new(ConstantTexture::new(&Spectrum::uniform(1.0))),
+            2.0,
+        );
+        let patterned = DielectricMaterial::new(1.5).with_roughness_texture(Box::new(checker));
+        assert_relative_eq!(scatter_at(&patterned, [0.99, 0.3, 0.7])[0].direction, refracted);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_rough_dielectric_roughness_range() {
+        DielectricMaterial::new(1.5).with_roughness(1.5);
+    }
+
+    #[test]
+    fn test_oren_nayar_smooth_is_lambertian() {
+        let color = Spectrum::new(0.5, 0.25, 1.0);
+        let smooth = OrenNayarMaterial::new(&color, PlanarAngle::Degrees(0.0));
+        let lambertian = LambertianMaterial::new(&color);
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let light = Vector::unit(1.0, 1.0, 0.0).unwrap();
+        let view = Vector::unit(-0.5, 1.0, 0.3).unwrap();
+
+        assert_relative_eq!(
+            smooth.f(&light, &view, &normal),
+            lambertian.f(&light, &view, &normal)
+        );
+    }
+
+    #[test]
+    fn test_oren_nayar_backscatter() {
+        // Rough surfaces reflect more light back toward a light at a grazing angle.
+        let white = Spectrum::uniform(1.0);
+        let rough = OrenNayarMaterial::new(&white, PlanarAngle::Degrees(30.0));
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let light = Vector::unit(1.0, 0.5, 0.0).unwrap();
+
+        let toward_light = rough.f(&light, &light, &normal);
+        let away_from_light = rough.f(&light, &Vector::unit(-1.0, 0.5, 0.0).unwrap(), &normal);
+        assert!(toward_light.r > away_from_light.r);
+        assert!(away_from_light.r < 1.0);
+    }
+
+    #[test]
+    fn test_sheen_strongest_at_grazing_angles() {
+        let black = Spectrum::black();
+        let white = Spectrum::uniform(1.0);
+        let m = SheenMaterial::new(&black, &white, 0.5);
+        let normal = Vector::new(0.0, 1.0, 0.0);
+
+        let head_on = m.sheen_weight(&normal, &normal, &normal);
+        let grazing_light = Vector::unit(1.0, 0.1, 0.0).unwrap();
+        let grazing_view = Vector::unit(1.0, 0.2, 0.1).unwrap();
+        let grazing = m.sheen_weight(&grazing_light, &grazing_view, &normal);
+
+        assert_relative_eq!(head_on, 0.0);
+        assert!(grazing > 0.1);
+        assert_relative_eq!(m.sheen_weight(&-normal, &normal, &normal), 0.0);
+    }
+
+    #[test]
+    fn test_microfacet_white_furnace() {
+        // A white metal reflects all of the light it receives, however rough.  Smoother metals
+        // have highlights too narrow for the albedo to be integrated accurately.
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let white = Spectrum::uniform(1.0);
+        for &roughness in [0.5, 0.75, 1.0].iter() {
+            let metal = MicrofacetMaterial::new(&white, roughness, 1.0);
+            for &cos_view in [1.0f32, 0.5, 0.2].iter() {
+                let view = Vector::new((1.0 - cos_view * cos_view).sqrt(), cos_view, 0.0);
+                let albedo = directional_albedo(&metal, &view, &normal);
+                assert_relative_eq!(albedo, white, epsilon = 0.02);
+            }
+        }
+    }
+
+    #[test]
+    fn test_blinn_phong() {
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let light = Vector::unit(1.0, 1.0, 0.0).unwrap();
+        let mirror = Vector::unit(-1.0, 1.0, 0.0).unwrap();
+        let aside = Vector::unit(0.0, 1.0, 1.0).unwrap();
+        let diffuse = Spectrum::new(0.5, 0.25, 0.0);
+        let glossy = BlinnPhongMaterial::new(&diffuse, &Spectrum::uniform(0.25), 100.0);
+
+        // Highlights are brightest in the mirror direction, and vanish far from it.
+        assert!(glossy.f(&light, &mirror, &normal).r > 1.0);
+        assert_relative_eq!(glossy.f(&light, &aside, &normal), diffuse, epsilon = 1e-5);
+        assert_relative_eq!(
+            glossy.f(&light, &aside, &normal),
+            glossy.f(&aside, &light, &normal)
+        );
+        assert_relative_eq!(glossy.f(&-light, &mirror, &normal), Spectrum::black());
+    }
+
+    #[test]
+    fn test_microfacet() {
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let light = Vector::unit(1.0, 1.0, 0.0).unwrap();
+        let mirror = Vector::unit(-1.0, 1.0, 0.0).unwrap();
+        let aside = Vector::unit(0.0, 1.0, 1.0).unwrap();
+        let gold = Spectrum::new(1.0, 0.8, 0.3);
+
+        // Reflection is reciprocal.
+        let plastic = MicrofacetMaterial::new(&gold, 0.5, 0.0);
+        assert_relative_eq!(
+            plastic.f(&light, &aside, &normal),
+            plastic.f(&aside, &light, &normal),
+            epsilon = 1e-5
+        );
+        assert_relative_eq!(plastic.f(&-light, &mirror, &normal), Spectrum::black());
+
+        // Smooth surfaces concentrate reflections toward the mirror direction.
+        let smooth = MicrofacetMaterial::new(&gold, 0.2, 1.0);
+        let rough = MicrofacetMaterial::new(&gold, 0.8, 1.0);
+        assert!(smooth.f(&light, &mirror, &normal).r > rough.f(&light, &mirror, &normal).r);
+        assert!(smooth.f(&light, &aside, &normal).r < rough.f(&light, &aside, &normal).r);
+
+        // Metals have no diffuse reflection, so they are dark away from the highlight, and
+        // their reflections are tinted.
+        let highlight = smooth.f(&light, &mirror, &normal);
+        assert!(highlight.b < highlight.r);
+        assert!(smooth.f(&light, &aside, &normal).r < plastic.f(&light, &aside, &normal).r);
+    }
+
+    #[test]
+    fn test_uv_checker() {
+        let checker = UvCheckerMaterial::new();
+        let cell = 1.0 / UV_CHECKER_CELLS as f32;
+
+        // Neighboring squares alternate in brightness.
+        let first = checker.color((0.5 * cell, 0.5 * cell));
+        let next = checker.color((1.5 * cell, 0.5 * cell));
+        assert!(next.g < first.g);
+
+        // Red increases with U, and green with V.
+        let far_u = checker.color((2.5 * cell, 0.5 * cell));
+        assert!(far_u.r > first.r);
+        assert_relative_eq!(far_u.g, first.g);
+        let far_v = checker.color((0.5 * cell, 2.5 * cell));
+        assert!(far_v.g > first.g);
+        assert_relative_eq!(far_v.r, first.r);
+
+        // The grid repeats every unit, including below zero.
+        assert_relative_eq!(checker.color((1.0 + 0.5 * cell, 0.5 * cell)), first);
+        assert_relative_eq!(checker.color((-1.0 + 0.5 * cell, 0.5 * cell)), first);
+
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        assert_relative_eq!(
+            checker.f_at(
+                &normal,
+                &normal,
+                &normal,
+                (2.5 * cell, 0.5 * cell),
+                &Point::new(0.0, 0.0, 0.0),
+            ),
+            far_u
+        );
     }
 }
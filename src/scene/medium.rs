@@ -0,0 +1,266 @@
+//! Media filling transparent entities, and the media a path is inside as it is traced.
+//!
+//! Overlapping transparent entities, such as ice floating in water in a glass, are resolved by
+//! priority following Schmidt and Budge's "Simple Nested Dielectrics in Ray Traced Images".  The
+//! medium of the entity with the highest priority fills the space where entities overlap, so
+//! surfaces of lower priority entities inside it are passed through without scattering, and
+//! surfaces which are hit refract between the media on either side of them.
+//!
+//! Light travelling through a medium is absorbed by the Beer-Lambert law, so thick parts of
+//! colored glass are darker than thin ones.  Rays escaping through holes in an entity's surface
+//! see the environment unabsorbed.
+//!
+//! Cameras are assumed to be outside every entity, and the normals of their surfaces to point out
+//! of them.
+use math::Vector;
+use scene::Spectrum;
+
+/// The material filling the inside of a transparent entity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Medium {
+    pub index_of_refraction: f32,
+    /// Where entities overlap, the medium with the highest priority fills the overlap.
+    pub priority: u32,
+    /// Fraction of light absorbed in each channel per unit of distance travelled, black for a
+    /// clear medium.
+    pub absorption: Spectrum,
+}
+
+impl Medium {
+    /// Fraction of light in each channel which travels `distance` through the medium without
+    /// being absorbed.
+    pub fn transmittance(&self, distance: f32) -> Spectrum {
+        self.absorption.map(|absorption| (-absorption * distance).exp())
+    }
+}
+
+/// Most media a path may be inside at once, entering more is ignored.
+const MAX_NESTED_MEDIA: usize = 8;
+
+/// The media of the entities a path is inside, in the order they were entered.
+///
+/// Stacks are copied for each scattered ray rather than allocated, so tracing does not allocate.
+#[derive(Clone, Copy, Debug)]
+pub struct MediumStack {
+    /// Entities entered, by their position in the order entities were added, with their media.
+    entered: [(usize, Medium); MAX_NESTED_MEDIA],
+    len: usize,
+}
+
+impl MediumStack {
+    /// Stack for a path outside every entity.
+    pub fn new() -> MediumStack {
+        let vacuum = Medium {
+            index_of_refraction: 1.0,
+            priority: 0,
+            absorption: Spectrum::black(),
+        };
+        MediumStack {
+            entered: [(0, vacuum); MAX_NESTED_MEDIA],
+            len: 0,
+        }
+    }
+
+    /// The medium the path is travelling through, `None` outside every entity.
+    pub fn current(&self) -> Option<Medium> {
+        self.highest_priority(None)
+    }
+
+    /// Fraction of light in each channel which travels `distance` through the current medium.
+    pub fn transmittance(&self, distance: f32) -> Spectrum {
+        match self.current() {
+            Some(medium) => medium.transmittance(distance),
+            None => Spectrum::uniform(1.0),
+        }
+    }
+
+    /// The medium bordering an entity's surface, on the other side from the entity's own medium.
+    ///
+    /// # Returns
+    /// `None` if only a vacuum borders the entity.
+    pub fn outside(&self, entity: usize) -> Option<Medium> {
+        self.highest_priority(Some(entity))
+    }
+
+    /// Whether an entity's surface lies inside a medium of higher priority, so rays pass through
+    /// it without scattering.
+    pub fn is_hidden(&self, entity: usize, medium: &Medium) -> bool {
+        match self.outside(entity) {
+            Some(outside) => outside.priority > medium.priority,
+            None => false,
+        }
+    }
+
+    /// The stack after passing into an entity.
+    pub fn entered(&self, entity: usize, medium: &Medium) -> MediumStack {
+        let mut stack = *self;
+        if stack.len < MAX_NESTED_MEDIA {
+            stack.entered[stack.len] = (entity, *medium);
+            stack.len += 1;
+        }
+        stack
+    }
+
+    /// The stack after a ray crosses an entity's surface, entering the entity if the ray travels
+    /// against the outward normal of the surface and leaving it otherwise.
+    pub fn crossed(
+        &self,
+        entity: usize,
+        medium: &Medium,
+        direction: &Vector,
+        normal: &Vector,
+    ) -> MediumStack {
+        if direction.dot(normal) < 0.0 {
+            self.entered(entity, medium)
+        } else {
+            self.left(entity)
+        }
+    }
+
+    /// The stack after passing out of an entity, unchanged if the path was not inside it.
+    pub fn left(&self, entity: usize) -> MediumStack {
+        let mut stack = *self;
+        if let Some(index) = stack.entered[..stack.len]
+            .iter()
+            .rposition(|&(inside, _)| inside == entity)
+        {
+            for next in index..stack.len - 1 {
+                stack.entered[next] = stack.entered[next + 1];
+            }
+            stack.len -= 1;
+        }
+        stack
+    }
+
+    /// The medium of highest priority, the most recently entered among equals.
+    ///
+    /// # Arguments
+    /// * `excluded` - entity whose medium is skipped.
+    fn highest_priority(&self, excluded: Option<usize>) -> Option<Medium> {
+        let mut highest: Option<Medium> = None;
+        for &(entity, medium) in self.entered[..self.len].iter() {
+            if Some(entity) == excluded {
+                continue;
+            }
+            highest = match highest {
+                Some(current) if current.priority > medium.priority => Some(current),
+                _ => Some(medium),
+            };
+        }
+        highest
+    }
+}
+
+impl Default for MediumStack {
+    fn default() -> MediumStack {
+        MediumStack::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Medium, MediumStack, MAX_NESTED_MEDIA};
+    use math::Vector;
+    use scene::Spectrum;
+
+    fn medium(index_of_refraction: f32, priority: u32) -> Medium {
+        Medium {
+            index_of_refraction: index_of_refraction,
+            priority: priority,
+            absorption: Spectrum::black(),
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        let stack = MediumStack::new();
+        assert!(stack.current().is_none());
+        assert!(stack.outside(0).is_none());
+        assert!(!stack.is_hidden(0, &medium(1.5, 0)));
+        assert!(stack.left(0).current().is_none());
+    }
+
+    #[test]
+    fn test_ice_in_water_in_glass() {
+        let glass = medium(1.5, 3);
+        let water = medium(1.33, 1);
+        let ice = medium(1.31, 2);
+
+        // Entering the glass, then the water filling it, whose surface against the glass is
+        // hidden by the higher priority glass.
+        let in_glass = MediumStack::new().entered(0, &glass);
+        assert_eq!(in_glass.current(), Some(glass));
+        assert!(in_glass.is_hidden(1, &water));
+        let in_water = in_glass.entered(1, &water);
+        assert_eq!(in_water.current(), Some(glass));
+
+        // Leaving the glass leaves the path in the water.
+        let in_water = in_water.left(0);
+        assert_eq!(in_water.current(), Some(water));
+        assert_eq!(in_water.outside(2), Some(water));
+        assert!(!in_water.is_hidden(2, &ice));
+
+        // The ice's surface borders the water, and the ice fills its inside.
+        let in_ice = in_water.entered(2, &ice);
+        assert_eq!(in_ice.outside(2), Some(water));
+        assert_eq!(in_ice.current(), Some(ice));
+        assert_eq!(in_ice.left(2).current(), Some(water));
+    }
+
+    #[test]
+    fn test_equal_priority_most_recent() {
+        let water = medium(1.33, 0);
+        let oil = medium(1.47, 0);
+        let stack = MediumStack::new().entered(0, &water).entered(1, &oil);
+        assert_eq!(stack.current(), Some(oil));
+        assert_eq!(stack.outside(1), Some(water));
+        assert!(!stack.is_hidden(0, &water));
+        assert_eq!(stack.left(1).current(), Some(water));
+        assert_eq!(stack.left(0).current(), Some(oil));
+    }
+
+    #[test]
+    fn test_crossed() {
+        let glass = medium(1.5, 0);
+        let outward = Vector::new(0.0, 0.0, -1.0);
+        let forward = Vector::new(0.0, 0.0, 1.0);
+        let inside = MediumStack::new().crossed(0, &glass, &forward, &outward);
+        assert_eq!(inside.current(), Some(glass));
+        // Leaving through the far side, whose normal points along the ray.
+        let outside = inside.crossed(0, &glass, &forward, &-outward);
+        assert!(outside.current().is_none());
+    }
+
+    #[test]
+    fn test_transmittance() {
+        let red_glass = Medium {
+            absorption: Spectrum::new(0.0, 1.0, 2.0),
+            ..medium(1.5, 0)
+        };
+        assert_relative_eq!(red_glass.transmittance(0.0), Spectrum::uniform(1.0));
+        let expected = Spectrum::new(1.0, (-0.5f32).exp(), (-1.0f32).exp());
+        assert_relative_eq!(red_glass.transmittance(0.5), expected);
+        // Twice the thickness transmits the square.
+        assert_relative_eq!(red_glass.transmittance(1.0), expected * expected);
+
+        let stack = MediumStack::new();
+        assert_relative_eq!(stack.transmittance(10.0), Spectrum::uniform(1.0));
+        let stack = stack.entered(0, &red_glass);
+        assert_relative_eq!(stack.transmittance(0.5), expected);
+    }
+
+    #[test]
+    fn test_too_deep() {
+        let glass = medium(1.5, 0);
+        let mut stack = MediumStack::new();
+        for entity in 0..MAX_NESTED_MEDIA + 2 {
+            stack = stack.entered(entity, &glass);
+        }
+        assert!(stack.outside(0).is_some());
+        assert!(stack.outside(MAX_NESTED_MEDIA).is_some());
+        for entity in 0..MAX_NESTED_MEDIA + 2 {
+            stack = stack.left(entity);
+        }
+        assert!(stack.current().is_none());
+    }
+}
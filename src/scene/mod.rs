@@ -19,60 +19,544 @@
 //! ## World space
 //! A left-handed coordinate system with X to the right, Y is up, and Z is into the screen.
 #![allow(dead_code)]
+pub mod aov;
+pub mod area_light;
+pub mod backplate;
 pub mod camera;
 pub mod dimensions;
+pub mod emitter;
+pub mod energy;
+pub mod environment;
+pub mod light_grid;
+pub mod loader;
 pub mod nonarea_light;
 pub mod material;
-pub use self::camera::{Camera, Film, Perspective, Projection};
-use self::nonarea_light::NonAreaLight;
-use self::material::Material;
+pub mod medium;
+pub mod overlay;
+pub mod query;
+pub mod sampler;
+pub mod scratch;
+pub mod stats;
+pub mod texture;
+pub mod tile;
+pub mod toon;
+pub mod visibility;
+pub mod wireframe;
+pub use self::camera::{Camera, Equirectangular, Film, Fisheye, Orthographic, Perspective,
+                       Projection};
+use self::area_light::AreaLight;
+use self::emitter::EmissiveTriangles;
+use self::environment::{EnvironmentLight, EnvironmentSample, Portal};
+use self::light_grid::LightGrid;
+use self::nonarea_light::{LightSample, NonAreaLight};
+use self::material::{Material, ScatterKind, fresnel_reflectance};
+use self::medium::MediumStack;
+use self::scratch::ScatteredFrame;
+use self::stats::{RayCounters, RenderStats};
 
-use std::f32::INFINITY;
-use math::{Intersection, Matrix4x4, Ray, Solid, Vector};
+use color::RgbSpectrum;
+use rand::{self, Rng, XorShiftRng};
+use std::f32::{INFINITY, NEG_INFINITY};
+use std::f32::consts::PI;
+use std::fmt;
+use std::ops::{Add, Div};
+use math::{AABB, AnimatedTransform, Bvh, BvhLayout, Intersection, Point, Ray, Solid, Transform,
+           TriangleHit, Vector, perpendicular_tangents};
+use output::Precision;
 
 // TODO: Define some set of units for this.
-pub type Spectrum = Vector;
+/// Radiance, and the fractions of it surfaces pass on, in linear RGB.
+pub type Spectrum = RgbSpectrum;
+
+/// Distance to move rays off of surfaces toward lights, so they do not hit the surface they start
+/// from.
+const PREVENT_SELF_INTERSECTION_RANGE: f32 = 0.01;
+
+/// Number of directions toward the environment sampled at each surface.
+const ENVIRONMENT_SAMPLES: u32 = 16;
+
+/// Number of directions toward each glowing entity sampled at each surface.
+const EMITTER_SAMPLES: u32 = 16;
+
+/// Number of directions toward each non-area light with a size sampled at each surface.
+const LIGHT_SIZE_SAMPLES: u32 = 16;
+
+/// Number of directions toward each area light sampled at each surface.
+const AREA_LIGHT_SAMPLES: u32 = 16;
 
 /// Some thing with a shape, and material properties.
 struct Entity {
     solid: Box<Solid>,
     material: Box<Material>,
 
-    // Transform into and out of this entity's coordinate space.
-    transform: Transform,
+    // Transform into and out of this entity's coordinate space, which may move over time.
+    transform: AnimatedTransform,
+
+    visibility: RayVisibility,
+}
+
+/// An entity whose own material gives off light, which is sampled like a light.
+struct Emitter {
+    entity: usize,
+    /// Triangles of glowing meshes, sampled instead of the sphere bounding the entity.
+    triangles: Option<EmissiveTriangles>,
 }
 
 impl Solid for Entity {
     fn intersect(&self, r: &Ray) -> Option<Intersection> {
-        let local_ray = self.transform.to_local * (*r);
+        let transform = self.transform.at(r.time);
+        let local_ray = *transform.to_local() * (*r);
 
         if let Some(intersection) = self.solid.intersect(&local_ray) {
             // Convert the intersection back into the world coordinate system.
-            return Some(self.transform.to_world * intersection);
+            let mut world_intersection = transform * intersection;
+
+            // Scaling changes the distance along the ray, so keep times comparable between
+            // entities.
+            world_intersection.time = (world_intersection.point - r.origin).dot(&r.direction);
+            return Some(world_intersection);
         }
         None
     }
+
+    /// Bounds the entity wherever it moves to.
+    fn bounding_box(&self) -> AABB {
+        self.transform.bound(&self.solid.bounding_box())
+    }
+
+    fn name(&self) -> &'static str {
+        self.solid.name()
+    }
+
+    fn set_bvh_layout(&mut self, layout: BvhLayout) {
+        self.solid.set_bvh_layout(layout);
+    }
 }
 
-/// Store to and from the transforms into and out of a given local coordinate space.
-///
-/// Since these inversions can be expensive if calculated, but not if the inversion
-/// gets created based on the original transform (e.g. rotating -30 degrees about X
-/// has the inverse of rotating 30 degrees, but calculating the inversion is expensive).
-pub struct Transform {
-    pub to_local: Matrix4x4,
-    pub to_world: Matrix4x4,
+impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} of {} material", self.solid.name(), self.material.name())?;
+        let bounds = self.bounding_box();
+        if bounds.is_bounded() {
+            write!(f, ", within {} to {}", bounds.lower, bounds.upper)?;
+        } else {
+            write!(f, ", unbounded")?;
+        }
+        if self.transform.is_animated() {
+            write!(f, ", moving")?;
+        }
+        if !self.visibility.glossy {
+            write!(f, ", hidden from glossy rays")?;
+        }
+        if !self.visibility.shadows {
+            write!(f, ", casting no shadows")?;
+        }
+        Ok(())
+    }
+}
+
+/// Summarizes the entity rather than listing the parts of its solid and its matrices.
+impl fmt::Debug for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Entity")
+            .field("solid", &self.solid.name())
+            .field("material", &self.material.name())
+            .field("bounds", &self.bounding_box())
+            .field("animated", &self.transform.is_animated())
+            .field("visibility", &self.visibility)
+            .finish()
+    }
+}
+
+/// Identifies an entity of a scene, returned when it is added.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EntityHandle(usize);
+
+impl EntityHandle {
+    /// Position of the entity in the order entities were added.
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// Which kinds of rays see an entity, to leave it out of effects which add noise or distract.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayVisibility {
+    /// Seen in glossy reflections, such as in mirrors.
+    pub glossy: bool,
+    /// Blocks light from lights, the environment and glowing entities.
+    ///
+    /// Light focused onto surfaces through glass or off of mirrors, caustics, is not traced, so
+    /// such entities cast solid shadows.  Turning off their shadows lets light through without
+    /// focusing it, a cheap stand-in for caustics without their noise.
+    pub shadows: bool,
+}
+
+impl Default for RayVisibility {
+    /// Seen by every kind of ray.
+    fn default() -> RayVisibility {
+        RayVisibility {
+            glossy: true,
+            shadows: true,
+        }
+    }
+}
+
+/// A light placed into the world through a transform, such as one light of a rig.
+struct TransformedLight {
+    light: Box<NonAreaLight>,
+
+    // Transform into and out of this light's coordinate space, which may move over time.
+    transform: AnimatedTransform,
+}
+
+impl TransformedLight {
+    /// Samples the light at a point in world coordinates, at the transform of some time.
+    ///
+    /// # Arguments
+    /// * `sample` - samples the light at a point in local coordinates
+    fn sample_at(
+        &self,
+        point: &Point,
+        time: f32,
+        sample: &Fn(&Point) -> LightSample,
+    ) -> LightSample {
+        let transform = self.transform.at(time);
+        let local_point = *transform.to_local() * (*point);
+        let local_sample = sample(&local_point);
+        let mut direction = *transform.to_world() * local_sample.direction;
+        direction.normalize().expect(
+            "Transformed light direction cannot be normalized.",
+        );
+
+        // Scaling changes the distance to the light, so find where the light is in world space.
+        let distance = if local_sample.distance == INFINITY {
+            INFINITY
+        } else {
+            let light_position = local_point + local_sample.distance * local_sample.direction;
+            point.distance_to(*transform.to_world() * light_position)
+        };
+
+        LightSample {
+            radiance: local_sample.radiance,
+            direction: direction,
+            distance: distance,
+        }
+    }
+}
+
+impl fmt::Display for TransformedLight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.transform.is_animated() {
+            write!(f, "{} in local coordinates, moved by an animated transform", self.light)
+        } else {
+            write!(f, "{} in local coordinates, placed by a transform", self.light)
+        }
+    }
+}
+
+/// Lights without a time are placed by the transform of their first keyframe.
+impl NonAreaLight for TransformedLight {
+    fn incident(&self, point: &Point) -> LightSample {
+        self.incident_at(point, NEG_INFINITY)
+    }
+
+    fn sample_incident(&self, point: &Point, u: (f32, f32)) -> LightSample {
+        self.sample_incident_at(point, NEG_INFINITY, u)
+    }
+
+    fn incident_at(&self, point: &Point, time: f32) -> LightSample {
+        self.sample_at(point, time, &|local| self.light.incident(local))
+    }
+
+    fn sample_incident_at(&self, point: &Point, time: f32, u: (f32, f32)) -> LightSample {
+        self.sample_at(point, time, &|local| self.light.sample_incident(local, u))
+    }
+
+    fn has_size(&self) -> bool {
+        self.light.has_size()
+    }
+
+    fn position(&self) -> Option<Point> {
+        self.light
+            .position()
+            .map(|p| *self.transform.start().to_world() * p)
+    }
+
+    /// Bounds the reach of the light over all time.
+    fn reach(&self, cutoff: f32) -> Option<AABB> {
+        self.light.reach(cutoff).map(|b| self.transform.bound(&b))
+    }
 }
 
 /// An intersection which occurred on the scene.
 struct SceneIntersection<'a> {
     pub entity: &'a Box<Entity>,
+    /// Position of the entity in the order entities were added.
+    pub entity_index: usize,
     pub intersection: Intersection,
 }
 
+/// The step of shading which produced a radiance value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RadianceSource {
+    /// Radiance arriving from a light, by its position in the order lights were added.
+    Light(usize),
+    /// Radiance arriving from an area light, by its position in the order area lights were added.
+    AreaLight(usize),
+    /// Radiance arriving from the environment.
+    Environment,
+    /// The BRDF of the entity's material.
+    Material,
+    /// The weight of a ray scattered by the entity's material.
+    Scatter,
+    /// Light given off by the entity's material.
+    Emission,
+    /// The total radiance leaving the entity.
+    Integrator,
+}
+
+/// A NaN, infinite or negative radiance value found while shading an entity.
+#[derive(Clone, Copy, Debug)]
+pub struct InvalidRadiance {
+    pub source: RadianceSource,
+    /// Position of the entity being shaded, in the order entities were added.
+    pub entity: usize,
+    pub value: Spectrum,
+}
+
+impl fmt::Display for InvalidRadiance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let source = match self.source {
+            RadianceSource::Light(index) => format!("light {}", index),
+            RadianceSource::AreaLight(index) => format!("area light {}", index),
+            RadianceSource::Environment => "environment light".to_string(),
+            RadianceSource::Material => "material BRDF".to_string(),
+            RadianceSource::Scatter => "material scattering weight".to_string(),
+            RadianceSource::Emission => "material emission".to_string(),
+            RadianceSource::Integrator => "integrator".to_string(),
+        };
+        write!(
+            f,
+            "invalid radiance ({}, {}, {}) from {} while shading entity {}",
+            self.value.r,
+            self.value.g,
+            self.value.b,
+            source,
+            self.entity
+        )
+    }
+}
+
+/// The first surface seen along a ray.
+#[derive(Clone, Copy, Debug)]
+pub struct SurfaceHit {
+    /// Distance from the ray origin to the surface.
+    pub depth: f32,
+    pub point: Point,
+    /// Unit vector perpendicular to the surface.
+    pub normal: Vector,
+    /// Position of the entity hit, in the order entities were added.
+    pub entity: usize,
+    /// Where on a triangle the surface was hit, for entities made of triangles.
+    pub triangle: Option<TriangleHit>,
+    /// Radiance reflected back along the ray from lights, ignoring any scattered rays.
+    pub direct: Spectrum,
+    /// BRDF of the surface lit and viewed along its normal, its color without lighting.  Mirrors
+    /// and glass only scatter rays, so have none.
+    pub albedo: Spectrum,
+}
+
+/// Radiance along a ray split into separate passes by how light reached the camera, to find
+/// where noise and energy come from or to composite them separately.
+///
+/// Passes split by the number of bounces, `direct`, `indirect` and `deeper`, sum to the full
+/// radiance, as do `diffuse` and `specular`.
+#[derive(Clone, Copy, Debug)]
+pub struct RadiancePasses {
+    /// Light from lights reflected by the first surface hit.
+    pub direct: Spectrum,
+    /// Light from lights reflected by the second surface along a path.
+    pub indirect: Spectrum,
+    /// Light from lights reflected by any later surface.
+    pub deeper: Spectrum,
+    /// Light reflected by the BRDF of the first surface hit.  Only lights are sampled through
+    /// BRDFs, so this is also the direct light.
+    pub diffuse: Spectrum,
+    /// Light along the rays scattered by the first surface hit, such as reflections and
+    /// refractions.
+    pub specular: Spectrum,
+}
+
+impl RadiancePasses {
+    /// Passes with no radiance.
+    pub fn new() -> RadiancePasses {
+        let black = Spectrum::black();
+        RadiancePasses {
+            direct: black,
+            indirect: black,
+            deeper: black,
+            diffuse: black,
+            specular: black,
+        }
+    }
+
+    /// The full radiance along the ray.
+    pub fn total(&self) -> Spectrum {
+        self.direct + self.indirect + self.deeper
+    }
+}
+
+impl Add for RadiancePasses {
+    type Output = RadiancePasses;
+    fn add(self, rhs: RadiancePasses) -> RadiancePasses {
+        RadiancePasses {
+            direct: self.direct + rhs.direct,
+            indirect: self.indirect + rhs.indirect,
+            deeper: self.deeper + rhs.deeper,
+            diffuse: self.diffuse + rhs.diffuse,
+            specular: self.specular + rhs.specular,
+        }
+    }
+}
+
+impl Div<f32> for RadiancePasses {
+    type Output = RadiancePasses;
+    fn div(self, rhs: f32) -> RadiancePasses {
+        RadiancePasses {
+            direct: self.direct / rhs,
+            indirect: self.indirect / rhs,
+            deeper: self.deeper / rhs,
+            diffuse: self.diffuse / rhs,
+            specular: self.specular / rhs,
+        }
+    }
+}
+
+/// Maximum number of times a path may scatter from surfaces, in total and of each kind.
+///
+/// Scenes of glass need many transmission bounces to see through several surfaces, while
+/// diffuse bounces contribute less with each one and are costly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BounceLimits {
+    pub total: u32,
+    pub diffuse: u32,
+    pub glossy: u32,
+    pub transmission: u32,
+}
+
+impl Default for BounceLimits {
+    fn default() -> BounceLimits {
+        BounceLimits {
+            total: 8,
+            diffuse: 4,
+            glossy: 8,
+            transmission: 8,
+        }
+    }
+}
+
+impl BounceLimits {
+    /// The limits remaining after scattering once.
+    ///
+    /// # Returns
+    /// `None` if scattering would exceed the total limit or the limit for its kind.
+    pub fn after(&self, kind: ScatterKind) -> Option<BounceLimits> {
+        if self.total == 0 {
+            return None;
+        }
+        let mut left = *self;
+        left.total -= 1;
+        {
+            let count = match kind {
+                ScatterKind::Diffuse => &mut left.diffuse,
+                ScatterKind::Glossy => &mut left.glossy,
+                ScatterKind::Transmission => &mut left.transmission,
+            };
+            if *count == 0 {
+                return None;
+            }
+            *count -= 1;
+        }
+        Some(left)
+    }
+}
+
+/// How a path reached the ray being traced.
+#[derive(Clone, Copy, Debug)]
+struct PathState {
+    /// How the ray was scattered, or `None` for rays from the camera.
+    kind: Option<ScatterKind>,
+    /// How many more times the path may scatter.
+    bounces_left: BounceLimits,
+    /// Number of surfaces the path hit before this ray.
+    depth: u32,
+    /// Fraction of light leaving along this ray which reaches the camera.
+    throughput: Spectrum,
+    /// Media of the entities the ray is travelling inside.
+    media: MediumStack,
+}
+
+impl PathState {
+    /// State of a path starting at the camera, outside every entity.
+    fn from_camera(bounce_limits: &BounceLimits) -> PathState {
+        PathState {
+            kind: None,
+            bounces_left: *bounce_limits,
+            depth: 0,
+            throughput: Spectrum::uniform(1.0),
+            media: MediumStack::new(),
+        }
+    }
+}
+
+/// A surface along a ray, for compositing with other deep images.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeepSample {
+    /// Distance from the ray origin to the surface.
+    pub depth: f32,
+    /// Opacity of the surface.
+    pub alpha: f32,
+}
+
 pub struct Scene {
     lights: Vec<Box<NonAreaLight>>,
+    area_lights: Vec<Box<AreaLight>>,
     entities: Vec<Box<Entity>>,
+
+    // Entities whose own materials give off light, which are sampled like lights.
+    emitters: Vec<Emitter>,
+
+    // Radiance below which light from a light is ignored, to skip distant lights.
+    light_cutoff: Option<f32>,
+    // Lights reaching each region of the scene, out of the first `light_grid_count` lights.
+    // Lights added since the last `finalize` are considered everywhere.
+    light_grid: Option<LightGrid>,
+    light_grid_count: usize,
+
+    // Hierarchy over the first `bvh_entity_count` entities.  Entities added since the last
+    // `finalize` are tested individually.
+    bvh: Bvh,
+    bvh_entity_count: usize,
+    // Node layout of the hierarchy over entities, and of those within their solids.
+    bvh_layout: BvhLayout,
+
+    // Whether to look for invalid radiance while shading.
+    check_radiance: bool,
+
+    // Material shading every entity in place of its own, for debugging.
+    material_override: Option<Box<Material>>,
+
+    bounce_limits: BounceLimits,
+
+    // Light seen by rays which miss every entity.
+    environment: Option<EnvironmentLight>,
+    // Windows the environment lights surfaces through, or empty to sample every direction.
+    portals: Vec<Portal>,
+    texture_precision: Precision,
+
+    // Rays traced so far, for render statistics.
+    counters: RayCounters,
 }
 
 impl Scene {
@@ -80,7 +564,204 @@ impl Scene {
     pub fn new() -> Scene {
         Scene {
             lights: Vec::new(),
+            area_lights: Vec::new(),
             entities: Vec::new(),
+            emitters: Vec::new(),
+            light_cutoff: None,
+            light_grid: None,
+            light_grid_count: 0,
+            bvh: Bvh::new(&[]),
+            bvh_entity_count: 0,
+            bvh_layout: BvhLayout::default(),
+            check_radiance: false,
+            material_override: None,
+            bounce_limits: BounceLimits::default(),
+            environment: None,
+            portals: Vec::new(),
+            texture_precision: Precision::default(),
+            counters: RayCounters::new(),
+        }
+    }
+
+    /// Enables looking for NaN, infinite and negative radiance while shading, which is off by
+    /// default.
+    ///
+    /// See `try_trace`.
+    pub fn set_radiance_checks(&mut self, enabled: bool) {
+        self.check_radiance = enabled;
+    }
+
+    /// Counts of the rays traced since the scene was created or the counts were last reset, with
+    /// no stages timed.
+    pub fn render_stats(&self) -> RenderStats {
+        self.counters.snapshot()
+    }
+
+    /// Starts counting rays traced from zero again.
+    pub fn reset_render_stats(&self) {
+        self.counters.reset();
+    }
+
+    /// Shades every entity with `material` instead of its own, or with their own materials again
+    /// if `None`.
+    ///
+    /// Glowing entities still give off the light of their own materials.
+    pub fn set_material_override(&mut self, material: Option<Box<Material>>) {
+        self.material_override = material;
+    }
+
+    /// Surrounds the scene with light from infinitely far away, which rays missing every entity
+    /// see, or leaves rays which miss black if `None`.
+    pub fn set_environment(&mut self, mut environment: Option<EnvironmentLight>) {
+        if let Some(ref mut environment) = environment {
+            environment.set_precision(self.texture_precision);
+        }
+        self.environment = environment;
+    }
+
+    /// Adds a window the environment lights surfaces through, see `Portal`.
+    pub fn add_portal(&mut self, portal: Portal) {
+        self.portals.push(portal);
+    }
+
+    /// Stores the pixels of the environment, now and when set later, at a precision, trading
+    /// accuracy for memory with very large environment maps.
+    pub fn set_texture_precision(&mut self, precision: Precision) {
+        self.texture_precision = precision;
+        if let Some(ref mut environment) = self.environment {
+            environment.set_precision(precision);
+        }
+    }
+
+    /// Limits how many times paths may scatter from surfaces.
+    pub fn set_bounce_limits(&mut self, limits: &BounceLimits) {
+        self.bounce_limits = *limits;
+    }
+
+    /// The bounds of every entity, infinite for entities which extend forever.
+    pub fn entity_bounds(&self) -> Vec<AABB> {
+        self.entities.iter().map(|e| e.bounding_box()).collect()
+    }
+
+    /// Positions of every light which has one.
+    pub fn light_positions(&self) -> Vec<Point> {
+        self.lights.iter().filter_map(|l| l.position()).collect()
+    }
+
+    /// The smallest box containing every entity of finite size.
+    ///
+    /// Entities which extend forever, such as planes, are left out.
+    ///
+    /// # Returns
+    /// `None` if there are no entities of finite size.
+    pub fn bounds(&self) -> Option<AABB> {
+        self.entities
+            .iter()
+            .map(|e| e.bounding_box())
+            .filter(|b| b.is_bounded())
+            .fold(None, |total, b| match total {
+                Some(t) => Some(b.union(&t)),
+                None => Some(b),
+            })
+    }
+
+    /// Lists everything in the scene, one item to a line, for inspecting scenes and explaining
+    /// problems found while rendering them.
+    ///
+    /// Lights and entities are numbered in the order they were added, as in `InvalidRadiance`.
+    pub fn dump(&self) -> String {
+        let mut dump = String::new();
+        // Writing to a string cannot fail.
+        let _ = self.write_dump(&mut dump);
+        dump
+    }
+
+    fn write_dump(&self, out: &mut fmt::Write) -> fmt::Result {
+        writeln!(out, "{}", self)?;
+        match self.bounds() {
+            Some(bounds) => writeln!(out, "bounds: {} to {}", bounds.lower, bounds.upper)?,
+            None => writeln!(out, "bounds: none")?,
+        }
+        for (index, light) in self.lights.iter().enumerate() {
+            writeln!(out, "light {}: {}", index, light)?;
+        }
+        for (index, light) in self.area_lights.iter().enumerate() {
+            writeln!(out, "area light {}: {}", index, light)?;
+        }
+        for (index, entity) in self.entities.iter().enumerate() {
+            writeln!(out, "entity {}: {}", index, entity)?;
+        }
+        if let Some(ref environment) = self.environment {
+            writeln!(out, "environment: {}", environment)?;
+        }
+        for (index, portal) in self.portals.iter().enumerate() {
+            writeln!(out, "portal {}: {}", index, portal)?;
+        }
+        if let Some(ref material) = self.material_override {
+            writeln!(out, "material override: {}", material.name())?;
+        }
+        let limits = self.bounce_limits;
+        writeln!(
+            out,
+            "bounce limits: {} in total, {} diffuse, {} glossy, {} transmission",
+            limits.total,
+            limits.diffuse,
+            limits.glossy,
+            limits.transmission
+        )?;
+        if self.bvh_entity_count < self.entities.len() {
+            writeln!(
+                out,
+                "{} entities added since the last finalize",
+                self.entities.len() - self.bvh_entity_count
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Builds the acceleration structure used to find intersections, once all entities have been
+    /// added.
+    ///
+    /// Entities added afterwards are still rendered, but more slowly until the next `finalize`.
+    pub fn finalize(&mut self) {
+        let bounds: Vec<AABB> = self.entities.iter().map(|e| e.bounding_box()).collect();
+        self.bvh = Bvh::with_layout(&bounds, self.bvh_layout);
+        self.bvh_entity_count = self.entities.len();
+
+        self.light_grid = match (self.light_cutoff, self.bounds()) {
+            (Some(cutoff), Some(bounds)) => Some(LightGrid::new(&bounds, &self.lights, cutoff)),
+            _ => None,
+        };
+        self.light_grid_count = self.lights.len();
+    }
+
+    /// Ignores light from lights too far away for any channel of their light to reach `cutoff`,
+    /// so shading in scenes with many lights only considers those nearby.  Lights are considered
+    /// everywhere if `None`, which is the default.
+    ///
+    /// Which lights reach where is found by `finalize`, and found again if already finalized.
+    ///
+    /// # Panics
+    /// If the cutoff is not positive.
+    pub fn set_light_cutoff(&mut self, cutoff: Option<f32>) {
+        if let Some(c) = cutoff {
+            assert!(c > 0.0, "Light cutoff must be positive.");
+        }
+        self.light_cutoff = cutoff;
+        if self.bvh_entity_count > 0 {
+            self.finalize();
+        }
+    }
+
+    /// Lays out the hierarchy over entities, and those within solids such as meshes, with another
+    /// node layout.  Those already built are rebuilt.
+    pub fn set_bvh_layout(&mut self, layout: BvhLayout) {
+        self.bvh_layout = layout;
+        for entity in self.entities.iter_mut() {
+            entity.set_bvh_layout(layout);
+        }
+        if self.bvh_entity_count > 0 {
+            self.finalize();
         }
     }
 
@@ -88,23 +769,94 @@ impl Scene {
         self.lights.push(light);
     }
 
+    /// Adds a light defined in its own coordinate space.
+    ///
+    /// Lights sharing the same transform move together as a unit, such as the lights of a rig.
+    ///
+    /// # Arguments
+    /// * `light` - the light to add, positioned in its local coordinate space
+    /// * `transform` - converts local coordinates to world coordinates
+    pub fn add_light_with_transform(&mut self, light: Box<NonAreaLight>, transform: Transform) {
+        self.add_animated_light(light, AnimatedTransform::still(transform));
+    }
+
+    /// Adds a light defined in its own coordinate space which moves while the camera's shutter is
+    /// open, such as a rig of lights swinging together.
+    ///
+    /// # Arguments
+    /// * `light` - the light to add, positioned in its local coordinate space
+    /// * `transform` - converts local coordinates to world coordinates, at the time of each ray
+    pub fn add_animated_light(&mut self, light: Box<NonAreaLight>, transform: AnimatedTransform) {
+        self.lights.push(Box::new(TransformedLight {
+            light: light,
+            transform: transform,
+        }));
+    }
+
+    /// Adds a light with a surface, which casts soft shadows.
+    pub fn add_area_light(&mut self, light: Box<AreaLight>) {
+        self.area_lights.push(light);
+    }
+
     /// Creates an entity with given properties.
     ///
+    /// Entities with emissive materials also light the scene.
+    ///
+    /// # Arguments
+    /// * `solid` - the intersection bounds of the object to create
+    /// * `material` - material to apply to the object
+    /// * `transform` - converts local coordinates to world coordinates
+    ///
+    /// # Returns
+    /// A handle identifying the entity in the results of ray queries.
+    pub fn add_entity(
+        &mut self,
+        solid: Box<Solid>,
+        material: Box<Material>,
+        transform: Transform,
+    ) -> EntityHandle {
+        self.add_animated_entity(solid, material, AnimatedTransform::still(transform))
+    }
+
+    /// Creates an entity which moves while the camera's shutter is open, blurring it.
+    ///
     /// # Arguments
     /// * `solid` - the intersection bounds of the object to create
     /// * `material` - material to apply to the object
-    /// * `transform` - converts world coordinates to local coordinates
-    pub fn add_entity(&mut self, solid: Box<Solid>, material: Box<Material>, transform: Matrix4x4) {
+    /// * `transform` - converts local coordinates to world coordinates, at the time of each ray
+    ///
+    /// # Returns
+    /// A handle identifying the entity in the results of ray queries.
+    pub fn add_animated_entity(
+        &mut self,
+        mut solid: Box<Solid>,
+        material: Box<Material>,
+        transform: AnimatedTransform,
+    ) -> EntityHandle {
+        if self.bvh_layout != BvhLayout::default() {
+            solid.set_bvh_layout(self.bvh_layout);
+        }
+        if material.is_emissive() {
+            self.emitters.push(Emitter {
+                entity: self.entities.len(),
+                triangles: EmissiveTriangles::new(solid.triangles(), transform.start()),
+            });
+        }
         self.entities.push(Box::new(Entity {
             solid: solid,
             material: material,
-            transform: Transform {
-                to_local: transform.inverse().expect(
-                    "Uninvertible transform used for an entity.",
-                ),
-                to_world: transform,
-            },
+            transform: transform,
+            visibility: RayVisibility::default(),
         }));
+        EntityHandle(self.entities.len() - 1)
+    }
+
+    /// Changes which kinds of rays see an entity, which every kind does when it is added.
+    ///
+    /// # Panics
+    /// If the entity is not part of this scene.
+    pub fn set_ray_visibility(&mut self, entity: EntityHandle, visibility: RayVisibility) {
+        self.entities[entity.0].visibility = visibility;
     }
 
     /// Called to determine the radiance returning along this ray in the opposite direction it was
@@ -114,54 +866,437 @@ impl Scene {
     /// * `ray` - a ray emanating from the camera from the viewer, along which the radiance
     /// should be determined.
     ///
+    /// Samples are drawn from a randomly seeded generator, so tracing the same ray twice gives
+    /// different noise.  Use `try_trace` with a seeded generator to repeat a render exactly.
+    ///
     /// # Returns
-    /// * `Spectrum` - the radiance along this ray in the opposite direction.
+    /// * `Spectrum` - the radiance along this ray in the opposite direction, or black if radiance
+    /// checks are enabled and find invalid radiance.  Use `try_trace` to find where it came from.
     pub fn trace(&self, ray: &Ray) -> Spectrum {
-        self.bounce(ray, 8)
+        let mut rng: XorShiftRng = rand::thread_rng().gen();
+        self.try_trace(ray, &mut rng).unwrap_or_else(|_| Spectrum::black())
     }
 
-    fn bounce(&self, ray: &Ray, bounces_left: u32) -> Spectrum {
-        // If no entity was intersected, return black.
-        // This might be changed to account for other types of ambient light.
+    /// Traces a ray like `trace`, reporting where invalid radiance came from if radiance checks
+    /// are enabled.
+    ///
+    /// # Arguments
+    /// * `rng` - the only source of randomness while tracing, so the same ray traced with
+    /// identically seeded generators gives the same radiance.
+    ///
+    /// # Returns
+    /// * `Ok(Spectrum)` - the radiance along this ray in the opposite direction.
+    /// * `Err(InvalidRadiance)` - the first invalid radiance found.
+    pub fn try_trace(
+        &self,
+        ray: &Ray,
+        rng: &mut XorShiftRng,
+    ) -> Result<Spectrum, InvalidRadiance> {
+        self.bounce(ray, rng)
+    }
+
+    /// Traces a ray like `try_trace`, splitting its radiance by how light reached the camera.
+    pub fn try_trace_passes(
+        &self,
+        ray: &Ray,
+        rng: &mut XorShiftRng,
+    ) -> Result<RadiancePasses, InvalidRadiance> {
+        let mut passes = RadiancePasses::new();
+        let path = PathState::from_camera(&self.bounce_limits);
+        self.bounce_recorded(ray, &path, rng, &mut |depth, radiance| {
+            match depth {
+                0 => {
+                    passes.direct += *radiance;
+                    passes.diffuse += *radiance;
+                }
+                1 => {
+                    passes.indirect += *radiance;
+                    passes.specular += *radiance;
+                }
+                _ => {
+                    passes.deeper += *radiance;
+                    passes.specular += *radiance;
+                }
+            }
+        })?;
+        Ok(passes)
+    }
+
+    /// Whether a ray hits anything in the scene.
+    pub fn hits(&self, ray: &Ray) -> bool {
+        self.intersect(ray).is_some()
+    }
+
+    /// Traces a ray leaving the camera like `try_trace`, reporting if it escapes the scene without
+    /// hitting anything.
+    ///
+    /// # Returns
+    /// * `Ok(Some(Spectrum))` - the radiance along this ray in the opposite direction.
+    /// * `Ok(None)` - if nothing was hit, so a background can be substituted.
+    /// * `Err(InvalidRadiance)` - the first invalid radiance found.
+    pub fn try_trace_primary(
+        &self,
+        ray: &Ray,
+        rng: &mut XorShiftRng,
+    ) -> Result<Option<Spectrum>, InvalidRadiance> {
         match self.intersect(ray) {
             Some(si) => {
-                if bounces_left == 0 {
-                    return Vector::new(0.0, 0.0, 0.0);
+                let path = PathState::from_camera(&self.bounce_limits);
+                self.shade_recorded(ray, &si, &path, rng, &mut |_, _| {})
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Finds the first surface along a ray, and the light it directly reflects back along it.
+    ///
+    /// The direct light is black if radiance checks are enabled and find invalid radiance.  Lights
+    /// with a size are sampled using `rng`.
+    pub fn first_hit(&self, ray: &Ray, rng: &mut XorShiftRng) -> Option<SurfaceHit> {
+        self.intersect(ray).map(|si| {
+            SurfaceHit {
+                depth: ray.origin.distance_to(si.intersection.point),
+                point: si.intersection.point,
+                normal: si.intersection.normal,
+                entity: si.entity_index,
+                triangle: si.intersection.triangle,
+                direct: self.radiance_from(ray, &si, rng)
+                    .unwrap_or_else(|_| Spectrum::black()),
+                albedo: self.material_of(si.entity).f_at(
+                    &si.intersection.normal,
+                    &si.intersection.normal,
+                    &si.intersection.normal,
+                    si.intersection.uv,
+                    &si.intersection.point,
+                ),
+            }
+        })
+    }
+
+    /// Where a point on an entity at one time has moved to at another, following the motion of
+    /// the entity.
+    ///
+    /// # Panics
+    /// If the entity is not part of this scene.
+    pub fn moved(&self, entity: usize, point: &Point, from: f32, to: f32) -> Point {
+        let transform = &self.entities[entity].transform;
+        let local = *transform.at(from).to_local() * *point;
+        *transform.at(to).to_world() * local
+    }
+
+    /// Finds the nearest surfaces along a ray, passing through each transparent one to find
+    /// the next.
+    ///
+    /// Opaque surfaces hide everything behind them, so they are the last surface found.
+    /// Transparent surfaces have the alpha of the light they reflect rather than let through,
+    /// with the ray continuing straight on rather than bending.  Surfaces passed through without
+    /// scattering inside higher priority media are skipped, see `medium`.
+    ///
+    /// # Arguments
+    /// * `ray` - a ray emanating from the camera
+    /// * `max_samples` - the most surfaces to find
+    ///
+    /// # Returns
+    /// * `Vec<DeepSample>` - surfaces in order from nearest to farthest.
+    pub fn deep_samples(&self, ray: &Ray, max_samples: usize) -> Vec<DeepSample> {
+        const STEP_THROUGH_SURFACE_DISTANCE: f32 = 0.01;
+        let mut samples = Vec::new();
+        let mut next_ray = *ray;
+        let mut media = MediumStack::new();
+        while samples.len() < max_samples {
+            let si = match self.intersect(&next_ray) {
+                Some(si) => si,
+                None => break,
+            };
+            let depth = ray.origin.distance_to(si.intersection.point);
+            next_ray.origin = si.intersection.point +
+                STEP_THROUGH_SURFACE_DISTANCE * ray.direction;
+            let medium = match self.material_of(si.entity).interior() {
+                Some(medium) => medium,
+                None => {
+                    samples.push(DeepSample {
+                        depth: depth,
+                        alpha: 1.0,
+                    });
+                    break;
+                }
+            };
+
+            let cos_incident = ray.direction.dot(&si.intersection.normal);
+            let entering = cos_incident < 0.0;
+            let hidden = media.is_hidden(si.entity_index, &medium);
+            if !hidden {
+                let outside_index = media
+                    .outside(si.entity_index)
+                    .map_or(1.0, |outside| outside.index_of_refraction);
+                let eta = if entering {
+                    outside_index / medium.index_of_refraction
+                } else {
+                    medium.index_of_refraction / outside_index
+                };
+                samples.push(DeepSample {
+                    depth: depth,
+                    alpha: fresnel_reflectance(eta, cos_incident.abs()),
+                });
+            }
+            media = media.crossed(
+                si.entity_index,
+                &medium,
+                &ray.direction,
+                &si.intersection.normal,
+            );
+        }
+        samples
+    }
+
+    fn bounce(&self, ray: &Ray, rng: &mut XorShiftRng) -> Result<Spectrum, InvalidRadiance> {
+        let path = PathState::from_camera(&self.bounce_limits);
+        self.bounce_recorded(ray, &path, rng, &mut |_, _| {})
+    }
+
+    /// Follows a ray like `bounce`, also passing the light each surface along the path reflects
+    /// from lights to `record`.
+    ///
+    /// # Arguments
+    /// * `path` - how the path reached this ray.
+    /// * `rng` - source of every random choice along the path.
+    /// * `record` - given the depth of each surface and its contribution to the camera.
+    fn bounce_recorded(
+        &self,
+        ray: &Ray,
+        path: &PathState,
+        rng: &mut XorShiftRng,
+        record: &mut FnMut(u32, &Spectrum),
+    ) -> Result<Spectrum, InvalidRadiance> {
+        // If no entity was intersected, return black.
+        // This might be changed to account for other types of ambient light.
+        let seen = |_, entity: &Entity| {
+            path.kind != Some(ScatterKind::Glossy) || entity.visibility.glossy
+        };
+        self.counters.add_ray();
+        match self.intersect_where(ray, seen) {
+            Some(si) => self.shade_recorded(ray, &si, path, rng, record),
+            None => {
+                match self.environment {
+                    Some(ref environment) => {
+                        let radiance = environment.radiance(&ray.direction);
+                        record(path.depth, &(path.throughput * radiance));
+                        Ok(radiance)
+                    }
+                    None => Ok(Spectrum::black()),
                 }
-                let min_surface_distance = 0.01;
-                let new_direction = ray.direction.reflect(&si.intersection.normal);
-                let new_origin = si.intersection.point + min_surface_distance * new_direction;
+            }
+        }
+    }
+
+    /// Shades the surface a ray hit like `bounce_recorded`, following the rays it scatters.
+    fn shade_recorded(
+        &self,
+        ray: &Ray,
+        si: &SceneIntersection,
+        path: &PathState,
+        rng: &mut XorShiftRng,
+        record: &mut FnMut(u32, &Spectrum),
+    ) -> Result<Spectrum, InvalidRadiance> {
+        let min_surface_distance = 0.01;
+        // Light from the surface is absorbed by the medium the ray travels through.
+        let transmittance = path.media
+            .transmittance(ray.origin.distance_to(si.intersection.point));
+        let path = &PathState {
+            throughput: path.throughput * transmittance,
+            ..*path
+        };
+        let material = self.material_of(si.entity);
+        let interior = material.interior();
+        // Surfaces inside higher priority media pass rays straight through.
+        if let Some(ref medium) = interior {
+            if path.media.is_hidden(si.entity_index, medium) {
+                let media = path.media.crossed(
+                    si.entity_index,
+                    medium,
+                    &ray.direction,
+                    &si.intersection.normal,
+                );
                 let next_ray = Ray {
-                    origin: new_origin,
-                    direction: new_direction,
+                    origin: si.intersection.point + min_surface_distance * ray.direction,
+                    direction: ray.direction,
+                    time: ray.time,
+                };
+                let next_path = PathState {
+                    media: media,
+                    ..*path
                 };
-                return self.radiance_from(ray, si.entity, &si.intersection) +
-                    self.bounce(&next_ray, bounces_left - 1);
+                let radiance = self.bounce_recorded(&next_ray, &next_path, rng, record)?;
+                return Ok(transmittance * radiance);
             }
-            None => Vector::new(0.0, 0.0, 0.),
         }
+
+        let mut radiance = self.radiance_from(ray, si, rng)?;
+        record(path.depth, &(path.throughput * radiance));
+        let outside_index = path.media
+            .outside(si.entity_index)
+            .map_or(1.0, |outside| outside.index_of_refraction);
+        let frame = ScatteredFrame::scatter(
+            material,
+            &ray.direction,
+            &self.shading_normal(si),
+            outside_index,
+            &si.intersection,
+            rng.gen(),
+        );
+        for index in 0..frame.len() {
+            let scattered = frame.get(index);
+            self.check(
+                &scattered.weight,
+                RadianceSource::Scatter,
+                si.entity_index,
+            )?;
+            let bounces_left = match path.bounces_left.after(scattered.kind) {
+                Some(limits) => limits,
+                None => continue,
+            };
+            let media = match interior {
+                Some(ref medium) if scattered.kind == ScatterKind::Transmission => {
+                    path.media.crossed(
+                        si.entity_index,
+                        medium,
+                        &scattered.direction,
+                        &si.intersection.normal,
+                    )
+                }
+                _ => path.media,
+            };
+            let next_ray = Ray {
+                origin: si.intersection.point + min_surface_distance * scattered.direction,
+                direction: scattered.direction,
+                time: ray.time,
+            };
+            let next_path = PathState {
+                kind: Some(scattered.kind),
+                bounces_left: bounces_left,
+                depth: path.depth + 1,
+                throughput: path.throughput * scattered.weight,
+                media: media,
+            };
+            radiance += scattered.weight *
+                self.bounce_recorded(&next_ray, &next_path, rng, record)?;
+        }
+        self.check(&radiance, RadianceSource::Integrator, si.entity_index)?;
+        Ok(transmittance * radiance)
+    }
+
+    /// The normal a surface is shaded with, perturbed by the normal map of its material.
+    fn shading_normal(&self, si: &SceneIntersection) -> Vector {
+        let intersection = &si.intersection;
+        match self.material_of(si.entity).normal_map() {
+            Some(normal_map) => normal_map.perturb(
+                &intersection.normal,
+                &intersection.tangent,
+                &intersection.bitangent,
+                intersection.uv,
+                &intersection.point,
+            ),
+            None => intersection.normal,
+        }
+    }
+
+    /// The material shading an entity.
+    fn material_of<'a>(&'a self, entity: &'a Entity) -> &'a Material {
+        match self.material_override {
+            Some(ref material) => material.as_ref(),
+            None => entity.material.as_ref(),
+        }
+    }
+
+    /// Ensures radiance is finite and non-negative, if radiance checks are enabled.
+    fn check(
+        &self,
+        value: &Spectrum,
+        source: RadianceSource,
+        entity: usize,
+    ) -> Result<(), InvalidRadiance> {
+        let valid = |c: f32| c.is_finite() && c >= 0.0;
+        if !self.check_radiance || value.to_array().iter().all(|&c| valid(c)) {
+            return Ok(());
+        }
+        Err(InvalidRadiance {
+            source: source,
+            entity: entity,
+            value: *value,
+        })
     }
 
     /// Finds the object and intersection point if a ray hits something.
-    fn intersect(&self, ray: &Ray) -> Option<SceneIntersection> {
-        let mut closest_object: Option<&Box<Entity>> = None;
+    fn intersect<'a>(&'a self, ray: &Ray) -> Option<SceneIntersection<'a>> {
+        self.counters.add_ray();
+        self.intersect_where(ray, |_, _| true)
+    }
+
+    /// Whether any entity casting a shadow lies along a ray before a time, such as the distance
+    /// to a light.
+    ///
+    /// Stops at the first such entity found rather than looking for the closest, so is cheaper
+    /// than intersecting the scene.
+    pub fn is_occluded(&self, ray: &Ray, max_time: f32) -> bool {
+        self.counters.add_shadow_ray();
+        let blocks = |entity: &Box<Entity>| {
+            if !entity.visibility.shadows {
+                return false;
+            }
+            self.counters.add_intersection_test();
+            match entity.intersect(ray) {
+                Some(intersection) => intersection.time > 0.0 && intersection.time < max_time,
+                None => false,
+            }
+        };
+
+        let entities = &self.entities;
+        self.bvh.any_hit(ray, max_time, |index| blocks(&entities[index])) ||
+            entities[self.bvh_entity_count..].iter().any(|entity| blocks(entity))
+    }
+
+    /// Finds the first entity along a ray which `visible` accepts, given the position and the
+    /// entity, passing through all others.
+    fn intersect_where<'a, F>(&'a self, ray: &Ray, visible: F) -> Option<SceneIntersection<'a>>
+    where
+        F: Fn(usize, &Entity) -> bool,
+    {
+        let mut closest_object: Option<(usize, &Box<Entity>)> = None;
         let mut closest_intersection: Option<Intersection> = None;
         let mut best_time: f32 = INFINITY;
 
-        for ref obj in self.entities.iter() {
-            if let Some(intersection) = obj.intersect(&ray) {
-                //println!("Intersection at {}", intersection.point);
-                if intersection.time < best_time && intersection.time > 0.0 {
-                    best_time = intersection.time;
-                    closest_intersection = Some(intersection);
-                    closest_object = Some(*obj);
+        {
+            let mut test_entity = |index: usize, obj: &'a Box<Entity>| -> Option<f32> {
+                if !visible(index, obj) {
+                    return None;
+                }
+                self.counters.add_intersection_test();
+                if let Some(intersection) = obj.intersect(&ray) {
+                    let time = intersection.time;
+                    if time < best_time && time > 0.0 {
+                        best_time = time;
+                        closest_intersection = Some(intersection);
+                        closest_object = Some((index, obj));
+                    }
+                    if time > 0.0 {
+                        return Some(time);
+                    }
                 }
+                None
+            };
+
+            let entities = &self.entities;
+            self.bvh.traverse(ray, |index| test_entity(index, &entities[index]));
+            for index in self.bvh_entity_count..self.entities.len() {
+                test_entity(index, &entities[index]);
             }
         }
 
         match closest_object {
-            Some(object) => Some(SceneIntersection {
+            Some((index, object)) => Some(SceneIntersection {
                 entity: object,
+                entity_index: index,
                 intersection: closest_intersection.unwrap(),
             }),
             None => None,
@@ -173,36 +1308,1499 @@ impl Scene {
     fn radiance_from(
         &self,
         ray: &Ray,
-        entity: &Box<Entity>,
-        intersection: &Intersection,
-    ) -> Spectrum {
-        // Sum the contributions from all lights.
-        let mut radiance = Vector::new(0.0, 0.0, 0.0);
-        for ref light in self.lights.iter() {
-            const PREVENT_SELF_INTERSECTION_RANGE: f32 = 0.01;
-            let light_vector = light.light_vector(&intersection.point);
-            let shadow_intersection = self.intersect(&Ray {
-                origin: intersection.point + (PREVENT_SELF_INTERSECTION_RANGE * light_vector),
-                direction: light_vector,
-            });
-            let light_hidden = match shadow_intersection {
-                Some(si) => light.is_hidden_from(&intersection.point, Some(si.intersection.time)),
-                None => light.is_hidden_from(&intersection.point, None),
+        si: &SceneIntersection,
+        rng: &mut XorShiftRng,
+    ) -> Result<Spectrum, InvalidRadiance> {
+        let intersection = &si.intersection;
+        let normal = self.shading_normal(si);
+
+        // Glowing surfaces give off their own light, as well as reflecting light.
+        let mut radiance = si.entity
+            .material
+            .emitted(&-ray.direction, &intersection.normal);
+        self.check(&radiance, RadianceSource::Emission, si.entity_index)?;
+
+        // Sum the contributions from all lights near enough to matter, averaging directions toward
+        // lights with a size.
+        let gridded = self.light_grid
+            .as_ref()
+            .map(|grid| grid.lights_near(&intersection.point));
+        let ungridded = match gridded {
+            Some(_) => self.light_grid_count..self.lights.len(),
+            None => 0..self.lights.len(),
+        };
+        for light_index in gridded.into_iter().flat_map(|near| near).chain(ungridded) {
+            let light = &self.lights[light_index];
+            let samples = if light.has_size() { LIGHT_SIZE_SAMPLES } else { 1 };
+            let mut total = Spectrum::black();
+            for _ in 0..samples {
+                let sample = if light.has_size() {
+                    light.sample_incident_at(&intersection.point, ray.time, (rng.gen(), rng.gen()))
+                } else {
+                    light.incident_at(&intersection.point, ray.time)
+                };
+                self.check(
+                    &sample.radiance,
+                    RadianceSource::Light(light_index),
+                    si.entity_index,
+                )?;
+                let cos_theta = sample.direction.dot(&normal);
+                if cos_theta <= 0.0 {
+                    continue;
+                }
+
+                let light_hidden = self.is_occluded(
+                    &Ray {
+                        origin: intersection.point +
+                            (PREVENT_SELF_INTERSECTION_RANGE * sample.direction),
+                        direction: sample.direction,
+                        time: ray.time,
+                    },
+                    sample.distance,
+                );
+
+                if !light_hidden {
+                    let f = self.material_of(si.entity).f_at(
+                        &sample.direction,
+                        &-ray.direction,
+                        &normal,
+                        intersection.uv,
+                        &intersection.point,
+                    );
+                    self.check(&f, RadianceSource::Material, si.entity_index)?;
+                    total += f * sample.radiance * cos_theta;
+                }
+            }
+            radiance += total / samples as f32;
+        }
+        if !self.area_lights.is_empty() {
+            radiance += self.radiance_from_area_lights(ray, si, rng)?;
+        }
+        if !self.emitters.is_empty() {
+            radiance += self.radiance_from_emitters(ray, si, rng)?;
+        }
+        if let Some(ref environment) = self.environment {
+            radiance += self.radiance_from_environment(environment, ray, si, rng)?;
+        }
+        Ok(radiance)
+    }
+
+    /// Estimates the light from area lights reflected back along a ray, by sampling directions
+    /// toward each light.
+    fn radiance_from_area_lights(
+        &self,
+        ray: &Ray,
+        si: &SceneIntersection,
+        rng: &mut XorShiftRng,
+    ) -> Result<Spectrum, InvalidRadiance> {
+        let intersection = &si.intersection;
+        let normal = self.shading_normal(si);
+        let mut radiance = Spectrum::black();
+        for (light_index, light) in self.area_lights.iter().enumerate() {
+            let mut total = Spectrum::black();
+            for _ in 0..AREA_LIGHT_SAMPLES {
+                let sample = match light.sample(&intersection.point, (rng.gen(), rng.gen())) {
+                    Some(sample) => sample,
+                    None => break,
+                };
+                self.check(
+                    &sample.radiance,
+                    RadianceSource::AreaLight(light_index),
+                    si.entity_index,
+                )?;
+                let cos_theta = sample.direction.dot(&normal);
+                if cos_theta <= 0.0 {
+                    continue;
+                }
+                // Surfaces touching the light do not shadow it.
+                let reach = sample.distance - 2.0 * PREVENT_SELF_INTERSECTION_RANGE;
+                let light_hidden = self.is_occluded(
+                    &Ray {
+                        origin: intersection.point +
+                            (PREVENT_SELF_INTERSECTION_RANGE * sample.direction),
+                        direction: sample.direction,
+                        time: ray.time,
+                    },
+                    reach,
+                );
+                if light_hidden {
+                    continue;
+                }
+
+                let f = self.material_of(si.entity).f_at(
+                    &sample.direction,
+                    &-ray.direction,
+                    &normal,
+                    intersection.uv,
+                    &intersection.point,
+                );
+                self.check(&f, RadianceSource::Material, si.entity_index)?;
+                // BRDFs are scaled by π, so divide it back out of the estimate.
+                total += f * sample.radiance * (cos_theta / (PI * sample.pdf));
+            }
+            radiance += total / AREA_LIGHT_SAMPLES as f32;
+        }
+        Ok(radiance)
+    }
+
+    /// Estimates the light from glowing entities reflected back along a ray, by sampling points on
+    /// the triangles of glowing meshes, and directions within the sphere bounding each other
+    /// entity.
+    ///
+    /// Entities which extend forever cannot be sampled, so only light from their own surfaces is
+    /// seen.
+    fn radiance_from_emitters(
+        &self,
+        ray: &Ray,
+        si: &SceneIntersection,
+        rng: &mut XorShiftRng,
+    ) -> Result<Spectrum, InvalidRadiance> {
+        let intersection = &si.intersection;
+        let normal = self.shading_normal(si);
+        let mut radiance = Spectrum::black();
+        for &Emitter { entity: emitter, ref triangles } in self.emitters.iter() {
+            if emitter == si.entity_index {
+                continue;
+            }
+            if let Some(ref triangles) = *triangles {
+                radiance += self.radiance_from_triangles(ray, si, emitter, triangles, rng)?;
+                continue;
+            }
+            let bounds = self.entities[emitter].bounding_box();
+            if !bounds.is_bounded() {
+                continue;
+            }
+            let center = bounds.centroid();
+            let radius = 0.5 * bounds.lower.distance_to(bounds.upper);
+            let distance = intersection.point.distance_to(center);
+            let mut axis = center - intersection.point;
+            if axis.normalize().is_err() {
+                axis = normal;
+            }
+            // From within the bounding sphere, the entity may be in any direction.
+            let cos_max = if distance > radius {
+                (1.0 - (radius / distance) * (radius / distance)).sqrt()
+            } else {
+                -1.0
             };
+            let pdf = 1.0 / (2.0 * PI * (1.0 - cos_max));
+            let (tangent, bitangent) = perpendicular_tangents(&axis);
+
+            let mut total = Spectrum::black();
+            for _ in 0..EMITTER_SAMPLES {
+                let cos_theta = 1.0 - rng.gen::<f32>() * (1.0 - cos_max);
+                let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+                let phi = 2.0 * PI * rng.gen::<f32>();
+                let direction = (sin_theta * phi.cos()) * tangent +
+                    (sin_theta * phi.sin()) * bitangent + cos_theta * axis;
+                let cos_light = direction.dot(&normal);
+                if cos_light <= 0.0 {
+                    continue;
+                }
+                let toward_emitter = Ray {
+                    origin: intersection.point + (PREVENT_SELF_INTERSECTION_RANGE * direction),
+                    direction: direction,
+                    time: ray.time,
+                };
+                let blocks = |index, entity: &Entity| index == emitter || entity.visibility.shadows;
+                self.counters.add_shadow_ray();
+                let hit = match self.intersect_where(&toward_emitter, blocks) {
+                    Some(hit) => hit,
+                    None => continue,
+                };
+                // Anything else in the way casts a shadow.
+                if hit.entity_index != emitter {
+                    continue;
+                }
+                let emitted = hit.entity
+                    .material
+                    .emitted(&-direction, &hit.intersection.normal);
+                self.check(&emitted, RadianceSource::Emission, emitter)?;
 
-            if !light_hidden {
-                // Determine if we can even see this light from the intersection point.
-                // FIXME: this is wrong, and is just a guess-timate, and not physically accurate.
-                radiance += entity.material.f(
-                    // TODO: get direction to light.
-                    &-light_vector,
+                let f = self.material_of(si.entity).f_at(
+                    &direction,
                     &-ray.direction,
-                ) *
-                    light.irradiance(&intersection.point, &intersection.normal)
+                    &normal,
+                    intersection.uv,
+                    &intersection.point,
+                );
+                self.check(&f, RadianceSource::Material, si.entity_index)?;
+                // BRDFs are scaled by π, so divide it back out of the estimate.
+                total += f * emitted * (cos_light / (PI * pdf));
+            }
+            radiance += total / EMITTER_SAMPLES as f32;
+        }
+        Ok(radiance)
+    }
+
+    /// Estimates the light from a glowing mesh reflected back along a ray, by sampling points on
+    /// its triangles in proportion to their areas.
+    fn radiance_from_triangles(
+        &self,
+        ray: &Ray,
+        si: &SceneIntersection,
+        emitter: usize,
+        triangles: &EmissiveTriangles,
+        rng: &mut XorShiftRng,
+    ) -> Result<Spectrum, InvalidRadiance> {
+        let intersection = &si.intersection;
+        let normal = self.shading_normal(si);
+        let entity = &self.entities[emitter];
+        let transform = entity.transform.at(ray.time);
+        let mut total = Spectrum::black();
+        for _ in 0..EMITTER_SAMPLES {
+            let sample = match triangles.sample(&transform, rng.gen(), (rng.gen(), rng.gen())) {
+                Some(sample) => sample,
+                None => continue,
+            };
+            let mut direction = sample.point - intersection.point;
+            let distance = direction.length();
+            if direction.normalize().is_err() {
+                continue;
+            }
+            let cos_light = direction.dot(&normal);
+            let cos_emitter = direction.dot(&sample.normal).abs();
+            if cos_light <= 0.0 || cos_emitter == 0.0 {
+                continue;
+            }
+            let emitted = entity.material.emitted(&-direction, &sample.normal);
+            self.check(&emitted, RadianceSource::Emission, emitter)?;
+            if emitted == Spectrum::black() {
+                continue;
+            }
+            // Stop short of the emitter, so the triangle sampled does not shadow itself.
+            let reach = distance - 2.0 * PREVENT_SELF_INTERSECTION_RANGE;
+            let light_hidden = self.is_occluded(
+                &Ray {
+                    origin: intersection.point + (PREVENT_SELF_INTERSECTION_RANGE * direction),
+                    direction: direction,
+                    time: ray.time,
+                },
+                reach,
+            );
+            if light_hidden {
+                continue;
+            }
+
+            let f = self.material_of(si.entity).f_at(
+                &direction,
+                &-ray.direction,
+                &normal,
+                intersection.uv,
+                &intersection.point,
+            );
+            self.check(&f, RadianceSource::Material, si.entity_index)?;
+            // Convert the density per unit of area to one per solid angle.
+            let pdf = sample.pdf * distance * distance / cos_emitter;
+            // BRDFs are scaled by π, so divide it back out of the estimate.
+            total += f * emitted * (cos_light / (PI * pdf));
+        }
+        Ok(total / EMITTER_SAMPLES as f32)
+    }
+
+    /// Chooses a direction toward the environment through one of the portals, each chosen
+    /// equally often.
+    ///
+    /// # Arguments
+    /// * `choice` - uniform random number in [0, 1) choosing the portal
+    /// * `u` - position in the unit square, which is mapped onto the portal
+    ///
+    /// # Returns
+    /// `None` if the chosen portal cannot be seen from the point.
+    fn sample_portals(
+        &self,
+        environment: &EnvironmentLight,
+        point: &Point,
+        choice: f32,
+        u: (f32, f32),
+    ) -> Option<EnvironmentSample> {
+        let count = self.portals.len();
+        let portal = &self.portals[((choice * count as f32) as usize).min(count - 1)];
+        let mut sample = portal.sample(environment, point, u)?;
+        sample.pdf /= count as f32;
+        Some(sample)
+    }
+
+    /// Estimates the light from the environment reflected back along a ray, by sampling
+    /// directions toward the environment, through the portals if there are any.
+    fn radiance_from_environment(
+        &self,
+        environment: &EnvironmentLight,
+        ray: &Ray,
+        si: &SceneIntersection,
+        rng: &mut XorShiftRng,
+    ) -> Result<Spectrum, InvalidRadiance> {
+        let intersection = &si.intersection;
+        let normal = self.shading_normal(si);
+        let mut radiance = Spectrum::black();
+        for _ in 0..ENVIRONMENT_SAMPLES {
+            let sample = if self.portals.is_empty() {
+                match environment.sample(rng.gen(), rng.gen()) {
+                    Some(sample) => sample,
+                    None => break,
+                }
             } else {
-                //radiance += Vector::new(0.0, 0.0, 0.5);
+                let u = (rng.gen(), rng.gen());
+                match self.sample_portals(environment, &intersection.point, rng.gen(), u) {
+                    Some(sample) => sample,
+                    None => continue,
+                }
+            };
+            self.check(
+                &sample.radiance,
+                RadianceSource::Environment,
+                si.entity_index,
+            )?;
+            let cos_theta = sample.direction.dot(&normal);
+            if cos_theta <= 0.0 {
+                continue;
+            }
+            let occluded = self.is_occluded(
+                &Ray {
+                    origin: intersection.point +
+                        (PREVENT_SELF_INTERSECTION_RANGE * sample.direction),
+                    direction: sample.direction,
+                    time: ray.time,
+                },
+                INFINITY,
+            );
+            if occluded {
+                continue;
+            }
+
+            let f = self.material_of(si.entity).f_at(
+                &sample.direction,
+                &-ray.direction,
+                &normal,
+                intersection.uv,
+                &intersection.point,
+            );
+            self.check(&f, RadianceSource::Material, si.entity_index)?;
+            // BRDFs are scaled by π, so divide it back out of the estimate.
+            radiance += f * sample.radiance * (cos_theta / (PI * sample.pdf));
+        }
+        Ok(radiance / ENVIRONMENT_SAMPLES as f32)
+    }
+}
+
+impl fmt::Display for Scene {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "scene of {}, {} and {}",
+            counted(self.entities.len(), "entity", "entities"),
+            counted(self.lights.len(), "light", "lights"),
+            counted(self.area_lights.len(), "area light", "area lights")
+        )?;
+        if self.environment.is_some() {
+            write!(f, ", surrounded by an environment")?;
+        }
+        Ok(())
+    }
+}
+
+/// A number of things, such as "1 light" or "2 lights".
+fn counted(count: usize, one: &str, many: &str) -> String {
+    format!("{} {}", count, if count == 1 { one } else { many })
+}
+
+/// Summarizes the scene rather than listing every entity, see `Scene::dump` for those.
+impl fmt::Debug for Scene {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Scene")
+            .field("entities", &self.entities.len())
+            .field("lights", &self.lights.len())
+            .field("area_lights", &self.area_lights.len())
+            .field("emitters", &self.emitters.len())
+            .field("bounds", &self.bounds())
+            .field("environment", &self.environment)
+            .field("portals", &self.portals.len())
+            .field("bounce_limits", &self.bounce_limits)
+            .field("light_cutoff", &self.light_cutoff)
+            .field("bvh_layout", &self.bvh_layout)
+            .field("finalized", &(self.bvh_entity_count == self.entities.len()))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BounceLimits, MediumStack, RadianceSource, RayVisibility, Scene, Spectrum};
+    use math::{AnimatedTransform, Csg, CsgOperation, Cuboid, PlanarAngle, Plane, Point, Ray,
+               Sphere, Transform, TriangleMesh, Vector};
+    use rand::XorShiftRng;
+    use scene::area_light::SphereLight;
+    use scene::environment::{EnvironmentLight, Portal};
+    use scene::material::{DielectricMaterial, EmissiveMaterial, LambertianMaterial, Material,
+                          ScatterKind, SpecularMaterial};
+    use scene::nonarea_light::{DirectionalLight, NonAreaLight, PointLight};
+    use scene::sampler::pixel_rng;
+    use std::f32::NAN;
+    use std::f32::consts::PI;
+
+    /// A material with broken shading math.
+    struct NanMaterial;
+
+    impl Material for NanMaterial {
+        #[allow(unused_variables)]
+        fn f(&self, light: &Vector, view: &Vector, normal: &Vector) -> Spectrum {
+            Spectrum::new(NAN, 0.0, 0.0)
+        }
+
+        fn name(&self) -> &'static str {
+            "NaN"
+        }
+    }
+
+    fn grid_of_spheres() -> Scene {
+        let mut scene = Scene::new();
+        scene.add_light(Box::new(PointLight::new(
+            Point::new(0.0, 20.0, 0.0),
+            Spectrum::uniform(1.0),
+        )));
+        for i in 0..10 {
+            for j in 0..10 {
+                scene.add_entity(
+                    Box::new(Sphere::new_with_radius(0.5)),
+                    Box::new(LambertianMaterial::new(&Spectrum::uniform(0.5))),
+                    Transform::scale(0.5 + 0.05 * i as f32, 1.0, 1.0)
+                        .then_translate(i as f32 - 5.0, j as f32 - 5.0, 20.0),
+                );
+            }
+        }
+        scene.add_entity(
+            Box::new(Plane::from_normal_and_point(
+                &Vector::new(0.0, 1.0, 0.0),
+                &Point::new(0.0, -6.0, 0.0),
+            )),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(0.2))),
+            Transform::identity(),
+        );
+        scene
+    }
+
+    #[test]
+    fn test_finalize_matches_linear_search() {
+        let linear = grid_of_spheres();
+        let mut accelerated = grid_of_spheres();
+        accelerated.finalize();
+
+        for i in 0..20 {
+            for j in 0..20 {
+                let mut ray = Ray {
+                    origin: Point::new(0.0, 0.0, 0.0),
+                    direction: Vector::new(i as f32 * 0.05 - 0.5, j as f32 * 0.05 - 0.5, 1.0),
+                    time: 0.0,
+                };
+                ray.normalize().unwrap();
+                let expected = linear.intersect(&ray).map(|si| si.intersection.point);
+                let actual = accelerated.intersect(&ray).map(|si| si.intersection.point);
+                match (expected, actual) {
+                    (Some(e), Some(a)) => assert_relative_eq!(e, a, max_relative = 1e-4),
+                    (None, None) => {}
+                    _ => panic!("Accelerated and linear intersection disagree."),
+                }
+                assert_relative_eq!(linear.trace(&ray), accelerated.trace(&ray));
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_radiance_is_reported() {
+        let mut scene = Scene::new();
+        scene.add_light(Box::new(PointLight::new(
+            Point::new(0.0, 0.0, 0.0),
+            Spectrum::uniform(1.0),
+        )));
+        scene.add_entity(
+            Box::new(Sphere::new_with_radius(1.0)),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(0.5))),
+            Transform::translate(0.0, 0.0, -10.0),
+        );
+        scene.add_entity(
+            Box::new(Sphere::new_with_radius(1.0)),
+            Box::new(NanMaterial),
+            Transform::translate(0.0, 0.0, 10.0),
+        );
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+
+        let mut rng = XorShiftRng::new_unseeded();
+        scene.set_radiance_checks(true);
+        let error = scene.try_trace(&ray, &mut rng).unwrap_err();
+        assert_eq!(error.source, RadianceSource::Material);
+        assert_eq!(error.entity, 1);
+
+        // Tracing without a report renders the invalid radiance as black.
+        assert_relative_eq!(scene.trace(&ray), Spectrum::black());
+        assert_relative_eq!(scene.first_hit(&ray, &mut rng).unwrap().direct, Spectrum::black());
+
+        // Checks are off unless asked for.
+        scene.set_radiance_checks(false);
+        assert!(scene.try_trace(&ray, &mut rng).unwrap().r.is_nan());
+        scene = Scene::new();
+        assert!(!scene.check_radiance);
+    }
+
+    #[test]
+    fn test_material_override() {
+        let mut scene = Scene::new();
+        scene.add_light(Box::new(PointLight::new(
+            Point::new(0.0, 0.0, 0.0),
+            Spectrum::uniform(1.0),
+        )));
+        scene.add_entity(
+            Box::new(Sphere::new_with_radius(1.0)),
+            Box::new(NanMaterial),
+            Transform::translate(0.0, 0.0, 10.0),
+        );
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let mut rng = XorShiftRng::new_unseeded();
+        scene.set_radiance_checks(true);
+        assert!(scene.try_trace(&ray, &mut rng).is_err());
+
+        scene.set_material_override(Some(
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+        ));
+        assert!(scene.try_trace(&ray, &mut rng).unwrap().r > 0.0);
+
+        scene.set_material_override(None);
+        assert!(scene.try_trace(&ray, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_ray_visibility() {
+        // A mirror in front of the camera, reflecting a sphere behind it.
+        let mut scene = Scene::new();
+        scene.add_light(Box::new(PointLight::new(
+            Point::new(0.0, 3.0, 0.0),
+            Spectrum::uniform(1.0),
+        )));
+        scene.add_entity(
+            Box::new(Plane::from_normal_and_point(
+                &Vector::new(0.0, 0.0, -1.0),
+                &Point::new(0.0, 0.0, 10.0),
+            )),
+            Box::new(SpecularMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::identity(),
+        );
+        let sphere = scene.add_entity(
+            Box::new(Sphere::new_with_radius(1.0)),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::translate(0.0, 0.0, -5.0),
+        );
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        assert!(scene.trace(&ray).r > 0.0);
+
+        let hidden_in_reflections = RayVisibility {
+            glossy: false,
+            ..RayVisibility::default()
+        };
+        scene.set_ray_visibility(sphere, hidden_in_reflections);
+        assert_relative_eq!(scene.trace(&ray), Spectrum::black());
+
+        // Without its shadow, light from above reaches a floor below the sphere.
+        let below = Ray {
+            origin: Point::new(0.0, -2.0, -5.0),
+            direction: Vector::new(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        scene.add_light(Box::new(PointLight::new(
+            Point::new(0.0, 5.0, -5.0),
+            Spectrum::uniform(1.0),
+        )));
+        scene.add_entity(
+            Box::new(Plane::from_normal_and_point(
+                &Vector::new(0.0, 1.0, 0.0),
+                &Point::new(0.0, -3.0, 0.0),
+            )),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::identity(),
+        );
+        let lit = scene.trace(&below);
+        let shadowless = RayVisibility {
+            shadows: false,
+            ..RayVisibility::default()
+        };
+        scene.set_ray_visibility(sphere, shadowless);
+        assert!(scene.trace(&below).r > lit.r);
+    }
+
+    #[test]
+    fn test_light_cosine() {
+        // The top of a sphere, seen from straight above.
+        let ray = Ray {
+            origin: Point::new(0.0, 2.0, 0.0),
+            direction: Vector::new(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        let lit = |light: Box<NonAreaLight>| {
+            let mut scene = Scene::new();
+            scene.add_light(light);
+            scene.add_entity(
+                Box::new(Sphere::new_with_radius(1.0)),
+                Box::new(LambertianMaterial::new(&Spectrum::uniform(0.5))),
+                Transform::identity(),
+            );
+            // A black sphere behind the point light, farther than the light so casting no shadow.
+            scene.add_entity(
+                Box::new(Sphere::new_with_radius(1.0)),
+                Box::new(LambertianMaterial::new(&Spectrum::black())),
+                Transform::translate(0.0, 6.0, 0.0),
+            );
+            scene.trace(&ray)
+        };
+
+        // Light arriving 60 degrees from the normal is weighted by the cosine of the angle.
+        let slanted = Vector::new((PI / 3.0).sin(), -0.5, 0.0);
+        let white = Spectrum::uniform(1.0);
+        let expected = Spectrum::uniform(0.5 * 0.5);
+        assert_relative_eq!(
+            lit(Box::new(DirectionalLight::new(&slanted, &white))),
+            expected,
+            epsilon = 1e-5
+        );
+
+        // Light from below the surface does not reach it.
+        let from_below = DirectionalLight::new(&Vector::new(0.0, 1.0, 0.0), &white);
+        assert_relative_eq!(lit(Box::new(from_below)), Spectrum::black());
+
+        // A point light shining straight down, 2 away.
+        let point = PointLight::new(Point::new(0.0, 3.0, 0.0), 4.0 * white);
+        assert_relative_eq!(lit(Box::new(point)), Spectrum::uniform(0.5), epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_transformed_lights() {
+        let origin = Point::new(0.0, 0.0, 0.0);
+        let white = Spectrum::uniform(1.0);
+        let mut scene = Scene::new();
+
+        // A rig scaled, turned a quarter turn about Z and lifted, holding a point light and a
+        // light shining down along the rig's Y-.
+        let rig = Transform::scale(2.0, 2.0, 2.0)
+            .then_rotate_z(PlanarAngle::Degrees(90.0))
+            .then_translate(0.0, 5.0, 0.0);
+        scene.add_light_with_transform(
+            Box::new(PointLight::new(Point::new(1.0, 0.0, 0.0), white)),
+            rig,
+        );
+        scene.add_light_with_transform(
+            Box::new(DirectionalLight::new(&Vector::new(0.0, -1.0, 0.0), &white)),
+            rig,
+        );
+
+        let position = *rig.to_world() * Point::new(1.0, 0.0, 0.0);
+        assert_relative_eq!(position, Point::new(0.0, 7.0, 0.0), epsilon = 1e-5);
+        assert_eq!(scene.light_positions().len(), 1);
+        assert_relative_eq!(scene.light_positions()[0], position, epsilon = 1e-5);
+
+        // Distances are measured in the world, not the rig's scaled coordinates.
+        let point = scene.lights[0].incident(&origin);
+        assert_relative_eq!(point.direction, Vector::new(0.0, 1.0, 0.0), epsilon = 1e-5);
+        assert_relative_eq!(point.distance, 7.0, epsilon = 1e-4);
+
+        // The rig's Y- is the world's X+, so the light arrives from X-.
+        let directional = scene.lights[1].incident(&origin);
+        assert_relative_eq!(directional.direction, Vector::new(-1.0, 0.0, 0.0), epsilon = 1e-5);
+        assert!(directional.distance.is_infinite());
+        assert!(format!("{}", scene.lights[1]).contains("placed by a transform"));
+    }
+
+    #[test]
+    fn test_animated_light() {
+        // A point light passing overhead from X- to X+ while the shutter is open.
+        let origin = Point::new(0.0, 0.0, 0.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, 0.0), Spectrum::uniform(100.0));
+        let mut scene = Scene::new();
+        scene.add_animated_light(
+            Box::new(light),
+            AnimatedTransform::new(
+                Transform::translate(-5.0, 10.0, 0.0),
+                0.0,
+                Transform::translate(5.0, 10.0, 0.0),
+                1.0,
+            ),
+        );
+        let light = &scene.lights[0];
+        assert!(format!("{}", light).contains("animated"));
+
+        let overhead = light.incident_at(&origin, 0.5);
+        assert_relative_eq!(overhead.direction, Vector::new(0.0, 1.0, 0.0), epsilon = 1e-5);
+        assert_relative_eq!(overhead.distance, 10.0, epsilon = 1e-4);
+        assert_relative_eq!(overhead.radiance, Spectrum::uniform(1.0), epsilon = 1e-5);
+        let end = light.incident_at(&origin, 1.0);
+        assert_relative_eq!(end.direction, Vector::unit(5.0, 10.0, 0.0).unwrap(), epsilon = 1e-5);
+
+        // Without a time the light is where it starts, and it reaches wherever it goes.
+        let start = light.incident(&origin);
+        let toward_start = Vector::unit(-5.0, 10.0, 0.0).unwrap();
+        assert_relative_eq!(start.direction, toward_start, epsilon = 1e-5);
+        assert_relative_eq!(scene.light_positions()[0], Point::new(-5.0, 10.0, 0.0));
+        let reach = light.reach(1.0).unwrap();
+        assert_relative_eq!(reach.lower.x, -15.0, epsilon = 1e-4);
+        assert_relative_eq!(reach.upper.x, 15.0, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_animated_light_shading() {
+        // The top of a sphere, lit from above while the shutter opens and from below once it has
+        // turned a half turn.
+        let mut scene = Scene::new();
+        scene.add_entity(
+            Box::new(Sphere::new_with_radius(1.0)),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(0.5))),
+            Transform::identity(),
+        );
+        scene.add_animated_light(
+            Box::new(DirectionalLight::new(
+                &Vector::new(0.0, -1.0, 0.0),
+                &Spectrum::uniform(1.0),
+            )),
+            AnimatedTransform::new(
+                Transform::identity(),
+                0.0,
+                Transform::rotate_z(PlanarAngle::Degrees(180.0)),
+                1.0,
+            ),
+        );
+        let down_at = |time: f32| {
+            Ray {
+                origin: Point::new(0.0, 2.0, 0.0),
+                direction: Vector::new(0.0, -1.0, 0.0),
+                time: time,
+            }
+        };
+        assert_relative_eq!(scene.trace(&down_at(0.0)), Spectrum::uniform(0.5), epsilon = 1e-5);
+        assert_relative_eq!(scene.trace(&down_at(1.0)), Spectrum::black());
+    }
+
+    #[test]
+    fn test_is_occluded() {
+        let mut scene = Scene::new();
+        let sphere = scene.add_entity(
+            Box::new(Sphere::new_with_radius(1.0)),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::translate(0.0, 0.0, 5.0),
+        );
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        for &finalized in [false, true].iter() {
+            if finalized {
+                scene.finalize();
+            }
+            // The sphere is entered at 4.
+            assert!(scene.is_occluded(&ray, 10.0));
+            assert!(!scene.is_occluded(&ray, 3.0));
+            assert!(!scene.is_occluded(&Ray { direction: -ray.direction, ..ray }, 10.0));
+        }
+
+        let shadowless = RayVisibility {
+            shadows: false,
+            ..RayVisibility::default()
+        };
+        scene.set_ray_visibility(sphere, shadowless);
+        assert!(!scene.is_occluded(&ray, 10.0));
+    }
+
+    #[test]
+    fn test_radiance_passes() {
+        // A diffuse sphere between two facing mirrors, lit from the side.
+        let mut scene = Scene::new();
+        scene.add_light(Box::new(PointLight::new(
+            Point::new(3.0, 0.5, 5.0),
+            Spectrum::uniform(10.0),
+        )));
+        scene.add_entity(
+            Box::new(Plane::from_normal_and_point(
+                &Vector::new(0.0, 0.0, -1.0),
+                &Point::new(0.0, 0.0, 10.0),
+            )),
+            Box::new(SpecularMaterial::new(&Spectrum::uniform(0.9))),
+            Transform::identity(),
+        );
+        scene.add_entity(
+            Box::new(Plane::from_normal_and_point(
+                &Vector::new(0.0, 0.0, 1.0),
+                &Point::new(0.0, 0.0, -1.0),
+            )),
+            Box::new(SpecularMaterial::new(&Spectrum::uniform(0.9))),
+            Transform::identity(),
+        );
+        scene.add_entity(
+            Box::new(Sphere::new_with_radius(1.0)),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(0.5))),
+            Transform::translate(0.0, 0.5, 5.0),
+        );
+
+        let mut ray = Ray {
+            origin: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(0.5, 0.5, 4.13),
+            time: 0.0,
+        };
+        let mut rng = XorShiftRng::new_unseeded();
+        ray.normalize().unwrap();
+        let passes = scene.try_trace_passes(&ray, &mut rng).unwrap();
+        let radiance = scene.try_trace(&ray, &mut rng).unwrap();
+        assert_relative_eq!(passes.total(), radiance, max_relative = 1e-5);
+        assert_relative_eq!(passes.diffuse + passes.specular, radiance, max_relative = 1e-5);
+        assert_relative_eq!(passes.diffuse, passes.direct);
+
+        assert!(passes.direct.r > 0.0);
+
+        // Through the mirror the sphere is only seen after a bounce.
+        let mirror_ray = Ray {
+            origin: Point::new(0.5, 0.5, 9.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let passes = scene.try_trace_passes(&mirror_ray, &mut rng).unwrap();
+        assert_relative_eq!(passes.direct, Spectrum::black());
+        assert!(passes.indirect.r > 0.0);
+        let radiance = scene.try_trace(&mirror_ray, &mut rng).unwrap();
+        assert_relative_eq!(passes.total(), radiance, max_relative = 1e-5);
+        assert_relative_eq!(passes.specular, radiance, max_relative = 1e-5);
+    }
+
+    #[test]
+    fn test_bounce_limits() {
+        let limits = BounceLimits {
+            total: 2,
+            diffuse: 0,
+            glossy: 1,
+            transmission: 2,
+        };
+        assert!(limits.after(ScatterKind::Diffuse).is_none());
+        let glossy = limits.after(ScatterKind::Glossy).unwrap();
+        assert_eq!(glossy.total, 1);
+        assert!(glossy.after(ScatterKind::Glossy).is_none());
+        let transmitted = glossy.after(ScatterKind::Transmission).unwrap();
+        assert_eq!(transmitted.transmission, 1);
+        assert!(transmitted.after(ScatterKind::Transmission).is_none());
+    }
+
+    #[test]
+    fn test_bounce_limits_stop_paths() {
+        // A lit diffuse sphere seen in a mirror.
+        let mut scene = Scene::new();
+        scene.add_light(Box::new(PointLight::new(
+            Point::new(0.0, 5.0, 9.0),
+            Spectrum::uniform(10.0),
+        )));
+        scene.add_entity(
+            Box::new(Plane::from_normal_and_point(
+                &Vector::new(0.0, 0.0, -1.0),
+                &Point::new(0.0, 0.0, 10.0),
+            )),
+            Box::new(SpecularMaterial::new(&Spectrum::uniform(0.9))),
+            Transform::identity(),
+        );
+        scene.add_entity(
+            Box::new(Sphere::new_with_radius(1.0)),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(0.5))),
+            Transform::translate(0.0, 0.0, 5.0),
+        );
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 8.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        assert!(scene.trace(&ray).r > 0.0);
+
+        scene.set_bounce_limits(&BounceLimits {
+            glossy: 0,
+            ..BounceLimits::default()
+        });
+        assert_relative_eq!(scene.trace(&ray), Spectrum::black());
+
+        scene.set_bounce_limits(&BounceLimits {
+            total: 0,
+            ..BounceLimits::default()
+        });
+        assert_relative_eq!(scene.trace(&ray), Spectrum::black());
+    }
+
+    #[test]
+    fn test_emissive_geometry() {
+        // A glowing sphere above a white floor, with no other lights.
+        let mut scene = Scene::new();
+        scene.add_entity(
+            Box::new(Sphere::new_with_radius(1.0)),
+            Box::new(EmissiveMaterial::new(&Spectrum::new(100.0, 50.0, 0.0))),
+            Transform::translate(0.0, 10.0, 0.0),
+        );
+        scene.add_entity(
+            Box::new(Plane::from_normal_and_point(
+                &Vector::new(0.0, 1.0, 0.0),
+                &Point::new(0.0, 0.0, 0.0),
+            )),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::identity(),
+        );
+        scene.finalize();
+
+        // The sphere is seen directly.
+        let at_sphere = Ray {
+            origin: Point::new(0.0, 10.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        assert_relative_eq!(scene.trace(&at_sphere), Spectrum::new(100.0, 50.0, 0.0));
+
+        // A small sphere of radiance L at distance d lights the floor below it with radiance
+        // L (r / d)².
+        let mut at_floor = Ray {
+            origin: Point::new(0.0, 5.0, -5.0),
+            direction: Vector::new(0.0, -5.0, 5.0),
+            time: 0.0,
+        };
+        at_floor.normalize().unwrap();
+        // Directions are sampled toward the box bounding the sphere, so many miss it.
+        let average = |scene: &Scene| {
+            let traces = 400;
+            let mut radiance = Spectrum::black();
+            for _ in 0..traces {
+                radiance += scene.trace(&at_floor);
             }
+            radiance / traces as f32
+        };
+        assert_relative_eq!(average(&scene), Spectrum::new(1.0, 0.5, 0.0), epsilon = 0.1);
+
+        // Glowing entities still light the scene when materials are overridden.
+        scene.set_material_override(Some(
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+        ));
+        assert_relative_eq!(average(&scene), Spectrum::new(1.0, 0.5, 0.0), epsilon = 0.1);
+    }
+
+    #[test]
+    fn test_emissive_mesh() {
+        // A glowing 2 by 2 square facing down onto a white floor, with no other lights.
+        let mut scene = Scene::new();
+        let square = TriangleMesh::new(
+            vec![
+                Point::new(-1.0, 0.0, -1.0),
+                Point::new(1.0, 0.0, -1.0),
+                Point::new(1.0, 0.0, 1.0),
+                Point::new(-1.0, 0.0, 1.0),
+            ],
+            vec![0, 1, 2, 0, 2, 3],
+        );
+        scene.add_entity(
+            Box::new(square),
+            Box::new(EmissiveMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::translate(0.0, 3.1, 0.0),
+        );
+        scene.add_entity(
+            Box::new(Plane::from_normal_and_point(
+                &Vector::new(0.0, 1.0, 0.0),
+                &Point::new(0.0, 0.0, 0.0),
+            )),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::identity(),
+        );
+        scene.finalize();
+
+        // The middle of the floor receives the form factor of the square, four times that of a 1
+        // by 1 rectangle with a corner above the point at height 3.1.
+        let (x, y) = (1.0f32 / 3.1, 1.0f32 / 3.1);
+        let corner_factor = (x / (1.0 + x * x).sqrt() * (y / (1.0 + x * x).sqrt()).atan() +
+                                 y / (1.0 + y * y).sqrt() * (x / (1.0 + y * y).sqrt()).atan()) /
+            (2.0 * PI);
+        let mut ray = Ray {
+            origin: Point::new(0.0, 1.0, -2.0),
+            direction: Vector::new(0.0, -1.0, 2.0),
+            time: 0.0,
+        };
+        ray.normalize().unwrap();
+        let traces = 200;
+        let average = (0..traces).map(|_| scene.trace(&ray).r).sum::<f32>() / traces as f32;
+        assert_relative_eq!(average, 4.0 * corner_factor, epsilon = 0.005);
+    }
+
+    #[test]
+    fn test_nested_dielectrics() {
+        // A glass ball inside a larger ball of air, in front of a small glowing ball.
+        let carved_scene = |air_priority: u32| {
+            let mut scene = Scene::new();
+            scene.add_entity(
+                Box::new(Sphere::new_with_radius(0.5)),
+                Box::new(EmissiveMaterial::new(&Spectrum::uniform(1.0))),
+                Transform::translate(0.5, 0.0, 15.0),
+            );
+            scene.add_entity(
+                Box::new(Sphere::new_with_radius(1.0)),
+                Box::new(DielectricMaterial::new(1.5).with_priority(1)),
+                Transform::translate(0.0, 0.0, 5.0),
+            );
+            scene.add_entity(
+                Box::new(Sphere::new_with_radius(2.0)),
+                Box::new(DielectricMaterial::new(1.0).with_priority(air_priority)),
+                Transform::translate(0.0, 0.0, 5.0),
+            );
+            scene.finalize();
+            scene
+        };
+        let ray = Ray {
+            origin: Point::new(0.5, 0.0, 0.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+
+        // Air of higher priority carves the glass away, so rays pass straight through.
+        let carved = carved_scene(2);
+        for _ in 0..20 {
+            assert_relative_eq!(carved.trace(&ray), Spectrum::uniform(1.0));
+        }
+
+        // Otherwise the glass bends rays away from the glowing ball.
+        let solid = carved_scene(0);
+        let average = (0..20).map(|_| solid.trace(&ray).r).sum::<f32>() / 20.0;
+        assert!(average < 0.5, "Glass should be seen, not {}", average);
+    }
+
+    #[test]
+    fn test_closed_glass_mesh() {
+        // A unit cube wound counterclockwise around its outward normals.
+        let cube = TriangleMesh::new(
+            (0..8)
+                .map(|i| Point::new((i & 1) as f32, ((i >> 1) & 1) as f32, (i >> 2) as f32))
+                .collect(),
+            vec![
+                0, 2, 3, 0, 3, 1, // Z-
+                4, 5, 7, 4, 7, 6, // Z+
+                0, 1, 5, 0, 5, 4, // Y-
+                2, 6, 7, 2, 7, 3, // Y+
+                0, 4, 6, 0, 6, 2, // X-
+                1, 3, 7, 1, 7, 5, // X+
+            ],
+        );
+        let glass = DielectricMaterial::new(1.5);
+        let medium = glass.interior().unwrap();
+        let mut scene = Scene::new();
+        scene.add_entity(
+            Box::new(cube),
+            Box::new(glass),
+            Transform::translate(-0.5, -0.5, 4.5),
+        );
+
+        // Both surfaces along a ray through the cube are crossed, into it and back out.
+        let mut ray = Ray {
+            origin: Point::new(0.1, 0.2, 0.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let mut media = MediumStack::new();
+        for &inside in [true, false].iter() {
+            let si = scene.intersect(&ray).unwrap();
+            media = media.crossed(
+                si.entity_index,
+                &medium,
+                &ray.direction,
+                &si.intersection.normal,
+            );
+            assert_eq!(media.current().is_some(), inside);
+            ray.origin = si.intersection.point + 0.01 * ray.direction;
         }
-        radiance
+        assert!(scene.intersect(&ray).is_none());
+        assert!(media.current().is_none());
+    }
+
+    #[test]
+    fn test_absorption() {
+        // A ball of colored glass, matched to the vacuum so it does not bend or reflect, in front
+        // of a large glowing ball.
+        let mut scene = Scene::new();
+        scene.add_entity(
+            Box::new(Sphere::new_with_radius(5.0)),
+            Box::new(EmissiveMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::translate(0.0, 0.0, 15.0),
+        );
+        scene.add_entity(
+            Box::new(Sphere::new_with_radius(1.0)),
+            Box::new(
+                DielectricMaterial::new(1.0).with_absorption(&Spectrum::new(0.0, 0.5, 1.0)),
+            ),
+            Transform::translate(0.0, 0.0, 5.0),
+        );
+        scene.finalize();
+        let through = |x: f32| {
+            scene.trace(&Ray {
+                origin: Point::new(x, 0.0, 0.0),
+                direction: Vector::new(0.0, 0.0, 1.0),
+                time: 0.0,
+            })
+        };
+
+        // Light is dimmed exponentially with the distance travelled through the glass, which is
+        // 2 through the middle and 1.6 nearer the edge, less the small step rays start from the
+        // surface.
+        let absorbed = |distance: f32| {
+            Spectrum::new(1.0, (-0.5 * distance).exp(), (-distance).exp())
+        };
+        assert_relative_eq!(through(0.0), absorbed(2.0), epsilon = 0.01);
+        assert_relative_eq!(through(0.6), absorbed(1.6), epsilon = 0.01);
+        assert!(through(0.0).b < through(0.6).b);
+        assert_relative_eq!(through(2.0), Spectrum::uniform(1.0));
+    }
+
+    #[test]
+    fn test_portals() {
+        // A closed white room lit only by a constant sky through a 2 by 2 skylight in its
+        // ceiling, whose outside is 3.1 above the middle of the floor.
+        let room = Csg::new(
+            CsgOperation::Difference,
+            Box::new(Csg::new(
+                CsgOperation::Difference,
+                Box::new(Cuboid::new(Point::new(-5.0, -1.0, -5.0), Point::new(5.0, 3.1, 5.0))),
+                Box::new(Cuboid::new(Point::new(-4.0, 0.0, -4.0), Point::new(4.0, 3.0, 4.0))),
+            )),
+            Box::new(Cuboid::new(Point::new(-1.0, 2.0, -1.0), Point::new(1.0, 4.0, 1.0))),
+        );
+        let mut scene = Scene::new();
+        scene.add_entity(
+            Box::new(room),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::identity(),
+        );
+        scene.set_environment(Some(EnvironmentLight::constant(&Spectrum::uniform(1.0))));
+        scene.add_portal(Portal::new(
+            Point::new(-1.0, 3.1, -1.0),
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 2.0),
+        ));
+        scene.finalize();
+
+        // The middle of the floor receives the form factor of the skylight, four times that of
+        // a 1 by 1 rectangle with a corner above the point at height 3.1.
+        let (x, y) = (1.0f32 / 3.1, 1.0f32 / 3.1);
+        let corner_factor = (x / (1.0 + x * x).sqrt() * (y / (1.0 + x * x).sqrt()).atan() +
+                                 y / (1.0 + y * y).sqrt() * (x / (1.0 + y * y).sqrt()).atan()) /
+            (2.0 * PI);
+        let mut ray = Ray {
+            origin: Point::new(0.0, 1.0, -2.0),
+            direction: Vector::new(0.0, -1.0, 2.0),
+            time: 0.0,
+        };
+        ray.normalize().unwrap();
+        let traces = 200;
+        let average = (0..traces).map(|_| scene.trace(&ray).r).sum::<f32>() / traces as f32;
+        assert_relative_eq!(average, 4.0 * corner_factor, epsilon = 0.005);
+        assert!(scene.dump().contains("portal 0: portal from"));
+    }
+
+    #[test]
+    fn test_trace_primary() {
+        let mut scene = Scene::new();
+        scene.add_light(Box::new(PointLight::new(
+            Point::new(0.0, 10.0, 0.0),
+            Spectrum::uniform(1.0),
+        )));
+        scene.add_entity(
+            Box::new(Sphere::new_with_radius(1.0)),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(0.5))),
+            Transform::translate(0.0, 0.0, 5.0),
+        );
+        scene.finalize();
+        let ray_at = |x: f32| {
+            Ray {
+                origin: Point::new(x, 0.0, 0.0),
+                direction: Vector::new(0.0, 0.0, 1.0),
+                time: 0.0,
+            }
+        };
+
+        let mut rng = XorShiftRng::new_unseeded();
+        // Hits are shaded as usual, while misses are left for a background.
+        let hit = scene.try_trace_primary(&ray_at(0.5), &mut rng).unwrap().unwrap();
+        assert_relative_eq!(hit, scene.trace(&ray_at(0.5)));
+        assert!(scene.try_trace_primary(&ray_at(2.0), &mut rng).unwrap().is_none());
+
+        // Invalid radiance is reported rather than panicking.
+        scene.set_material_override(Some(Box::new(NanMaterial)));
+        scene.set_radiance_checks(true);
+        assert!(scene.try_trace_primary(&ray_at(0.5), &mut rng).is_err());
+        assert!(scene.try_trace_primary(&ray_at(2.0), &mut rng).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_seeded_traces_repeat() {
+        // Glass over a floor lit by a glowing ball, so both scattering and lights are sampled.
+        let mut scene = Scene::new();
+        scene.add_area_light(Box::new(SphereLight::new(
+            Point::new(0.0, 4.0, 0.0),
+            1.0,
+            &Spectrum::uniform(16.0),
+        )));
+        scene.add_entity(
+            Box::new(Plane::from_normal_and_point(
+                &Vector::new(0.0, 1.0, 0.0),
+                &Point::new(0.0, 0.0, 0.0),
+            )),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::identity(),
+        );
+        scene.add_entity(
+            Box::new(Sphere::new_with_radius(0.5)),
+            Box::new(DielectricMaterial::new(1.5)),
+            Transform::translate(0.0, 0.5, 1.0),
+        );
+        scene.finalize();
+        let mut ray = Ray {
+            origin: Point::new(0.0, 1.0, -1.0),
+            direction: Vector::new(0.0, -0.25, 1.0),
+            time: 0.0,
+        };
+        ray.normalize().unwrap();
+
+        let traces = |seed| {
+            let mut rng = pixel_rng(seed, 0, 0);
+            (0..10)
+                .map(|_| scene.try_trace(&ray, &mut rng).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(traces(1), traces(1));
+        assert_ne!(traces(1), traces(2));
+    }
+
+    #[test]
+    fn test_deep_samples() {
+        // Glass in front of two opaque balls, one behind the other.
+        let mut scene = Scene::new();
+        scene.add_entity(
+            Box::new(Sphere::new_with_radius(1.0)),
+            Box::new(DielectricMaterial::new(1.5)),
+            Transform::translate(0.0, 0.0, 5.0),
+        );
+        for &z in [10.0, 15.0].iter() {
+            scene.add_entity(
+                Box::new(Sphere::new_with_radius(1.0)),
+                Box::new(LambertianMaterial::new(&Spectrum::uniform(0.5))),
+                Transform::translate(0.0, 0.0, z),
+            );
+        }
+        scene.finalize();
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+
+        // Both sides of the glass reflect 4% of light head on, and the nearer opaque ball hides
+        // the farther one.
+        let samples = scene.deep_samples(&ray, 8);
+        assert_eq!(samples.len(), 3);
+        let depths: Vec<f32> = samples.iter().map(|s| s.depth).collect();
+        assert_relative_eq!(depths[0], 4.0, epsilon = 1e-4);
+        assert_relative_eq!(depths[1], 6.0, epsilon = 1e-4);
+        assert_relative_eq!(depths[2], 9.0, epsilon = 1e-4);
+        assert_relative_eq!(samples[0].alpha, 0.04, epsilon = 1e-4);
+        assert_relative_eq!(samples[1].alpha, 0.04, epsilon = 1e-4);
+        assert_relative_eq!(samples[2].alpha, 1.0);
+
+        assert_eq!(scene.deep_samples(&ray, 2).len(), 2);
+    }
+
+    #[test]
+    fn test_soft_shadows() {
+        // A white floor under a ball, lit from directly above by a light 10 degrees in radius.
+        let mut scene = Scene::new();
+        scene.add_light(Box::new(DirectionalLight::new_with_angular_radius(
+            &Vector::new(0.0, -1.0, 0.0),
+            &Spectrum::uniform(1.0),
+            PlanarAngle::Degrees(10.0),
+        )));
+        scene.add_entity(
+            Box::new(Plane::from_normal_and_point(
+                &Vector::new(0.0, 1.0, 0.0),
+                &Point::new(0.0, 0.0, 0.0),
+            )),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::identity(),
+        );
+        scene.add_entity(
+            Box::new(Sphere::new_with_radius(2.0)),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::translate(0.0, 4.0, 0.0),
+        );
+        let at_floor = |x: f32| {
+            let mut ray = Ray {
+                origin: Point::new(x, 1.0, -1.0),
+                direction: Vector::new(0.0, -1.0, 1.0),
+                time: 0.0,
+            };
+            ray.normalize().unwrap();
+            let traces = 100;
+            (0..traces).map(|_| scene.trace(&ray).r).sum::<f32>() / traces as f32
+        };
+
+        // Far from the ball the floor is fully lit, less the light arriving at an angle.  Below
+        // the edge of the ball it is in partial shadow, and below its center in full shadow.
+        let cos_max = PlanarAngle::Degrees(10.0).to_radians().cos();
+        assert_relative_eq!(at_floor(5.0), (1.0 + cos_max) / 2.0, epsilon = 0.01);
+        let penumbra = at_floor(2.0);
+        assert!(penumbra > 0.25 && penumbra < 0.85);
+        assert_relative_eq!(at_floor(0.0), 0.0);
+    }
+
+    #[test]
+    fn test_area_light() {
+        // A white floor under a glowing ball.
+        let mut scene = Scene::new();
+        scene.add_area_light(Box::new(SphereLight::new(
+            Point::new(0.0, 4.0, 0.0),
+            1.0,
+            &Spectrum::uniform(16.0),
+        )));
+        scene.add_entity(
+            Box::new(Plane::from_normal_and_point(
+                &Vector::new(0.0, 1.0, 0.0),
+                &Point::new(0.0, 0.0, 0.0),
+            )),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::identity(),
+        );
+        let mut at_floor = Ray {
+            origin: Point::new(0.0, 1.0, -1.0),
+            direction: Vector::new(0.0, -1.0, 1.0),
+            time: 0.0,
+        };
+        at_floor.normalize().unwrap();
+
+        // A sphere of radiance L lights the floor below it with radiance L sin²θ, for the angle θ
+        // between the center of the sphere and its edge.
+        assert_relative_eq!(scene.trace(&at_floor), Spectrum::uniform(1.0), epsilon = 0.1);
+
+        // Entities between the floor and the light cast shadows.
+        scene.add_entity(
+            Box::new(Sphere::new_with_radius(1.0)),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::translate(0.0, 2.0, 0.0),
+        );
+        assert_relative_eq!(scene.trace(&at_floor), Spectrum::black());
+    }
+
+    #[test]
+    fn test_environment_light() {
+        // A white floor under a uniformly white sky reflects all of it.
+        let mut scene = Scene::new();
+        scene.add_entity(
+            Box::new(Plane::from_normal_and_point(
+                &Vector::new(0.0, 1.0, 0.0),
+                &Point::new(0.0, 0.0, 0.0),
+            )),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::identity(),
+        );
+        let down = Ray {
+            origin: Point::new(0.0, 1.0, 0.0),
+            direction: Vector::new(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        let up = Ray {
+            origin: Point::new(0.0, 1.0, 0.0),
+            direction: Vector::new(0.0, 1.0, 0.0),
+            time: 0.0,
+        };
+        assert_relative_eq!(scene.trace(&down), Spectrum::black());
+        assert_relative_eq!(scene.trace(&up), Spectrum::black());
+
+        let sky = Spectrum::uniform(1.0);
+        scene.set_environment(Some(EnvironmentLight::constant(&sky)));
+        assert_relative_eq!(scene.trace(&up), sky);
+
+        let traces = 1000;
+        let mut total = Spectrum::black();
+        for _ in 0..traces {
+            total += scene.trace(&down);
+        }
+        assert_relative_eq!(total / traces as f32, sky, epsilon = 0.05);
+    }
+
+    #[test]
+    fn test_render_stats() {
+        let mut scene = Scene::new();
+        scene.add_light(Box::new(PointLight::new(
+            Point::new(0.0, 10.0, 0.0),
+            Spectrum::uniform(1.0),
+        )));
+        scene.add_entity(
+            Box::new(Plane::from_normal_and_point(
+                &Vector::new(0.0, 1.0, 0.0),
+                &Point::new(0.0, 0.0, 0.0),
+            )),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::identity(),
+        );
+        let down = Ray {
+            origin: Point::new(0.0, 1.0, 0.0),
+            direction: Vector::new(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+
+        // One ray finds the floor, and one more checks whether the light reaches it.
+        scene.trace(&down);
+        let stats = scene.render_stats();
+        assert_eq!((stats.rays, stats.shadow_rays), (1, 1));
+        assert_eq!(stats.intersection_tests, 2);
+
+        scene.reset_render_stats();
+        assert_eq!(scene.render_stats().rays, 0);
+    }
+
+    #[test]
+    fn test_dump() {
+        let mut scene = Scene::new();
+        scene.add_light(Box::new(PointLight::new(
+            Point::new(0.0, 10.0, 0.0),
+            Spectrum::uniform(1.0),
+        )));
+        scene.add_area_light(Box::new(
+            SphereLight::new(Point::new(0.0, 5.0, 0.0), 1.0, &Spectrum::uniform(1.0)),
+        ));
+        scene.add_entity(
+            Box::new(Plane::from_normal_and_point(
+                &Vector::new(0.0, 1.0, 0.0),
+                &Point::new(0.0, 0.0, 0.0),
+            )),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::identity(),
+        );
+        let sphere = scene.add_entity(
+            Box::new(Sphere::new_with_radius(1.0)),
+            Box::new(SpecularMaterial::new(&Spectrum::uniform(0.9))),
+            Transform::translate(0.0, 1.0, 0.0),
+        );
+        let shadowless = RayVisibility {
+            shadows: false,
+            ..RayVisibility::default()
+        };
+        scene.set_ray_visibility(sphere, shadowless);
+        scene.finalize();
+
+        let dump = scene.dump();
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines[0], "scene of 2 entities, 1 light and 1 area light");
+        assert!(lines[2].starts_with("light 0: point light at"));
+        assert!(lines[3].starts_with("area light 0: sphere light at"));
+        assert!(lines[4].starts_with("entity 0: plane of Lambertian material"));
+        assert!(lines[5].starts_with("entity 1: sphere of specular material"));
+        assert!(lines[5].ends_with("casting no shadows"));
+        assert!(!dump.contains("since the last finalize"));
+        assert!(format!("{:?}", scene).contains("finalized: true"));
     }
 }
@@ -1,100 +1,584 @@
-use math::{Point, Vector};
+use math::{AABB, PlanarAngle, Point, Vector, perpendicular_tangents};
 use scene::Spectrum;
+use std::f32::INFINITY;
+use std::fmt;
+use std::f32::consts::PI;
 
+/// Angular radius of the sun seen from the earth, half of its angular diameter of about 0.53
+/// degrees.
+pub const SUN_ANGULAR_RADIUS: PlanarAngle = PlanarAngle::Degrees(0.2665);
 
-pub trait NonAreaLight {
-    /// We use a simplified version of the BRDF in this case, so here use irradiance instead of
-    /// radiance.
+/// Light arriving at a point from a single light.
+///
+/// Non-area lights are delta distributions, so there is exactly one incident direction per point
+/// and no probability density to account for.  Weighting by the BRDF and the cosine of the angle
+/// with the surface normal is left to the integrator.
+pub struct LightSample {
+    /// Radiance arriving at the point, after any falloff with distance.
+    pub radiance: Spectrum,
+    /// Unit vector pointing from the illuminated point to the light.
+    pub direction: Vector,
+    /// Distance from the illuminated point to the light, `INFINITY` for lights at infinity.
+    pub distance: f32,
+}
+
+/// Lights are shared between rendering threads, and display as a short summary for inspecting
+/// scenes.
+pub trait NonAreaLight: Send + Sync + fmt::Display {
+    /// Determines the light arriving at a point, ignoring any occluders.
     ///
     /// # Arguments
-    /// * `position` - point to illuminate with the light
-    /// * `normal` - the surface normal being illuminated.
+    /// * `point` - point to illuminate with the light
     ///
     /// # Returns
-    /// * `Spectrum` - the irradiance measured at the surface
-    fn irradiance(&self, position: &Point, normal: &Vector) -> Spectrum;
+    /// * `LightSample` - the incident radiance, with the direction and distance to the light.
+    fn incident(&self, point: &Point) -> LightSample;
+
+    /// Determines the light arriving at a point along one of the directions toward a light with
+    /// some size, ignoring any occluders.  Averaging many samples softens the edges of shadows.
+    ///
+    /// Lights without a size give the same sample as `incident`.
+    ///
+    /// # Arguments
+    /// * `point` - point to illuminate with the light
+    /// * `u` - position in the unit square, which is mapped onto the directions toward the light
+    #[allow(unused_variables)]
+    fn sample_incident(&self, point: &Point, u: (f32, f32)) -> LightSample {
+        self.incident(point)
+    }
 
-    /// Gives the vector pointing to the light.
-    fn light_vector(&self, point: &Point) -> Vector;
+    /// Determines the light arriving at a point like `incident`, at a time while the camera's
+    /// shutter is open.
+    ///
+    /// Lights which do not move give the same sample at every time.
+    #[allow(unused_variables)]
+    fn incident_at(&self, point: &Point, time: f32) -> LightSample {
+        self.incident(point)
+    }
+
+    /// Samples the light arriving at a point like `sample_incident`, at a time while the camera's
+    /// shutter is open.
+    #[allow(unused_variables)]
+    fn sample_incident_at(&self, point: &Point, time: f32, u: (f32, f32)) -> LightSample {
+        self.sample_incident(point, u)
+    }
+
+    /// Whether light arrives from more than one direction, so must be sampled with
+    /// `sample_incident`.
+    fn has_size(&self) -> bool {
+        false
+    }
 
-    /// As the light if it lies before or after the given intersection point along the light
-    /// vector.
-    fn is_hidden_from(&self, point: &Point, first_hit_along_light_vector: Option<f32>) -> bool;
+    /// Where the light is, if it has a position.
+    fn position(&self) -> Option<Point> {
+        None
+    }
+
+    /// A box outside of which every channel of the light arriving is below a cutoff, or `None` if
+    /// the light may reach anywhere.
+    #[allow(unused_variables)]
+    fn reach(&self, cutoff: f32) -> Option<AABB> {
+        None
+    }
 }
 
 /// A light who supplies light from a specific direction.
+///
+/// Lights with an angular radius, like the sun, shine from a small cone of directions around it
+/// and cast shadows with soft edges.
+#[derive(Clone, Copy, Debug)]
 pub struct DirectionalLight {
     direction: Vector,
     radiance: Spectrum,
+    // Cosine of the angle from the direction to the edge of the light.
+    cos_max: f32,
 }
 
 impl DirectionalLight {
+    /// Creates a light shining from a single direction, which casts sharp shadows.
     pub fn new(direction: &Vector, radiance: &Spectrum) -> DirectionalLight {
+        DirectionalLight::new_with_angular_radius(direction, radiance, PlanarAngle::Radians(0.0))
+    }
+
+    /// # Arguments
+    /// * `direction` - the direction the light shines in, toward the center of the light
+    /// * `radiance` - light arriving at surfaces facing the light, from all of its directions
+    /// * `angular_radius` - angle from the center of the light to its edge, such as
+    ///   `SUN_ANGULAR_RADIUS`
+    ///
+    /// # Panics
+    /// If the direction cannot be normalized, or the angular radius is negative or 90 degrees or
+    /// more.
+    pub fn new_with_angular_radius(
+        direction: &Vector,
+        radiance: &Spectrum,
+        angular_radius: PlanarAngle,
+    ) -> DirectionalLight {
         let mut d = *direction;
         d.normalize().expect(
             "Provide a direction vector which cannot be normalized for a directional light.",
         );
+        let angular_radius = angular_radius.to_radians();
+        assert!(
+            0.0 <= angular_radius && angular_radius < PI / 2.0,
+            "Directional light angular radius must be at least 0 and less than 90 degrees."
+        );
         DirectionalLight {
             direction: d,
             radiance: *radiance,
+            cos_max: angular_radius.cos(),
         }
     }
 }
 
+impl fmt::Display for DirectionalLight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "directional light shining along {}, radiance {}",
+            self.direction,
+            self.radiance
+        )?;
+        if self.has_size() {
+            write!(f, ", angular radius {:.4} degrees", self.cos_max.acos().to_degrees())?;
+        }
+        Ok(())
+    }
+}
+
 impl NonAreaLight for DirectionalLight {
+    /// The light is infinitely far away, so the first object hit will shadow all further objects.
     #[allow(unused_variables)]
-    fn irradiance(&self, position: &Point, normal: &Vector) -> Spectrum {
-        (-self.direction).dot(&normal).max(0.0) * self.radiance
+    fn incident(&self, point: &Point) -> LightSample {
+        LightSample {
+            radiance: self.radiance,
+            direction: -self.direction,
+            distance: INFINITY,
+        }
     }
 
+    /// Samples the cone of directions toward the light uniformly.  Each direction carries all of
+    /// the radiance, so the average is as bright as a light with no size.
     #[allow(unused_variables)]
-    fn light_vector(&self, point: &Point) -> Vector {
-        -self.direction
+    fn sample_incident(&self, point: &Point, u: (f32, f32)) -> LightSample {
+        let axis = -self.direction;
+        let cos_theta = 1.0 - u.0 * (1.0 - self.cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * u.1;
+        let (tangent, bitangent) = perpendicular_tangents(&axis);
+        LightSample {
+            radiance: self.radiance,
+            direction: (sin_theta * phi.cos()) * tangent + (sin_theta * phi.sin()) * bitangent +
+                cos_theta * axis,
+            distance: INFINITY,
+        }
     }
 
-    /// The first object hit will shadow all further objects.
-    #[allow(unused_variables)]
-    fn is_hidden_from(&self, point: &Point, first_hit_along_light_vector: Option<f32>) -> bool {
-        first_hit_along_light_vector.is_some()
+    fn has_size(&self) -> bool {
+        self.cos_max < 1.0
+    }
+}
+
+/// How the light of a point light falls off with distance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Attenuation {
+    /// Equally bright at every distance.
+    None,
+    /// Falls off in proportion to distance.
+    Linear,
+    /// Falls off with the square of distance, as light from a physical point does.
+    Quadratic,
+    /// Falls off with the square of distance, and fades smoothly to nothing at a radius, so the
+    /// light reaches only the region within it.
+    SmoothCutoff(f32),
+}
+
+impl Default for Attenuation {
+    fn default() -> Attenuation {
+        Attenuation::Quadratic
+    }
+}
+
+impl fmt::Display for Attenuation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Attenuation::None => write!(f, "no falloff"),
+            Attenuation::Linear => write!(f, "linear falloff"),
+            Attenuation::Quadratic => write!(f, "inverse square falloff"),
+            Attenuation::SmoothCutoff(radius) => {
+                write!(f, "inverse square falloff, cut off at {}", radius)
+            }
+        }
+    }
+}
+
+impl Attenuation {
+    /// Fraction of a light's intensity arriving at a distance from it.
+    pub fn factor(&self, distance: f32) -> f32 {
+        match *self {
+            Attenuation::None => 1.0,
+            Attenuation::Linear => 1.0 / distance,
+            Attenuation::Quadratic => 1.0 / (distance * distance),
+            Attenuation::SmoothCutoff(radius) => {
+                let ratio = distance / radius;
+                let window = (1.0 - ratio * ratio * ratio * ratio).max(0.0);
+                window * window / (distance * distance)
+            }
+        }
+    }
+
+    /// Distance beyond which light of some intensity falls below a cutoff, or `None` if it never
+    /// does.
+    pub fn reach(&self, intensity: f32, cutoff: f32) -> Option<f32> {
+        match *self {
+            Attenuation::None => None,
+            Attenuation::Linear => Some(intensity / cutoff),
+            Attenuation::Quadratic => Some((intensity / cutoff).sqrt()),
+            Attenuation::SmoothCutoff(radius) => Some((intensity / cutoff).sqrt().min(radius)),
+        }
     }
 }
 
+#[derive(Clone, Copy, Debug)]
 pub struct PointLight {
     position: Point,
     intensity: Spectrum,
+    attenuation: Attenuation,
 }
 
 impl PointLight {
+    /// A light falling off with the square of distance.
     pub fn new(position: Point, intensity: Spectrum) -> PointLight {
+        PointLight::new_with_attenuation(position, intensity, Attenuation::default())
+    }
+
+    /// # Arguments
+    /// * `position` - where the light is
+    /// * `intensity` - radiance at a unit distance
+    /// * `attenuation` - how the light falls off with distance
+    ///
+    /// # Panics
+    /// If the attenuation has a cutoff radius which is not positive.
+    pub fn new_with_attenuation(
+        position: Point,
+        intensity: Spectrum,
+        attenuation: Attenuation,
+    ) -> PointLight {
+        if let Attenuation::SmoothCutoff(radius) = attenuation {
+            assert!(radius > 0.0, "Point light cutoff radius must be positive.");
+        }
         PointLight {
-            position,
-            intensity,
+            position: position,
+            intensity: intensity,
+            attenuation: attenuation,
         }
     }
+
+    pub fn attenuation(&self) -> Attenuation {
+        self.attenuation
+    }
+}
+
+impl fmt::Display for PointLight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "point light at {}, intensity {}, {}",
+            self.position,
+            self.intensity,
+            self.attenuation
+        )
+    }
 }
 
 impl NonAreaLight for PointLight {
-    fn irradiance(&self, position: &Point, normal: &Vector) -> Spectrum {
-        let distance = position.distance_to(self.position);
-        self.light_vector(position).dot(normal).max(0.0) * self.intensity /
-            (distance * distance).min(1.0)
+    fn incident(&self, point: &Point) -> LightSample {
+        let distance = self.position.distance_to(*point);
+        let mut direction = self.position - *point;
+        direction.normalize().expect("Cannot normalize light vector.");
+        LightSample {
+            radiance: self.attenuation.factor(distance) * self.intensity,
+            direction: direction,
+            distance: distance,
+        }
     }
 
-    #[allow(unused_variables)]
-    fn light_vector(&self, point: &Point) -> Vector {
-        let mut light_vector = self.position - *point;
-        light_vector.normalize().expect(
-            "Cannot normalize light vector.",
+    fn position(&self) -> Option<Point> {
+        Some(self.position)
+    }
+
+    fn reach(&self, cutoff: f32) -> Option<AABB> {
+        attenuated_reach(&self.position, &self.intensity, &self.attenuation, cutoff)
+    }
+}
+
+/// A point light shining in a cone, such as a flashlight or stage light.
+///
+/// Light is at full strength inside the inner cone, and fades out toward the outer cone.
+#[derive(Clone, Copy, Debug)]
+pub struct SpotLight {
+    position: Point,
+    direction: Vector,
+    intensity: Spectrum,
+    cos_inner: f32,
+    cos_outer: f32,
+    falloff: f32,
+}
+
+impl SpotLight {
+    /// # Arguments
+    /// * `position` - apex of the cone
+    /// * `direction` - the direction the light shines in, along the axis of the cone
+    /// * `intensity` - radiance at a unit distance inside the inner cone
+    /// * `inner` - angle from the axis within which the light is at full strength
+    /// * `outer` - angle from the axis outside of which there is no light
+    /// * `falloff` - exponent shaping the fade between the cones, 1 fades linearly with the
+    ///   cosine of the angle, and larger values fade faster
+    ///
+    /// # Panics
+    /// If the direction cannot be normalized, the inner angle is larger than the outer one, the
+    /// outer angle is more than 180 degrees or the falloff is not positive.
+    pub fn new(
+        position: Point,
+        direction: &Vector,
+        intensity: Spectrum,
+        inner: PlanarAngle,
+        outer: PlanarAngle,
+        falloff: f32,
+    ) -> SpotLight {
+        let mut d = *direction;
+        d.normalize().expect(
+            "Provide a direction vector which cannot be normalized for a spot light.",
         );
-        light_vector
+        let (inner, outer) = (inner.to_radians(), outer.to_radians());
+        assert!(
+            0.0 <= inner && inner <= outer && outer <= ::std::f32::consts::PI,
+            "Spot light cone angles must satisfy 0 <= inner <= outer <= 180 degrees."
+        );
+        assert!(falloff > 0.0, "Spot light falloff must be positive.");
+        SpotLight {
+            position: position,
+            direction: d,
+            intensity: intensity,
+            cos_inner: inner.cos(),
+            cos_outer: outer.cos(),
+            falloff: falloff,
+        }
     }
 
-    #[allow(unused_variables)]
-    fn is_hidden_from(&self, point: &Point, first_hit_along_light_vector: Option<f32>) -> bool {
+    /// Fraction of the intensity shining along a unit vector leaving the light.
+    fn cone(&self, outgoing: &Vector) -> f32 {
+        let cos_theta = outgoing.dot(&self.direction);
+        if cos_theta >= self.cos_inner {
+            1.0
+        } else if cos_theta <= self.cos_outer {
+            0.0
+        } else {
+            ((cos_theta - self.cos_outer) / (self.cos_inner - self.cos_outer)).powf(self.falloff)
+        }
+    }
+}
+
+/// A box around a light, outside of which its light is below a cutoff, or `None` if its light
+/// may reach anywhere.
+fn attenuated_reach(
+    position: &Point,
+    intensity: &Spectrum,
+    attenuation: &Attenuation,
+    cutoff: f32,
+) -> Option<AABB> {
+    let brightest = intensity.max_channel();
+    attenuation.reach(brightest, cutoff).map(|radius| {
+        let offset = Vector::new(radius, radius, radius);
+        AABB::new(*position + -offset, *position + offset)
+    })
+}
+
+impl fmt::Display for SpotLight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "spot light at {} shining along {}, intensity {}, cone {:.1} to {:.1} degrees",
+            self.position,
+            self.direction,
+            self.intensity,
+            self.cos_inner.acos().to_degrees(),
+            self.cos_outer.acos().to_degrees()
+        )
+    }
+}
+
+impl NonAreaLight for SpotLight {
+    fn incident(&self, point: &Point) -> LightSample {
         let distance = self.position.distance_to(*point);
-        match first_hit_along_light_vector {
-            Some(hit) => hit < distance,
-            None => false,
+        let mut direction = self.position - *point;
+        direction.normalize().expect("Cannot normalize light vector.");
+        LightSample {
+            radiance: self.cone(&-direction) * self.intensity / (distance * distance),
+            direction: direction,
+            distance: distance,
         }
     }
+
+    fn position(&self) -> Option<Point> {
+        Some(self.position)
+    }
+
+    fn reach(&self, cutoff: f32) -> Option<AABB> {
+        attenuated_reach(&self.position, &self.intensity, &Attenuation::Quadratic, cutoff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Attenuation, DirectionalLight, NonAreaLight, PointLight, SUN_ANGULAR_RADIUS,
+                SpotLight};
+    use math::{PlanarAngle, Point, Vector};
+    use scene::Spectrum;
+
+    #[test]
+    fn test_directional_light_incident() {
+        // The direction need not be normalized, and is reversed to point toward the light.
+        let light = DirectionalLight::new(&Vector::new(0.0, -2.0, 0.0), &Spectrum::uniform(3.0));
+        for point in [Point::new(0.0, 0.0, 0.0), Point::new(100.0, -50.0, 7.0)].iter() {
+            let sample = light.incident(point);
+            assert_relative_eq!(sample.radiance, Spectrum::uniform(3.0));
+            assert_relative_eq!(sample.direction, Vector::new(0.0, 1.0, 0.0));
+            assert!(sample.distance.is_infinite());
+        }
+
+        // Lights without a size give the same sample wherever in the square is asked for.
+        let sample = light.sample_incident(&Point::new(0.0, 0.0, 0.0), (0.7, 0.2));
+        assert_relative_eq!(sample.direction, Vector::new(0.0, 1.0, 0.0));
+        assert_relative_eq!(sample.radiance, Spectrum::uniform(3.0));
+    }
+
+    #[test]
+    fn test_point_light_incident() {
+        let light = PointLight::new(Point::new(3.0, 4.0, 0.0), Spectrum::new(25.0, 50.0, 0.0));
+        let sample = light.incident(&Point::new(0.0, 0.0, 0.0));
+        assert_relative_eq!(sample.direction, Vector::new(0.6, 0.8, 0.0));
+        assert_relative_eq!(sample.distance, 5.0);
+        // Radiance arriving, with no cosine toward any surface applied.
+        assert_relative_eq!(sample.radiance, Spectrum::new(1.0, 2.0, 0.0));
+        assert_eq!(light.position(), Some(Point::new(3.0, 4.0, 0.0)));
+    }
+
+    #[test]
+    fn test_directional_light_size() {
+        let down = Vector::new(0.0, -1.0, 0.0);
+        let white = Spectrum::uniform(1.0);
+        let origin = Point::new(0.0, 0.0, 0.0);
+        assert!(!DirectionalLight::new(&down, &white).has_size());
+
+        let sun = DirectionalLight::new_with_angular_radius(&down, &white, SUN_ANGULAR_RADIUS);
+        assert!(sun.has_size());
+        assert_relative_eq!(sun.incident(&origin).direction, Vector::new(0.0, 1.0, 0.0));
+
+        // Samples stay within the disk of the sun, reaching its edge.
+        let cos_max = SUN_ANGULAR_RADIUS.to_radians().cos();
+        for &u in [(0.0, 0.0), (0.5, 0.5), (1.0, 0.3), (0.9, 0.8)].iter() {
+            let sample = sun.sample_incident(&origin, u);
+            assert_relative_eq!(sample.direction.length(), 1.0, epsilon = 1e-5);
+            assert!(sample.direction.y >= cos_max - 1e-6);
+            assert_relative_eq!(sample.radiance, white);
+        }
+        let edge = sun.sample_incident(&origin, (1.0, 0.3));
+        assert_relative_eq!(edge.direction.y, cos_max, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_spot_light_cone() {
+        let spot = SpotLight::new(
+            Point::new(0.0, 10.0, 0.0),
+            &Vector::new(0.0, -1.0, 0.0),
+            Spectrum::uniform(100.0),
+            PlanarAngle::Degrees(20.0),
+            PlanarAngle::Degrees(40.0),
+            1.0,
+        );
+
+        // Straight below, with inverse square falloff.
+        let below = spot.incident(&Point::new(0.0, 0.0, 0.0));
+        assert_relative_eq!(below.radiance, Spectrum::uniform(1.0));
+        assert_relative_eq!(below.direction, Vector::new(0.0, 1.0, 0.0));
+        assert_relative_eq!(below.distance, 10.0);
+
+        // At 30 degrees, between the cones.
+        let offset = 10.0 * PlanarAngle::Degrees(30.0).to_radians().tan();
+        let edge = spot.incident(&Point::new(offset, 0.0, 0.0));
+        assert!(edge.radiance.r > 0.0 && edge.radiance.r < 0.75);
+
+        // At 45 degrees, outside the outer cone.
+        let outside = spot.incident(&Point::new(10.0, 0.0, 0.0));
+        assert_relative_eq!(outside.radiance, Spectrum::black());
+
+        // Behind the light.
+        let behind = spot.incident(&Point::new(0.0, 20.0, 0.0));
+        assert_relative_eq!(behind.radiance, Spectrum::black());
+    }
+
+    #[test]
+    fn test_spot_light_falloff() {
+        let spot = |falloff: f32| {
+            SpotLight::new(
+                Point::new(0.0, 0.0, 0.0),
+                &Vector::new(0.0, 0.0, 1.0),
+                Spectrum::uniform(1.0),
+                PlanarAngle::Degrees(10.0),
+                PlanarAngle::Degrees(50.0),
+                falloff,
+            )
+        };
+        let point = Point::new(1.0, 0.0, 2.0);
+        assert!(spot(4.0).incident(&point).radiance.r < spot(1.0).incident(&point).radiance.r);
+    }
+
+    #[test]
+    fn test_point_light_falloff() {
+        let white = Spectrum::uniform(1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, 0.0), 4.0 * white);
+        // Nearby points are brighter than the intensity, rather than limited to it.
+        assert_relative_eq!(light.incident(&Point::new(0.0, 0.5, 0.0)).radiance, 16.0 * white);
+        assert_relative_eq!(light.incident(&Point::new(0.0, 2.0, 0.0)).radiance, white);
+        assert_relative_eq!(light.incident(&Point::new(0.0, 0.0, 4.0)).radiance, 0.25 * white);
+    }
+
+    #[test]
+    fn test_attenuation() {
+        assert_relative_eq!(Attenuation::None.factor(10.0), 1.0);
+        assert_relative_eq!(Attenuation::Linear.factor(10.0), 0.1);
+        assert_relative_eq!(Attenuation::Quadratic.factor(10.0), 0.01);
+
+        // Close to the light, the cutoff barely changes inverse square falloff, and it fades to
+        // nothing at the radius.
+        let cutoff = Attenuation::SmoothCutoff(10.0);
+        assert_relative_eq!(cutoff.factor(1.0), 1.0, epsilon = 1e-3);
+        assert!(cutoff.factor(5.0) < Attenuation::Quadratic.factor(5.0));
+        assert!(cutoff.factor(9.9) > 0.0);
+        assert_relative_eq!(cutoff.factor(10.0), 0.0);
+        assert_relative_eq!(cutoff.factor(20.0), 0.0);
+    }
+
+    #[test]
+    fn test_point_light_reach() {
+        let light = |attenuation: Attenuation| {
+            PointLight::new_with_attenuation(
+                Point::new(1.0, 0.0, 0.0),
+                Spectrum::new(1.0, 4.0, 2.0),
+                attenuation,
+            )
+        };
+        assert!(light(Attenuation::None).reach(0.01).is_none());
+        let reach = |attenuation: Attenuation| light(attenuation).reach(0.01).unwrap().upper.y;
+        assert_relative_eq!(reach(Attenuation::Linear), 400.0);
+        assert_relative_eq!(reach(Attenuation::Quadratic), 20.0);
+        assert_relative_eq!(reach(Attenuation::SmoothCutoff(5.0)), 5.0);
+        assert_relative_eq!(reach(Attenuation::SmoothCutoff(50.0)), 20.0);
+
+        // Light arriving at the edge of the reach is at the cutoff.
+        let quadratic = light(Attenuation::Quadratic);
+        let edge = quadratic.incident(&Point::new(1.0, 20.0, 0.0));
+        assert_relative_eq!(edge.radiance.g, 0.01, epsilon = 1e-6);
+    }
 }
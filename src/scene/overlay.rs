@@ -0,0 +1,263 @@
+//! Debugging aids drawn over a render, showing where things are in the world.
+use math::{AABB, Point, Vector};
+use scene::{Camera, Scene};
+
+/// Color of the world X axis.
+pub const X_AXIS_COLOR: [u8; 3] = [255, 0, 0];
+/// Color of the world Y axis.
+pub const Y_AXIS_COLOR: [u8; 3] = [0, 255, 0];
+/// Color of the world Z axis.
+pub const Z_AXIS_COLOR: [u8; 3] = [0, 0, 255];
+const GRID_COLOR: [u8; 3] = [96, 96, 96];
+const LIGHT_COLOR: [u8; 3] = [255, 255, 0];
+const BOUNDS_COLOR: [u8; 3] = [255, 0, 255];
+
+/// Closest distance in front of the camera lines are drawn at.
+const MIN_DEPTH: f32 = 1e-3;
+
+/// Lines in world space, drawn on top of a render.
+pub struct Overlay {
+    lines: Vec<(Point, Point, [u8; 3])>,
+}
+
+impl Overlay {
+    pub fn new() -> Overlay {
+        Overlay { lines: Vec::new() }
+    }
+
+    /// Creates an overlay showing the world axes, a grid on the ground (Y=0) plane, light
+    /// positions and the bounds of each entity.
+    pub fn for_scene(scene: &Scene) -> Overlay {
+        let mut overlay = Overlay::new();
+
+        // Size everything relative to the contents of the scene.
+        let size = match scene.bounds() {
+            Some(b) => {
+                let corner_distance = Vector::from(b.lower)
+                    .length()
+                    .max(Vector::from(b.upper).length());
+                corner_distance.max(1.0)
+            }
+            None => 10.0,
+        };
+        let spacing = 10.0_f32.powf(size.log10().floor() - 1.0).max(0.1);
+
+        overlay.add_grid(size, spacing);
+        overlay.add_axes(size);
+        for position in scene.light_positions() {
+            overlay.add_marker(&position, 0.05 * size, LIGHT_COLOR);
+        }
+        for bounds in scene.entity_bounds().iter().filter(|b| b.is_bounded()) {
+            overlay.add_box(bounds, BOUNDS_COLOR);
+        }
+        overlay
+    }
+
+    pub fn add_line(&mut self, a: &Point, b: &Point, color: [u8; 3]) {
+        self.lines.push((*a, *b, color));
+    }
+
+    /// Adds the positive world axes from the origin.
+    pub fn add_axes(&mut self, length: f32) {
+        let origin = Point::new(0.0, 0.0, 0.0);
+        self.add_line(&origin, &Point::new(length, 0.0, 0.0), X_AXIS_COLOR);
+        self.add_line(&origin, &Point::new(0.0, length, 0.0), Y_AXIS_COLOR);
+        self.add_line(&origin, &Point::new(0.0, 0.0, length), Z_AXIS_COLOR);
+    }
+
+    /// Adds a square grid on the Y=0 plane, centered on the origin.
+    pub fn add_grid(&mut self, half_extent: f32, spacing: f32) {
+        assert!(spacing > 0.0);
+        let lines_per_side = (half_extent / spacing).floor() as i32;
+        let edge = lines_per_side as f32 * spacing;
+        for i in -lines_per_side..(lines_per_side + 1) {
+            let offset = i as f32 * spacing;
+            self.add_line(
+                &Point::new(offset, 0.0, -edge),
+                &Point::new(offset, 0.0, edge),
+                GRID_COLOR,
+            );
+            self.add_line(
+                &Point::new(-edge, 0.0, offset),
+                &Point::new(edge, 0.0, offset),
+                GRID_COLOR,
+            );
+        }
+    }
+
+    /// Adds three short crossing lines centered on a point.
+    pub fn add_marker(&mut self, center: &Point, size: f32, color: [u8; 3]) {
+        let h = size / 2.0;
+        for offset in [
+            Vector::new(h, 0.0, 0.0),
+            Vector::new(0.0, h, 0.0),
+            Vector::new(0.0, 0.0, h),
+        ].iter()
+        {
+            self.add_line(&(*center + -*offset), &(*center + *offset), color);
+        }
+    }
+
+    /// Adds the twelve edges of a box.
+    pub fn add_box(&mut self, bounds: &AABB, color: [u8; 3]) {
+        let (l, u) = (bounds.lower, bounds.upper);
+        let corner = |x: bool, y: bool, z: bool| {
+            Point::new(
+                if x { u.x } else { l.x },
+                if y { u.y } else { l.y },
+                if z { u.z } else { l.z },
+            )
+        };
+        for &a in [false, true].iter() {
+            for &b in [false, true].iter() {
+                self.add_line(&corner(false, a, b), &corner(true, a, b), color);
+                self.add_line(&corner(a, false, b), &corner(a, true, b), color);
+                self.add_line(&corner(a, b, false), &corner(a, b, true), color);
+            }
+        }
+    }
+
+    /// Draws every line as seen by a camera.
+    ///
+    /// Panoramic cameras bend straight lines into curves, so nothing is drawn for them.
+    ///
+    /// # Arguments
+    /// * `camera` - the camera the render was made with
+    /// * `plot` - colors a single pixel of the raster
+    pub fn draw<F: FnMut(u32, u32, [u8; 3])>(&self, camera: &Camera, mut plot: F) {
+        if camera.panorama().is_some() {
+            return;
+        }
+        let world_to_camera = camera.world_to_camera();
+        let camera_to_raster = camera.camera_to_raster();
+        let width = camera.raster_width() as f32;
+        let height = camera.raster_height() as f32;
+
+        for &(a, b, color) in self.lines.iter() {
+            let (a, b) = match clip_behind_camera(world_to_camera * a, world_to_camera * b) {
+                Some(segment) => segment,
+                None => continue,
+            };
+            let a = camera_to_raster * a;
+            let b = camera_to_raster * b;
+            if let Some((start, end)) = clip_to_raster(a, b, width, height) {
+                rasterize(start, end, color, &mut plot);
+            }
+        }
+    }
+}
+
+/// Clips a camera space segment to the part in front of the camera.
+fn clip_behind_camera(a: Point, b: Point) -> Option<(Point, Point)> {
+    match (a.z >= MIN_DEPTH, b.z >= MIN_DEPTH) {
+        (true, true) => Some((a, b)),
+        (false, false) => None,
+        (a_in_front, _) => {
+            let t = (MIN_DEPTH - a.z) / (b.z - a.z);
+            let crossing = a + t * (b - a);
+            if a_in_front {
+                Some((a, crossing))
+            } else {
+                Some((crossing, b))
+            }
+        }
+    }
+}
+
+/// Liang-Barsky clipping of a raster space segment to the raster.
+fn clip_to_raster(a: Point, b: Point, width: f32, height: f32) -> Option<(Point, Point)> {
+    let d = b - a;
+    let mut t0 = 0.0_f32;
+    let mut t1 = 1.0_f32;
+    let edges = [
+        (-d.x, a.x),
+        (d.x, width - a.x),
+        (-d.y, a.y),
+        (d.y, height - a.y),
+    ];
+    for &(p, q) in edges.iter() {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+    Some((a + t0 * d, a + t1 * d))
+}
+
+/// Plots pixels along a segment lying within the raster.
+fn rasterize<F: FnMut(u32, u32, [u8; 3])>(a: Point, b: Point, color: [u8; 3], plot: &mut F) {
+    let d = b - a;
+    let steps = d.x.abs().max(d.y.abs()).ceil().max(1.0) as u32;
+    for i in 0..(steps + 1) {
+        let p = a + (i as f32 / steps as f32) * d;
+        plot(p.x as u32, p.y as u32, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Overlay, X_AXIS_COLOR};
+    use math::{PlanarAngle, Point};
+    use scene::{Camera, Film, Perspective};
+
+    fn draw(overlay: &Overlay) -> Vec<(u32, u32)> {
+        let film = Film::new(40, 20);
+        let camera = Camera::new(&film, &Perspective::new(1.0, 100.0, PlanarAngle::Degrees(90.0)));
+        let mut plotted = Vec::new();
+        overlay.draw(&camera, |x, y, _| {
+            assert!(x <= 40 && y <= 20);
+            plotted.push((x, y));
+        });
+        plotted
+    }
+
+    #[test]
+    fn test_draw_line_in_view() {
+        let mut overlay = Overlay::new();
+        overlay.add_line(
+            &Point::new(-1000.0, 0.0, 10.0),
+            &Point::new(1000.0, 0.0, 10.0),
+            X_AXIS_COLOR,
+        );
+        let plotted = draw(&overlay);
+
+        // Crosses the whole raster horizontally through the middle.
+        assert!(plotted.len() >= 40);
+        assert!(plotted.iter().all(|&(_, y)| y == 10 || y == 9));
+    }
+
+    #[test]
+    fn test_skip_line_behind_camera() {
+        let mut overlay = Overlay::new();
+        overlay.add_line(
+            &Point::new(-1.0, 0.0, -10.0),
+            &Point::new(1.0, 0.0, -10.0),
+            X_AXIS_COLOR,
+        );
+        assert!(draw(&overlay).is_empty());
+    }
+
+    #[test]
+    fn test_clip_line_through_camera() {
+        let mut overlay = Overlay::new();
+        overlay.add_line(
+            &Point::new(0.0, -1.0, -10.0),
+            &Point::new(0.0, -1.0, 10.0),
+            X_AXIS_COLOR,
+        );
+        let plotted = draw(&overlay);
+        assert!(!plotted.is_empty());
+        assert!(plotted.iter().all(|&(_, y)| y >= 10));
+    }
+}
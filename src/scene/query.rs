@@ -0,0 +1,142 @@
+//! Casting rays into a scene without shading, for tools such as mouse picking, measuring
+//! distances and line of sight checks.
+//!
+//! Queries only test geometry, so they are much cheaper than tracing, and work on scenes without
+//! lights.
+use math::{Point, Ray, Vector};
+use rayon::prelude::*;
+use scene::{EntityHandle, Scene};
+
+/// Where a ray first met an entity.
+#[derive(Clone, Copy, Debug)]
+pub struct RayHit {
+    pub entity: EntityHandle,
+    /// Distance from the ray origin to the surface, in world units.
+    pub distance: f32,
+    pub point: Point,
+    /// Unit vector out of the surface.
+    pub normal: Vector,
+    /// Texture coordinates of the surface.
+    pub uv: (f32, f32),
+}
+
+impl Scene {
+    /// Finds the first entity along a ray.
+    ///
+    /// The direction of the ray does not need to be a unit vector.
+    ///
+    /// # Returns
+    /// `None` if the ray hits nothing, or has no direction.
+    pub fn cast_ray(&self, ray: &Ray) -> Option<RayHit> {
+        let mut unit_ray = *ray;
+        unit_ray.normalize().ok()?;
+        self.intersect(&unit_ray).map(|si| {
+            let mut normal = si.intersection.normal;
+            // Scaled entities have normals of other lengths.
+            let _ = normal.normalize();
+            RayHit {
+                entity: EntityHandle(si.entity_index),
+                distance: ray.origin.distance_to(si.intersection.point),
+                point: si.intersection.point,
+                normal: normal,
+                uv: si.intersection.uv,
+            }
+        })
+    }
+
+    /// Casts many rays in parallel.
+    ///
+    /// # Returns
+    /// The first hit of each ray, in the same order as the rays.
+    pub fn cast_rays(&self, rays: &[Ray]) -> Vec<Option<RayHit>> {
+        rays.par_iter().map(|r| self.cast_ray(r)).collect()
+    }
+
+    /// Whether nothing lies on the straight line between two points.  Moving entities are where
+    /// they are at time 0.
+    pub fn line_of_sight(&self, from: Point, to: Point) -> bool {
+        let distance = from.distance_to(to);
+        let ray = Ray {
+            origin: from,
+            direction: to - from,
+            time: 0.0,
+        };
+        match self.cast_ray(&ray) {
+            Some(hit) => hit.distance >= distance,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use math::{Point, Ray, Sphere, Transform, Vector};
+    use scene::{EntityHandle, Scene, Spectrum};
+    use scene::material::LambertianMaterial;
+
+    /// Spheres to the left and right of the Z axis, and their handles.
+    fn two_spheres() -> (Scene, Vec<EntityHandle>) {
+        let mut scene = Scene::new();
+        let handles = [-2.0, 2.0]
+            .iter()
+            .map(|&x| {
+                scene.add_entity(
+                    Box::new(Sphere::new_with_radius(1.0)),
+                    Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+                    Transform::translate(x, 0.0, 5.0),
+                )
+            })
+            .collect();
+        scene.finalize();
+        (scene, handles)
+    }
+
+    #[test]
+    fn test_cast_ray() {
+        let (scene, handles) = two_spheres();
+        let ray = Ray {
+            origin: Point::new(2.0, 0.0, 0.0),
+            direction: Vector::new(0.0, 0.0, 10.0),
+            time: 0.0,
+        };
+        let hit = scene.cast_ray(&ray).unwrap();
+        assert_eq!(hit.entity, handles[1]);
+        assert_relative_eq!(hit.distance, 4.0, epsilon = 1e-5);
+        assert_relative_eq!(hit.normal, Vector::new(0.0, 0.0, -1.0), epsilon = 1e-5);
+
+        let no_direction = Ray {
+            origin: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(0.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        assert!(scene.cast_ray(&no_direction).is_none());
+    }
+
+    #[test]
+    fn test_cast_rays() {
+        let (scene, handles) = two_spheres();
+        let rays: Vec<Ray> = [-2.0, 0.0, 2.0]
+            .iter()
+            .map(|&x| {
+                Ray {
+                    origin: Point::new(x, 0.0, 0.0),
+                    direction: Vector::new(0.0, 0.0, 1.0),
+                    time: 0.0,
+                }
+            })
+            .collect();
+        let hits = scene.cast_rays(&rays);
+        assert_eq!(hits[0].unwrap().entity, handles[0]);
+        assert!(hits[1].is_none());
+        assert_eq!(hits[2].unwrap().entity, handles[1]);
+    }
+
+    #[test]
+    fn test_line_of_sight() {
+        let (scene, _) = two_spheres();
+        let eye = Point::new(-2.0, 0.0, 0.0);
+        assert!(scene.line_of_sight(eye, Point::new(-2.0, 0.0, 3.0)));
+        assert!(!scene.line_of_sight(eye, Point::new(-2.0, 0.0, 10.0)));
+        assert!(scene.line_of_sight(eye, Point::new(-2.0, 5.0, 10.0)));
+    }
+}
@@ -0,0 +1,334 @@
+//! Choosing where within a pixel to cast rays.
+use rand::{Rng, SeedableRng, XorShiftRng};
+use std::f32::consts::PI;
+
+/// Chooses positions within a pixel to sample.
+///
+/// Averaging several samples per pixel reduces aliasing along edges.
+pub trait Sampler: Send + Sync {
+    /// Number of samples taken in each pixel.
+    fn samples_per_pixel(&self) -> u32;
+
+    /// Generates the positions to sample within a single pixel.
+    ///
+    /// # Arguments
+    /// * `rng` - source of randomness for samplers which need it
+    ///
+    /// # Returns
+    /// `samples_per_pixel()` offsets from the top left corner of the pixel, each in [0, 1).
+    fn pixel_offsets(&self, rng: &mut Rng) -> Vec<(f32, f32)>;
+}
+
+/// Random numbers for sampling one pixel, the same for every render with the same seed.
+///
+/// Pixels are independent of the order they are rendered in, and the thread rendering them.
+pub fn pixel_rng(seed: u32, x: u32, y: u32) -> XorShiftRng {
+    // Nearby pixels have similar coordinates, so scramble them to avoid correlated sequences.
+    XorShiftRng::from_seed([
+        scramble(seed),
+        scramble(x ^ scramble(seed.wrapping_add(1))),
+        scramble(y ^ scramble(seed.wrapping_add(2))),
+        // XorShift never leaves a state of all zeros.
+        scramble(x.wrapping_add(y.rotate_left(16))) | 1,
+    ])
+}
+
+/// The finalizer of MurmurHash3, which changes about half of the output bits for each input bit.
+fn scramble(mut h: u32) -> u32 {
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+    h
+}
+
+/// Maps a position in the unit square onto the unit disk, such as to choose a point on a lens.
+///
+/// Uses the concentric mapping of Shirley and Chiu, which keeps evenly spread samples of the square
+/// evenly spread on the disk.  The center of the square maps to the center of the disk.
+pub fn sample_disk(u: f32, v: f32) -> (f32, f32) {
+    let (a, b) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return (0.0, 0.0);
+    }
+    let (radius, theta) = if a.abs() > b.abs() {
+        (a, (PI / 4.0) * (b / a))
+    } else {
+        (b, PI / 2.0 - (PI / 4.0) * (a / b))
+    };
+    (radius * theta.cos(), radius * theta.sin())
+}
+
+/// Dimensions of the grid of cells used by the uniform and stratified samplers.
+///
+/// If the sample count is not a square, the last row is only partially filled.
+fn grid_size(samples: u32) -> (u32, u32) {
+    let columns = (samples as f32).sqrt().ceil() as u32;
+    let rows = (samples + columns - 1) / columns;
+    (columns, rows)
+}
+
+/// Cell `index` in a grid, and its size.
+fn grid_cell(index: u32, samples: u32) -> (f32, f32, f32, f32) {
+    let (columns, rows) = grid_size(samples);
+    let (width, height) = (1.0 / columns as f32, 1.0 / rows as f32);
+    (
+        (index % columns) as f32 * width,
+        (index / columns) as f32 * height,
+        width,
+        height,
+    )
+}
+
+/// Samples the centers of a regular grid of cells covering the pixel.
+///
+/// With a single sample, this is the center of the pixel.
+pub struct UniformSampler {
+    samples: u32,
+}
+
+impl UniformSampler {
+    pub fn new(samples: u32) -> UniformSampler {
+        assert!(samples > 0, "Cannot sample a pixel zero times.");
+        UniformSampler { samples: samples }
+    }
+}
+
+impl Sampler for UniformSampler {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples
+    }
+
+    #[allow(unused_variables)]
+    fn pixel_offsets(&self, rng: &mut Rng) -> Vec<(f32, f32)> {
+        (0..self.samples)
+            .map(|i| {
+                let (x, y, width, height) = grid_cell(i, self.samples);
+                (x + 0.5 * width, y + 0.5 * height)
+            })
+            .collect()
+    }
+}
+
+/// Samples random positions anywhere in the pixel.
+pub struct JitteredSampler {
+    samples: u32,
+}
+
+impl JitteredSampler {
+    pub fn new(samples: u32) -> JitteredSampler {
+        assert!(samples > 0, "Cannot sample a pixel zero times.");
+        JitteredSampler { samples: samples }
+    }
+}
+
+impl Sampler for JitteredSampler {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples
+    }
+
+    fn pixel_offsets(&self, rng: &mut Rng) -> Vec<(f32, f32)> {
+        (0..self.samples)
+            .map(|_| (rng.next_f32(), rng.next_f32()))
+            .collect()
+    }
+}
+
+/// Samples a random position in each cell of a regular grid covering the pixel.
+///
+/// This avoids the clumping of purely random samples while breaking up the regular patterns of a
+/// uniform grid.
+pub struct StratifiedSampler {
+    samples: u32,
+}
+
+impl StratifiedSampler {
+    pub fn new(samples: u32) -> StratifiedSampler {
+        assert!(samples > 0, "Cannot sample a pixel zero times.");
+        StratifiedSampler { samples: samples }
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples
+    }
+
+    fn pixel_offsets(&self, rng: &mut Rng) -> Vec<(f32, f32)> {
+        (0..self.samples)
+            .map(|i| {
+                let (x, y, width, height) = grid_cell(i, self.samples);
+                (x + rng.next_f32() * width, y + rng.next_f32() * height)
+            })
+            .collect()
+    }
+}
+
+/// Samples the points of a Halton sequence, shifted by a random offset in each pixel.
+///
+/// The Halton sequence covers the pixel more evenly than random samples at any sample count.
+/// Using the same points in every pixel repeats any gaps across the image as visible patterns at
+/// low sample counts, so each pixel shifts all of its points by the same random offset, wrapping
+/// around the edges of the pixel (a Cranley-Patterson rotation).  This keeps their even spacing.
+pub struct HaltonSampler {
+    samples: u32,
+}
+
+impl HaltonSampler {
+    pub fn new(samples: u32) -> HaltonSampler {
+        assert!(samples > 0, "Cannot sample a pixel zero times.");
+        HaltonSampler { samples: samples }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples
+    }
+
+    fn pixel_offsets(&self, rng: &mut Rng) -> Vec<(f32, f32)> {
+        let (shift_x, shift_y) = (rng.next_f32(), rng.next_f32());
+        (0..self.samples)
+            .map(|i| {
+                (
+                    rotate(radical_inverse(i, 2), shift_x),
+                    rotate(radical_inverse(i, 3), shift_y),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Mirrors the digits of `index` in `base` about the decimal point, giving a value in [0, 1).
+fn radical_inverse(mut index: u32, base: u32) -> f32 {
+    let inverse_base = 1.0 / base as f64;
+    let mut scale = inverse_base;
+    let mut value = 0.0;
+    while index > 0 {
+        value += (index % base) as f64 * scale;
+        index /= base;
+        scale *= inverse_base;
+    }
+    value as f32
+}
+
+/// Shifts a value in [0, 1) by `shift`, wrapping around to stay in [0, 1).
+fn rotate(value: f32, shift: f32) -> f32 {
+    let rotated = value + shift;
+    let wrapped = rotated - rotated.floor();
+    // Rounding can land exactly on 1.
+    if wrapped < 1.0 { wrapped } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pixel_rng, radical_inverse, sample_disk, HaltonSampler, JitteredSampler,
+                Sampler, StratifiedSampler, UniformSampler};
+    use rand::Rng;
+    use rand::XorShiftRng;
+
+    fn assert_in_pixel(offsets: &[(f32, f32)]) {
+        for &(x, y) in offsets.iter() {
+            assert!(x >= 0.0 && x < 1.0 && y >= 0.0 && y < 1.0);
+        }
+    }
+
+    #[test]
+    fn test_sample_disk() {
+        assert_eq!(sample_disk(0.5, 0.5), (0.0, 0.0));
+        let (x, y) = sample_disk(1.0, 0.5);
+        assert_relative_eq!(x, 1.0);
+        assert_relative_eq!(y, 0.0);
+
+        let mut rng = XorShiftRng::new_unseeded();
+        for _ in 0..100 {
+            let (x, y) = sample_disk(rng.gen(), rng.gen());
+            assert!(x * x + y * y <= 1.0 + 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_uniform_sampler() {
+        let mut rng = XorShiftRng::new_unseeded();
+        assert_eq!(
+            UniformSampler::new(1).pixel_offsets(&mut rng),
+            vec![(0.5, 0.5)]
+        );
+        assert_eq!(
+            UniformSampler::new(4).pixel_offsets(&mut rng),
+            vec![(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)]
+        );
+    }
+
+    #[test]
+    fn test_samplers_stay_in_pixel() {
+        let mut rng = XorShiftRng::new_unseeded();
+        for &samples in [1, 2, 5, 9, 16].iter() {
+            let samplers: [Box<Sampler>; 4] = [
+                Box::new(UniformSampler::new(samples)),
+                Box::new(JitteredSampler::new(samples)),
+                Box::new(StratifiedSampler::new(samples)),
+                Box::new(HaltonSampler::new(samples)),
+            ];
+            for sampler in samplers.iter() {
+                let offsets = sampler.pixel_offsets(&mut rng);
+                assert_eq!(offsets.len() as u32, sampler.samples_per_pixel());
+                assert_in_pixel(&offsets);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stratified_sampler_covers_pixel() {
+        let mut rng = XorShiftRng::new_unseeded();
+        let offsets = StratifiedSampler::new(4).pixel_offsets(&mut rng);
+
+        // One sample lies in each quadrant.
+        for (i, &(x, y)) in offsets.iter().enumerate() {
+            assert_eq!((x >= 0.5) as usize + 2 * (y >= 0.5) as usize, i);
+        }
+    }
+
+    #[test]
+    fn test_radical_inverse() {
+        assert_eq!(radical_inverse(0, 2), 0.0);
+        assert_eq!(radical_inverse(1, 2), 0.5);
+        assert_eq!(radical_inverse(2, 2), 0.25);
+        assert_eq!(radical_inverse(3, 2), 0.75);
+        assert_relative_eq!(radical_inverse(1, 3), 1.0 / 3.0);
+        assert_relative_eq!(radical_inverse(5, 3), 7.0 / 9.0);
+    }
+
+    #[test]
+    fn test_halton_sampler_differs_between_pixels() {
+        let mut rng = XorShiftRng::new_unseeded();
+        let sampler = HaltonSampler::new(8);
+        let first = sampler.pixel_offsets(&mut rng);
+        let second = sampler.pixel_offsets(&mut rng);
+        assert!(first != second);
+
+        // Both pixels keep the spacing of the sequence, only shifted.
+        let spacing = |offsets: &[(f32, f32)]| {
+            let d = offsets[1].0 - offsets[0].0;
+            d - d.floor()
+        };
+        assert_relative_eq!(spacing(&first), spacing(&second), epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_pixel_rng_is_reproducible() {
+        let first: Vec<u32> = (0..4).map(|_| pixel_rng(7, 10, 20).next_u32()).collect();
+        assert!(first.iter().all(|&n| n == first[0]));
+
+        let mut neighbors = vec![
+            pixel_rng(7, 10, 20).next_u32(),
+            pixel_rng(7, 11, 20).next_u32(),
+            pixel_rng(7, 10, 21).next_u32(),
+            pixel_rng(8, 10, 20).next_u32(),
+        ];
+        neighbors.sort();
+        neighbors.dedup();
+        assert_eq!(neighbors.len(), 4);
+    }
+}
@@ -0,0 +1,104 @@
+//! Scratch space for shading, kept for each rendering thread and reused by every ray it traces so
+//! tracing does not allocate once the space has grown to fit the deepest path.
+use math::{Intersection, Vector};
+use scene::material::{Material, ScatteredRay};
+use std::cell::RefCell;
+
+/// Rays to start with room for, enough for several bounces through glass.
+const INITIAL_SCATTERED_CAPACITY: usize = 32;
+
+thread_local! {
+    /// Rays scattered from each surface along the path being traced, as a stack with the rays of
+    /// the nearest surface at the bottom.
+    static SCATTERED: RefCell<Vec<ScatteredRay>> =
+        RefCell::new(Vec::with_capacity(INITIAL_SCATTERED_CAPACITY));
+}
+
+/// The rays scattered from one surface, held on the scratch stack of the current thread until
+/// dropped.
+///
+/// Frames must be dropped in the reverse order they were made, which recursively tracing the
+/// rays of each frame does.
+pub struct ScatteredFrame {
+    start: usize,
+    end: usize,
+}
+
+impl ScatteredFrame {
+    /// Scatters rays from a surface onto the scratch stack, see `Material::scatter_at`.
+    pub fn scatter(
+        material: &Material,
+        incident: &Vector,
+        normal: &Vector,
+        outside_index: f32,
+        surface: &Intersection,
+        samples: [f32; 3],
+    ) -> ScatteredFrame {
+        SCATTERED.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            let start = stack.len();
+            material.scatter_at(incident, normal, outside_index, surface, samples, &mut stack);
+            ScatteredFrame {
+                start: start,
+                end: stack.len(),
+            }
+        })
+    }
+
+    /// Number of rays scattered.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A copy of a scattered ray, so the stack is free for tracing it.
+    pub fn get(&self, index: usize) -> ScatteredRay {
+        assert!(index < self.len(), "Scattered ray index out of range.");
+        SCATTERED.with(|stack| stack.borrow()[self.start + index])
+    }
+}
+
+impl Drop for ScatteredFrame {
+    fn drop(&mut self) {
+        SCATTERED.with(|stack| stack.borrow_mut().truncate(self.start));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScatteredFrame;
+    use math::{Intersection, Point, Vector};
+    use scene::Spectrum;
+    use scene::material::{DielectricMaterial, SpecularMaterial};
+
+    #[test]
+    fn test_frames_nest() {
+        let glass = DielectricMaterial::new(1.5);
+        let mirror = SpecularMaterial::new(&Spectrum::uniform(1.0));
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let down = Vector::new(0.0, -1.0, 0.0);
+        let surface = Intersection {
+            time: 0.0,
+            point: Point::new(0.0, 0.0, 0.0),
+            normal: normal,
+            uv: (0.0, 0.0),
+            tangent: Vector::new(1.0, 0.0, 0.0),
+            bitangent: Vector::new(0.0, 0.0, 1.0),
+            triangle: None,
+        };
+        let samples = [0.5; 3];
+
+        let outer = ScatteredFrame::scatter(&glass, &down, &normal, 1.0, &surface, samples);
+        assert_eq!(outer.len(), 1);
+        {
+            let inner = ScatteredFrame::scatter(&mirror, &down, &normal, 1.0, &surface, samples);
+            assert_eq!(inner.len(), 1);
+            assert_relative_eq!(inner.get(0).direction, normal);
+        }
+        // The inner frame is gone, leaving the outer rays in place.
+        assert_relative_eq!(outer.get(0).direction, down);
+    }
+}
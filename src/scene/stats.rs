@@ -0,0 +1,131 @@
+//! Counting the work done while rendering, to see where time goes.
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Counts of the rays a scene has traced, shared by every rendering thread.
+pub struct RayCounters {
+    rays: AtomicUsize,
+    shadow_rays: AtomicUsize,
+    intersection_tests: AtomicUsize,
+}
+
+impl RayCounters {
+    pub fn new() -> RayCounters {
+        RayCounters {
+            rays: AtomicUsize::new(0),
+            shadow_rays: AtomicUsize::new(0),
+            intersection_tests: AtomicUsize::new(0),
+        }
+    }
+
+    /// Counts a ray looking for the nearest surface along it.
+    pub fn add_ray(&self) {
+        self.rays.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a ray checking whether a light is blocked.
+    pub fn add_shadow_ray(&self) {
+        self.shadow_rays.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a test of a ray against the solid of an entity.
+    pub fn add_intersection_test(&self) {
+        self.intersection_tests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The counts so far, with no stages timed.
+    pub fn snapshot(&self) -> RenderStats {
+        RenderStats {
+            rays: self.rays.load(Ordering::Relaxed) as u64,
+            shadow_rays: self.shadow_rays.load(Ordering::Relaxed) as u64,
+            intersection_tests: self.intersection_tests.load(Ordering::Relaxed) as u64,
+            stages: Vec::new(),
+        }
+    }
+
+    /// Starts counting from zero again.
+    pub fn reset(&self) {
+        self.rays.store(0, Ordering::Relaxed);
+        self.shadow_rays.store(0, Ordering::Relaxed);
+        self.intersection_tests.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Work done by a render, and how long each stage of it took.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderStats {
+    /// Rays looking for the nearest surface, from the camera and scattered from surfaces.
+    pub rays: u64,
+    /// Rays checking whether lights are blocked, which are not included in `rays`.
+    pub shadow_rays: u64,
+    /// Tests of rays against the solids of entities the hierarchy could not rule out.
+    pub intersection_tests: u64,
+    /// Time taken by each stage, such as loading and rendering, in the order they ran.
+    pub stages: Vec<(&'static str, Duration)>,
+}
+
+impl RenderStats {
+    /// Records how long a stage took.
+    pub fn add_stage(&mut self, name: &'static str, duration: Duration) {
+        self.stages.push((name, duration));
+    }
+
+    /// Total time taken by every stage.
+    pub fn total_time(&self) -> Duration {
+        self.stages
+            .iter()
+            .fold(Duration::new(0, 0), |total, &(_, duration)| total + duration)
+    }
+}
+
+/// Seconds in a duration, for display.
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Rays: {}", self.rays)?;
+        writeln!(f, "Shadow rays: {}", self.shadow_rays)?;
+        writeln!(f, "Intersection tests: {}", self.intersection_tests)?;
+        for &(name, duration) in self.stages.iter() {
+            writeln!(f, "{:<10} {:>9.3}s", name, seconds(duration))?;
+        }
+        let total = seconds(self.total_time());
+        writeln!(f, "{:<10} {:>9.3}s", "total", total)?;
+        if total > 0.0 {
+            writeln!(
+                f,
+                "Rays per second: {:.0}",
+                (self.rays + self.shadow_rays) as f64 / total
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RayCounters;
+    use std::time::Duration;
+
+    #[test]
+    fn test_counters() {
+        let counters = RayCounters::new();
+        counters.add_ray();
+        counters.add_ray();
+        counters.add_shadow_ray();
+        counters.add_intersection_test();
+
+        let mut stats = counters.snapshot();
+        assert_eq!((stats.rays, stats.shadow_rays, stats.intersection_tests), (2, 1, 1));
+        stats.add_stage("load", Duration::from_millis(250));
+        stats.add_stage("render", Duration::from_millis(750));
+        assert_eq!(stats.total_time(), Duration::from_secs(1));
+        assert!(format!("{}", stats).contains("Rays per second: 3"));
+
+        counters.reset();
+        assert_eq!(counters.snapshot().rays, 0);
+    }
+}
@@ -0,0 +1,475 @@
+//! Colors which vary over a surface, looked up from texture coordinates or positions.
+use image;
+use math::{Perlin, Point, Vector};
+use scene::Spectrum;
+use std::f32::consts::PI;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Octaves of turbulence disturbing marble veins and wood rings.
+const TURBULENCE_OCTAVES: u32 = 6;
+
+/// Width and height of the atlases small image textures are packed into.
+pub const ATLAS_SIZE: u32 = 2048;
+
+/// Largest width or height of image textures packed into atlases.  Larger images gain little from
+/// sharing, and would leave large gaps in the atlases.
+pub const MAX_ATLAS_TEXTURE_SIZE: u32 = 256;
+
+/// A color which varies across a surface.
+///
+/// Textures are shared between rendering threads.
+pub trait Texture: Send + Sync {
+    /// The color at a point on a surface.
+    ///
+    /// # Arguments
+    /// * `u`, `v` - texture coordinates of the point
+    /// * `point` - the point in world space, for textures defined throughout space
+    fn eval(&self, u: f32, v: f32, point: &Point) -> Spectrum;
+}
+
+/// The same color everywhere.
+pub struct ConstantTexture {
+    color: Spectrum,
+}
+
+impl ConstantTexture {
+    pub fn new(color: &Spectrum) -> ConstantTexture {
+        ConstantTexture { color: *color }
+    }
+}
+
+impl Texture for ConstantTexture {
+    #[allow(unused_variables)]
+    fn eval(&self, u: f32, v: f32, point: &Point) -> Spectrum {
+        self.color
+    }
+}
+
+/// An image repeated across texture coordinates, with (0, 0) at the bottom left corner of the
+/// image and (1, 1) at the top right.
+///
+/// Colors are blended between the four nearest pixels.  Clones share the same pixels, so one
+/// image can be used by many materials.
+///
+/// The image may be a region of a larger atlas of images, see `pack_atlases`.  Texture
+/// coordinates are mapped into the region, which repeats on its own.
+#[derive(Clone)]
+pub struct ImageTexture {
+    width: u32,
+    height: u32,
+    /// Column and row of the top left corner of the image within `pixels`.
+    x: u32,
+    y: u32,
+    /// Pixels in each row of `pixels`, the width of the atlas the image is in.
+    stride: u32,
+    pixels: Arc<Vec<Spectrum>>,
+}
+
+impl ImageTexture {
+    /// Loads a texture from an image file.
+    pub fn open<P: AsRef<Path>>(path: P) -> image::ImageResult<ImageTexture> {
+        let image = image::open(path)?.to_rgb();
+        let (width, height) = image.dimensions();
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let rgb = image.get_pixel(x, y).data;
+                pixels.push(
+                    Spectrum::new(rgb[0] as f32, rgb[1] as f32, rgb[2] as f32) / 255.0,
+                );
+            }
+        }
+        Ok(ImageTexture::new(width, height, pixels))
+    }
+
+    /// Creates a texture from row-major pixels, with (0, 0) in the top left corner.
+    ///
+    /// # Panics
+    /// If there is not exactly one pixel for each position in the image.
+    pub fn new(width: u32, height: u32, pixels: Vec<Spectrum>) -> ImageTexture {
+        assert!(width > 0 && height > 0, "A texture needs at least one pixel.");
+        assert_eq!(pixels.len(), (width * height) as usize);
+        ImageTexture {
+            width: width,
+            height: height,
+            x: 0,
+            y: 0,
+            stride: width,
+            pixels: Arc::new(pixels),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Whether the image is a region of the same pixels as another, such as the same atlas.
+    pub fn shares_pixels_with(&self, other: &ImageTexture) -> bool {
+        Arc::ptr_eq(&self.pixels, &other.pixels)
+    }
+
+    /// The pixel at a column and row, wrapping around the edges of the image.
+    fn pixel(&self, x: i64, y: i64) -> Spectrum {
+        let wrap = |i: i64, size: u32| ((i % size as i64) + size as i64) % size as i64;
+        let (x, y) = (wrap(x, self.width), wrap(y, self.height));
+        let (x, y) = (x + self.x as i64, y + self.y as i64);
+        self.pixels[(y * self.stride as i64 + x) as usize]
+    }
+}
+
+/// Copies small images into shared atlases, so materials sampling many small textures read
+/// from fewer, contiguous blocks of memory.
+///
+/// Images are placed on shelves, rows as tall as their tallest image, from the tallest image to
+/// the shortest.  Images wider or taller than `MAX_ATLAS_TEXTURE_SIZE` are left as they are.
+///
+/// # Returns
+/// A texture for each of `textures`, in the same order and giving the same colors.
+pub fn pack_atlases(textures: &[ImageTexture]) -> Vec<ImageTexture> {
+    let mut packed = textures.to_vec();
+    let mut order: Vec<usize> = (0..textures.len())
+        .filter(|&i| {
+            textures[i].width <= MAX_ATLAS_TEXTURE_SIZE &&
+                textures[i].height <= MAX_ATLAS_TEXTURE_SIZE
+        })
+        .collect();
+    order.sort_by(|&a, &b| textures[b].height.cmp(&textures[a].height));
+
+    // Each atlas's images and where they are placed, filled one at a time.
+    let mut atlases: Vec<Vec<(usize, u32, u32)>> = Vec::new();
+    let (mut shelf_x, mut shelf_y, mut shelf_height) = (0, 0, 0);
+    for &index in order.iter() {
+        let (width, height) = (textures[index].width, textures[index].height);
+        if shelf_x + width > ATLAS_SIZE {
+            shelf_x = 0;
+            shelf_y += shelf_height;
+            shelf_height = 0;
+        }
+        if atlases.is_empty() || shelf_y + height > ATLAS_SIZE {
+            atlases.push(Vec::new());
+            shelf_x = 0;
+            shelf_y = 0;
+            shelf_height = 0;
+        }
+        atlases.last_mut().unwrap().push((index, shelf_x, shelf_y));
+        shelf_x += width;
+        shelf_height = shelf_height.max(height);
+    }
+
+    for placements in atlases.iter() {
+        // Atlases are only as tall as their images need.
+        let height = placements
+            .iter()
+            .map(|&(index, _, y)| y + textures[index].height)
+            .max()
+            .unwrap();
+        let mut pixels = vec![Spectrum::black(); (ATLAS_SIZE * height) as usize];
+        for &(index, x, y) in placements.iter() {
+            let texture = &textures[index];
+            for row in 0..texture.height {
+                for column in 0..texture.width {
+                    pixels[((y + row) * ATLAS_SIZE + x + column) as usize] =
+                        texture.pixel(column as i64, row as i64);
+                }
+            }
+        }
+        let pixels = Arc::new(pixels);
+        for &(index, x, y) in placements.iter() {
+            packed[index] = ImageTexture {
+                width: textures[index].width,
+                height: textures[index].height,
+                x: x,
+                y: y,
+                stride: ATLAS_SIZE,
+                pixels: pixels.clone(),
+            };
+        }
+    }
+    packed
+}
+
+impl Texture for ImageTexture {
+    #[allow(unused_variables)]
+    fn eval(&self, u: f32, v: f32, point: &Point) -> Spectrum {
+        // Pixel centers are at half pixel positions.
+        let x = u * self.width as f32 - 0.5;
+        let y = (1.0 - v) * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let top = (1.0 - tx) * self.pixel(x0, y0) + tx * self.pixel(x0 + 1, y0);
+        let bottom = (1.0 - tx) * self.pixel(x0, y0 + 1) + tx * self.pixel(x0 + 1, y0 + 1);
+        (1.0 - ty) * top + ty * bottom
+    }
+}
+
+/// Alternating squares of two textures, in a grid over texture coordinates.
+pub struct CheckerTexture {
+    even: Box<Texture>,
+    odd: Box<Texture>,
+    cells: f32,
+}
+
+impl CheckerTexture {
+    /// # Arguments
+    /// * `even`, `odd` - textures of alternate squares, `even` covering the square at (0, 0)
+    /// * `cells` - number of squares along each side of a unit of texture coordinates
+    ///
+    /// # Panics
+    /// If there are no cells.
+    pub fn new(even: Box<Texture>, odd: Box<Texture>, cells: f32) -> CheckerTexture {
+        assert!(cells > 0.0, "A checker texture needs a positive number of cells.");
+        CheckerTexture {
+            even: even,
+            odd: odd,
+            cells: cells,
+        }
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn eval(&self, u: f32, v: f32, point: &Point) -> Spectrum {
+        let cell = |t: f32| (t * self.cells).floor() as i64;
+        if (cell(u) + cell(v)) % 2 == 0 {
+            self.even.eval(u, v, point)
+        } else {
+            self.odd.eval(u, v, point)
+        }
+    }
+}
+
+/// Veined stone, bands of two colors along X disturbed by turbulence.
+///
+/// Marble is defined throughout space, so it looks carved from a block rather than painted on.
+pub struct MarbleTexture {
+    noise: Perlin,
+    base: Spectrum,
+    vein: Spectrum,
+    scale: f32,
+}
+
+impl MarbleTexture {
+    /// # Arguments
+    /// * `base`, `vein` - colors of the stone and of its veins
+    /// * `scale` - number of veins per unit of distance
+    /// * `seed` - varies the pattern of the veins
+    pub fn new(base: &Spectrum, vein: &Spectrum, scale: f32, seed: u32) -> MarbleTexture {
+        MarbleTexture {
+            noise: Perlin::new(seed),
+            base: *base,
+            vein: *vein,
+            scale: scale,
+        }
+    }
+}
+
+impl Texture for MarbleTexture {
+    #[allow(unused_variables)]
+    fn eval(&self, u: f32, v: f32, point: &Point) -> Spectrum {
+        let p = Point::new(self.scale * point.x, self.scale * point.y, self.scale * point.z);
+        let turbulence = self.noise.turbulence(&p, TURBULENCE_OCTAVES);
+        let t = 0.5 * (1.0 + (PI * p.x + 5.0 * turbulence).sin());
+        t * self.base + (1.0 - t) * self.vein
+    }
+}
+
+/// Wood grain, rings of two colors around the Y axis, made irregular by noise.
+pub struct WoodTexture {
+    noise: Perlin,
+    light: Spectrum,
+    dark: Spectrum,
+    rings: f32,
+}
+
+impl WoodTexture {
+    /// # Arguments
+    /// * `light`, `dark` - colors between and of the rings
+    /// * `rings` - number of rings per unit of distance from the axis
+    /// * `seed` - varies the irregularity of the rings
+    pub fn new(light: &Spectrum, dark: &Spectrum, rings: f32, seed: u32) -> WoodTexture {
+        WoodTexture {
+            noise: Perlin::new(seed),
+            light: *light,
+            dark: *dark,
+            rings: rings,
+        }
+    }
+}
+
+impl Texture for WoodTexture {
+    #[allow(unused_variables)]
+    fn eval(&self, u: f32, v: f32, point: &Point) -> Spectrum {
+        let radius = (point.x * point.x + point.z * point.z).sqrt();
+        let p = Point::new(self.rings * point.x, 0.25 * self.rings * point.y, self.rings * point.z);
+        let ring = self.rings * radius + 0.5 * self.noise.turbulence(&p, TURBULENCE_OCTAVES);
+        // Sharpen the rings, so most of the wood is light.
+        let t = (ring - ring.floor()).powi(4);
+        (1.0 - t) * self.light + t * self.dark
+    }
+}
+
+/// Perturbs shading normals by a texture, to give surfaces detail without extra geometry.
+///
+/// Red, green and blue are the components of the perturbed normal along the surface's tangent,
+/// bitangent and normal, mapped from -1 to 1 onto 0 to 1, as in common tangent space normal maps.
+/// An even blue of (0.5, 0.5, 1) leaves normals unchanged.
+pub struct NormalMap {
+    texture: Box<Texture>,
+}
+
+impl NormalMap {
+    pub fn new(texture: Box<Texture>) -> NormalMap {
+        NormalMap { texture: texture }
+    }
+
+    /// The shading normal at a point on a surface.
+    ///
+    /// # Arguments
+    /// * `normal` - unit vector perpendicular to the surface
+    /// * `tangent`, `bitangent` - directions along the surface in which U and V increase
+    /// * `uv` - texture coordinates of the point
+    /// * `point` - the point in world space
+    ///
+    /// # Returns
+    /// A unit vector on the same side of the surface as `normal`, or `normal` itself if the map
+    /// or the tangents are degenerate.
+    pub fn perturb(
+        &self,
+        normal: &Vector,
+        tangent: &Vector,
+        bitangent: &Vector,
+        uv: (f32, f32),
+        point: &Point,
+    ) -> Vector {
+        // Tangents may not be perpendicular to each other or the normal once transformed.
+        let mut t = *tangent - tangent.dot(normal) * *normal;
+        if t.normalize().is_err() {
+            return *normal;
+        }
+        let mut b = *bitangent - bitangent.dot(normal) * *normal - bitangent.dot(&t) * t;
+        if b.normalize().is_err() {
+            return *normal;
+        }
+
+        let encoded = self.texture.eval(uv.0, uv.1, point);
+        let local = Vector::new(
+            2.0 * encoded.r - 1.0,
+            2.0 * encoded.g - 1.0,
+            2.0 * encoded.b - 1.0,
+        );
+        let mut perturbed = local.x * t + local.y * b + local.z * *normal;
+        match perturbed.normalize() {
+            Ok(_) if perturbed.dot(normal) > 0.0 => perturbed,
+            _ => *normal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pack_atlases, CheckerTexture, ConstantTexture, ImageTexture,
+                MAX_ATLAS_TEXTURE_SIZE, MarbleTexture, NormalMap, Texture, WoodTexture};
+    use math::{Point, Vector};
+    use scene::Spectrum;
+
+    #[test]
+    fn test_image_texture() {
+        let (black, white) = (Spectrum::black(), Spectrum::uniform(1.0));
+        // White on the top row, black on the bottom.
+        let texture = ImageTexture::new(2, 2, vec![white, white, black, black]);
+        let origin = Point::new(0.0, 0.0, 0.0);
+
+        assert_relative_eq!(texture.eval(0.25, 0.75, &origin), white);
+        assert_relative_eq!(texture.eval(0.75, 0.25, &origin), black);
+        assert_relative_eq!(texture.eval(0.5, 0.5, &origin), 0.5 * white);
+        // Coordinates outside of 0 to 1 repeat the image.
+        assert_relative_eq!(texture.eval(1.25, -0.75, &origin), black);
+    }
+
+    #[test]
+    fn test_pack_atlases() {
+        let gradient = |width: u32, height: u32, seed: f32| {
+            let pixels = (0..width * height)
+                .map(|i| Spectrum::new(seed, (i % width) as f32, (i / width) as f32))
+                .collect();
+            ImageTexture::new(width, height, pixels)
+        };
+        let large = MAX_ATLAS_TEXTURE_SIZE + 1;
+        let textures = vec![
+            gradient(3, 2, 0.0),
+            gradient(large, 1, 1.0),
+            gradient(5, 7, 2.0),
+            gradient(1, 1, 3.0),
+        ];
+        let packed = pack_atlases(&textures);
+
+        assert!(packed[0].shares_pixels_with(&packed[2]));
+        assert!(packed[0].shares_pixels_with(&packed[3]));
+        assert!(!packed[0].shares_pixels_with(&packed[1]));
+        let origin = Point::new(0.0, 0.0, 0.0);
+        for (texture, packed) in textures.iter().zip(packed.iter()) {
+            // Including repeating past the edges, which must not reach neighbors in the atlas.
+            for &(u, v) in [(0.1, 0.2), (0.5, 0.5), (0.9, 0.95), (1.3, -0.4)].iter() {
+                assert_relative_eq!(texture.eval(u, v, &origin), packed.eval(u, v, &origin));
+            }
+        }
+    }
+
+    #[test]
+    fn test_checker_texture() {
+        let checker = CheckerTexture::new(
+            Box::new(ConstantTexture::new(&Spectrum::new(1.0, 0.0, 0.0))),
+            Box::new(ConstantTexture::new(&Spectrum::new(0.0, 0.0, 1.0))),
+            4.0,
+        );
+        let origin = Point::new(0.0, 0.0, 0.0);
+        assert_relative_eq!(checker.eval(0.1, 0.1, &origin), Spectrum::new(1.0, 0.0, 0.0));
+        assert_relative_eq!(checker.eval(0.3, 0.1, &origin), Spectrum::new(0.0, 0.0, 1.0));
+        assert_relative_eq!(checker.eval(0.3, 0.3, &origin), Spectrum::new(1.0, 0.0, 0.0));
+        assert_relative_eq!(checker.eval(-0.1, 0.1, &origin), Spectrum::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_noise_textures() {
+        let (light, dark) = (Spectrum::new(0.9, 0.8, 0.6), Spectrum::new(0.3, 0.2, 0.1));
+        let marble = MarbleTexture::new(&light, &dark, 2.0, 0);
+        let wood = WoodTexture::new(&light, &dark, 4.0, 0);
+        let mut marble_colors = Vec::new();
+        for i in 0..50 {
+            let p = Point::new(0.13 * i as f32, 0.07 * i as f32, 0.29 * i as f32);
+            for color in [marble.eval(0.0, 0.0, &p), wood.eval(0.0, 0.0, &p)].iter() {
+                // Blends of the two colors.
+                assert!(color.r >= dark.r - 1e-5 && color.r <= light.r + 1e-5);
+                assert!(color.b >= dark.b - 1e-5 && color.b <= light.b + 1e-5);
+            }
+            marble_colors.push(marble.eval(0.0, 0.0, &p).r);
+        }
+        // Both colors appear.
+        assert!(marble_colors.iter().any(|&red| red > 0.8));
+        assert!(marble_colors.iter().any(|&red| red < 0.4));
+    }
+
+    #[test]
+    fn test_normal_map() {
+        let origin = Point::new(0.0, 0.0, 0.0);
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let (tangent, bitangent) = (Vector::new(1.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        let perturb = |encoded: Spectrum| {
+            NormalMap::new(Box::new(ConstantTexture::new(&encoded)))
+                .perturb(&normal, &tangent, &bitangent, (0.0, 0.0), &origin)
+        };
+
+        assert_relative_eq!(perturb(Spectrum::new(0.5, 0.5, 1.0)), normal);
+        // Leaning toward the tangent.
+        let leaning = perturb(Spectrum::new(1.0, 0.5, 1.0));
+        assert_relative_eq!(leaning, Vector::new(1.0, 1.0, 0.0) / 2.0f32.sqrt(), epsilon = 1e-5);
+        // Normals pointing into the surface are ignored.
+        assert_relative_eq!(perturb(Spectrum::new(0.5, 0.5, 0.0)), normal);
+    }
+}
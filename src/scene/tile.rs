@@ -0,0 +1,89 @@
+/// A rectangular region of the film, which can be rendered independently of other regions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tile {
+    /// Left edge on the raster.
+    pub x: u32,
+    /// Top edge on the raster.
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Tile {
+    /// Splits a raster into tiles of at most `size` by `size` pixels, in row-major order.
+    ///
+    /// Tiles along the right and bottom edges are smaller when the raster is not a multiple of
+    /// `size`.
+    pub fn split(width: u32, height: u32, size: u32) -> Vec<Tile> {
+        assert!(size > 0, "Tiles must have a non-zero size.");
+        let mut tiles = Vec::new();
+        let mut y = 0;
+        while y < height {
+            let mut x = 0;
+            while x < width {
+                tiles.push(Tile {
+                    x: x,
+                    y: y,
+                    width: size.min(width - x),
+                    height: size.min(height - y),
+                });
+                x += size;
+            }
+            y += size;
+        }
+        tiles
+    }
+
+    /// The number of pixels covered by this tile.
+    pub fn pixel_count(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+
+    /// Determines if a raster position lies within this tile.
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        self.x <= x && x < self.x + self.width && self.y <= y && y < self.y + self.height
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tile;
+
+    #[test]
+    fn test_split_even() {
+        let tiles = Tile::split(64, 32, 32);
+        assert_eq!(
+            tiles,
+            vec![
+                Tile {
+                    x: 0,
+                    y: 0,
+                    width: 32,
+                    height: 32,
+                },
+                Tile {
+                    x: 32,
+                    y: 0,
+                    width: 32,
+                    height: 32,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_covers_raster() {
+        let (width, height) = (100, 70);
+        let tiles = Tile::split(width, height, 32);
+        assert_eq!(tiles.len(), 4 * 3);
+
+        let total: usize = tiles.iter().map(|t| t.pixel_count()).sum();
+        assert_eq!(total, (width * height) as usize);
+
+        for y in 0..height {
+            for x in 0..width {
+                assert_eq!(tiles.iter().filter(|t| t.contains(x, y)).count(), 1);
+            }
+        }
+    }
+}
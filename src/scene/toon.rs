@@ -0,0 +1,154 @@
+//! Stylized rendering with flat bands of shading and outlines, like a technical illustration.
+use scene::{Camera, Scene, Spectrum, SurfaceHit};
+use scene::sampler::pixel_rng;
+
+/// Options for toon rendering.
+#[derive(Clone, Copy, Debug)]
+pub struct ToonSettings {
+    /// Number of distinct levels of brightness.
+    pub bands: u32,
+    /// Fraction of the depth of the nearer of two neighboring pixels they must differ by to be
+    /// separated by an outline.
+    pub depth_threshold: f32,
+    /// Neighboring pixels whose normals have a smaller cosine than this are separated by an
+    /// outline.
+    pub normal_threshold: f32,
+    pub outline: Spectrum,
+    pub background: Spectrum,
+}
+
+impl Default for ToonSettings {
+    fn default() -> ToonSettings {
+        ToonSettings {
+            bands: 4,
+            depth_threshold: 0.05,
+            // About 30 degrees.
+            normal_threshold: 0.866,
+            outline: Spectrum::black(),
+            background: Spectrum::uniform(1.0),
+        }
+    }
+}
+
+/// Renders a scene with stepped shading and outlines along silhouettes and creases.
+///
+/// # Returns
+/// The radiance of each pixel, in row-major order from the top left corner.
+pub fn render(camera: &Camera, scene: &Scene, settings: &ToonSettings) -> Vec<Spectrum> {
+    assert!(settings.bands > 0, "Toon shading needs at least one band.");
+    let (width, height) = (camera.raster_width(), camera.raster_height());
+    let mut hits = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let ray = camera.generate_ray_sample(x as f32 + 0.5, y as f32 + 0.5);
+            hits.push(scene.first_hit(&ray, &mut pixel_rng(0, x, y)));
+        }
+    }
+
+    let hit_at = |x: u32, y: u32| &hits[(y * width + x) as usize];
+    let mut pixels = Vec::with_capacity(hits.len());
+    for y in 0..height {
+        for x in 0..width {
+            let hit = hit_at(x, y);
+            let mut neighbors = Vec::with_capacity(2);
+            if x + 1 < width {
+                neighbors.push(hit_at(x + 1, y));
+            }
+            if y + 1 < height {
+                neighbors.push(hit_at(x, y + 1));
+            }
+            if x > 0 {
+                neighbors.push(hit_at(x - 1, y));
+            }
+            if y > 0 {
+                neighbors.push(hit_at(x, y - 1));
+            }
+
+            // Outlines are drawn on the nearer side of an edge, to keep them a pixel wide.
+            let outlined = neighbors.iter().any(|n| is_edge(hit, n, settings));
+            pixels.push(match *hit {
+                _ if outlined => settings.outline,
+                Some(ref h) => stepped(&h.direct, settings.bands),
+                None => settings.background,
+            });
+        }
+    }
+    pixels
+}
+
+/// Whether `hit` lies on an edge, on the nearer side of it, compared to a neighboring pixel.
+fn is_edge(
+    hit: &Option<SurfaceHit>,
+    neighbor: &Option<SurfaceHit>,
+    settings: &ToonSettings,
+) -> bool {
+    match (*hit, *neighbor) {
+        (Some(_), None) => true,
+        (Some(h), Some(n)) => {
+            let depth_edge = n.depth - h.depth > settings.depth_threshold * h.depth;
+            let crease = h.normal.dot(&n.normal) < settings.normal_threshold;
+            depth_edge || (crease && h.depth <= n.depth)
+        }
+        _ => false,
+    }
+}
+
+/// Quantizes the brightness of radiance into bands, keeping its color.
+fn stepped(radiance: &Spectrum, bands: u32) -> Spectrum {
+    let l = radiance.luminance();
+    if l <= 0.0 {
+        return Spectrum::black();
+    }
+    let band = (l.min(1.0) * bands as f32).ceil() / bands as f32;
+    band / l * *radiance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, stepped, ToonSettings};
+    use math::{PlanarAngle, Point, Sphere, Transform};
+    use scene::{Camera, Film, Perspective, Scene, Spectrum};
+    use scene::material::LambertianMaterial;
+    use scene::nonarea_light::PointLight;
+
+    #[test]
+    fn test_stepped_shading() {
+        let dim = stepped(&Spectrum::uniform(0.1), 4);
+        let dimmer = stepped(&Spectrum::uniform(0.2), 4);
+        assert_relative_eq!(dim, dimmer);
+        assert_relative_eq!(dim.luminance(), 0.25, epsilon = 1e-5);
+        assert_relative_eq!(stepped(&Spectrum::black(), 4), Spectrum::black());
+    }
+
+    #[test]
+    fn test_outline_silhouette() {
+        let mut scene = Scene::new();
+        scene.add_light(Box::new(PointLight::new(
+            Point::new(0.0, 0.0, 0.0),
+            100.0 * Spectrum::uniform(1.0),
+        )));
+        scene.add_entity(
+            Box::new(Sphere::new_with_radius(2.0)),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::translate(0.0, 0.0, 10.0),
+        );
+        let film = Film::new(21, 21);
+        let camera = Camera::new(&film, &Perspective::new(1.0, 100.0, PlanarAngle::Degrees(60.0)));
+        let settings = ToonSettings::default();
+        let pixels = render(&camera, &scene, &settings);
+
+        let row: Vec<_> = pixels[(10 * 21)..(11 * 21)].iter().collect();
+        assert_relative_eq!(*row[0], settings.background);
+        assert_relative_eq!(*row[10], Spectrum::uniform(1.0));
+
+        // Outlines run along both sides of the silhouette, where the normals also turn sharply.
+        let outlines: Vec<usize> = (0..21)
+            .filter(|&i| relative_eq!(*row[i], settings.outline))
+            .collect();
+        assert!(!outlines.is_empty());
+        assert!(outlines.iter().all(|&i| i != 10));
+        assert_eq!(outlines.iter().filter(|&&i| i < 10).count(), outlines.len() / 2);
+        assert_relative_eq!(*row[outlines[0] - 1], settings.background);
+        assert_relative_eq!(*row[outlines[outlines.len() - 1] + 1], settings.background);
+    }
+}
@@ -0,0 +1,201 @@
+//! Which of a set of points can see each other, for precomputing visibility between probes, or
+//! between lights and surface elements.
+use math::{Point, Ray};
+use rayon::prelude::*;
+use scene::{Scene, PREVENT_SELF_INTERSECTION_RANGE};
+use std::io::{self, BufRead, Write};
+
+/// Whether each of a set of points can see each of another set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VisibilityMatrix {
+    rows: usize,
+    columns: usize,
+    // Row-major, a row for each point looked from.
+    visible: Vec<bool>,
+}
+
+impl VisibilityMatrix {
+    /// Finds which points of `to` can be seen from each point of `from`.
+    ///
+    /// Points may lie on surfaces, such as the centers of surface elements, without hiding
+    /// themselves.
+    pub fn between(scene: &Scene, from: &[Point], to: &[Point]) -> VisibilityMatrix {
+        let visible = from.par_iter()
+            .map(|a| to.iter().map(|b| scene.visible(a, b)).collect::<Vec<bool>>())
+            .collect::<Vec<Vec<bool>>>();
+        VisibilityMatrix {
+            rows: from.len(),
+            columns: to.len(),
+            visible: visible.into_iter().flat_map(|row| row.into_iter()).collect(),
+        }
+    }
+
+    /// Finds which points of a set can see each other.
+    ///
+    /// Visibility is symmetric, so only half of the pairs are tested.  Points can always see
+    /// themselves.
+    pub fn among(scene: &Scene, points: &[Point]) -> VisibilityMatrix {
+        let count = points.len();
+        let upper = (0..count)
+            .collect::<Vec<usize>>()
+            .par_iter()
+            .map(|&i| {
+                (i + 1..count)
+                    .map(|j| scene.visible(&points[i], &points[j]))
+                    .collect::<Vec<bool>>()
+            })
+            .collect::<Vec<Vec<bool>>>();
+
+        let mut visible = vec![true; count * count];
+        for (i, row) in upper.iter().enumerate() {
+            for (offset, &v) in row.iter().enumerate() {
+                let j = i + 1 + offset;
+                visible[i * count + j] = v;
+                visible[j * count + i] = v;
+            }
+        }
+        VisibilityMatrix {
+            rows: count,
+            columns: count,
+            visible: visible,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Whether the point of row `from` can see the point of column `to`.
+    ///
+    /// # Panics
+    /// If either index is outside of the matrix.
+    pub fn is_visible(&self, from: usize, to: usize) -> bool {
+        assert!(from < self.rows && to < self.columns);
+        self.visible[from * self.columns + to]
+    }
+
+    /// Writes the matrix as text, a line for each row with 1 for visible and 0 for hidden.
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for row in 0..self.rows {
+            let line: Vec<&str> = (0..self.columns)
+                .map(|column| if self.is_visible(row, column) {
+                    "1"
+                } else {
+                    "0"
+                })
+                .collect();
+            writeln!(out, "{}", line.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads points written as three numbers separated by whitespace, one point per line.
+///
+/// Blank lines and lines starting with `#` are skipped.
+///
+/// # Returns
+/// A message naming the line which is not a point, if there is one.
+pub fn read_points<R: BufRead>(input: R) -> Result<Vec<Point>, String> {
+    let mut points = Vec::new();
+    for (number, line) in input.lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let coordinates: Vec<f32> = line.split_whitespace()
+            .map(|c| c.parse::<f32>())
+            .collect::<Result<Vec<f32>, _>>()
+            .map_err(|e| format!("line {}: {}", number + 1, e))?;
+        if coordinates.len() != 3 {
+            return Err(format!("line {}: expected x y z", number + 1));
+        }
+        points.push(Point::new(coordinates[0], coordinates[1], coordinates[2]));
+    }
+    Ok(points)
+}
+
+impl Scene {
+    /// Whether nothing lies between two points, using the same offsets from surfaces as shadow
+    /// rays.  Moving entities are where they are at time 0.
+    pub fn visible(&self, a: &Point, b: &Point) -> bool {
+        // Too close to tell apart from the surfaces they lie on.
+        let distance = a.distance_to(*b);
+        if distance <= 2.0 * PREVENT_SELF_INTERSECTION_RANGE {
+            return true;
+        }
+        let mut ray = Ray {
+            origin: *a,
+            direction: *b - *a,
+            time: 0.0,
+        };
+        ray.normalize().expect("Points far apart have a direction between them.");
+        ray.origin = *a + PREVENT_SELF_INTERSECTION_RANGE * ray.direction;
+        match self.intersect(&ray) {
+            Some(si) => {
+                si.intersection.time >= distance - 2.0 * PREVENT_SELF_INTERSECTION_RANGE
+            }
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_points, VisibilityMatrix};
+    use math::{Point, Sphere, Transform};
+    use scene::{Scene, Spectrum};
+    use scene::material::LambertianMaterial;
+
+    /// A sphere at the origin.
+    fn blocker() -> Scene {
+        let mut scene = Scene::new();
+        scene.add_entity(
+            Box::new(Sphere::new_with_radius(1.0)),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::identity(),
+        );
+        scene.finalize();
+        scene
+    }
+
+    #[test]
+    fn test_among() {
+        let points = [
+            Point::new(-5.0, 0.0, 0.0),
+            Point::new(5.0, 0.0, 0.0),
+            Point::new(0.0, 5.0, 0.0),
+            // On top of the sphere, so it faces the third point and the others only see it
+            // through the sphere.
+            Point::new(0.0, 1.0, 0.0),
+        ];
+        let matrix = VisibilityMatrix::among(&blocker(), &points);
+        assert!(!matrix.is_visible(0, 1));
+        assert!(!matrix.is_visible(1, 0));
+        assert!(matrix.is_visible(0, 2));
+        assert!(matrix.is_visible(2, 3));
+        assert!(!matrix.is_visible(0, 3));
+        assert!(matrix.is_visible(3, 3));
+        assert_eq!(matrix, VisibilityMatrix::between(&blocker(), &points, &points));
+
+        let mut text = Vec::new();
+        matrix.write(&mut text).unwrap();
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "1 0 1 0\n0 1 1 0\n1 1 1 1\n0 0 1 1\n"
+        );
+    }
+
+    #[test]
+    fn test_read_points() {
+        let points = read_points("# probes\n1 2 3\n\n-4.5 0 1e1\n".as_bytes()).unwrap();
+        assert_eq!(points, vec![Point::new(1.0, 2.0, 3.0), Point::new(-4.5, 0.0, 10.0)]);
+        assert!(read_points("1 2\n".as_bytes()).is_err());
+        assert!(read_points("1 2 z\n".as_bytes()).is_err());
+    }
+}
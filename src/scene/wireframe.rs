@@ -0,0 +1,201 @@
+//! Drawing the edges of triangles over a render, to inspect imported meshes and their
+//! tessellation.
+use scene::{Camera, Scene, Spectrum, SurfaceHit};
+use scene::sampler::pixel_rng;
+
+/// Options for wireframe rendering.
+#[derive(Clone, Copy, Debug)]
+pub struct WireframeSettings {
+    /// Width of the lines along triangle edges, in pixels.
+    pub line_width: f32,
+    pub line_color: Spectrum,
+    pub background: Spectrum,
+}
+
+impl Default for WireframeSettings {
+    fn default() -> WireframeSettings {
+        WireframeSettings {
+            line_width: 1.0,
+            line_color: Spectrum::new(0.0, 1.0, 0.0),
+            background: Spectrum::black(),
+        }
+    }
+}
+
+/// Renders triangle edges over the directly lit surfaces of a scene.
+///
+/// Edges are found from how quickly the barycentric coordinates of the triangle seen through each
+/// pixel change between neighboring pixels, so lines keep the same width at any distance.  Only
+/// entities made of triangles have edges drawn.
+///
+/// # Returns
+/// The radiance of each pixel, in row-major order from the top left corner.
+pub fn render(camera: &Camera, scene: &Scene, settings: &WireframeSettings) -> Vec<Spectrum> {
+    let (width, height) = (camera.raster_width(), camera.raster_height());
+    let mut hits = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let ray = camera.generate_ray_sample(x as f32 + 0.5, y as f32 + 0.5);
+            hits.push(scene.first_hit(&ray, &mut pixel_rng(0, x, y)));
+        }
+    }
+
+    let hit_at = |x: i64, y: i64| if x < 0 || y < 0 || x >= width as i64 ||
+        y >= height as i64
+    {
+        None
+    } else {
+        hits[(y as u32 * width + x as u32) as usize]
+    };
+
+    let mut pixels = Vec::with_capacity(hits.len());
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            let hit = match hit_at(x, y) {
+                Some(hit) => hit,
+                None => {
+                    pixels.push(settings.background);
+                    continue;
+                }
+            };
+            if hit.triangle.is_none() {
+                pixels.push(hit.direct);
+                continue;
+            }
+
+            let dx = gradient(&hit, &hit_at(x + 1, y), &hit_at(x - 1, y));
+            let dy = gradient(&hit, &hit_at(x, y + 1), &hit_at(x, y - 1));
+            let distance = match (dx, dy) {
+                (Some(dx), Some(dy)) => edge_distance(&hit, &dx, &dy),
+                // Another triangle is on both sides, so an edge is within a pixel.
+                _ => 0.5,
+            };
+
+            // Each triangle draws its half of the line, blending over the last half pixel.
+            let coverage = (0.5 * settings.line_width - distance + 0.5).max(0.0).min(1.0);
+            pixels.push((1.0 - coverage) * hit.direct + coverage * settings.line_color);
+        }
+    }
+    pixels
+}
+
+/// Change in barycentric coordinates per pixel toward one neighbor, or away from the other if
+/// the first is on another triangle.
+fn gradient(
+    hit: &SurfaceHit,
+    next: &Option<SurfaceHit>,
+    previous: &Option<SurfaceHit>,
+) -> Option<[f32; 3]> {
+    let same_triangle = |other: &Option<SurfaceHit>| match *other {
+        Some(ref o) if o.entity == hit.entity && o.triangle.map(|t| t.index) ==
+            hit.triangle.map(|t| t.index) => o.triangle.map(|t| t.barycentric),
+        _ => None,
+    };
+    let here = hit.triangle.unwrap().barycentric;
+    if let Some(next) = same_triangle(next) {
+        return Some([next[0] - here[0], next[1] - here[1], next[2] - here[2]]);
+    }
+    same_triangle(previous).map(|previous| {
+        [
+            here[0] - previous[0],
+            here[1] - previous[1],
+            here[2] - previous[2],
+        ]
+    })
+}
+
+/// Distance in pixels to the nearest edge of the triangle.
+fn edge_distance(hit: &SurfaceHit, dx: &[f32; 3], dy: &[f32; 3]) -> f32 {
+    let barycentric = hit.triangle.unwrap().barycentric;
+    (0..3)
+        .map(|i| {
+            let change = (dx[i] * dx[i] + dy[i] * dy[i]).sqrt();
+            if change > 0.0 {
+                barycentric[i] / change
+            } else {
+                ::std::f32::INFINITY
+            }
+        })
+        .fold(::std::f32::INFINITY, f32::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, WireframeSettings};
+    use math::{Point, Transform, TriangleMesh, Vector};
+    use scene::{Camera, Film, Orthographic, Scene, Spectrum};
+    use scene::material::LambertianMaterial;
+    use scene::nonarea_light::DirectionalLight;
+
+    #[test]
+    fn test_edges_of_square() {
+        // A square of two triangles split along a diagonal, exactly filling the view and facing
+        // the camera.
+        let mut scene = Scene::new();
+        scene.add_light(Box::new(DirectionalLight::new(
+            &Vector::new(0.0, 0.0, 1.0),
+            &Spectrum::uniform(0.5),
+        )));
+        scene.add_entity(
+            Box::new(TriangleMesh::new(
+                vec![
+                    Point::new(-1.0, -1.0, 5.0),
+                    Point::new(1.0, -1.0, 5.0),
+                    Point::new(1.0, 1.0, 5.0),
+                    Point::new(-1.0, 1.0, 5.0),
+                ],
+                vec![0, 2, 1, 0, 3, 2],
+            )),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::identity(),
+        );
+        let film = Film::new(32, 32);
+        let camera = Camera::new(&film, &Orthographic::new(1.0, 10.0, 1.0));
+        let settings = WireframeSettings::default();
+        let pixels = render(&camera, &scene, &settings);
+
+        let at = |x: usize, y: usize| pixels[y * 32 + x];
+        let is_line = |x: usize, y: usize| at(x, y).g > at(x, y).r + 0.2;
+
+        // Along the border and the diagonal.
+        assert!(is_line(0, 16));
+        assert!(is_line(16, 31));
+        assert!(is_line(10, 21));
+
+        // Inside each triangle.
+        assert!(!is_line(8, 16));
+        assert!(!is_line(24, 16));
+        assert_relative_eq!(at(8, 16), Spectrum::uniform(0.5));
+    }
+
+    #[test]
+    fn test_wider_lines() {
+        let mut scene = Scene::new();
+        scene.add_entity(
+            Box::new(TriangleMesh::new(
+                vec![
+                    Point::new(-1.0, -1.0, 5.0),
+                    Point::new(1.0, -1.0, 5.0),
+                    Point::new(1.0, 1.0, 5.0),
+                    Point::new(-1.0, 1.0, 5.0),
+                ],
+                vec![0, 1, 2, 0, 2, 3],
+            )),
+            Box::new(LambertianMaterial::new(&Spectrum::uniform(1.0))),
+            Transform::identity(),
+        );
+        let film = Film::new(32, 32);
+        let camera = Camera::new(&film, &Orthographic::new(1.0, 10.0, 1.0));
+        let line_pixels = |width: f32| {
+            let settings = WireframeSettings {
+                line_width: width,
+                ..WireframeSettings::default()
+            };
+            render(&camera, &scene, &settings)
+                .iter()
+                .filter(|p| p.g > 0.5)
+                .count()
+        };
+        assert!(line_pixels(4.0) > line_pixels(1.0));
+    }
+}
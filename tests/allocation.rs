@@ -0,0 +1,144 @@
+//! Checks tracing does not allocate once each thread's scratch space has grown to fit.
+//!
+//! These run in their own test binary because counting allocations replaces the global
+//! allocator, which would otherwise slow every other test.
+extern crate rrt;
+
+use rrt::math::{Point, Ray, Sphere, Transform, Vector};
+use rrt::scene::{Scene, Spectrum};
+use rrt::scene::area_light::RectangleLight;
+use rrt::scene::environment::{EnvironmentLight, Portal};
+use rrt::scene::material::{DielectricMaterial, LambertianMaterial, SpecularMaterial};
+use rrt::scene::nonarea_light::{DirectionalLight, PointLight, SUN_ANGULAR_RADIUS};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// The system allocator, counting the allocations made on each thread.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|count| count.get())
+}
+
+/// Glass and mirror spheres resting on a diffuse floor, unlit.
+fn spheres() -> Scene {
+    let mut scene = Scene::new();
+    scene.add_entity(
+        Box::new(Sphere::new_with_radius(1.0)),
+        Box::new(DielectricMaterial::new(1.5)),
+        Transform::translate(0.0, 0.0, 5.0),
+    );
+    scene.add_entity(
+        Box::new(Sphere::new_with_radius(1.0)),
+        Box::new(SpecularMaterial::new(&Spectrum::uniform(0.9))),
+        Transform::translate(2.5, 0.0, 5.0),
+    );
+    scene.add_entity(
+        Box::new(Sphere::new_with_radius(10.0)),
+        Box::new(LambertianMaterial::new(&Spectrum::uniform(0.5))),
+        Transform::translate(0.0, -11.0, 5.0),
+    );
+    scene
+}
+
+/// Traces a fan of rays through the spheres twice, checking the second pass does not allocate.
+fn assert_traces_without_allocating(mut scene: Scene) {
+    scene.finalize();
+    let rays: Vec<Ray> = (0..40)
+        .map(|i| {
+            Ray {
+                origin: Point::new(0.0, 0.0, 0.0),
+                direction: Vector::unit(i as f32 * 0.02 - 0.2, -0.1, 1.0).unwrap(),
+                time: 0.0,
+            }
+        })
+        .collect();
+
+    // The first traces grow the scratch space to fit.
+    for ray in rays.iter() {
+        scene.trace(ray);
+    }
+    let before = allocations();
+    for ray in rays.iter() {
+        scene.trace(ray);
+    }
+    assert_eq!(allocations(), before);
+}
+
+#[test]
+fn test_point_light() {
+    let mut scene = spheres();
+    scene.add_light(Box::new(PointLight::new(
+        Point::new(0.0, 10.0, 0.0),
+        Spectrum::uniform(1.0),
+    )));
+    assert_traces_without_allocating(scene);
+}
+
+#[test]
+fn test_area_light() {
+    let mut scene = spheres();
+    scene.add_area_light(Box::new(RectangleLight::new(
+        Point::new(-2.0, 6.0, 3.0),
+        Vector::new(4.0, 0.0, 0.0),
+        Vector::new(0.0, 0.0, 4.0),
+        &Spectrum::uniform(2.0),
+    )));
+    assert_traces_without_allocating(scene);
+}
+
+#[test]
+fn test_environment() {
+    let mut scene = spheres();
+    let pixels = (0..32)
+        .map(|i| Spectrum::uniform(0.1 + i as f32 * 0.05))
+        .collect();
+    scene.set_environment(Some(EnvironmentLight::from_pixels(8, 4, pixels)));
+    assert_traces_without_allocating(scene);
+}
+
+#[test]
+fn test_environment_through_portal() {
+    let mut scene = spheres();
+    scene.set_environment(Some(EnvironmentLight::constant(&Spectrum::uniform(1.0))));
+    scene.add_portal(Portal::new(
+        Point::new(-3.0, 5.0, 2.0),
+        Vector::new(6.0, 0.0, 0.0),
+        Vector::new(0.0, 0.0, 6.0),
+    ));
+    assert_traces_without_allocating(scene);
+}
+
+#[test]
+fn test_sun() {
+    let mut scene = spheres();
+    scene.add_light(Box::new(DirectionalLight::new_with_angular_radius(
+        &Vector::new(0.3, -1.0, 0.2),
+        &Spectrum::uniform(3.0),
+        SUN_ANGULAR_RADIUS,
+    )));
+    assert_traces_without_allocating(scene);
+}